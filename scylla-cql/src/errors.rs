@@ -365,6 +365,11 @@ pub enum NewSessionError {
     #[error("Empty known nodes list")]
     EmptyKnownNodesList,
 
+    /// A Unix domain socket was passed along with other known nodes.
+    /// The node reached through the socket has to be the only known node.
+    #[error("A Unix domain socket has to be the only known node")]
+    UnixSocketWithOtherKnownNodes,
+
     /// Database sent a response containing some error with a message
     #[error("Database returned an error: {0}, Error message: {1}")]
     DbError(DbError, String),
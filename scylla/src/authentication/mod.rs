@@ -1,5 +1,7 @@
 use async_trait::async_trait;
 use bytes::{BufMut, BytesMut};
+use std::sync::{Arc, Mutex};
+use std::time::SystemTime;
 
 /// Type to represent an authentication error message.
 pub type AuthError = String;
@@ -35,6 +37,64 @@ pub trait AuthenticatorProvider: Sync + Send {
         &self,
         authenticator_name: &str,
     ) -> Result<(Option<Vec<u8>>, Box<dyn AuthenticatorSession>), AuthError>;
+
+    /// Called when the server rejects the credentials sent by this provider.
+    /// Providers which can fetch new credentials should drop the cached ones and return `true`,
+    /// in which case the driver opens the connection once again before giving up.
+    ///
+    /// Default: returns `false`, so the connection attempt fails immediately.
+    fn invalidate_credentials(&self) -> bool {
+        false
+    }
+}
+
+/// Username and password used by [`PlainTextAuthenticator`].
+#[derive(Clone, PartialEq, Eq)]
+pub struct Credentials {
+    pub username: String,
+    pub password: String,
+
+    /// After this time the credentials are fetched again from the [`CredentialsProvider`].
+    /// `None` means that they don't expire.
+    pub expires_at: Option<SystemTime>,
+}
+
+impl Credentials {
+    /// Creates non-expiring credentials.
+    pub fn new(username: impl Into<String>, password: impl Into<String>) -> Self {
+        Credentials {
+            username: username.into(),
+            password: password.into(),
+            expires_at: None,
+        }
+    }
+
+    fn is_expired(&self) -> bool {
+        match self.expires_at {
+            Some(expires_at) => expires_at <= SystemTime::now(),
+            None => false,
+        }
+    }
+}
+
+// Don't leak the password into logs
+impl std::fmt::Debug for Credentials {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Credentials")
+            .field("username", &self.username)
+            .field("password", &"<hidden>")
+            .field("expires_at", &self.expires_at)
+            .finish()
+    }
+}
+
+/// Trait used to fetch the current credentials, e.g. from a secret manager.
+///
+/// The credentials are cached by [`PlainTextAuthenticator`] until they expire
+/// or until the server rejects them, so the provider is not called for every new connection.
+#[async_trait]
+pub trait CredentialsProvider: Send + Sync {
+    async fn credentials(&self) -> Result<Credentials, AuthError>;
 }
 
 struct PlainTextAuthenticatorSession;
@@ -55,14 +115,73 @@ impl AuthenticatorSession for PlainTextAuthenticatorSession {
 
 /// Default authenticator provider that requires username and password if authentication is required.
 pub struct PlainTextAuthenticator {
-    username: String,
-    password: String,
+    source: CredentialsSource,
+}
+
+enum CredentialsSource {
+    Fixed(Credentials),
+    Provider {
+        provider: Arc<dyn CredentialsProvider>,
+        cached: Mutex<Option<Credentials>>,
+        // Held while fetching, so that connections opened at the same time
+        // (e.g. when a pool is filled) don't all call the provider
+        fetch_lock: tokio::sync::Mutex<()>,
+    },
 }
 
 impl PlainTextAuthenticator {
     /// Creates new [`PlainTextAuthenticator`] instance with provided username and password.
     pub fn new(username: String, password: String) -> Self {
-        PlainTextAuthenticator { username, password }
+        PlainTextAuthenticator {
+            source: CredentialsSource::Fixed(Credentials::new(username, password)),
+        }
+    }
+
+    /// Creates new [`PlainTextAuthenticator`] instance which fetches the credentials
+    /// from the given provider when they are needed for the first time, after they expire,
+    /// and after the server rejects them.
+    pub fn with_credentials_provider(provider: Arc<dyn CredentialsProvider>) -> Self {
+        PlainTextAuthenticator {
+            source: CredentialsSource::Provider {
+                provider,
+                cached: Mutex::new(None),
+                fetch_lock: tokio::sync::Mutex::new(()),
+            },
+        }
+    }
+
+    async fn credentials(&self) -> Result<Credentials, AuthError> {
+        match &self.source {
+            CredentialsSource::Fixed(credentials) => Ok(credentials.clone()),
+            CredentialsSource::Provider {
+                provider,
+                cached,
+                fetch_lock,
+            } => {
+                let valid_cached = || {
+                    cached
+                        .lock()
+                        .unwrap()
+                        .as_ref()
+                        .filter(|c| !c.is_expired())
+                        .cloned()
+                };
+
+                if let Some(credentials) = valid_cached() {
+                    return Ok(credentials);
+                }
+
+                let _guard = fetch_lock.lock().await;
+                // Someone else could have fetched the credentials while we were waiting
+                if let Some(credentials) = valid_cached() {
+                    return Ok(credentials);
+                }
+
+                let credentials = provider.credentials().await?;
+                *cached.lock().unwrap() = Some(credentials.clone());
+                Ok(credentials)
+            }
+        }
     }
 }
 
@@ -72,9 +191,11 @@ impl AuthenticatorProvider for PlainTextAuthenticator {
         &self,
         _authenticator_name: &str,
     ) -> Result<(Option<Vec<u8>>, Box<dyn AuthenticatorSession>), AuthError> {
+        let credentials = self.credentials().await?;
+
         let mut response = BytesMut::new();
-        let username_as_bytes = self.username.as_bytes();
-        let password_as_bytes = self.password.as_bytes();
+        let username_as_bytes = credentials.username.as_bytes();
+        let password_as_bytes = credentials.password.as_bytes();
 
         response.put_u8(0);
         response.put_slice(username_as_bytes);
@@ -86,4 +207,89 @@ impl AuthenticatorProvider for PlainTextAuthenticator {
             Box::new(PlainTextAuthenticatorSession),
         ))
     }
+
+    fn invalidate_credentials(&self) -> bool {
+        match &self.source {
+            CredentialsSource::Fixed(_) => false,
+            CredentialsSource::Provider { cached, .. } => {
+                *cached.lock().unwrap() = None;
+                true
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        AuthError, AuthenticatorProvider, Credentials, CredentialsProvider, PlainTextAuthenticator,
+    };
+    use async_trait::async_trait;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+    use std::time::{Duration, SystemTime};
+
+    struct CountingProvider {
+        calls: AtomicUsize,
+        valid_for: Option<Duration>,
+    }
+
+    #[async_trait]
+    impl CredentialsProvider for CountingProvider {
+        async fn credentials(&self) -> Result<Credentials, AuthError> {
+            let call = self.calls.fetch_add(1, Ordering::Relaxed);
+            Ok(Credentials {
+                username: "user".to_string(),
+                password: format!("secret{}", call),
+                expires_at: self.valid_for.map(|d| SystemTime::now() + d),
+            })
+        }
+    }
+
+    async fn initial_response(authenticator: &PlainTextAuthenticator) -> Vec<u8> {
+        authenticator
+            .start_authentication_session("PasswordAuthenticator")
+            .await
+            .unwrap()
+            .0
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn provided_credentials_are_cached() {
+        let provider = Arc::new(CountingProvider {
+            calls: AtomicUsize::new(0),
+            valid_for: None,
+        });
+        let authenticator = PlainTextAuthenticator::with_credentials_provider(provider.clone());
+
+        assert_eq!(initial_response(&authenticator).await, b"\0user\0secret0");
+        assert_eq!(initial_response(&authenticator).await, b"\0user\0secret0");
+        assert_eq!(provider.calls.load(Ordering::Relaxed), 1);
+
+        // Rejected credentials are fetched again
+        assert!(authenticator.invalidate_credentials());
+        assert_eq!(initial_response(&authenticator).await, b"\0user\0secret1");
+        assert_eq!(provider.calls.load(Ordering::Relaxed), 2);
+    }
+
+    #[tokio::test]
+    async fn expired_credentials_are_fetched_again() {
+        let provider = Arc::new(CountingProvider {
+            calls: AtomicUsize::new(0),
+            valid_for: Some(Duration::ZERO),
+        });
+        let authenticator = PlainTextAuthenticator::with_credentials_provider(provider.clone());
+
+        assert_eq!(initial_response(&authenticator).await, b"\0user\0secret0");
+        assert_eq!(initial_response(&authenticator).await, b"\0user\0secret1");
+        assert_eq!(provider.calls.load(Ordering::Relaxed), 2);
+    }
+
+    #[tokio::test]
+    async fn fixed_credentials_are_not_invalidated() {
+        let authenticator = PlainTextAuthenticator::new("user".to_string(), "pass".to_string());
+        assert_eq!(initial_response(&authenticator).await, b"\0user\0pass");
+        assert!(!authenticator.invalidate_credentials());
+    }
 }
@@ -1,9 +1,6 @@
 use std::{collections::HashMap, io};
 
-use openssl::{
-    pkey::{PKey, Private},
-    x509::X509,
-};
+use super::tls::{Certificate, PrivateKey};
 use scylla_cql::{frame::types::SerialConsistency, Consistency};
 use thiserror::Error;
 
@@ -22,8 +19,13 @@ pub enum CloudConfigError {
     #[error("Error during cloud config validation: {0}")]
     Validation(String),
 
+    #[cfg(feature = "ssl")]
     #[error("Error during key/cert parsing: {0}")]
     Ssl(#[from] openssl::error::ErrorStack),
+
+    #[cfg(feature = "rustls")]
+    #[error("Error while setting up rustls: {0}")]
+    Rustls(#[from] rustls::Error),
 }
 
 #[derive(Debug)]
@@ -69,8 +71,8 @@ impl CloudConfig {
 
 #[derive(Debug)]
 pub(crate) struct AuthInfo {
-    key: PKey<Private>,
-    cert: X509,
+    key: PrivateKey,
+    cert: Certificate,
     #[allow(unused)]
     username: Option<String>,
     #[allow(unused)]
@@ -78,11 +80,11 @@ pub(crate) struct AuthInfo {
 }
 
 impl AuthInfo {
-    pub(crate) fn get_key(&self) -> &PKey<Private> {
+    pub(crate) fn get_key(&self) -> &PrivateKey {
         &self.key
     }
 
-    pub(crate) fn get_cert(&self) -> &X509 {
+    pub(crate) fn get_cert(&self) -> &Certificate {
         &self.cert
     }
 
@@ -99,7 +101,7 @@ impl AuthInfo {
 
 #[derive(Debug)]
 pub(crate) struct Datacenter {
-    certificate_authority: X509,
+    certificate_authority: Certificate,
     server: String,
     #[allow(unused)]
     tls_server_name: Option<String>,
@@ -110,7 +112,7 @@ pub(crate) struct Datacenter {
 }
 
 impl Datacenter {
-    pub(crate) fn get_certificate_authority(&self) -> &X509 {
+    pub(crate) fn get_certificate_authority(&self) -> &Certificate {
         &self.certificate_authority
     }
 
@@ -148,7 +150,7 @@ mod deserialize {
     use scylla_cql::{frame::types::SerialConsistency, Consistency};
     use std::{collections::HashMap, fs::File, io::Read, path::Path};
 
-    use openssl::{pkey::PKey, x509::X509};
+    use crate::cloud::tls;
 
     use serde::Deserialize;
     use tracing::warn;
@@ -409,9 +411,9 @@ mod deserialize {
                 auth_info.clientKeyPath.as_deref(),
             )?;
 
-            let cert = X509::from_pem(&cert_pem[..]).map_err(CloudConfigError::Ssl)?;
+            let cert = tls::parse_certificate(&cert_pem)?;
 
-            let key = PKey::private_key_from_pem(&key_pem[..]).map_err(CloudConfigError::Ssl)?;
+            let key = tls::parse_private_key(&key_pem)?;
 
             Ok(super::AuthInfo {
                 key,
@@ -509,8 +511,7 @@ mod deserialize {
                 datacenter.certificateAuthorityPath.as_deref(),
             )?;
 
-            let certificate_authority =
-                X509::from_pem(&cert_pem[..]).map_err(CloudConfigError::Ssl)?;
+            let certificate_authority = tls::parse_certificate(&cert_pem)?;
 
             Ok(super::Datacenter {
                 certificate_authority,
@@ -546,8 +547,8 @@ mod deserialize {
 
         use super::super::CloudConfig;
         use super::RawCloudConfig;
+        use crate::cloud::tls;
         use assert_matches::assert_matches;
-        use openssl::x509::X509;
         use scylla_cql::frame::types::SerialConsistency;
         use scylla_cql::Consistency;
 
@@ -814,9 +815,9 @@ mod deserialize {
 
                 assert_eq!(
                     auth_info.cert,
-                    X509::from_pem(&base64::decode(TEST_CA.as_bytes()).unwrap()).unwrap()
+                    tls::parse_certificate(&base64::decode(TEST_CA.as_bytes()).unwrap()).unwrap()
                 );
-                // comparison of PKey<Private> is not possible, so auth_info.key won't be tested here.
+                // comparison of PKey<Private> is not possible with OpenSSL, so auth_info.key won't be tested here.
 
                 assert_eq!(auth_info.username, Some(String::from("cassandra1")));
                 assert_eq!(auth_info.password, Some(String::from("scylla1")));
@@ -824,7 +825,7 @@ mod deserialize {
                 let datacenter = validated_config.datacenters.get("eu-west-1").unwrap();
                 assert_eq!(
                     datacenter.certificate_authority,
-                    X509::from_pem(&base64::decode(TEST_CA.as_bytes()).unwrap()).unwrap()
+                    tls::parse_certificate(&base64::decode(TEST_CA.as_bytes()).unwrap()).unwrap()
                 );
                 assert_eq!(datacenter.server.as_str(), "127.0.1.12:9142");
                 assert_eq!(datacenter.node_domain, "cql.my-cluster-id.scylla.com");
@@ -1,13 +1,10 @@
 mod config;
+mod tls;
 
 use std::net::SocketAddr;
 
 pub(crate) use config::CloudConfig;
 pub use config::CloudConfigError;
-use openssl::{
-    error::ErrorStack,
-    ssl::{SslContext, SslMethod, SslVerifyMode},
-};
 use tracing::warn;
 use uuid::Uuid;
 
@@ -18,7 +15,7 @@ pub(crate) fn set_ssl_config_for_scylla_cloud_host(
     dc: Option<&str>,
     proxy_address: SocketAddr,
     connection_config: &mut ConnectionConfig,
-) -> Result<(), ErrorStack> {
+) -> Result<(), CloudConfigError> {
     if connection_config.ssl_config.is_some() {
         // This can only happen if the user builds SessionConfig by hand, as SessionBuilder in cloud mode prevents setting custom SslContext.
         warn!(
@@ -40,19 +37,9 @@ pub(crate) fn set_ssl_config_for_scylla_cloud_host(
         let key = auth_info.get_key();
         let cert = auth_info.get_cert();
 
-        let ssl_context = {
-            let mut builder = SslContext::builder(SslMethod::tls())?;
-            builder.set_verify(if datacenter.get_insecure_skip_tls_verify() {
-                SslVerifyMode::NONE
-            } else {
-                SslVerifyMode::PEER
-            });
-            builder.cert_store_mut().add_cert(ca.clone())?;
-            builder.set_certificate(cert)?;
-            builder.set_private_key(key)?;
-            builder.build()
-        };
-        let ssl_config = SslConfig::new_for_sni(ssl_context, domain_name, host_id);
+        let tls_context =
+            tls::build_tls_context(ca, cert, key, datacenter.get_insecure_skip_tls_verify())?;
+        let ssl_config = SslConfig::new_for_sni(tls_context, domain_name, host_id);
         connection_config.ssl_config = Some(ssl_config);
     } else {
         warn!("Datacenter {:?} of node {:?} with addr {} not described in cloud config. Proceeding without setting SNI for the node, which will most probably result in nonworking connections,.",
@@ -60,3 +47,47 @@ pub(crate) fn set_ssl_config_for_scylla_cloud_host(
     }
     Ok(())
 }
+
+#[cfg(all(test, feature = "rustls"))]
+mod tests {
+    use std::net::IpAddr;
+    use std::sync::Arc;
+
+    use uuid::Uuid;
+
+    use super::{set_ssl_config_for_scylla_cloud_host, CloudConfig};
+    use crate::transport::connection::ConnectionConfig;
+
+    #[test]
+    fn test_cloud_host_sni_server_name() {
+        let cloud_config = CloudConfig::read_from_yaml("src/cloud/ccm_config.yaml").unwrap();
+        let mut connection_config = ConnectionConfig {
+            cloud_config: Some(Arc::new(cloud_config)),
+            ..Default::default()
+        };
+        let host_id = Uuid::new_v4();
+        set_ssl_config_for_scylla_cloud_host(
+            Some(host_id),
+            Some("eu-west-1"),
+            "127.0.1.1:9142".parse().unwrap(),
+            &mut connection_config,
+        )
+        .unwrap();
+
+        let ssl_config = connection_config.ssl_config.unwrap();
+        #[cfg(not(feature = "ssl"))]
+        assert_matches::assert_matches!(
+            ssl_config.context(),
+            crate::transport::connection::TlsContext::Rustls(_)
+        );
+
+        // The node is verified against its name in the node domain, not the proxy address.
+        let expected_name = format!("{}.cql.cluster-id.scylla.com", host_id);
+        assert_eq!(
+            ssl_config
+                .rustls_server_name(IpAddr::from([127, 0, 1, 1]))
+                .unwrap(),
+            rustls::ServerName::try_from(expected_name.as_str()).unwrap()
+        );
+    }
+}
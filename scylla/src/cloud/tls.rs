@@ -0,0 +1,202 @@
+// TLS backend used for connections to Scylla Cloud.
+// OpenSSL is used if the `ssl` feature is enabled, rustls otherwise.
+
+pub(crate) use backend::{build_tls_context, parse_certificate, parse_private_key};
+pub(crate) use backend::{Certificate, PrivateKey};
+
+#[cfg(feature = "ssl")]
+mod backend {
+    use openssl::{
+        pkey::{PKey, Private},
+        ssl::{SslContext, SslMethod, SslVerifyMode},
+        x509::X509,
+    };
+
+    use crate::cloud::CloudConfigError;
+    use crate::transport::connection::TlsContext;
+
+    pub(crate) type Certificate = X509;
+    pub(crate) type PrivateKey = PKey<Private>;
+
+    pub(crate) fn parse_certificate(pem: &[u8]) -> Result<Certificate, CloudConfigError> {
+        Ok(X509::from_pem(pem)?)
+    }
+
+    pub(crate) fn parse_private_key(pem: &[u8]) -> Result<PrivateKey, CloudConfigError> {
+        Ok(PKey::private_key_from_pem(pem)?)
+    }
+
+    pub(crate) fn build_tls_context(
+        certificate_authority: &Certificate,
+        cert: &Certificate,
+        key: &PrivateKey,
+        insecure_skip_tls_verify: bool,
+    ) -> Result<TlsContext, CloudConfigError> {
+        let mut builder = SslContext::builder(SslMethod::tls())?;
+        builder.set_verify(if insecure_skip_tls_verify {
+            SslVerifyMode::NONE
+        } else {
+            SslVerifyMode::PEER
+        });
+        builder
+            .cert_store_mut()
+            .add_cert(certificate_authority.clone())?;
+        builder.set_certificate(cert)?;
+        builder.set_private_key(key)?;
+        Ok(builder.build().into())
+    }
+}
+
+#[cfg(not(feature = "ssl"))]
+mod backend {
+    use std::sync::Arc;
+    use std::time::SystemTime;
+
+    use rustls::client::{ServerCertVerified, ServerCertVerifier};
+    use rustls::{ClientConfig, RootCertStore, ServerName};
+    use rustls_pemfile::Item;
+
+    use crate::cloud::CloudConfigError;
+    use crate::transport::connection::TlsContext;
+
+    // A PEM file may contain a whole certificate chain.
+    pub(crate) type Certificate = Vec<rustls::Certificate>;
+    pub(crate) type PrivateKey = rustls::PrivateKey;
+
+    fn pem_items(pem: &[u8]) -> Result<Vec<Item>, CloudConfigError> {
+        rustls_pemfile::read_all(&mut &*pem)
+            .map_err(|e| CloudConfigError::Validation(format!("Invalid PEM data: {}", e)))
+    }
+
+    pub(crate) fn parse_certificate(pem: &[u8]) -> Result<Certificate, CloudConfigError> {
+        let certs: Certificate = pem_items(pem)?
+            .into_iter()
+            .filter_map(|item| match item {
+                Item::X509Certificate(der) => Some(rustls::Certificate(der)),
+                _ => None,
+            })
+            .collect();
+        if certs.is_empty() {
+            return Err(CloudConfigError::Validation(
+                "No certificate found in PEM data".into(),
+            ));
+        }
+        Ok(certs)
+    }
+
+    pub(crate) fn parse_private_key(pem: &[u8]) -> Result<PrivateKey, CloudConfigError> {
+        pem_items(pem)?
+            .into_iter()
+            .find_map(|item| match item {
+                Item::PKCS8Key(der) | Item::RSAKey(der) | Item::ECKey(der) => {
+                    Some(rustls::PrivateKey(der))
+                }
+                _ => None,
+            })
+            .ok_or_else(|| CloudConfigError::Validation("No private key found in PEM data".into()))
+    }
+
+    // Accepts any certificate, used if the datacenter sets `insecureSkipTlsVerify`.
+    struct NoCertificateVerification;
+
+    impl ServerCertVerifier for NoCertificateVerification {
+        fn verify_server_cert(
+            &self,
+            _end_entity: &rustls::Certificate,
+            _intermediates: &[rustls::Certificate],
+            _server_name: &ServerName,
+            _scts: &mut dyn Iterator<Item = &[u8]>,
+            _ocsp_response: &[u8],
+            _now: SystemTime,
+        ) -> Result<ServerCertVerified, rustls::Error> {
+            Ok(ServerCertVerified::assertion())
+        }
+    }
+
+    pub(crate) fn build_tls_context(
+        certificate_authority: &Certificate,
+        cert: &Certificate,
+        key: &PrivateKey,
+        insecure_skip_tls_verify: bool,
+    ) -> Result<TlsContext, CloudConfigError> {
+        let mut root_store = RootCertStore::empty();
+        for ca in certificate_authority {
+            root_store.add(ca)?;
+        }
+        let mut config = ClientConfig::builder()
+            .with_safe_defaults()
+            .with_root_certificates(root_store)
+            .with_client_auth_cert(cert.clone(), key.clone())?;
+        if insecure_skip_tls_verify {
+            config
+                .dangerous()
+                .set_certificate_verifier(Arc::new(NoCertificateVerification));
+        }
+        Ok(config.into())
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use std::sync::Arc;
+
+        use rustls::{ClientConnection, ServerConfig, ServerConnection};
+
+        use super::{build_tls_context, parse_certificate, parse_private_key};
+        use crate::transport::connection::TlsContext;
+
+        static TEST_CA: &str = include_str!("test_ca");
+        static TEST_KEY: &str = include_str!("test_key");
+
+        // Performs an in-memory handshake with a server presenting the (expired) test certificate.
+        fn handshake(insecure_skip_tls_verify: bool) -> Result<(), rustls::Error> {
+            let cert = parse_certificate(&base64::decode(TEST_CA).unwrap()).unwrap();
+            let key = parse_private_key(&base64::decode(TEST_KEY).unwrap()).unwrap();
+
+            let TlsContext::Rustls(client_config) =
+                build_tls_context(&cert, &cert, &key, insecure_skip_tls_verify).unwrap();
+            let server_config = ServerConfig::builder()
+                .with_safe_defaults()
+                .with_no_client_auth()
+                .with_single_cert(cert, key)
+                .unwrap();
+
+            let mut client = ClientConnection::new(
+                client_config,
+                "cql.cluster-id.scylla.com".try_into().unwrap(),
+            )
+            .unwrap();
+            let mut server = ServerConnection::new(Arc::new(server_config)).unwrap();
+
+            for _ in 0..10 {
+                if !client.is_handshaking() && !server.is_handshaking() {
+                    return Ok(());
+                }
+                let mut buf = Vec::new();
+                client.write_tls(&mut buf).unwrap();
+                let mut data = buf.as_slice();
+                while !data.is_empty() {
+                    server.read_tls(&mut data).unwrap();
+                    server.process_new_packets().unwrap();
+                }
+
+                buf.clear();
+                server.write_tls(&mut buf).unwrap();
+                let mut data = buf.as_slice();
+                while !data.is_empty() {
+                    client.read_tls(&mut data).unwrap();
+                    client.process_new_packets()?;
+                }
+            }
+            panic!("TLS handshake did not finish");
+        }
+
+        #[test]
+        fn test_certificate_verification_skipped_only_if_insecure() {
+            assert!(matches!(
+                handshake(false),
+                Err(rustls::Error::InvalidCertificate(_))
+            ));
+            handshake(true).unwrap();
+        }
+    }
+}
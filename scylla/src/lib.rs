@@ -102,12 +102,14 @@ pub use scylla_cql::frame;
 pub use scylla_cql::macros::{self, *};
 
 pub mod authentication;
-#[cfg(feature = "cloud")]
+#[cfg(any(feature = "cloud", feature = "cloud-rustls"))]
 pub mod cloud;
 
 pub mod history;
+pub mod migration;
 pub mod routing;
 pub mod statement;
+pub mod system;
 pub mod tracing;
 pub mod transport;
 
@@ -131,7 +133,7 @@ pub use transport::query_result::QueryResult;
 pub use transport::session::{IntoTypedRows, Session, SessionConfig};
 pub use transport::session_builder::SessionBuilder;
 
-#[cfg(feature = "cloud")]
+#[cfg(any(feature = "cloud", feature = "cloud-rustls"))]
 pub use transport::session_builder::CloudSessionBuilder;
 
 pub use transport::execution_profile;
@@ -0,0 +1,870 @@
+//! Versioned schema migrations applied through a [`Session`].
+//!
+//! A [`Migrator`] takes an ordered list of CQL [`Migration`] scripts and applies
+//! the ones that were not applied yet. Applied migrations are recorded in a tracking
+//! table, together with a checksum of their script, so that editing an already applied
+//! script is detected instead of being silently ignored.\
+//! Statements of a script are sent one by one and the driver waits for schema agreement
+//! after each of them. An LWT-based lock guarantees that only one client at a time
+//! applies migrations, so many instances of a service can run the migrator on startup.
+//!
+//! # Example
+//! ```rust
+//! # use scylla::Session;
+//! # use std::error::Error;
+//! # async fn check_only_compiles(session: &Session) -> Result<(), Box<dyn Error>> {
+//! use scylla::migration::{Migration, Migrator};
+//!
+//! let migrator = Migrator::new(
+//!     "my_keyspace",
+//!     vec![
+//!         Migration::new(1, "create users", "CREATE TABLE users (id uuid PRIMARY KEY, name text);"),
+//!         Migration::new(2, "add email", "ALTER TABLE users ADD email text;"),
+//!     ],
+//! )?;
+//!
+//! let report = migrator.run(session).await?;
+//! println!("Applied migrations: {:?}", report.applied);
+//! # Ok(())
+//! # }
+//! ```
+
+use std::collections::{BTreeMap, HashSet};
+use std::ffi::OsStr;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+use chrono::{DateTime, Utc};
+use scylla_cql::errors::{BadKeyspaceName, QueryError};
+use thiserror::Error;
+use tracing::{debug, info, warn};
+use uuid::Uuid;
+
+use crate::query::Query;
+use crate::statement::{Consistency, SerialConsistency};
+use crate::transport::connection::VerifiedKeyspaceName;
+use crate::transport::partitioner::Murmur3Partitioner;
+use crate::{QueryResult, Session};
+
+const DEFAULT_TRACKING_TABLE: &str = "schema_migrations";
+const DEFAULT_LOCK_TABLE: &str = "schema_migrations_lock";
+const LOCK_ID: &str = "migrations";
+
+/// A single versioned CQL script.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Migration {
+    version: i64,
+    description: String,
+    script: String,
+}
+
+impl Migration {
+    /// Creates a new migration.
+    /// The script may contain multiple statements separated with semicolons.
+    pub fn new(version: i64, description: impl Into<String>, script: impl Into<String>) -> Self {
+        Self {
+            version,
+            description: description.into(),
+            script: script.into(),
+        }
+    }
+
+    /// Loads all migrations from files in the given directory.
+    ///
+    /// Only files with the `.cql` extension are taken into account.
+    /// Their names must have the form `V<version>__<description>.cql`,
+    /// e.g. `V3__add_email_column.cql`; underscores in the description
+    /// are replaced with spaces.
+    pub fn load_dir(dir: impl AsRef<Path>) -> Result<Vec<Migration>, MigrationError> {
+        let mut migrations = Vec::new();
+        for entry in std::fs::read_dir(dir.as_ref())? {
+            let path = entry?.path();
+            if !path.is_file() || path.extension() != Some(OsStr::new("cql")) {
+                continue;
+            }
+            let (version, description) = parse_file_name(&path)?;
+            let script = std::fs::read_to_string(&path)?;
+            migrations.push(Migration::new(version, description, script));
+        }
+        migrations.sort_by_key(|m| m.version);
+        Ok(migrations)
+    }
+
+    pub fn version(&self) -> i64 {
+        self.version
+    }
+
+    pub fn description(&self) -> &str {
+        &self.description
+    }
+
+    pub fn script(&self) -> &str {
+        &self.script
+    }
+
+    /// Checksum of the script, stored in the tracking table when the migration is applied.
+    ///
+    /// It's a hex-encoded 128-bit MurmurHash3 of the script with surrounding whitespace trimmed.
+    pub fn checksum(&self) -> String {
+        format!(
+            "{:032x}",
+            Murmur3Partitioner::hash3_x64_128(self.script.trim().as_bytes()) as u128
+        )
+    }
+
+    /// Returns the statements contained in the script, in order.
+    ///
+    /// Statements are split on semicolons, skipping the ones that occur
+    /// inside string literals, quoted identifiers and comments.
+    pub fn statements(&self) -> Vec<&str> {
+        split_statements(&self.script)
+    }
+}
+
+/// A migration as recorded in the tracking table.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AppliedMigration {
+    pub version: i64,
+    pub description: String,
+    pub checksum: String,
+    pub applied_at: DateTime<Utc>,
+    pub execution_time: Duration,
+}
+
+/// Summary of a single [`Migrator::run`].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct MigrationReport {
+    /// Versions applied by this run, in order.
+    pub applied: Vec<i64>,
+    /// Number of migrations which had already been applied before this run.
+    pub already_applied: usize,
+}
+
+/// Error that occurred while loading or applying migrations.
+#[derive(Error, Debug)]
+pub enum MigrationError {
+    /// Reading migration scripts from disk failed
+    #[error("IO Error: {0}")]
+    IoError(#[from] std::io::Error),
+
+    /// A migration file name doesn't follow the `V<version>__<description>.cql` convention
+    #[error("Invalid migration file name: {0}, expected V<version>__<description>.cql")]
+    InvalidFileName(PathBuf),
+
+    /// Two migrations share the same version
+    #[error("Duplicate migration version: {0}")]
+    DuplicateVersion(i64),
+
+    /// Invalid name of the keyspace or of one of the migration tables
+    #[error(transparent)]
+    BadName(#[from] BadKeyspaceName),
+
+    /// A migration that was already applied has been edited since
+    #[error(
+        "Checksum mismatch for already applied migration {version}: \
+        applied {applied}, local {local}"
+    )]
+    ChecksumMismatch {
+        version: i64,
+        applied: String,
+        local: String,
+    },
+
+    /// The migration lock is held by another client and wasn't released in time
+    #[error("Failed to acquire the migration lock within {0:?}")]
+    LockTimeout(Duration),
+
+    /// The migration lock expired and was taken over by another client
+    #[error("Migration lock was lost while applying migration {0}")]
+    LockLost(i64),
+
+    /// Schema agreement wasn't reached after creating the migration tables
+    #[error("Failed to reach schema agreement after creating the migration tables")]
+    SetupSchemaAgreementTimeout,
+
+    /// Schema agreement wasn't reached after a statement of a migration
+    #[error("Failed to reach schema agreement after applying a statement of migration {0}")]
+    SchemaAgreementTimeout(i64),
+
+    /// A statement of a migration failed
+    #[error("Statement {statement_index} of migration {version} failed: {error}")]
+    StatementFailed {
+        version: i64,
+        statement_index: usize,
+        error: QueryError,
+    },
+
+    /// The tracking table contains data that can't be interpreted
+    #[error("Invalid data in the migration tracking table: {0}")]
+    InvalidTrackingData(String),
+
+    /// Other query error, e.g. while accessing the tracking table
+    #[error(transparent)]
+    QueryError(#[from] QueryError),
+}
+
+/// Applies [`Migration`]s to a keyspace.
+///
+/// The keyspace itself must already exist, the tracking and lock tables
+/// are created in it on the first run.
+#[derive(Debug, Clone)]
+pub struct Migrator {
+    migrations: Vec<Migration>,
+    keyspace: VerifiedKeyspaceName,
+    tracking_table: VerifiedKeyspaceName,
+    lock_table: VerifiedKeyspaceName,
+    consistency: Consistency,
+    schema_agreement_timeout: Duration,
+    lock_ttl: Duration,
+    lock_timeout: Duration,
+    lock_retry_interval: Duration,
+    owner: Uuid,
+}
+
+impl Migrator {
+    /// Creates a migrator for the given keyspace.
+    /// Migrations are sorted by version, duplicate versions are rejected.
+    pub fn new(
+        keyspace: impl Into<String>,
+        mut migrations: Vec<Migration>,
+    ) -> Result<Self, MigrationError> {
+        migrations.sort_by_key(|m| m.version);
+        if let Some(duplicate) = migrations.windows(2).find(|w| w[0].version == w[1].version) {
+            return Err(MigrationError::DuplicateVersion(duplicate[0].version));
+        }
+
+        Ok(Self {
+            migrations,
+            keyspace: VerifiedKeyspaceName::new(keyspace.into(), false)?,
+            tracking_table: VerifiedKeyspaceName::new(DEFAULT_TRACKING_TABLE.to_owned(), false)?,
+            lock_table: VerifiedKeyspaceName::new(DEFAULT_LOCK_TABLE.to_owned(), false)?,
+            consistency: Consistency::Quorum,
+            schema_agreement_timeout: Duration::from_secs(60),
+            lock_ttl: Duration::from_secs(300),
+            lock_timeout: Duration::from_secs(360),
+            lock_retry_interval: Duration::from_secs(1),
+            owner: Uuid::new_v4(),
+        })
+    }
+
+    /// Creates a migrator from the `.cql` files in the given directory,
+    /// see [`Migration::load_dir`].
+    pub fn from_dir(
+        keyspace: impl Into<String>,
+        dir: impl AsRef<Path>,
+    ) -> Result<Self, MigrationError> {
+        Self::new(keyspace, Migration::load_dir(dir)?)
+    }
+
+    /// Sets the name of the table storing applied migrations.
+    /// Default: `schema_migrations`.
+    pub fn tracking_table(mut self, name: impl Into<String>) -> Result<Self, MigrationError> {
+        self.tracking_table = VerifiedKeyspaceName::new(name.into(), false)?;
+        Ok(self)
+    }
+
+    /// Sets the name of the table holding the migration lock.
+    /// Default: `schema_migrations_lock`.
+    pub fn lock_table(mut self, name: impl Into<String>) -> Result<Self, MigrationError> {
+        self.lock_table = VerifiedKeyspaceName::new(name.into(), false)?;
+        Ok(self)
+    }
+
+    /// Consistency used to read and write the tracking and lock tables.
+    /// Default: `Quorum`.
+    pub fn consistency(mut self, consistency: Consistency) -> Self {
+        self.consistency = consistency;
+        self
+    }
+
+    /// How long to wait for schema agreement after each statement.
+    /// Default: 60 seconds.
+    pub fn schema_agreement_timeout(mut self, timeout: Duration) -> Self {
+        self.schema_agreement_timeout = timeout;
+        self
+    }
+
+    /// Time after which a lock left by a crashed client expires.
+    /// The lock is refreshed before each applied migration, so this only has
+    /// to be longer than the time needed to apply a single migration.
+    /// Default: 5 minutes.
+    pub fn lock_ttl(mut self, ttl: Duration) -> Self {
+        self.lock_ttl = ttl;
+        self
+    }
+
+    /// How long to wait for a lock held by another client before giving up.
+    /// A lock left by a crashed client is only released after [`lock_ttl`](Self::lock_ttl),
+    /// so a timeout shorter than the TTL makes a restarted client fail with
+    /// [`MigrationError::LockTimeout`] until the lock expires.
+    /// Default: 6 minutes.
+    pub fn lock_timeout(mut self, timeout: Duration) -> Self {
+        self.lock_timeout = timeout;
+        self
+    }
+
+    /// How often to retry taking a lock held by another client.
+    /// Default: 1 second.
+    pub fn lock_retry_interval(mut self, interval: Duration) -> Self {
+        self.lock_retry_interval = interval;
+        self
+    }
+
+    /// Migrations known to this migrator, sorted by version.
+    pub fn migrations(&self) -> &[Migration] {
+        &self.migrations
+    }
+
+    /// Reads the migrations recorded in the tracking table, sorted by version.
+    /// Doesn't modify the schema: if the tracking table doesn't exist yet,
+    /// no migration is considered applied.
+    pub async fn applied(
+        &self,
+        session: &Session,
+    ) -> Result<Vec<AppliedMigration>, MigrationError> {
+        if !self.tracking_table_exists(session).await? {
+            return Ok(Vec::new());
+        }
+        self.fetch_applied(session).await
+    }
+
+    /// Returns the migrations that haven't been applied yet.
+    /// Also verifies checksums of the applied ones.
+    pub async fn pending(&self, session: &Session) -> Result<Vec<&Migration>, MigrationError> {
+        let applied = self.applied(session).await?;
+        self.verify_and_filter_pending(&applied)
+    }
+
+    /// Applies all pending migrations.
+    ///
+    /// The migration lock is taken for the whole run and released afterwards,
+    /// also when applying a migration fails.
+    pub async fn run(&self, session: &Session) -> Result<MigrationReport, MigrationError> {
+        self.create_tables(session).await?;
+        self.acquire_lock(session).await?;
+
+        let result = self.run_locked(session).await;
+
+        if let Err(e) = self.release_lock(session).await {
+            warn!("Failed to release the migration lock: {}", e);
+        }
+
+        result
+    }
+
+    async fn run_locked(&self, session: &Session) -> Result<MigrationReport, MigrationError> {
+        let applied = self.fetch_applied(session).await?;
+        let pending = self.verify_and_filter_pending(&applied)?;
+
+        let mut report = MigrationReport {
+            applied: Vec::with_capacity(pending.len()),
+            already_applied: self.migrations.len() - pending.len(),
+        };
+
+        for migration in pending {
+            self.refresh_lock(session, migration.version).await?;
+            self.apply(session, migration).await?;
+            report.applied.push(migration.version);
+        }
+
+        Ok(report)
+    }
+
+    fn verify_and_filter_pending(
+        &self,
+        applied: &[AppliedMigration],
+    ) -> Result<Vec<&Migration>, MigrationError> {
+        let applied_by_version: BTreeMap<i64, &AppliedMigration> =
+            applied.iter().map(|a| (a.version, a)).collect();
+
+        let mut pending = Vec::new();
+        for migration in &self.migrations {
+            match applied_by_version.get(&migration.version) {
+                Some(applied) => {
+                    let local = migration.checksum();
+                    if applied.checksum != local {
+                        return Err(MigrationError::ChecksumMismatch {
+                            version: migration.version,
+                            applied: applied.checksum.clone(),
+                            local,
+                        });
+                    }
+                }
+                None => pending.push(migration),
+            }
+        }
+
+        let known: HashSet<i64> = self.migrations.iter().map(|m| m.version).collect();
+        for version in applied_by_version.keys().filter(|v| !known.contains(v)) {
+            // Most likely applied by a newer version of the application
+            debug!("Migration {} is applied but not known locally", version);
+        }
+
+        Ok(pending)
+    }
+
+    async fn apply(&self, session: &Session, migration: &Migration) -> Result<(), MigrationError> {
+        info!(
+            "Applying migration {}: {}",
+            migration.version, migration.description
+        );
+        let start = Instant::now();
+
+        for (statement_index, statement) in migration.statements().into_iter().enumerate() {
+            session.query(statement, &[]).await.map_err(|error| {
+                MigrationError::StatementFailed {
+                    version: migration.version,
+                    statement_index,
+                    error,
+                }
+            })?;
+
+            if !session
+                .await_timed_schema_agreement(self.schema_agreement_timeout)
+                .await?
+            {
+                return Err(MigrationError::SchemaAgreementTimeout(migration.version));
+            }
+        }
+
+        let execution_time_ms = start.elapsed().as_millis() as i64;
+        let mut insert = Query::new(format!(
+            "INSERT INTO {}.{} (version, description, checksum, applied_at, execution_time_ms) \
+            VALUES (?, ?, ?, ?, ?)",
+            self.keyspace.as_str(),
+            self.tracking_table.as_str()
+        ));
+        insert.set_consistency(self.consistency);
+        session
+            .query(
+                insert,
+                (
+                    migration.version,
+                    &migration.description,
+                    migration.checksum(),
+                    Utc::now(),
+                    execution_time_ms,
+                ),
+            )
+            .await?;
+
+        Ok(())
+    }
+
+    async fn create_tables(&self, session: &Session) -> Result<(), MigrationError> {
+        session
+            .query(
+                format!(
+                    "CREATE TABLE IF NOT EXISTS {}.{} (version bigint PRIMARY KEY, \
+                    description text, checksum text, applied_at timestamp, execution_time_ms bigint)",
+                    self.keyspace.as_str(),
+                    self.tracking_table.as_str()
+                ),
+                &[],
+            )
+            .await?;
+        session
+            .query(
+                format!(
+                    "CREATE TABLE IF NOT EXISTS {}.{} (id text PRIMARY KEY, owner uuid, acquired_at timestamp)",
+                    self.keyspace.as_str(),
+                    self.lock_table.as_str()
+                ),
+                &[],
+            )
+            .await?;
+
+        if !session
+            .await_timed_schema_agreement(self.schema_agreement_timeout)
+            .await?
+        {
+            return Err(MigrationError::SetupSchemaAgreementTimeout);
+        }
+        Ok(())
+    }
+
+    async fn tracking_table_exists(&self, session: &Session) -> Result<bool, MigrationError> {
+        // Unquoted names are stored in lowercase.
+        let rows = session
+            .query(
+                "SELECT table_name FROM system_schema.tables WHERE keyspace_name = ? AND table_name = ?",
+                (
+                    self.keyspace.as_str().to_lowercase(),
+                    self.tracking_table.as_str().to_lowercase(),
+                ),
+            )
+            .await?
+            .rows_num()
+            .map_err(|_| QueryError::ProtocolError("Response to SELECT was not Rows"))?;
+        Ok(rows > 0)
+    }
+
+    async fn fetch_applied(
+        &self,
+        session: &Session,
+    ) -> Result<Vec<AppliedMigration>, MigrationError> {
+        let mut select = Query::new(format!(
+            "SELECT version, description, checksum, applied_at, execution_time_ms FROM {}.{}",
+            self.keyspace.as_str(),
+            self.tracking_table.as_str()
+        ));
+        select.set_consistency(self.consistency);
+
+        type AppliedRow = (
+            i64,
+            Option<String>,
+            Option<String>,
+            Option<DateTime<Utc>>,
+            Option<i64>,
+        );
+        let rows = session
+            .query(select, &[])
+            .await?
+            .rows_typed::<AppliedRow>()
+            .map_err(|_| QueryError::ProtocolError("Response to SELECT was not Rows"))?;
+
+        let mut applied = Vec::new();
+        for row in rows {
+            let (version, description, checksum, applied_at, execution_time_ms) =
+                row.map_err(|e| MigrationError::InvalidTrackingData(e.to_string()))?;
+            applied.push(AppliedMigration {
+                version,
+                description: description.unwrap_or_default(),
+                checksum: checksum.ok_or_else(|| {
+                    MigrationError::InvalidTrackingData(format!(
+                        "missing checksum of migration {}",
+                        version
+                    ))
+                })?,
+                applied_at: applied_at.unwrap_or_default(),
+                execution_time: Duration::from_millis(execution_time_ms.unwrap_or(0) as u64),
+            });
+        }
+        applied.sort_by_key(|a| a.version);
+        Ok(applied)
+    }
+
+    async fn acquire_lock(&self, session: &Session) -> Result<(), MigrationError> {
+        let mut insert = Query::new(format!(
+            "INSERT INTO {}.{} (id, owner, acquired_at) VALUES (?, ?, ?) IF NOT EXISTS USING TTL ?",
+            self.keyspace.as_str(),
+            self.lock_table.as_str()
+        ));
+        self.configure_lwt(&mut insert);
+
+        let deadline = Instant::now() + self.lock_timeout;
+        loop {
+            let result = session
+                .query(
+                    insert.clone(),
+                    (LOCK_ID, self.owner, Utc::now(), self.lock_ttl_secs()),
+                )
+                .await?;
+            if lwt_applied(result)? {
+                debug!("Acquired the migration lock as {}", self.owner);
+                return Ok(());
+            }
+
+            if Instant::now() + self.lock_retry_interval > deadline {
+                return Err(MigrationError::LockTimeout(self.lock_timeout));
+            }
+            debug!("Migration lock is held by another client, retrying");
+            tokio::time::sleep(self.lock_retry_interval).await;
+        }
+    }
+
+    async fn refresh_lock(&self, session: &Session, version: i64) -> Result<(), MigrationError> {
+        let mut update = Query::new(format!(
+            "UPDATE {}.{} USING TTL ? SET owner = ?, acquired_at = ? WHERE id = ? IF owner = ?",
+            self.keyspace.as_str(),
+            self.lock_table.as_str()
+        ));
+        self.configure_lwt(&mut update);
+
+        let result = session
+            .query(
+                update,
+                (
+                    self.lock_ttl_secs(),
+                    self.owner,
+                    Utc::now(),
+                    LOCK_ID,
+                    self.owner,
+                ),
+            )
+            .await?;
+        if lwt_applied(result)? {
+            Ok(())
+        } else {
+            Err(MigrationError::LockLost(version))
+        }
+    }
+
+    async fn release_lock(&self, session: &Session) -> Result<(), MigrationError> {
+        let mut delete = Query::new(format!(
+            "DELETE FROM {}.{} WHERE id = ? IF owner = ?",
+            self.keyspace.as_str(),
+            self.lock_table.as_str()
+        ));
+        self.configure_lwt(&mut delete);
+
+        session.query(delete, (LOCK_ID, self.owner)).await?;
+        Ok(())
+    }
+
+    fn configure_lwt(&self, query: &mut Query) {
+        query.set_consistency(self.consistency);
+        query.set_serial_consistency(Some(SerialConsistency::Serial));
+    }
+
+    fn lock_ttl_secs(&self) -> i32 {
+        self.lock_ttl.as_secs().clamp(1, i32::MAX as u64) as i32
+    }
+}
+
+// The first column of an LWT result is always the `[applied]` boolean.
+fn lwt_applied(result: QueryResult) -> Result<bool, MigrationError> {
+    let row = result
+        .first_row()
+        .map_err(|_| QueryError::ProtocolError("LWT response contained no rows"))?;
+    row.columns
+        .first()
+        .and_then(|c| c.as_ref())
+        .and_then(|c| c.as_boolean())
+        .ok_or(MigrationError::QueryError(QueryError::ProtocolError(
+            "LWT response has no [applied] column",
+        )))
+}
+
+fn parse_file_name(path: &Path) -> Result<(i64, String), MigrationError> {
+    let invalid = || MigrationError::InvalidFileName(path.to_owned());
+
+    let stem = path
+        .file_stem()
+        .and_then(|s| s.to_str())
+        .ok_or_else(invalid)?;
+    let (version, description) = stem
+        .strip_prefix('V')
+        .and_then(|s| s.split_once("__"))
+        .ok_or_else(invalid)?;
+    let version = version.parse().map_err(|_| invalid())?;
+
+    Ok((version, description.replace('_', " ")))
+}
+
+// Splits a CQL script into statements. Semicolons inside string literals ('...'),
+// quoted identifiers ("..."), dollar-quoted strings ($$...$$) and comments
+// (-- ..., // ..., /* ... */) are not treated as separators. Comments are kept
+// as part of the statement text, statements consisting only of whitespace
+// and comments are dropped.
+fn split_statements(script: &str) -> Vec<&str> {
+    #[derive(Clone, Copy, PartialEq, Eq)]
+    enum State {
+        Code,
+        SingleQuoted,
+        DoubleQuoted,
+        DollarQuoted,
+        LineComment,
+        BlockComment,
+    }
+
+    let bytes = script.as_bytes();
+    let mut statements = Vec::new();
+    let mut state = State::Code;
+    let mut start = 0;
+    let mut has_code = false;
+    let mut i = 0;
+
+    while i < bytes.len() {
+        let current = bytes[i];
+        let next = bytes.get(i + 1).copied();
+        match state {
+            State::Code => match (current, next) {
+                (b';', _) => {
+                    if has_code {
+                        statements.push(script[start..i].trim());
+                    }
+                    start = i + 1;
+                    has_code = false;
+                }
+                (b'\'', _) => {
+                    state = State::SingleQuoted;
+                    has_code = true;
+                }
+                (b'"', _) => {
+                    state = State::DoubleQuoted;
+                    has_code = true;
+                }
+                (b'$', Some(b'$')) => {
+                    state = State::DollarQuoted;
+                    has_code = true;
+                    i += 1;
+                }
+                (b'-', Some(b'-')) | (b'/', Some(b'/')) => {
+                    state = State::LineComment;
+                    i += 1;
+                }
+                (b'/', Some(b'*')) => {
+                    state = State::BlockComment;
+                    i += 1;
+                }
+                (c, _) if !c.is_ascii_whitespace() => has_code = true,
+                _ => {}
+            },
+            // Quotes inside literals are escaped by doubling them, which is handled
+            // by leaving the literal and immediately entering it again.
+            State::SingleQuoted if current == b'\'' => state = State::Code,
+            State::DoubleQuoted if current == b'"' => state = State::Code,
+            State::DollarQuoted if current == b'$' && next == Some(b'$') => {
+                state = State::Code;
+                i += 1;
+            }
+            State::LineComment if current == b'\n' => state = State::Code,
+            State::BlockComment if current == b'*' && next == Some(b'/') => {
+                state = State::Code;
+                i += 1;
+            }
+            _ => {}
+        }
+        i += 1;
+    }
+
+    if has_code {
+        statements.push(script[start..].trim());
+    }
+
+    statements
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{parse_file_name, split_statements, Migration, MigrationError, Migrator};
+    use crate::test_utils::create_new_session_builder;
+    use crate::utils::test_utils::unique_keyspace_name;
+    use assert_matches::assert_matches;
+    use std::path::Path;
+
+    #[test]
+    fn test_split_statements() {
+        let script = "
+            -- create the table; it's the first statement
+            CREATE TABLE t (a int PRIMARY KEY, b text);
+            INSERT INTO t (a, b) VALUES (1, 'semi;colon');
+            INSERT INTO t (a, b) VALUES (2, 'it''s; quoted');
+            /* block; comment */
+            CREATE FUNCTION f(x int) RETURNS NULL ON NULL INPUT RETURNS int
+                LANGUAGE lua AS $$ return x; $$;
+            ALTER TABLE \"Quoted;Name\" ADD c int
+        ";
+
+        let statements = split_statements(script);
+        assert_eq!(statements.len(), 5);
+        assert!(statements[0].ends_with("CREATE TABLE t (a int PRIMARY KEY, b text)"));
+        assert_eq!(
+            statements[1],
+            "INSERT INTO t (a, b) VALUES (1, 'semi;colon')"
+        );
+        assert_eq!(
+            statements[2],
+            "INSERT INTO t (a, b) VALUES (2, 'it''s; quoted')"
+        );
+        assert!(statements[3].ends_with("AS $$ return x; $$"));
+        assert_eq!(statements[4], "ALTER TABLE \"Quoted;Name\" ADD c int");
+
+        assert!(split_statements("  ;; -- nothing here;\n /* ; */ ").is_empty());
+    }
+
+    #[test]
+    fn test_checksum() {
+        let m1 = Migration::new(1, "a", "CREATE TABLE t (a int PRIMARY KEY);");
+        let m2 = Migration::new(1, "b", "\nCREATE TABLE t (a int PRIMARY KEY);\n");
+        let m3 = Migration::new(1, "a", "CREATE TABLE t (a bigint PRIMARY KEY);");
+
+        assert_eq!(m1.checksum().len(), 32);
+        assert_eq!(m1.checksum(), m2.checksum());
+        assert_ne!(m1.checksum(), m3.checksum());
+    }
+
+    #[test]
+    fn test_parse_file_name() {
+        assert_eq!(
+            parse_file_name(Path::new("migrations/V12__add_email_column.cql")).unwrap(),
+            (12, "add email column".to_owned())
+        );
+        for invalid in ["V12_add.cql", "12__add.cql", "Vx__add.cql"] {
+            assert_matches!(
+                parse_file_name(Path::new(invalid)),
+                Err(MigrationError::InvalidFileName(_))
+            );
+        }
+    }
+
+    #[test]
+    fn test_duplicate_versions() {
+        let migrations = vec![
+            Migration::new(2, "b", "SELECT * FROM t"),
+            Migration::new(1, "a", "SELECT * FROM t"),
+            Migration::new(2, "c", "SELECT * FROM t"),
+        ];
+        assert_matches!(
+            Migrator::new("ks", migrations),
+            Err(MigrationError::DuplicateVersion(2))
+        );
+        assert_matches!(
+            Migrator::new("bad-keyspace", Vec::new()),
+            Err(MigrationError::BadName(_))
+        );
+    }
+
+    #[tokio::test]
+    async fn test_migrator() {
+        let session = create_new_session_builder().build().await.unwrap();
+        let ks = unique_keyspace_name();
+        session
+            .query(format!("CREATE KEYSPACE IF NOT EXISTS {} WITH REPLICATION = {{'class' : 'NetworkTopologyStrategy', 'replication_factor' : 1}}", ks), &[])
+            .await
+            .unwrap();
+
+        let v1 = Migration::new(
+            1,
+            "create t",
+            format!(
+                "CREATE TABLE {ks}.t (a int PRIMARY KEY, b int); INSERT INTO {ks}.t (a, b) VALUES (1, 1);",
+                ks = ks
+            ),
+        );
+        let v2 = Migration::new(2, "add c", format!("ALTER TABLE {}.t ADD c text", ks));
+
+        let migrator = Migrator::new(&*ks, vec![v1.clone()]).unwrap();
+        // Reading the status doesn't create the migration tables
+        assert_eq!(migrator.pending(&session).await.unwrap(), vec![&v1]);
+        assert!(migrator.applied(&session).await.unwrap().is_empty());
+        assert!(!migrator.tracking_table_exists(&session).await.unwrap());
+        let report = migrator.run(&session).await.unwrap();
+        assert_eq!(report.applied, vec![1]);
+
+        // Running again applies nothing
+        let migrator = Migrator::new(&*ks, vec![v1.clone(), v2.clone()]).unwrap();
+        assert_eq!(migrator.pending(&session).await.unwrap(), vec![&v2]);
+        let report = migrator.run(&session).await.unwrap();
+        assert_eq!(report.applied, vec![2]);
+        assert_eq!(report.already_applied, 1);
+        assert_eq!(migrator.applied(&session).await.unwrap().len(), 2);
+
+        session
+            .query(
+                format!("INSERT INTO {}.t (a, b, c) VALUES (2, 2, 'x')", ks),
+                &[],
+            )
+            .await
+            .unwrap();
+
+        // Editing an applied migration is detected
+        let edited = Migration::new(2, "add c", format!("ALTER TABLE {}.t ADD d text", ks));
+        let migrator = Migrator::new(&*ks, vec![v1, edited]).unwrap();
+        assert_matches!(
+            migrator.run(&session).await,
+            Err(MigrationError::ChecksumMismatch { version: 2, .. })
+        );
+    }
+}
@@ -3,6 +3,7 @@ use std::collections::HashMap;
 use std::convert::TryFrom;
 use std::net::SocketAddr;
 use std::num::NonZeroU16;
+use std::sync::Arc;
 use thiserror::Error;
 
 #[derive(PartialEq, Eq, PartialOrd, Ord, Hash, Clone, Copy)]
@@ -12,9 +13,84 @@ pub struct Node {
     pub addr: SocketAddr,
 }
 
-#[derive(PartialEq, Eq, PartialOrd, Ord, Clone, Copy, Debug)]
+/// A position on the token ring.
+///
+/// Murmur3Partitioner and CDCPartitioner tokens are 64-bit integers, RandomPartitioner tokens
+/// are integers from 0 to 2^127. ByteOrderedPartitioner tokens are the partition keys
+/// themselves, compared byte by byte.
+///
+/// Tokens of different partitioners are never compared with each other.
+///
+/// Up to version 0.8, `Token` was a `Copy` struct with a public `value: i64` field.
+/// Use [`Token::new`] to create a 64-bit token and [`Token::as_i64`] to read its value.
+#[derive(PartialEq, Eq, PartialOrd, Ord, Hash, Clone)]
 pub struct Token {
-    pub value: i64,
+    repr: TokenRepr,
+}
+
+#[derive(PartialEq, Eq, PartialOrd, Ord, Hash, Clone)]
+enum TokenRepr {
+    Integer(i128),
+    Bytes(Arc<[u8]>),
+}
+
+impl Token {
+    /// Creates a 64-bit token, as used by Murmur3Partitioner and CDCPartitioner.
+    pub const fn new(value: i64) -> Self {
+        Self::from_i128(value as i128)
+    }
+
+    /// Creates an integer token, as used by RandomPartitioner.
+    pub const fn from_i128(value: i128) -> Self {
+        Token {
+            repr: TokenRepr::Integer(value),
+        }
+    }
+
+    /// Creates a byte string token, as used by ByteOrderedPartitioner.
+    pub fn from_bytes(bytes: impl Into<Arc<[u8]>>) -> Self {
+        Token {
+            repr: TokenRepr::Bytes(bytes.into()),
+        }
+    }
+
+    /// Returns the value of the token if it is an integer which fits in 64 bits.
+    pub fn as_i64(&self) -> Option<i64> {
+        self.as_i128().and_then(|value| i64::try_from(value).ok())
+    }
+
+    /// Returns the value of the token if it is an integer.
+    pub fn as_i128(&self) -> Option<i128> {
+        match self.repr {
+            TokenRepr::Integer(value) => Some(value),
+            TokenRepr::Bytes(_) => None,
+        }
+    }
+
+    /// Returns the value of the token if it is a byte string.
+    pub fn as_bytes(&self) -> Option<&[u8]> {
+        match &self.repr {
+            TokenRepr::Integer(_) => None,
+            TokenRepr::Bytes(bytes) => Some(bytes),
+        }
+    }
+}
+
+/// Integer tokens are printed in decimal, byte string tokens in hex,
+/// like in `system.local` and `system.peers`.
+impl std::fmt::Display for Token {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match &self.repr {
+            TokenRepr::Integer(value) => write!(f, "{}", value),
+            TokenRepr::Bytes(bytes) => bytes.iter().try_for_each(|b| write!(f, "{:02x}", b)),
+        }
+    }
+}
+
+impl std::fmt::Debug for Token {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Token({})", self)
+    }
 }
 
 pub type Shard = u32;
@@ -36,7 +112,7 @@ pub struct Sharder {
 impl std::str::FromStr for Token {
     type Err = std::num::ParseIntError;
     fn from_str(s: &str) -> Result<Token, std::num::ParseIntError> {
-        Ok(Token { value: s.parse()? })
+        Ok(Token::from_i128(s.parse()?))
     }
 }
 
@@ -62,12 +138,48 @@ impl Sharder {
         }
     }
 
+    /// Returns the shard owning the token.
+    ///
+    /// Scylla shards only 64-bit tokens. Tokens of other partitioners belong to shard 0.
     pub fn shard_of(&self, token: Token) -> Shard {
-        let mut biased_token = (token.value as u64).wrapping_add(1u64 << 63);
+        let value = match token.as_i64() {
+            Some(value) => value,
+            None => return 0,
+        };
+        let mut biased_token = (value as u64).wrapping_add(1u64 << 63);
         biased_token <<= self.msb_ignore;
         (((biased_token as u128) * (self.nr_shards.get() as u128)) >> 64) as Shard
     }
 
+    /// Returns the first token greater than `token` which belongs to a different shard,
+    /// or `None` if all the tokens up to the end of the ring belong to the shard of `token`.
+    /// Tokens which aren't 64-bit all belong to the same shard.
+    pub(crate) fn next_shard_boundary(&self, token: &Token) -> Option<Token> {
+        let value = token.as_i64()?;
+        if self.nr_shards.get() == 1 {
+            return None;
+        }
+        let biased_token = (value as u64).wrapping_add(1u64 << 63) as u128;
+        let nr_shards = self.nr_shards.get() as u128;
+
+        // Ignoring the most significant bits splits the ring into 2^msb_ignore cycles,
+        // each of them going through all the shards in order.
+        let cycle_len = 1u128 << (64 - self.msb_ignore as u32);
+        let cycle_start = biased_token - biased_token % cycle_len;
+        let shard = (biased_token % cycle_len) * nr_shards / cycle_len;
+
+        // The first offset in the cycle belonging to the next shard, rounded up.
+        let next_shard_offset = ((shard + 1) * cycle_len - 1) / nr_shards + 1;
+        let next_biased_token = cycle_start + next_shard_offset;
+        if next_biased_token >> 64 != 0 {
+            return None;
+        }
+
+        Some(Token::new(
+            (next_biased_token as u64).wrapping_sub(1u64 << 63) as i64,
+        ))
+    }
+
     /// If we connect to Scylla using Scylla's shard aware port, then Scylla assigns a shard to the
     /// connection based on the source port. This calculates the assigned shard.
     pub fn shard_of_source_port(&self, source_port: u16) -> Shard {
@@ -151,18 +263,48 @@ mod tests {
     fn test_shard_of() {
         /* Test values taken from the gocql driver.  */
         let sharder = Sharder::new(ShardCount::new(4).unwrap(), 12);
-        assert_eq!(
-            sharder.shard_of(Token {
-                value: -9219783007514621794
-            }),
-            3
-        );
-        assert_eq!(
-            sharder.shard_of(Token {
-                value: 9222582454147032830
-            }),
-            3
-        );
+        assert_eq!(sharder.shard_of(Token::new(-9219783007514621794)), 3);
+        assert_eq!(sharder.shard_of(Token::new(9222582454147032830)), 3);
+
+        // Only 64-bit tokens are sharded
+        assert_eq!(sharder.shard_of(Token::from_i128(i128::MAX)), 0);
+        assert_eq!(sharder.shard_of(Token::from_bytes(&b"key"[..])), 0);
+    }
+
+    #[test]
+    fn test_next_shard_boundary() {
+        for (nr_shards, msb_ignore) in [(1, 0), (3, 0), (4, 12), (7, 12)] {
+            let sharder = Sharder::new(ShardCount::new(nr_shards).unwrap(), msb_ignore);
+            for value in [
+                i64::MIN,
+                -9219783007514621794,
+                -1,
+                0,
+                12345678901234,
+                9222582454147032830,
+            ] {
+                let token = Token::new(value);
+                match sharder.next_shard_boundary(&token) {
+                    Some(boundary) => {
+                        let last_in_shard = Token::new(boundary.as_i64().unwrap() - 1);
+                        assert!(boundary > token);
+                        assert_eq!(
+                            sharder.shard_of(last_in_shard),
+                            sharder.shard_of(token.clone())
+                        );
+                        assert_ne!(sharder.shard_of(boundary), sharder.shard_of(token));
+                    }
+                    None => {
+                        let max = Token::new(i64::MAX);
+                        assert_eq!(sharder.shard_of(max), sharder.shard_of(token));
+                    }
+                }
+            }
+        }
+
+        // A single shard owns the whole ring.
+        let sharder = Sharder::new(ShardCount::new(1).unwrap(), 0);
+        assert_eq!(sharder.next_shard_boundary(&Token::new(0)), None);
     }
 
     #[test]
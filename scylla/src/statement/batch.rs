@@ -5,8 +5,8 @@ use crate::retry_policy::RetryPolicy;
 use crate::statement::{prepared_statement::PreparedStatement, query::Query};
 use crate::transport::execution_profile::ExecutionProfileHandle;
 
-use super::StatementConfig;
 pub use super::{Consistency, SerialConsistency};
+use super::{NodeTarget, StatementConfig};
 pub use crate::frame::request::batch::BatchType;
 
 /// CQL batch statement.
@@ -144,6 +144,17 @@ impl Batch {
     pub fn get_execution_profile_handle(&self) -> Option<&ExecutionProfileHandle> {
         self.config.execution_profile_handle.as_ref()
     }
+
+    /// Sends the batch directly to the given node (and optionally shard),
+    /// bypassing the load balancing policy. See [`NodeTarget`].
+    pub fn set_node_target(&mut self, node_target: Option<NodeTarget>) {
+        self.config.node_target = node_target;
+    }
+
+    /// Gets the node the batch is sent to directly, if any.
+    pub fn get_node_target(&self) -> Option<&NodeTarget> {
+        self.config.node_target.as_ref()
+    }
 }
 
 impl Default for Batch {
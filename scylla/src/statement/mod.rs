@@ -1,7 +1,9 @@
 use std::{sync::Arc, time::Duration};
 
 use crate::history::HistoryListener;
+use crate::routing::Shard;
 use crate::transport::execution_profile::ExecutionProfileHandle;
+use crate::transport::Node;
 
 pub mod batch;
 pub mod prepared_statement;
@@ -23,6 +25,8 @@ pub struct StatementConfig {
     pub history_listener: Option<Arc<dyn HistoryListener>>,
 
     pub execution_profile_handle: Option<ExecutionProfileHandle>,
+
+    pub node_target: Option<NodeTarget>,
 }
 
 #[allow(clippy::derivable_impls)]
@@ -37,6 +41,7 @@ impl Default for StatementConfig {
             request_timeout: None,
             history_listener: None,
             execution_profile_handle: None,
+            node_target: None,
         }
     }
 }
@@ -46,11 +51,44 @@ impl Clone for StatementConfig {
         Self {
             history_listener: self.history_listener.clone(),
             execution_profile_handle: self.execution_profile_handle.clone(),
+            node_target: self.node_target.clone(),
             ..*self
         }
     }
 }
 
+/// A node, and optionally one of its shards, to which a statement is sent directly.
+///
+/// Statements with a target bypass the load balancing policy and use only the connections
+/// of the target node. Retries allowed by the retry policy happen on the same node;
+/// a decision to retry on the next node ends the execution, as there is no other node to try.
+/// Paging works as usual, all pages are fetched from the target node.
+///
+/// Useful for querying node-local tables such as `system.local` or virtual tables.
+#[derive(Debug, Clone)]
+pub struct NodeTarget {
+    pub node: Arc<Node>,
+    /// If set, the statement is sent only on a connection to this shard.
+    /// If the node has no working connection to the shard, the execution fails.
+    pub shard: Option<Shard>,
+}
+
+impl NodeTarget {
+    /// Targets the given node, with the shard chosen as for any other statement.
+    pub fn node(node: Arc<Node>) -> Self {
+        Self { node, shard: None }
+    }
+
+    /// Targets the given shard of the given node.
+    /// Nodes which aren't sharded (e.g. Cassandra nodes) have only shard 0.
+    pub fn shard(node: Arc<Node>, shard: Shard) -> Self {
+        Self {
+            node,
+            shard: Some(shard),
+        }
+    }
+}
+
 impl StatementConfig {
     /// Determines the consistency of a query
     #[must_use]
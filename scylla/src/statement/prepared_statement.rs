@@ -6,7 +6,7 @@ use std::time::Duration;
 use thiserror::Error;
 use uuid::Uuid;
 
-use super::StatementConfig;
+use super::{NodeTarget, StatementConfig};
 use crate::frame::response::result::PreparedMetadata;
 use crate::frame::types::{Consistency, SerialConsistency};
 use crate::frame::value::SerializedValues;
@@ -328,6 +328,17 @@ impl PreparedStatement {
     pub fn get_execution_profile_handle(&self) -> Option<&ExecutionProfileHandle> {
         self.config.execution_profile_handle.as_ref()
     }
+
+    /// Sends the statement directly to the given node (and optionally shard),
+    /// bypassing the load balancing policy. See [`NodeTarget`].
+    pub fn set_node_target(&mut self, node_target: Option<NodeTarget>) {
+        self.config.node_target = node_target;
+    }
+
+    /// Gets the node the statement is sent to directly, if any.
+    pub fn get_node_target(&self) -> Option<&NodeTarget> {
+        self.config.node_target.as_ref()
+    }
 }
 
 #[derive(Clone, Debug, Error, PartialEq, Eq, PartialOrd, Ord)]
@@ -1,8 +1,12 @@
-use super::StatementConfig;
+use super::{NodeTarget, StatementConfig};
 use crate::frame::types::{Consistency, SerialConsistency};
+use crate::frame::value::{SerializeValuesError, SerializedValues, ValueList};
 use crate::history::HistoryListener;
 use crate::retry_policy::RetryPolicy;
+use crate::routing::Token;
 use crate::transport::execution_profile::ExecutionProfileHandle;
+use crate::transport::load_balancing::RoutingInfo;
+use bytes::Bytes;
 use std::sync::Arc;
 use std::time::Duration;
 
@@ -18,6 +22,81 @@ pub struct Query {
 
     pub contents: String,
     page_size: Option<i32>,
+    routing_hint: Option<RoutingHint>,
+}
+
+/// Tells the driver which partition an unprepared query accesses, so that
+/// the query can be routed to its replicas (and to the shard owning it).
+///
+/// Unlike prepared statements, simple queries don't carry information about
+/// their partition key, so they are not token-aware unless given a hint.
+/// See [`Query::set_routing_hint`].
+#[derive(Debug, Clone)]
+#[non_exhaustive]
+pub enum RoutingHint {
+    /// An explicitly computed token of the partition.
+    Token { keyspace: String, token: Token },
+    /// The serialized partition key, in the format used by the database: the value of
+    /// the only partition key column, or the components of a composite partition key,
+    /// each prefixed with its 2-byte length and followed by a zero byte.
+    /// It is hashed with the partitioner of the table.
+    RoutingKey {
+        keyspace: String,
+        table: String,
+        routing_key: Bytes,
+    },
+    /// Values of the partition key columns, in the order of the columns in the partition key.
+    /// They are serialized and hashed with the partitioner of the table.
+    PartitionKey {
+        keyspace: String,
+        table: String,
+        values: SerializedValues,
+    },
+}
+
+impl RoutingHint {
+    /// Routes the query according to the given token of a partition of `keyspace`.
+    pub fn token(keyspace: impl Into<String>, token: Token) -> Self {
+        RoutingHint::Token {
+            keyspace: keyspace.into(),
+            token,
+        }
+    }
+
+    /// Routes the query according to the serialized partition key of a row of the table.
+    pub fn routing_key(
+        keyspace: impl Into<String>,
+        table: impl Into<String>,
+        routing_key: impl Into<Bytes>,
+    ) -> Self {
+        RoutingHint::RoutingKey {
+            keyspace: keyspace.into(),
+            table: table.into(),
+            routing_key: routing_key.into(),
+        }
+    }
+
+    /// Routes the query according to the values of the partition key columns of the table.
+    pub fn partition_key(
+        keyspace: impl Into<String>,
+        table: impl Into<String>,
+        values: impl ValueList,
+    ) -> Result<Self, SerializeValuesError> {
+        Ok(RoutingHint::PartitionKey {
+            keyspace: keyspace.into(),
+            table: table.into(),
+            values: values.serialized()?.into_owned(),
+        })
+    }
+
+    /// Returns the keyspace of the partition accessed by the query.
+    pub fn keyspace(&self) -> &str {
+        match self {
+            RoutingHint::Token { keyspace, .. }
+            | RoutingHint::RoutingKey { keyspace, .. }
+            | RoutingHint::PartitionKey { keyspace, .. } => keyspace,
+        }
+    }
 }
 
 impl Query {
@@ -27,6 +106,7 @@ impl Query {
             contents: query_text.into(),
             retry_policy: None,
             page_size: None,
+            routing_hint: None,
             config: Default::default(),
         }
     }
@@ -160,6 +240,50 @@ impl Query {
     pub fn get_execution_profile_handle(&self) -> Option<&ExecutionProfileHandle> {
         self.config.execution_profile_handle.as_ref()
     }
+
+    /// Sends the query directly to the given node (and optionally shard),
+    /// bypassing the load balancing policy. See [`NodeTarget`].
+    pub fn set_node_target(&mut self, node_target: Option<NodeTarget>) {
+        self.config.node_target = node_target;
+    }
+
+    /// Gets the node the query is sent to directly, if any.
+    pub fn get_node_target(&self) -> Option<&NodeTarget> {
+        self.config.node_target.as_ref()
+    }
+
+    /// Sets the hint about the partition accessed by the query, which makes the query
+    /// token-aware: it is sent to the replicas of the partition, on the shard owning it.
+    ///
+    /// The hint is trusted, the driver doesn't check if it matches the query text.
+    /// A wrong hint doesn't break the query, but sends it to nodes which have to
+    /// forward it to the replicas.
+    pub fn set_routing_hint(&mut self, routing_hint: Option<RoutingHint>) {
+        self.routing_hint = routing_hint;
+    }
+
+    /// Gets the hint about the partition accessed by the query, if set.
+    pub fn get_routing_hint(&self) -> Option<&RoutingHint> {
+        self.routing_hint.as_ref()
+    }
+
+    /// Routing information of the query, given the token computed from its routing hint.
+    pub(crate) fn routing_info(
+        &self,
+        token: Option<Token>,
+        consistency: Consistency,
+    ) -> RoutingInfo<'_> {
+        match (token, self.routing_hint.as_ref()) {
+            (Some(token), Some(hint)) => RoutingInfo {
+                consistency,
+                serial_consistency: self.get_serial_consistency(),
+                token: Some(token),
+                keyspace: Some(hint.keyspace()),
+                is_confirmed_lwt: false,
+            },
+            _ => RoutingInfo::default(),
+        }
+    }
 }
 
 impl From<String> for Query {
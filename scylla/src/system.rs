@@ -0,0 +1,303 @@
+//! Typed access to system tables\
+//! [`SystemTables`] reads commonly needed system and virtual tables into structs.
+//! Tables which describe the node serving the query, like `system.local` or `system.clients`,
+//! are read from the node passed by the caller, see [`NodeTarget`].
+
+use std::net::IpAddr;
+use std::sync::Arc;
+
+use chrono::{DateTime, Utc};
+use uuid::Uuid;
+
+use crate::query::Query;
+use crate::statement::NodeTarget;
+use crate::transport::errors::QueryError;
+use crate::transport::iterator::TypedRowIterator;
+use crate::transport::Node;
+use crate::FromRow;
+use crate::Session;
+
+/// A row of `system.local` - information about the node serving the query.
+#[derive(FromRow, Debug, Clone, PartialEq, Eq)]
+#[scylla_crate = "crate"]
+pub struct LocalNodeInfo {
+    pub host_id: Option<Uuid>,
+    pub cluster_name: Option<String>,
+    pub data_center: Option<String>,
+    pub rack: Option<String>,
+    pub release_version: Option<String>,
+    pub cql_version: Option<String>,
+    pub native_protocol_version: Option<String>,
+    pub partitioner: Option<String>,
+    pub broadcast_address: Option<IpAddr>,
+    pub listen_address: Option<IpAddr>,
+    pub rpc_address: Option<IpAddr>,
+    pub schema_version: Option<Uuid>,
+    pub tokens: Option<Vec<String>>,
+}
+
+/// A row of `system.peers` - the queried node's view of another node.
+#[derive(FromRow, Debug, Clone, PartialEq, Eq)]
+#[scylla_crate = "crate"]
+pub struct PeerInfo {
+    pub peer: IpAddr,
+    pub host_id: Option<Uuid>,
+    pub data_center: Option<String>,
+    pub rack: Option<String>,
+    pub release_version: Option<String>,
+    pub preferred_ip: Option<IpAddr>,
+    pub rpc_address: Option<IpAddr>,
+    pub schema_version: Option<Uuid>,
+    pub tokens: Option<Vec<String>>,
+}
+
+/// A row of `system.peers_v2` - the queried node's view of another node, with ports.
+/// The table exists in Cassandra 4.0 and newer, Scylla doesn't have it.
+#[derive(FromRow, Debug, Clone, PartialEq, Eq)]
+#[scylla_crate = "crate"]
+pub struct PeerV2Info {
+    pub peer: IpAddr,
+    pub peer_port: i32,
+    pub host_id: Option<Uuid>,
+    pub data_center: Option<String>,
+    pub rack: Option<String>,
+    pub release_version: Option<String>,
+    pub native_address: Option<IpAddr>,
+    pub native_port: Option<i32>,
+    pub preferred_ip: Option<IpAddr>,
+    pub preferred_port: Option<i32>,
+    pub schema_version: Option<Uuid>,
+    pub tokens: Option<Vec<String>>,
+}
+
+/// A row of `system.size_estimates` - estimated size of a table in a token range
+/// owned by the queried node.
+#[derive(FromRow, Debug, Clone, PartialEq, Eq)]
+#[scylla_crate = "crate"]
+pub struct SizeEstimate {
+    pub keyspace_name: String,
+    pub table_name: String,
+    /// Start of the token range (exclusive), as stored by the database
+    pub range_start: String,
+    /// End of the token range (inclusive), as stored by the database
+    pub range_end: String,
+    pub mean_partition_size: Option<i64>,
+    pub partitions_count: Option<i64>,
+}
+
+/// A row of Scylla's `system.large_partitions` - a partition found to exceed
+/// the configured size threshold in an sstable of the queried node.
+#[derive(FromRow, Debug, Clone, PartialEq, Eq)]
+#[scylla_crate = "crate"]
+pub struct LargePartition {
+    pub keyspace_name: String,
+    pub table_name: String,
+    pub sstable_name: String,
+    /// Size of the partition in bytes
+    pub partition_size: i64,
+    pub partition_key: String,
+    pub compaction_time: Option<DateTime<Utc>>,
+}
+
+/// A row of Scylla's `system.clients` - a client connected to the queried node.
+#[derive(FromRow, Debug, Clone, PartialEq, Eq)]
+#[scylla_crate = "crate"]
+pub struct ClientInfo {
+    pub address: IpAddr,
+    pub port: i32,
+    pub client_type: String,
+    pub shard_id: Option<i32>,
+    pub connection_stage: Option<String>,
+    pub driver_name: Option<String>,
+    pub driver_version: Option<String>,
+    pub hostname: Option<String>,
+    pub protocol_version: Option<i32>,
+    pub ssl_enabled: Option<bool>,
+    pub ssl_protocol: Option<String>,
+    pub ssl_cipher_suite: Option<String>,
+    pub username: Option<String>,
+}
+
+/// A row of Scylla's `system_distributed.view_build_status` -
+/// progress of building a materialized view on a node.
+#[derive(FromRow, Debug, Clone, PartialEq, Eq)]
+#[scylla_crate = "crate"]
+pub struct ViewBuildStatus {
+    pub keyspace_name: String,
+    pub view_name: String,
+    pub host_id: Uuid,
+    pub status: Option<String>,
+}
+
+/// A row of Scylla's `system_distributed.cdc_generation_timestamps` -
+/// a CDC generation and the time it was superseded, if it was.
+#[derive(FromRow, Debug, Clone, PartialEq, Eq)]
+#[scylla_crate = "crate"]
+pub struct CdcGenerationTimestamp {
+    pub time: DateTime<Utc>,
+    pub expired: Option<DateTime<Utc>>,
+}
+
+const LOCAL_QUERY_STR: &str = "SELECT host_id, cluster_name, data_center, rack, \
+    release_version, cql_version, native_protocol_version, partitioner, broadcast_address, \
+    listen_address, rpc_address, schema_version, tokens FROM system.local WHERE key = 'local'";
+
+const PEERS_QUERY_STR: &str = "SELECT peer, host_id, data_center, rack, release_version, \
+    preferred_ip, rpc_address, schema_version, tokens FROM system.peers";
+
+const PEERS_V2_QUERY_STR: &str = "SELECT peer, peer_port, host_id, data_center, rack, \
+    release_version, native_address, native_port, preferred_ip, preferred_port, \
+    schema_version, tokens FROM system.peers_v2";
+
+const SIZE_ESTIMATES_QUERY_STR: &str = "SELECT keyspace_name, table_name, range_start, \
+    range_end, mean_partition_size, partitions_count FROM system.size_estimates \
+    WHERE keyspace_name = ? AND table_name = ?";
+
+const LARGE_PARTITIONS_QUERY_STR: &str = "SELECT keyspace_name, table_name, sstable_name, \
+    partition_size, partition_key, compaction_time FROM system.large_partitions";
+
+const CLIENTS_QUERY_STR: &str = "SELECT address, port, client_type, shard_id, \
+    connection_stage, driver_name, driver_version, hostname, protocol_version, ssl_enabled, \
+    ssl_protocol, ssl_cipher_suite, username FROM system.clients";
+
+const VIEW_BUILD_STATUS_QUERY_STR: &str = "SELECT keyspace_name, view_name, host_id, status \
+    FROM system_distributed.view_build_status";
+
+const CDC_GENERATION_TIMESTAMPS_QUERY_STR: &str = "SELECT time, expired \
+    FROM system_distributed.cdc_generation_timestamps WHERE key = 'timestamps'";
+
+// Page size used for reading system tables
+const SYSTEM_TABLES_PAGE_SIZE: i32 = 1024;
+
+/// Reads system tables into typed structs, see [`Session::system_tables`].
+///
+/// Node-local tables are read from the given node directly, bypassing the load balancing policy.
+/// The remaining tables are queried like any other statement.
+///
+/// # Example
+/// ```rust
+/// # use scylla::Session;
+/// # use std::error::Error;
+/// # async fn check_only_compiles(session: &Session) -> Result<(), Box<dyn Error>> {
+/// use futures::TryStreamExt;
+///
+/// let cluster_data = session.get_cluster_data();
+/// for node in cluster_data.get_nodes_info() {
+///     let local = session.system_tables().local(node).await?;
+///     println!("{}: {:?}", node.address, local.release_version);
+///
+///     let mut clients = session.system_tables().clients(node).await?;
+///     while let Some(client) = clients.try_next().await? {
+///         println!("  {}:{} {:?}", client.address, client.port, client.driver_name);
+///     }
+/// }
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Clone, Copy)]
+pub struct SystemTables<'a> {
+    session: &'a Session,
+}
+
+impl<'a> SystemTables<'a> {
+    pub(crate) fn new(session: &'a Session) -> Self {
+        Self { session }
+    }
+
+    /// Reads `system.local` of the given node.
+    pub async fn local(&self, node: &Arc<Node>) -> Result<LocalNodeInfo, QueryError> {
+        let query = Self::node_local_query(LOCAL_QUERY_STR, node);
+        self.session
+            .query(query, &[])
+            .await?
+            .single_row_typed::<LocalNodeInfo>()
+            .map_err(|err| {
+                QueryError::InvalidMessage(format!("Failed to read system.local: {}", err))
+            })
+    }
+
+    /// Reads `system.peers` of the given node.
+    pub async fn peers(&self, node: &Arc<Node>) -> Result<TypedRowIterator<PeerInfo>, QueryError> {
+        self.read_node_local(PEERS_QUERY_STR, node).await
+    }
+
+    /// Reads `system.peers_v2` of the given node. Available in Cassandra 4.0 and newer.
+    pub async fn peers_v2(
+        &self,
+        node: &Arc<Node>,
+    ) -> Result<TypedRowIterator<PeerV2Info>, QueryError> {
+        self.read_node_local(PEERS_V2_QUERY_STR, node).await
+    }
+
+    /// Reads the size estimates of the given table from `system.size_estimates` of the given node.
+    /// The estimates cover only the token ranges owned by the node.
+    pub async fn size_estimates(
+        &self,
+        node: &Arc<Node>,
+        keyspace: &str,
+        table: &str,
+    ) -> Result<TypedRowIterator<SizeEstimate>, QueryError> {
+        let query = Self::node_local_query(SIZE_ESTIMATES_QUERY_STR, node);
+        Ok(self
+            .session
+            .query_iter(query, (keyspace, table))
+            .await?
+            .into_typed())
+    }
+
+    /// Reads Scylla's `system.large_partitions` of the given node.
+    pub async fn large_partitions(
+        &self,
+        node: &Arc<Node>,
+    ) -> Result<TypedRowIterator<LargePartition>, QueryError> {
+        self.read_node_local(LARGE_PARTITIONS_QUERY_STR, node).await
+    }
+
+    /// Reads Scylla's `system.clients` of the given node.
+    pub async fn clients(
+        &self,
+        node: &Arc<Node>,
+    ) -> Result<TypedRowIterator<ClientInfo>, QueryError> {
+        self.read_node_local(CLIENTS_QUERY_STR, node).await
+    }
+
+    /// Reads Scylla's `system_distributed.view_build_status`.
+    pub async fn view_build_statuses(
+        &self,
+    ) -> Result<TypedRowIterator<ViewBuildStatus>, QueryError> {
+        self.read_distributed(VIEW_BUILD_STATUS_QUERY_STR).await
+    }
+
+    /// Reads Scylla's `system_distributed.cdc_generation_timestamps`.
+    pub async fn cdc_generation_timestamps(
+        &self,
+    ) -> Result<TypedRowIterator<CdcGenerationTimestamp>, QueryError> {
+        self.read_distributed(CDC_GENERATION_TIMESTAMPS_QUERY_STR)
+            .await
+    }
+
+    fn node_local_query(contents: &str, node: &Arc<Node>) -> Query {
+        let mut query = Query::new(contents);
+        query.set_page_size(SYSTEM_TABLES_PAGE_SIZE);
+        query.set_node_target(Some(NodeTarget::node(node.clone())));
+        query
+    }
+
+    async fn read_node_local<RowT: FromRow>(
+        &self,
+        contents: &str,
+        node: &Arc<Node>,
+    ) -> Result<TypedRowIterator<RowT>, QueryError> {
+        let query = Self::node_local_query(contents, node);
+        Ok(self.session.query_iter(query, &[]).await?.into_typed())
+    }
+
+    async fn read_distributed<RowT: FromRow>(
+        &self,
+        contents: &str,
+    ) -> Result<TypedRowIterator<RowT>, QueryError> {
+        let mut query = Query::new(contents);
+        query.set_page_size(SYSTEM_TABLES_PAGE_SIZE);
+        Ok(self.session.query_iter(query, &[]).await?.into_typed())
+    }
+}
@@ -8,6 +8,7 @@ use crate::transport::{
     connection_pool::PoolConfig,
     errors::QueryError,
     node::Node,
+    partition_routing::{self, PartitionKeyValues, PartitionRouting, PartitionRoutingError},
     partitioner::PartitionerName,
     topology::{Keyspace, Metadata, MetadataReader},
 };
@@ -33,9 +34,11 @@ use super::node::NodeAddr;
 pub struct ContactPoint {
     pub address: SocketAddr,
     pub datacenter: Option<String>,
+    /// The hostname which `address` was resolved from, if the contact point was given as a hostname.
+    pub hostname: Option<String>,
 }
 
-use super::locator::ReplicaLocator;
+use super::locator::{ReplicaLocator, ReplicationStrategy};
 use super::topology::Strategy;
 
 /// Cluster manages up to date information and connections to database nodes.
@@ -74,6 +77,8 @@ pub struct ClusterData {
     pub(crate) known_peers: HashMap<Uuid, Arc<Node>>, // Invariant: nonempty after Cluster::new()
     pub(crate) keyspaces: HashMap<String, Keyspace>,
     pub(crate) locator: ReplicaLocator,
+    // Used for tables which don't specify their own partitioner
+    pub(crate) partitioner: PartitionerName,
 }
 
 /// Enables printing [ClusterData] struct in a neat way, skipping the clutter involved by
@@ -123,6 +128,12 @@ struct ClusterWorker {
     // The host filter determines towards which nodes we should open
     // connections
     host_filter: Option<Arc<dyn HostFilter>>,
+
+    // How often contact points given as hostnames should be resolved again
+    hostname_resolution_interval: Option<Duration>,
+
+    // Custom replication strategies, passed to the replica locator on each refresh
+    replication_strategies: HashMap<String, Arc<dyn ReplicationStrategy>>,
 }
 
 #[derive(Debug)]
@@ -143,6 +154,8 @@ impl Cluster {
         keyspaces_to_fetch: Vec<String>,
         fetch_schema_metadata: bool,
         host_filter: Option<Arc<dyn HostFilter>>,
+        hostname_resolution_interval: Option<Duration>,
+        replication_strategies: HashMap<String, Arc<dyn ReplicationStrategy>>,
     ) -> Result<Cluster, QueryError> {
         let (refresh_sender, refresh_receiver) = tokio::sync::mpsc::channel(32);
         let (use_keyspace_sender, use_keyspace_receiver) = tokio::sync::mpsc::channel(32);
@@ -152,6 +165,7 @@ impl Cluster {
             initial_peers,
             pool_config.connection_config.clone(),
             pool_config.keepalive_interval,
+            pool_config.reconnection_policy.clone(),
             server_events_sender,
             keyspaces_to_fetch,
             fetch_schema_metadata,
@@ -165,6 +179,7 @@ impl Cluster {
             &HashMap::new(),
             &None,
             host_filter.as_deref(),
+            &replication_strategies,
         )
         .await;
         cluster_data.wait_until_all_pools_are_initialized().await;
@@ -184,6 +199,8 @@ impl Cluster {
             used_keyspace: None,
 
             host_filter,
+            hostname_resolution_interval,
+            replication_strategies,
         };
 
         let (fut, worker_handle) = worker.work().remote_handle();
@@ -203,6 +220,12 @@ impl Cluster {
         self.data.load_full()
     }
 
+    /// Returns a handle which always yields the most recent cluster data,
+    /// for long-running operations which must not stick to a stale topology.
+    pub(crate) fn data_handle(&self) -> Arc<ArcSwap<ClusterData>> {
+        self.data.clone()
+    }
+
     pub async fn refresh_metadata(&self) -> Result<(), QueryError> {
         let (response_sender, response_receiver) = tokio::sync::oneshot::channel();
 
@@ -289,6 +312,7 @@ impl ClusterData {
         known_peers: &HashMap<Uuid, Arc<Node>>,
         used_keyspace: &Option<VerifiedKeyspaceName>,
         host_filter: Option<&dyn HostFilter>,
+        replication_strategies: &HashMap<String, Arc<dyn ReplicationStrategy>>,
     ) -> Self {
         // Create new updated known_peers and ring
         let mut new_known_peers: HashMap<Uuid, Arc<Node>> =
@@ -298,11 +322,20 @@ impl ClusterData {
         let mut all_nodes: Vec<Arc<Node>> = Vec::with_capacity(metadata.peers.len());
 
         for peer in metadata.peers {
+            let is_enabled = host_filter.map_or(true, |f| f.accept(&peer));
+
             // Take existing Arc<Node> if possible, otherwise create new one
             // Changing rack/datacenter but not ip address seems improbable
-            // so we can just create new node and connections then
+            // so we can just create new node and connections then.
+            // The same happens if the host filter changed its decision about the node:
+            // the new node opens a pool if it became enabled, and dropping the old one
+            // closes its pool if it became disabled.
             let node: Arc<Node> = match known_peers.get(&peer.host_id) {
-                Some(node) if node.datacenter == peer.datacenter && node.rack == peer.rack => {
+                Some(node)
+                    if node.datacenter == peer.datacenter
+                        && node.rack == peer.rack
+                        && node.is_enabled() == is_enabled =>
+                {
                     if node.address == peer.address {
                         node.clone()
                     } else {
@@ -310,17 +343,15 @@ impl ClusterData {
                         Arc::new(Node::inherit_with_ip_changed(node, peer.to_peer_endpoint()))
                     }
                 }
-                _ => {
-                    let is_enabled = host_filter.map_or(true, |f| f.accept(&peer));
-                    Arc::new(Node::new(
-                        peer.to_peer_endpoint(),
-                        pool_config.clone(),
-                        used_keyspace.clone(),
-                        is_enabled,
-                    ))
-                }
+                _ => Arc::new(Node::new(
+                    peer.to_peer_endpoint(),
+                    pool_config.clone(),
+                    used_keyspace.clone(),
+                    is_enabled,
+                )),
             };
 
+            node.update_metadata(&peer);
             new_known_peers.insert(peer.host_id, node.clone());
 
             if let Some(dc) = &node.datacenter {
@@ -351,16 +382,28 @@ impl ClusterData {
             .map(|ks| ks.strategy.clone())
             .collect();
 
+        let replication_strategies = replication_strategies.clone();
         let locator = tokio::task::spawn_blocking(move || {
-            ReplicaLocator::new(ring.into_iter(), keyspace_strategies.iter())
+            ReplicaLocator::with_custom_strategies(
+                ring.into_iter(),
+                keyspace_strategies.iter(),
+                replication_strategies,
+            )
         })
         .await
         .unwrap();
 
+        let partitioner = metadata
+            .partitioner
+            .as_deref()
+            .and_then(PartitionerName::from_str)
+            .unwrap_or_default();
+
         ClusterData {
             known_peers: new_known_peers,
             keyspaces: metadata.keyspaces,
             locator,
+            partitioner,
         }
     }
 
@@ -402,13 +445,7 @@ impl ClusterData {
         table: &str,
         partition_key: impl ValueList,
     ) -> Result<Token, BadQuery> {
-        let partitioner = self
-            .keyspaces
-            .get(keyspace)
-            .and_then(|k| k.tables.get(table))
-            .and_then(|t| t.partitioner.as_deref())
-            .and_then(PartitionerName::from_str)
-            .unwrap_or_default();
+        let partitioner = self.table_partitioner(keyspace, table);
         let serialized_values = partition_key.serialized()?;
         // Null values are skipped in computation; null values in partition key are unsound,
         // but it is consistent with computation of prepared statements token.
@@ -436,6 +473,41 @@ impl ClusterData {
         Ok(partitioner.hash(&serialized_pk.unwrap_or_default()))
     }
 
+    /// Compute token of a table partition key already serialized into a routing key,
+    /// i.e. in the format returned by [`PreparedStatement::compute_partition_key`](crate::prepared_statement::PreparedStatement::compute_partition_key).
+    pub fn compute_token_from_routing_key(
+        &self,
+        keyspace: &str,
+        table: &str,
+        routing_key: &[u8],
+    ) -> Token {
+        self.table_partitioner(keyspace, table).hash(routing_key)
+    }
+
+    /// Compute the token, the replicas and the shards on them owning a table partition,
+    /// given the values of its partition key columns by column name.
+    ///
+    /// Unlike [`compute_token`](Self::compute_token), the values are validated against the
+    /// partition key and the column types of the table in the schema metadata,
+    /// so the metadata of the keyspace has to be fetched.
+    pub fn compute_partition_routing(
+        &self,
+        keyspace: &str,
+        table: &str,
+        values: &(impl PartitionKeyValues + ?Sized),
+    ) -> Result<PartitionRouting, PartitionRoutingError> {
+        partition_routing::compute_partition_routing(self, keyspace, table, values)
+    }
+
+    pub(crate) fn table_partitioner(&self, keyspace: &str, table: &str) -> PartitionerName {
+        self.keyspaces
+            .get(keyspace)
+            .and_then(|k| k.tables.get(table))
+            .and_then(|t| t.partitioner.as_deref())
+            .and_then(PartitionerName::from_str)
+            .unwrap_or_else(|| self.partitioner.clone())
+    }
+
     /// Access to replicas owning a given token
     pub fn get_token_endpoints(&self, keyspace: &str, token: Token) -> Vec<Arc<Node>> {
         self.get_token_endpoints_iter(keyspace, token)
@@ -451,7 +523,9 @@ impl ClusterData {
         let keyspace = self.keyspaces.get(keyspace);
         let strategy = keyspace
             .map(|k| &k.strategy)
-            .unwrap_or(&Strategy::LocalStrategy);
+            .unwrap_or(&Strategy::SimpleStrategy {
+                replication_factor: 1,
+            });
         let replica_set = self
             .replica_locator()
             .replicas_for_token(token, strategy, None);
@@ -485,6 +559,10 @@ impl ClusterWorker {
         let refresh_duration = Duration::from_secs(60); // Refresh topology every 60 seconds
         let mut last_refresh_time = Instant::now();
 
+        let hostname_resolution_interval = self.hostname_resolution_interval;
+        let mut next_hostname_resolution =
+            Instant::now() + hostname_resolution_interval.unwrap_or_default();
+
         loop {
             let mut cur_request: Option<RefreshRequest> = None;
 
@@ -498,6 +576,11 @@ impl ClusterWorker {
 
             tokio::select! {
                 _ = sleep_future => {},
+                _ = tokio::time::sleep_until(next_hostname_resolution), if hostname_resolution_interval.is_some() => {
+                    self.metadata_reader.resolve_contact_points().await;
+                    next_hostname_resolution = Instant::now() + hostname_resolution_interval.unwrap_or_default();
+                    continue; // Don't go to refreshing
+                }
                 recv_res = self.refresh_channel.recv() => {
                     match recv_res {
                         Some(request) => cur_request = Some(request),
@@ -640,6 +723,7 @@ impl ClusterWorker {
                 &cluster_data.known_peers,
                 &self.used_keyspace,
                 self.host_filter.as_deref(),
+                &self.replication_strategies,
             )
             .await,
         );
@@ -657,3 +741,48 @@ impl ClusterWorker {
         self.cluster_data.store(new_cluster_data);
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::ClusterData;
+    use crate::transport::locator::test::{mock_metadata_for_token_aware_tests, KEYSPACE_SS_RF_2};
+    use crate::transport::partitioner::{
+        ByteOrderedPartitioner, Murmur3Partitioner, Partitioner, RandomPartitioner,
+    };
+    use std::collections::HashMap;
+
+    #[tokio::test]
+    async fn compute_token_uses_cluster_partitioner() {
+        for (partitioner, expected) in [
+            (None, Murmur3Partitioner::hash(b"key")),
+            (
+                Some("org.apache.cassandra.dht.RandomPartitioner"),
+                RandomPartitioner::hash(b"key"),
+            ),
+            (
+                Some("org.apache.cassandra.dht.ByteOrderedPartitioner"),
+                ByteOrderedPartitioner::hash(b"key"),
+            ),
+        ] {
+            let mut metadata = mock_metadata_for_token_aware_tests();
+            metadata.partitioner = partitioner.map(String::from);
+            let cluster_data = ClusterData::new(
+                metadata,
+                &Default::default(),
+                &HashMap::new(),
+                &None,
+                None,
+                &HashMap::new(),
+            )
+            .await;
+
+            // The mock keyspaces don't have tables, so the cluster's partitioner is used
+            assert_eq!(
+                cluster_data
+                    .compute_token(KEYSPACE_SS_RF_2, "t", ("key",))
+                    .unwrap(),
+                expected
+            );
+        }
+    }
+}
@@ -6,6 +6,8 @@ use scylla_cql::frame::response::Error;
 use scylla_cql::frame::types::SerialConsistency;
 use socket2::{SockRef, TcpKeepalive};
 use tokio::io::{split, AsyncRead, AsyncWrite, AsyncWriteExt, BufReader, BufWriter};
+#[cfg(unix)]
+use tokio::net::UnixStream;
 use tokio::net::{TcpSocket, TcpStream};
 use tokio::sync::{mpsc, oneshot};
 use tokio::time::Instant;
@@ -15,20 +17,25 @@ use uuid::Uuid;
 
 #[cfg(feature = "ssl")]
 use std::pin::Pin;
-use std::sync::atomic::AtomicU64;
+use std::sync::atomic::{AtomicU64, AtomicUsize};
 use std::time::Duration;
 #[cfg(feature = "ssl")]
 use tokio_openssl::SslStream;
 
-#[cfg(feature = "ssl")]
+#[cfg(any(feature = "ssl", feature = "rustls"))]
 pub(crate) use ssl_config::SslConfig;
+#[cfg(any(feature = "ssl", feature = "rustls"))]
+pub use ssl_config::{ReloadableTlsContext, TlsContext, TlsContextProvider};
 
 use crate::authentication::AuthenticatorProvider;
+use crate::transport::proxy::ProxyConfig;
 use scylla_cql::frame::response::authenticate::Authenticate;
 use std::collections::{BTreeSet, HashMap};
 use std::convert::TryFrom;
 use std::io::ErrorKind;
 use std::net::{IpAddr, SocketAddr};
+#[cfg(unix)]
+use std::path::PathBuf;
 use std::sync::Arc;
 use std::sync::Mutex as StdMutex;
 use std::{
@@ -41,7 +48,7 @@ use super::iterator::RowIterator;
 use super::session::AddressTranslator;
 use super::topology::{PeerEndpoint, UntranslatedEndpoint, UntranslatedPeer};
 use super::NodeAddr;
-#[cfg(feature = "cloud")]
+#[cfg(any(feature = "cloud", feature = "cloud-rustls"))]
 use crate::cloud::CloudConfig;
 
 use crate::batch::{Batch, BatchStatement};
@@ -81,6 +88,8 @@ pub struct Connection {
     _worker_handle: RemoteHandle<()>,
 
     connect_address: SocketAddr,
+    // Generation of the TLS context the connection was opened with
+    tls_generation: Option<u64>,
     config: ConnectionConfig,
     features: ConnectionFeatures,
     router_handle: Arc<RouterHandle>,
@@ -98,6 +107,18 @@ struct RouterHandle {
     // pushing values in a synchronous way (without an `.await`), which is
     // needed for pushing values in `Drop` implementations.
     orphan_notification_sender: mpsc::UnboundedSender<RequestId>,
+
+    // Stream id usage, updated by the router and read by pool introspection.
+    stream_usage: Arc<StreamUsage>,
+}
+
+// Counts of stream ids which are currently allocated on the connection.
+#[derive(Default)]
+struct StreamUsage {
+    // Stream ids whose requests still wait for a response.
+    in_flight: AtomicUsize,
+    // Stream ids whose requests were cancelled, but the response did not arrive yet.
+    orphaned: AtomicUsize,
 }
 
 impl RouterHandle {
@@ -280,52 +301,194 @@ impl NonErrorQueryResponse {
         })
     }
 }
-#[cfg(feature = "ssl")]
+#[cfg(any(feature = "ssl", feature = "rustls"))]
 mod ssl_config {
-    use openssl::{
-        error::ErrorStack,
-        ssl::{Ssl, SslContext},
-    };
-    #[cfg(feature = "cloud")]
+    #[cfg(feature = "ssl")]
+    use openssl::ssl::{Ssl, SslContext};
+    #[cfg(feature = "rustls")]
+    use std::net::IpAddr;
+    use std::sync::atomic::{AtomicU64, Ordering};
+    use std::sync::{Arc, RwLock};
+    #[cfg(any(feature = "cloud", feature = "cloud-rustls"))]
     use uuid::Uuid;
 
+    /// TLS configuration used to open encrypted connections to the database,
+    /// independent of the TLS library backing it.
+    ///
+    /// The available variants depend on the enabled features: `ssl` for OpenSSL
+    /// and `rustls` for rustls. Both features can be enabled at the same time.
+    #[derive(Clone)]
+    #[non_exhaustive]
+    pub enum TlsContext {
+        /// Connections are encrypted using OpenSSL.
+        #[cfg(feature = "ssl")]
+        OpenSsl(SslContext),
+
+        /// Connections are encrypted using rustls.
+        ///
+        /// Unless Scylla Cloud provides a server name, the certificate presented
+        /// by a node is verified against the IP address the driver connects to.
+        #[cfg(feature = "rustls")]
+        Rustls(Arc<rustls::ClientConfig>),
+    }
+
+    impl std::fmt::Debug for TlsContext {
+        fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+            match self {
+                #[cfg(feature = "ssl")]
+                TlsContext::OpenSsl(_) => write!(f, "OpenSsl"),
+                #[cfg(feature = "rustls")]
+                TlsContext::Rustls(_) => write!(f, "Rustls"),
+            }
+        }
+    }
+
+    #[cfg(feature = "ssl")]
+    impl From<SslContext> for TlsContext {
+        fn from(context: SslContext) -> Self {
+            TlsContext::OpenSsl(context)
+        }
+    }
+
+    #[cfg(feature = "rustls")]
+    impl From<Arc<rustls::ClientConfig>> for TlsContext {
+        fn from(config: Arc<rustls::ClientConfig>) -> Self {
+            TlsContext::Rustls(config)
+        }
+    }
+
+    #[cfg(feature = "rustls")]
+    impl From<rustls::ClientConfig> for TlsContext {
+        fn from(config: rustls::ClientConfig) -> Self {
+            TlsContext::Rustls(Arc::new(config))
+        }
+    }
+
+    /// Supplies the TLS configuration for new connections.
+    ///
+    /// The provider is consulted each time a connection is opened, so the returned
+    /// context may change at runtime, e.g. after client certificates are rotated.
+    /// Connections which are already open keep using the context they were opened with,
+    /// unless [`SessionConfig::tls_reload_check_interval`](crate::transport::session::SessionConfig::tls_reload_check_interval)
+    /// is set.
+    pub trait TlsContextProvider: Send + Sync {
+        /// Returns the TLS context to be used for a new connection.
+        fn tls_context(&self) -> TlsContext;
+
+        /// Identifies the context currently returned by `tls_context`.
+        /// It must be increased after the returned context changes.
+        ///
+        /// Connections opened with a context of an older generation are considered stale
+        /// and are replaced if connection recycling is enabled.
+        fn generation(&self) -> u64 {
+            0
+        }
+    }
+
+    // A fixed context serves as a provider which never changes.
+    impl TlsContextProvider for TlsContext {
+        fn tls_context(&self) -> TlsContext {
+            self.clone()
+        }
+    }
+
+    /// A [`TlsContextProvider`] whose context can be swapped at runtime.
+    ///
+    /// # Example
+    /// ```
+    /// # #[cfg(feature = "ssl")]
+    /// # fn example() -> Result<(), Box<dyn std::error::Error>> {
+    /// use openssl::ssl::{SslContextBuilder, SslMethod};
+    /// use scylla::transport::session::ReloadableTlsContext;
+    /// use std::sync::Arc;
+    ///
+    /// let provider = Arc::new(ReloadableTlsContext::new(
+    ///     SslContextBuilder::new(SslMethod::tls())?.build(),
+    /// ));
+    /// // Pass `provider.clone()` to `SessionBuilder::tls_context_provider`, and later:
+    /// provider.reload(SslContextBuilder::new(SslMethod::tls())?.build());
+    /// assert_eq!(provider.generation(), 1);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub struct ReloadableTlsContext {
+        context: RwLock<TlsContext>,
+        generation: AtomicU64,
+    }
+
+    impl ReloadableTlsContext {
+        pub fn new(context: impl Into<TlsContext>) -> Self {
+            Self {
+                context: RwLock::new(context.into()),
+                generation: AtomicU64::new(0),
+            }
+        }
+
+        /// Replaces the context used for new connections.
+        pub fn reload(&self, context: impl Into<TlsContext>) {
+            *self.context.write().unwrap() = context.into();
+            self.generation.fetch_add(1, Ordering::AcqRel);
+        }
+
+        /// Returns the number of times the context was reloaded.
+        pub fn generation(&self) -> u64 {
+            self.generation.load(Ordering::Acquire)
+        }
+    }
+
+    impl TlsContextProvider for ReloadableTlsContext {
+        fn tls_context(&self) -> TlsContext {
+            self.context.read().unwrap().clone()
+        }
+
+        fn generation(&self) -> u64 {
+            ReloadableTlsContext::generation(self)
+        }
+    }
+
     /// This struct encapsulates all Ssl-regarding configuration and helps pass it tidily through the code.
     //
     // There are 3 possible options for SslConfig, whose behaviour is somewhat subtle.
     // Option 1: No ssl configuration. Then it is None everytime.
-    // Option 2: User-provided global SslContext. Then, a SslConfig is created upon Session creation
-    // and henceforth stored in the ConnectionConfig.
+    // Option 2: User-provided global TlsContext or TlsContextProvider. Then, a SslConfig is created
+    // upon Session creation and henceforth stored in the ConnectionConfig. The provider is asked
+    // for the context whenever a connection is opened.
     // Option 3: Serverless Cloud. The Option<SslConfig> remains None in ConnectionConfig until it reaches
     // NodeConnectionPool::new(). Inside that function, the field is mutated to contain SslConfig specific
     // for the particular node. (The SslConfig must be different, because SNIs differ for different nodes.)
     // Thenceforth, all connections to that node share the same SslConfig.
     #[derive(Clone)]
     pub struct SslConfig {
-        context: SslContext,
-        #[cfg(feature = "cloud")]
+        provider: Arc<dyn TlsContextProvider>,
+        #[cfg(any(feature = "cloud", feature = "cloud-rustls"))]
         sni: Option<String>,
     }
 
     impl SslConfig {
-        // Used in case when the user provided their own SslContext to be used in all connections.
-        pub fn new_with_global_context(context: SslContext) -> Self {
+        // Used in case when the user provided their own TlsContext to be used in all connections.
+        pub fn new_with_global_context(context: TlsContext) -> Self {
+            Self::new_with_provider(Arc::new(context))
+        }
+
+        // Used in case when the user provided a TlsContextProvider.
+        pub fn new_with_provider(provider: Arc<dyn TlsContextProvider>) -> Self {
             Self {
-                context,
-                #[cfg(feature = "cloud")]
+                provider,
+                #[cfg(any(feature = "cloud", feature = "cloud-rustls"))]
                 sni: None,
             }
         }
 
         // Used in case of Serverless Cloud connections.
-        #[cfg(feature = "cloud")]
+        #[cfg(any(feature = "cloud", feature = "cloud-rustls"))]
         pub(crate) fn new_for_sni(
-            context: SslContext,
+            context: TlsContext,
             domain_name: &str,
             host_id: Option<Uuid>,
         ) -> Self {
             Self {
-                context,
-                #[cfg(feature = "cloud")]
+                provider: Arc::new(context),
+                #[cfg(any(feature = "cloud", feature = "cloud-rustls"))]
                 sni: Some(if let Some(host_id) = host_id {
                     format!("{}.{}", host_id, domain_name)
                 } else {
@@ -334,16 +497,40 @@ mod ssl_config {
             }
         }
 
+        pub(crate) fn context(&self) -> TlsContext {
+            self.provider.tls_context()
+        }
+
+        pub(crate) fn generation(&self) -> u64 {
+            self.provider.generation()
+        }
+
         // Produces a new Ssl object that is able to wrap a TCP stream.
-        pub(crate) fn new_ssl(&self) -> Result<Ssl, ErrorStack> {
+        #[cfg(feature = "ssl")]
+        pub(crate) fn new_ssl(&self, context: &SslContext) -> Result<Ssl, std::io::Error> {
             #[allow(unused_mut)]
-            let mut ssl = Ssl::new(&self.context)?;
-            #[cfg(feature = "cloud")]
+            let mut ssl = Ssl::new(context)?;
+            #[cfg(any(feature = "cloud", feature = "cloud-rustls"))]
             if let Some(sni) = self.sni.as_ref() {
                 ssl.set_hostname(sni)?;
             }
             Ok(ssl)
         }
+
+        // Chooses the name which the node's certificate is verified against
+        // and which is sent as SNI.
+        #[cfg(feature = "rustls")]
+        pub(crate) fn rustls_server_name(
+            &self,
+            addr: IpAddr,
+        ) -> Result<rustls::ServerName, std::io::Error> {
+            #[cfg(any(feature = "cloud", feature = "cloud-rustls"))]
+            if let Some(sni) = self.sni.as_ref() {
+                return rustls::ServerName::try_from(sni.as_str())
+                    .map_err(|err| std::io::Error::new(std::io::ErrorKind::InvalidInput, err));
+            }
+            Ok(rustls::ServerName::IpAddress(addr))
+        }
     }
 }
 
@@ -352,13 +539,13 @@ pub struct ConnectionConfig {
     pub compression: Option<Compression>,
     pub tcp_nodelay: bool,
     pub tcp_keepalive_interval: Option<Duration>,
-    #[cfg(feature = "ssl")]
+    #[cfg(any(feature = "ssl", feature = "rustls"))]
     pub ssl_config: Option<SslConfig>,
     pub connect_timeout: std::time::Duration,
     // should be Some only in control connections,
     pub event_sender: Option<mpsc::Sender<Event>>,
     pub default_consistency: Consistency,
-    #[cfg(feature = "cloud")]
+    #[cfg(any(feature = "cloud", feature = "cloud-rustls"))]
     pub(crate) cloud_config: Option<Arc<CloudConfig>>,
     pub authenticator: Option<Arc<dyn AuthenticatorProvider>>,
     pub address_translator: Option<Arc<dyn AddressTranslator>>,
@@ -366,6 +553,75 @@ pub struct ConnectionConfig {
 
     pub keepalive_interval: Option<Duration>,
     pub keepalive_timeout: Option<Duration>,
+
+    /// If set, connections are opened to this Unix domain socket
+    /// instead of the address passed to [`Connection::new`].
+    #[cfg(unix)]
+    pub unix_socket_path: Option<PathBuf>,
+
+    /// If set, connections are tunneled through this proxy.
+    pub proxy: Option<ProxyConfig>,
+
+    /// Identification of the client sent in the STARTUP message.
+    pub client_identity: ClientIdentity,
+}
+
+/// Identification of the application, sent to the server in the STARTUP message
+/// of every connection, next to the driver's name and version.
+///
+/// Servers which expose the startup options of connected clients
+/// (e.g. Cassandra's `system_views.clients`) show these values,
+/// which helps to attribute load to particular services and deployments.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ClientIdentity {
+    /// Sent as `APPLICATION_NAME`.
+    pub application_name: Option<String>,
+    /// Sent as `APPLICATION_VERSION`.
+    pub application_version: Option<String>,
+    /// Sent as `CLIENT_ID`, identifies a particular instance of the application.
+    pub client_id: Option<Uuid>,
+    /// Additional options sent as they are. Options reserved for the driver
+    /// (see [`is_reserved_startup_option`]) are not sent.
+    pub extra_options: HashMap<String, String>,
+}
+
+// Exact keys and prefixes of STARTUP options set by the driver
+const RESERVED_STARTUP_OPTIONS: &[&str] = &["CQL_VERSION", "COMPRESSION", "CLIENT_ID"];
+const RESERVED_STARTUP_OPTION_PREFIXES: &[&str] = &["DRIVER_", "APPLICATION_", "SCYLLA_"];
+
+/// Checks whether a STARTUP option is set only by the driver, so it can't be given
+/// as an extra option: `CQL_VERSION`, `COMPRESSION`, `CLIENT_ID`, and options starting
+/// with `DRIVER_`, `APPLICATION_` or `SCYLLA_` (Scylla protocol extensions).
+///
+/// Such options affect how the driver talks to the server, e.g. the server would compress
+/// frames which the driver doesn't expect to be compressed.
+pub fn is_reserved_startup_option(key: &str) -> bool {
+    let key = key.to_ascii_uppercase();
+    RESERVED_STARTUP_OPTIONS.contains(&key.as_str())
+        || RESERVED_STARTUP_OPTION_PREFIXES
+            .iter()
+            .any(|prefix| key.starts_with(prefix))
+}
+
+impl ClientIdentity {
+    // Extra options reserved for the driver are skipped
+    fn add_startup_options(&self, options: &mut HashMap<String, String>) {
+        options.extend(
+            self.extra_options
+                .iter()
+                .filter(|(key, _)| !is_reserved_startup_option(key))
+                .map(|(key, value)| (key.clone(), value.clone())),
+        );
+        if let Some(name) = &self.application_name {
+            options.insert("APPLICATION_NAME".to_string(), name.clone());
+        }
+        if let Some(version) = &self.application_version {
+            options.insert("APPLICATION_VERSION".to_string(), version.clone());
+        }
+        if let Some(client_id) = &self.client_id {
+            options.insert("CLIENT_ID".to_string(), client_id.to_string());
+        }
+    }
 }
 
 impl Default for ConnectionConfig {
@@ -375,37 +631,70 @@ impl Default for ConnectionConfig {
             tcp_nodelay: true,
             tcp_keepalive_interval: None,
             event_sender: None,
-            #[cfg(feature = "ssl")]
+            #[cfg(any(feature = "ssl", feature = "rustls"))]
             ssl_config: None,
             connect_timeout: std::time::Duration::from_secs(5),
             default_consistency: Default::default(),
             authenticator: None,
             address_translator: None,
-            #[cfg(feature = "cloud")]
+            #[cfg(any(feature = "cloud", feature = "cloud-rustls"))]
             cloud_config: None,
             enable_write_coalescing: true,
 
             // Note: this is different than SessionConfig default values.
             keepalive_interval: None,
             keepalive_timeout: None,
+
+            #[cfg(unix)]
+            unix_socket_path: None,
+
+            proxy: None,
+            client_identity: ClientIdentity::default(),
         }
     }
 }
 
 impl ConnectionConfig {
-    #[cfg(feature = "ssl")]
+    #[cfg(any(feature = "ssl", feature = "rustls"))]
     pub fn is_ssl(&self) -> bool {
-        #[cfg(feature = "cloud")]
+        #[cfg(any(feature = "cloud", feature = "cloud-rustls"))]
         if self.cloud_config.is_some() {
             return true;
         }
         self.ssl_config.is_some()
     }
 
-    #[cfg(not(feature = "ssl"))]
+    #[cfg(not(any(feature = "ssl", feature = "rustls")))]
     pub fn is_ssl(&self) -> bool {
         false
     }
+
+    // Current generation of the TLS context provider, if TLS is configured.
+    #[cfg(any(feature = "ssl", feature = "rustls"))]
+    pub(crate) fn tls_generation(&self) -> Option<u64> {
+        self.ssl_config.as_ref().map(|c| c.generation())
+    }
+
+    #[cfg(not(any(feature = "ssl", feature = "rustls")))]
+    pub(crate) fn tls_generation(&self) -> Option<u64> {
+        None
+    }
+
+    #[cfg(unix)]
+    pub(crate) fn uses_unix_socket(&self) -> bool {
+        self.unix_socket_path.is_some()
+    }
+
+    #[cfg(not(unix))]
+    pub(crate) fn uses_unix_socket(&self) -> bool {
+        false
+    }
+}
+
+/// The address under which the node reached through a Unix domain socket is known to the driver.
+/// The socket has no IP address, but the rest of the driver identifies nodes by one.
+pub(crate) fn unix_socket_node_address() -> SocketAddr {
+    SocketAddr::new(IpAddr::V4(Ipv4Addr::LOCALHOST), 0)
 }
 
 // Used to listen for fatal error in connection
@@ -418,12 +707,31 @@ impl Connection {
         source_port: Option<u16>,
         config: ConnectionConfig,
     ) -> Result<(Self, ErrorReceiver), QueryError> {
-        let stream_connector = match source_port {
-            Some(p) => {
+        #[cfg(unix)]
+        if let Some(path) = &config.unix_socket_path {
+            let stream =
+                match tokio::time::timeout(config.connect_timeout, UnixStream::connect(path)).await
+                {
+                    Ok(stream) => stream?,
+                    Err(_) => {
+                        return Err(QueryError::TimeoutError);
+                    }
+                };
+            return Self::new_with_stream(addr, stream, config).await;
+        }
+
+        let stream_connector = match (&config.proxy, source_port) {
+            // Source port can't be chosen when connecting through a proxy
+            (Some(proxy), _) => {
+                tokio::time::timeout(config.connect_timeout, proxy.connect(addr)).await
+            }
+            (None, Some(p)) => {
                 tokio::time::timeout(config.connect_timeout, connect_with_source_port(addr, p))
                     .await
             }
-            None => tokio::time::timeout(config.connect_timeout, TcpStream::connect(addr)).await,
+            (None, None) => {
+                tokio::time::timeout(config.connect_timeout, TcpStream::connect(addr)).await
+            }
         };
         let stream = match stream_connector {
             Ok(stream) => stream?,
@@ -482,6 +790,14 @@ impl Connection {
             sf.set_tcp_keepalive(&tcp_keepalive)?;
         }
 
+        Self::new_with_stream(addr, stream, config).await
+    }
+
+    async fn new_with_stream(
+        addr: SocketAddr,
+        stream: impl AsyncRead + AsyncWrite + Unpin + Send + 'static,
+        config: ConnectionConfig,
+    ) -> Result<(Self, ErrorReceiver), QueryError> {
         // TODO: What should be the size of the channel?
         let (sender, receiver) = mpsc::channel(1024);
         let (error_sender, error_receiver) = tokio::sync::oneshot::channel();
@@ -492,8 +808,13 @@ impl Connection {
             submit_channel: sender,
             request_id_generator: AtomicU64::new(0),
             orphan_notification_sender,
+            stream_usage: Default::default(),
         });
 
+        // Read before the context is taken in `run_router`, so that a concurrent
+        // reload can only make the connection look older than it is.
+        let tls_generation = config.tls_generation();
+
         let _worker_handle = Self::run_router(
             config.clone(),
             stream,
@@ -510,6 +831,7 @@ impl Connection {
             config,
             features: Default::default(),
             connect_address: addr,
+            tls_generation,
             router_handle,
         };
 
@@ -934,33 +1256,72 @@ impl Connection {
 
     async fn run_router(
         config: ConnectionConfig,
-        stream: TcpStream,
+        stream: impl AsyncRead + AsyncWrite + Unpin + Send + 'static,
         receiver: mpsc::Receiver<Task>,
         error_sender: tokio::sync::oneshot::Sender<QueryError>,
         orphan_notification_receiver: mpsc::UnboundedReceiver<RequestId>,
         router_handle: Arc<RouterHandle>,
         node_address: IpAddr,
     ) -> Result<RemoteHandle<()>, std::io::Error> {
-        #[cfg(feature = "ssl")]
+        #[cfg(any(feature = "ssl", feature = "rustls"))]
         if let Some(ssl_config) = &config.ssl_config {
-            let ssl = ssl_config.new_ssl()?;
-            let mut stream = SslStream::new(ssl, stream)?;
-            let _pin = Pin::new(&mut stream).connect().await;
-
-            let (task, handle) = Self::router(
-                config,
-                stream,
-                receiver,
-                error_sender,
-                orphan_notification_receiver,
-                router_handle,
-                node_address,
-            )
-            .remote_handle();
-            tokio::task::spawn(task.with_current_subscriber());
-            return Ok(handle);
+            match ssl_config.context() {
+                #[cfg(feature = "ssl")]
+                TlsContext::OpenSsl(context) => {
+                    let ssl = ssl_config.new_ssl(&context)?;
+                    let mut stream = SslStream::new(ssl, stream)?;
+                    let _pin = Pin::new(&mut stream).connect().await;
+
+                    return Ok(Self::spawn_router(
+                        config,
+                        stream,
+                        receiver,
+                        error_sender,
+                        orphan_notification_receiver,
+                        router_handle,
+                        node_address,
+                    ));
+                }
+                #[cfg(feature = "rustls")]
+                TlsContext::Rustls(client_config) => {
+                    let server_name = ssl_config.rustls_server_name(node_address)?;
+                    let stream = tokio_rustls::TlsConnector::from(client_config)
+                        .connect(server_name, stream)
+                        .await?;
+
+                    return Ok(Self::spawn_router(
+                        config,
+                        stream,
+                        receiver,
+                        error_sender,
+                        orphan_notification_receiver,
+                        router_handle,
+                        node_address,
+                    ));
+                }
+            }
         }
 
+        Ok(Self::spawn_router(
+            config,
+            stream,
+            receiver,
+            error_sender,
+            orphan_notification_receiver,
+            router_handle,
+            node_address,
+        ))
+    }
+
+    fn spawn_router(
+        config: ConnectionConfig,
+        stream: impl AsyncRead + AsyncWrite + Send + 'static,
+        receiver: mpsc::Receiver<Task>,
+        error_sender: tokio::sync::oneshot::Sender<QueryError>,
+        orphan_notification_receiver: mpsc::UnboundedReceiver<RequestId>,
+        router_handle: Arc<RouterHandle>,
+        node_address: IpAddr,
+    ) -> RemoteHandle<()> {
         let (task, handle) = Self::router(
             config,
             stream,
@@ -972,7 +1333,7 @@ impl Connection {
         )
         .remote_handle();
         tokio::task::spawn(task.with_current_subscriber());
-        Ok(handle)
+        handle
     }
 
     async fn router(
@@ -997,7 +1358,8 @@ impl Connection {
         // and writer futures are run on the same fiber, and both of them
         // are carefully written in such a way that they do not hold the lock
         // across .await points. Therefore, it should not be too expensive.
-        let handler_map = StdMutex::new(ResponseHandlerMap::new());
+        let handler_map =
+            StdMutex::new(ResponseHandlerMap::new(router_handle.stream_usage.clone()));
 
         let enable_write_coalescing = config.enable_write_coalescing;
 
@@ -1302,6 +1664,10 @@ impl Connection {
         self.features.shard_aware_port
     }
 
+    pub(crate) fn get_protocol_features(&self) -> &ProtocolFeatures {
+        &self.features.protocol_features
+    }
+
     fn set_features(&mut self, features: ConnectionFeatures) {
         self.features = features;
     }
@@ -1309,6 +1675,33 @@ impl Connection {
     pub fn get_connect_address(&self) -> SocketAddr {
         self.connect_address
     }
+
+    /// Generation of the TLS context provider at the moment the connection was opened,
+    /// or `None` if the connection is not encrypted.
+    pub(crate) fn tls_generation(&self) -> Option<u64> {
+        self.tls_generation
+    }
+
+    pub(crate) fn uses_unix_socket(&self) -> bool {
+        self.config.uses_unix_socket()
+    }
+
+    /// Number of requests sent on this connection which still await a response.
+    pub(crate) fn in_flight_requests(&self) -> usize {
+        self.router_handle
+            .stream_usage
+            .in_flight
+            .load(std::sync::atomic::Ordering::Relaxed)
+    }
+
+    /// Number of stream ids held by requests which were cancelled before
+    /// their response arrived.
+    pub(crate) fn orphaned_streams(&self) -> usize {
+        self.router_handle
+            .stream_usage
+            .orphaned
+            .load(std::sync::atomic::Ordering::Relaxed)
+    }
 }
 
 async fn maybe_translated_addr(
@@ -1372,6 +1765,42 @@ pub async fn open_named_connection(
     config: ConnectionConfig,
     driver_name: Option<String>,
     driver_version: Option<String>,
+) -> Result<(Connection, ErrorReceiver), QueryError> {
+    let result = open_named_connection_once(
+        addr,
+        source_port,
+        config.clone(),
+        driver_name.clone(),
+        driver_version.clone(),
+    )
+    .await;
+
+    match result {
+        Err(QueryError::DbError(DbError::AuthenticationError, ref reason))
+            if config
+                .authenticator
+                .as_ref()
+                .filter(|auth| auth.invalidate_credentials())
+                .is_some() =>
+        {
+            // The credentials could have been rotated since they were fetched.
+            // Retry once with fresh ones.
+            debug!(
+                "Authentication to {} failed ({}), retrying with refreshed credentials",
+                addr, reason
+            );
+            open_named_connection_once(addr, source_port, config, driver_name, driver_version).await
+        }
+        result => result,
+    }
+}
+
+async fn open_named_connection_once(
+    addr: SocketAddr,
+    source_port: Option<u16>,
+    config: ConnectionConfig,
+    driver_name: Option<String>,
+    driver_version: Option<String>,
 ) -> Result<(Connection, ErrorReceiver), QueryError> {
     // TODO: shouldn't all this logic be in Connection::new?
     let (mut connection, error_receiver) =
@@ -1407,6 +1836,7 @@ pub async fn open_named_connection(
     let protocol_features = ProtocolFeatures::parse_from_supported(&supported.options);
 
     let mut options = HashMap::new();
+    config.client_identity.add_startup_options(&mut options);
     protocol_features.add_startup_options(&mut options);
 
     let features = ConnectionFeatures {
@@ -1583,6 +2013,8 @@ struct ResponseHandlerMap {
 
     request_to_stream: HashMap<RequestId, i16>,
     orphanage_tracker: OrphanageTracker,
+
+    usage: Arc<StreamUsage>,
 }
 
 enum HandlerLookupResult {
@@ -1592,21 +2024,35 @@ enum HandlerLookupResult {
 }
 
 impl ResponseHandlerMap {
-    pub fn new() -> Self {
+    fn new(usage: Arc<StreamUsage>) -> Self {
         Self {
             stream_set: StreamIdSet::new(),
             handlers: HashMap::new(),
             request_to_stream: HashMap::new(),
             orphanage_tracker: OrphanageTracker::new(),
+            usage,
         }
     }
 
+    // Publishes current stream id usage, so that it can be read
+    // without taking the handler map lock.
+    fn update_usage(&self) {
+        self.usage
+            .in_flight
+            .store(self.handlers.len(), std::sync::atomic::Ordering::Relaxed);
+        self.usage.orphaned.store(
+            self.orphanage_tracker.orphans.len(),
+            std::sync::atomic::Ordering::Relaxed,
+        );
+    }
+
     pub fn allocate(&mut self, response_handler: ResponseHandler) -> Result<i16, ResponseHandler> {
         if let Some(stream_id) = self.stream_set.allocate() {
             self.request_to_stream
                 .insert(response_handler.request_id, stream_id);
             let prev_handler = self.handlers.insert(stream_id, response_handler);
             assert!(prev_handler.is_none());
+            self.update_usage();
 
             Ok(stream_id)
         } else {
@@ -1625,6 +2071,7 @@ impl ResponseHandlerMap {
             self.orphanage_tracker.insert(*stream_id);
             self.handlers.remove(stream_id);
             self.request_to_stream.remove(&request_id);
+            self.update_usage();
         }
     }
 
@@ -1638,6 +2085,7 @@ impl ResponseHandlerMap {
 
         if self.orphanage_tracker.contains(stream_id) {
             self.orphanage_tracker.remove(stream_id);
+            self.update_usage();
             // This `stream_id` had been orphaned, so its handler got removed.
             // This is a valid state (as opposed to missing handler)
             return HandlerLookupResult::Orphaned;
@@ -1648,6 +2096,7 @@ impl ResponseHandlerMap {
             // prevent marking this `stream_id` as orphaned by some late
             // orphan notification.
             self.request_to_stream.remove(&handler.request_id);
+            self.update_usage();
 
             HandlerLookupResult::Handler(handler)
         } else {
@@ -1809,6 +2258,7 @@ mod tests {
             UntranslatedEndpoint::ContactPoint(ContactPoint {
                 address: addr,
                 datacenter: None,
+                hostname: None,
             }),
             None,
             ConnectionConfig::default(),
@@ -1927,6 +2377,7 @@ mod tests {
                 UntranslatedEndpoint::ContactPoint(ContactPoint {
                     address: addr,
                     datacenter: None,
+                    hostname: None,
                 }),
                 None,
                 ConnectionConfig {
@@ -2054,7 +2505,7 @@ mod tests {
 
         // We must interrupt the driver's full connection opening, because our proxy does not interact further after Startup.
         let startup_without_lwt_optimisation = select! {
-            _ = open_connection(UntranslatedEndpoint::ContactPoint(ContactPoint{address: proxy_addr, datacenter: None}), None, config.clone()) => unreachable!(),
+            _ = open_connection(UntranslatedEndpoint::ContactPoint(ContactPoint{address: proxy_addr, datacenter: None, hostname: None}), None, config.clone()) => unreachable!(),
             startup = startup_rx.recv() => startup.unwrap(),
         };
 
@@ -2062,7 +2513,7 @@ mod tests {
             .change_request_rules(Some(make_rules(options_with_lwt_optimisation_support)));
 
         let startup_with_lwt_optimisation = select! {
-            _ = open_connection(UntranslatedEndpoint::ContactPoint(ContactPoint{address: proxy_addr, datacenter: None}), None, config.clone()) => unreachable!(),
+            _ = open_connection(UntranslatedEndpoint::ContactPoint(ContactPoint{address: proxy_addr, datacenter: None, hostname: None}), None, config.clone()) => unreachable!(),
             startup = startup_rx.recv() => startup.unwrap(),
         };
 
@@ -2120,6 +2571,7 @@ mod tests {
             UntranslatedEndpoint::ContactPoint(ContactPoint {
                 address: proxy_addr,
                 datacenter: None,
+                hostname: None,
             }),
             None,
             config,
@@ -2156,4 +2608,122 @@ mod tests {
 
         let _ = proxy.finish().await;
     }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn connection_over_unix_socket() {
+        use super::{unix_socket_node_address, Connection};
+        use tokio::io::AsyncReadExt;
+        use tokio::net::UnixListener;
+
+        let path = std::env::temp_dir().join(format!(
+            "scylla-rust-driver-test-{}.sock",
+            std::process::id()
+        ));
+        let _ = std::fs::remove_file(&path);
+        let listener = UnixListener::bind(&path).unwrap();
+
+        let config = ConnectionConfig {
+            unix_socket_path: Some(path.clone()),
+            ..Default::default()
+        };
+        let (connection, _error_receiver) =
+            Connection::new(unix_socket_node_address(), None, config)
+                .await
+                .unwrap();
+        assert!(connection.uses_unix_socket());
+
+        let (mut server_stream, _) = listener.accept().await.unwrap();
+        let _request = tokio::spawn(async move { connection.get_options().await });
+
+        // The OPTIONS request is sent over the socket
+        let mut header = [0u8; 9];
+        server_stream.read_exact(&mut header).await.unwrap();
+        assert_eq!(header[4], 0x05);
+
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[cfg(feature = "rustls")]
+    #[test]
+    fn rustls_server_name_is_node_ip() {
+        use super::{SslConfig, TlsContext};
+        use std::net::IpAddr;
+
+        let client_config = rustls::ClientConfig::builder()
+            .with_safe_defaults()
+            .with_root_certificates(rustls::RootCertStore::empty())
+            .with_no_client_auth();
+        let ssl_config = SslConfig::new_with_global_context(client_config.into());
+        assert_matches!(ssl_config.context(), TlsContext::Rustls(_));
+
+        let ip: IpAddr = "127.0.0.1".parse().unwrap();
+        assert_eq!(
+            ssl_config.rustls_server_name(ip).unwrap(),
+            rustls::ServerName::IpAddress(ip)
+        );
+    }
+
+    #[test]
+    fn client_identity_startup_options() {
+        use super::ClientIdentity;
+
+        let client_id = uuid::Uuid::new_v4();
+        let identity = ClientIdentity {
+            application_name: Some("app".to_string()),
+            application_version: Some("1.0".to_string()),
+            client_id: Some(client_id),
+            extra_options: [
+                ("DEPLOYMENT".to_string(), "canary".to_string()),
+                ("APPLICATION_NAME".to_string(), "other".to_string()),
+                ("COMPRESSION".to_string(), "lz4".to_string()),
+                ("cql_version".to_string(), "3.0.0".to_string()),
+                ("DRIVER_NAME".to_string(), "other".to_string()),
+                ("SCYLLA_RATE_LIMIT_ERROR".to_string(), String::new()),
+            ]
+            .into_iter()
+            .collect(),
+        };
+
+        let mut options = HashMap::new();
+        identity.add_startup_options(&mut options);
+        assert_eq!(options.len(), 4);
+        assert_eq!(options["APPLICATION_NAME"], "app");
+        assert_eq!(options["APPLICATION_VERSION"], "1.0");
+        assert_eq!(options["CLIENT_ID"], client_id.to_string());
+        assert_eq!(options["DEPLOYMENT"], "canary");
+
+        let mut options = HashMap::new();
+        ClientIdentity::default().add_startup_options(&mut options);
+        assert!(options.is_empty());
+    }
+
+    #[cfg(feature = "rustls")]
+    #[test]
+    fn reloadable_tls_context_bumps_generation() {
+        use super::{SslConfig, TlsContext, TlsContextProvider};
+        use crate::transport::session::ReloadableTlsContext;
+        use std::sync::Arc;
+
+        let make_config = || {
+            Arc::new(
+                rustls::ClientConfig::builder()
+                    .with_safe_defaults()
+                    .with_root_certificates(rustls::RootCertStore::empty())
+                    .with_no_client_auth(),
+            )
+        };
+        let first = make_config();
+        let second = make_config();
+
+        let provider = Arc::new(ReloadableTlsContext::new(first.clone()));
+        let ssl_config = SslConfig::new_with_provider(provider.clone());
+        assert_eq!(ssl_config.generation(), 0);
+        assert_matches!(ssl_config.context(), TlsContext::Rustls(c) if Arc::ptr_eq(&c, &first));
+
+        provider.reload(second.clone());
+        assert_eq!(TlsContextProvider::generation(provider.as_ref()), 1);
+        assert_eq!(ssl_config.generation(), 1);
+        assert_matches!(ssl_config.context(), TlsContext::Rustls(c) if Arc::ptr_eq(&c, &second));
+    }
 }
@@ -1,30 +1,34 @@
-#[cfg(feature = "cloud")]
+#[cfg(any(feature = "cloud", feature = "cloud-rustls"))]
 use crate::cloud::set_ssl_config_for_scylla_cloud_host;
 
-use crate::routing::{Shard, ShardCount, Sharder, Token};
-use crate::transport::errors::QueryError;
+use crate::routing::{Shard, Sharder, Token};
+use crate::transport::errors::{BadQuery, QueryError};
 use crate::transport::{
     connection,
     connection::{Connection, ConnectionConfig, ErrorReceiver, VerifiedKeyspaceName},
 };
 
-#[cfg(feature = "cloud")]
+#[cfg(any(feature = "cloud", feature = "cloud-rustls"))]
 use super::session::resolve_hostname;
 
-#[cfg(feature = "cloud")]
+#[cfg(any(feature = "cloud", feature = "cloud-rustls"))]
 use super::cluster::ContactPoint;
+use super::reconnection_policy::{
+    ExponentialReconnectionPolicy, ReconnectionPolicy, ReconnectionSchedule,
+};
 use super::topology::{PeerEndpoint, UntranslatedEndpoint};
 use super::NodeAddr;
 
 use arc_swap::ArcSwap;
 use futures::{future::RemoteHandle, stream::FuturesUnordered, Future, FutureExt, StreamExt};
 use rand::Rng;
+use std::collections::HashMap;
 use std::convert::TryInto;
 use std::io::ErrorKind;
 use std::num::NonZeroUsize;
 use std::pin::Pin;
-use std::sync::{Arc, RwLock, Weak};
-use std::time::Duration;
+use std::sync::{Arc, Mutex, RwLock, Weak};
+use std::time::{Duration, Instant};
 use tokio::sync::{mpsc, Notify};
 use tracing::instrument::WithSubscriber;
 use tracing::{debug, trace, warn};
@@ -45,8 +49,147 @@ pub enum PoolSize {
     ///
     /// The recommended setting for Scylla is one connection per shard - `PerShard(1)`.
     PerShard(NonZeroUsize),
+
+    /// Indicates that the number of connections to each shard should follow the load,
+    /// within the given bounds. See [`AdaptivePoolSize`].
+    ///
+    /// Cassandra nodes will be treated as if they have only one shard.
+    Adaptive(AdaptivePoolSize),
+}
+
+/// Bounds and thresholds of the adaptive pool size.
+///
+/// Every shard starts with `min_per_shard` connections. When the number of requests
+/// waiting for a response, averaged over the connections to a shard, exceeds
+/// `in_flight_threshold`, another connection is opened to the shard, up to `max_per_shard`.
+/// When the load of the shard would fit in one connection fewer with plenty of room
+/// (below half of the threshold) for `idle_timeout`, the least loaded connection above
+/// `min_per_shard` is closed. Requests already sent on it are allowed to complete.
+///
+/// # Example
+/// ```
+/// use std::num::NonZeroUsize;
+/// use std::time::Duration;
+/// use scylla::transport::session::AdaptivePoolSize;
+///
+/// let size = AdaptivePoolSize::new(NonZeroUsize::new(1).unwrap(), NonZeroUsize::new(8).unwrap())
+///     .with_in_flight_threshold(512)
+///     .with_idle_timeout(Duration::from_secs(30));
+/// ```
+#[derive(Debug, Clone, Copy)]
+#[non_exhaustive]
+pub struct AdaptivePoolSize {
+    /// The number of connections kept to each shard regardless of the load. The default is 1.
+    pub min_per_shard: NonZeroUsize,
+
+    /// The upper bound of the number of connections to each shard. The default is 4.
+    pub max_per_shard: NonZeroUsize,
+
+    /// The average number of requests in flight per connection above which
+    /// another connection is opened. The default is 1024.
+    pub in_flight_threshold: usize,
+
+    /// How long the load has to stay low before a connection is closed. The default is 60 seconds.
+    pub idle_timeout: Duration,
+}
+
+impl Default for AdaptivePoolSize {
+    fn default() -> Self {
+        Self {
+            min_per_shard: NonZeroUsize::new(1).unwrap(),
+            max_per_shard: NonZeroUsize::new(4).unwrap(),
+            in_flight_threshold: 1024,
+            idle_timeout: Duration::from_secs(60),
+        }
+    }
+}
+
+impl AdaptivePoolSize {
+    /// Creates an adaptive pool size with the given bounds and default thresholds.
+    pub fn new(min_per_shard: NonZeroUsize, max_per_shard: NonZeroUsize) -> Self {
+        Self {
+            min_per_shard,
+            max_per_shard,
+            ..Default::default()
+        }
+    }
+
+    /// Sets the average number of requests in flight per connection
+    /// above which another connection is opened.
+    pub fn with_in_flight_threshold(mut self, in_flight_threshold: usize) -> Self {
+        self.in_flight_threshold = in_flight_threshold;
+        self
+    }
+
+    /// Sets how long the load has to stay low before a connection is closed.
+    pub fn with_idle_timeout(mut self, idle_timeout: Duration) -> Self {
+        self.idle_timeout = idle_timeout;
+        self
+    }
+
+    fn max(&self) -> usize {
+        std::cmp::max(self.min_per_shard, self.max_per_shard).get()
+    }
+
+    // Decides whether the shard needs one connection more or less,
+    // given the requests in flight on its `open_connections` connections.
+    fn decide(
+        &self,
+        state: &mut AdaptiveShardState,
+        open_connections: usize,
+        in_flight: usize,
+        now: tokio::time::Instant,
+    ) -> AdaptiveDecision {
+        if open_connections == 0 {
+            state.low_load_since = None;
+            return AdaptiveDecision::Keep;
+        }
+
+        if in_flight > self.in_flight_threshold.saturating_mul(open_connections) {
+            state.low_load_since = None;
+            // Don't grow further until the previously requested connection is open
+            if state.target < self.max() && open_connections >= state.target {
+                return AdaptiveDecision::Grow;
+            }
+            return AdaptiveDecision::Keep;
+        }
+
+        let fits_in_fewer_connections = open_connections > self.min_per_shard.get()
+            && in_flight <= (self.in_flight_threshold / 2).saturating_mul(open_connections - 1);
+        if !fits_in_fewer_connections {
+            state.low_load_since = None;
+            return AdaptiveDecision::Keep;
+        }
+
+        let low_load_since = *state.low_load_since.get_or_insert(now);
+        if now.duration_since(low_load_since) >= self.idle_timeout {
+            state.low_load_since = None;
+            AdaptiveDecision::Shrink
+        } else {
+            AdaptiveDecision::Keep
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum AdaptiveDecision {
+    Grow,
+    Shrink,
+    Keep,
+}
+
+// Per-shard state of the adaptive pool size
+#[derive(Debug, Clone)]
+struct AdaptiveShardState {
+    // How many connections the pool currently aims to keep to the shard
+    target: usize,
+    // Since when the shard could do with one connection fewer
+    low_load_since: Option<tokio::time::Instant>,
 }
 
+// How often the refiller checks the load of connections in the adaptive mode
+const ADAPTIVE_POOL_LOAD_CHECK_INTERVAL: Duration = Duration::from_millis(100);
+
 impl Default for PoolSize {
     fn default() -> Self {
         PoolSize::PerShard(NonZeroUsize::new(1).unwrap())
@@ -59,6 +202,8 @@ pub struct PoolConfig {
     pub pool_size: PoolSize,
     pub can_use_shard_aware_port: bool,
     pub keepalive_interval: Option<Duration>,
+    pub reconnection_policy: Arc<dyn ReconnectionPolicy>,
+    pub tls_reload_check_interval: Option<Duration>,
 }
 
 impl Default for PoolConfig {
@@ -68,6 +213,103 @@ impl Default for PoolConfig {
             pool_size: Default::default(),
             can_use_shard_aware_port: true,
             keepalive_interval: None,
+            reconnection_policy: Arc::new(ExponentialReconnectionPolicy::default()),
+            tls_reload_check_interval: None,
+        }
+    }
+}
+
+/// State of a node's connection pool, as reported by [`PoolSnapshot`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum PoolState {
+    /// The pool is being filled for the first time.
+    Initializing,
+    /// The pool has no connections, either because initial filling failed
+    /// or because all connections broke. It is being refilled in the background.
+    Broken,
+    /// The pool has at least one usable connection.
+    Ready,
+}
+
+/// A point-in-time view of a node's connection pool.
+///
+/// Obtained with [`Node::pool_snapshot`](crate::transport::Node::pool_snapshot).
+#[derive(Debug, Clone)]
+#[non_exhaustive]
+pub struct PoolSnapshot {
+    pub state: PoolState,
+    /// Whether new connections are opened to the shard-aware port.
+    pub uses_shard_aware_port: bool,
+    /// Number of failed connection attempts and broken connections
+    /// since the pool was created.
+    pub connection_errors: u64,
+    /// The most recent connection error, if there was any.
+    pub last_error: Option<QueryError>,
+    /// When the most recent refill of the pool was started.
+    /// `None` if the pool has not been refilled yet.
+    pub last_refill: Option<Instant>,
+    /// Per-shard statistics. Nodes which are not sharded (e.g. Cassandra nodes)
+    /// are reported as having a single shard.
+    pub shards: Vec<ShardPoolSnapshot>,
+}
+
+impl PoolSnapshot {
+    /// Total number of open connections in the pool.
+    pub fn open_connections(&self) -> usize {
+        self.shards.iter().map(|s| s.open_connections).sum()
+    }
+
+    /// Total number of requests awaiting a response on the pool's connections.
+    pub fn in_flight_requests(&self) -> usize {
+        self.shards.iter().map(|s| s.in_flight_requests).sum()
+    }
+
+    /// Total number of orphaned stream ids on the pool's connections.
+    pub fn orphaned_streams(&self) -> usize {
+        self.shards.iter().map(|s| s.orphaned_streams).sum()
+    }
+}
+
+/// A point-in-time view of connections to a single shard, part of [`PoolSnapshot`].
+#[derive(Debug, Clone)]
+#[non_exhaustive]
+pub struct ShardPoolSnapshot {
+    pub shard: Shard,
+    pub open_connections: usize,
+    /// Requests sent on the shard's connections which still await a response.
+    pub in_flight_requests: usize,
+    /// Stream ids held by requests which were cancelled before their response arrived.
+    pub orphaned_streams: usize,
+    /// Number of broken connections to this shard, and failed attempts
+    /// to connect to it through the shard-aware port.
+    pub connection_errors: u64,
+    pub last_error: Option<QueryError>,
+}
+
+// Health information gathered by the refiller and read by `NodeConnectionPool::snapshot`.
+#[derive(Default)]
+struct PoolHealth {
+    uses_shard_aware_port: bool,
+    connection_errors: u64,
+    last_error: Option<QueryError>,
+    last_refill: Option<Instant>,
+    shards: Vec<ShardHealth>,
+}
+
+#[derive(Default, Clone)]
+struct ShardHealth {
+    connection_errors: u64,
+    last_error: Option<QueryError>,
+}
+
+impl PoolHealth {
+    fn record_error(&mut self, shard: Option<usize>, error: &QueryError) {
+        self.connection_errors += 1;
+        self.last_error = Some(error.clone());
+        if let Some(shard_health) = shard.and_then(|shard| self.shards.get_mut(shard)) {
+            shard_health.connection_errors += 1;
+            shard_health.last_error = Some(error.clone());
         }
     }
 }
@@ -154,6 +396,7 @@ pub struct NodeConnectionPool {
     _refiller_handle: Arc<RemoteHandle<()>>,
     pool_updated_notify: Arc<Notify>,
     endpoint: Arc<RwLock<UntranslatedEndpoint>>,
+    health: Arc<Mutex<PoolHealth>>,
 }
 
 impl std::fmt::Debug for NodeConnectionPool {
@@ -173,12 +416,13 @@ impl NodeConnectionPool {
         let (use_keyspace_request_sender, use_keyspace_request_receiver) = mpsc::channel(1);
         let pool_updated_notify = Arc::new(Notify::new());
 
-        #[cfg(feature = "cloud")]
+        #[cfg(any(feature = "cloud", feature = "cloud-rustls"))]
         if pool_config.connection_config.cloud_config.is_some() {
             let (host_id, address, dc) = match endpoint {
                 UntranslatedEndpoint::ContactPoint(ContactPoint {
                     address,
                     ref datacenter,
+                    ..
                 }) => (None, address, datacenter.as_deref()), // FIXME: Pass DC in ContactPoint
                 UntranslatedEndpoint::Peer(PeerEndpoint {
                     host_id,
@@ -208,6 +452,7 @@ impl NodeConnectionPool {
         );
 
         let conns = refiller.get_shared_connections();
+        let health = refiller.health.clone();
         let (fut, refiller_handle) = refiller.run(use_keyspace_request_receiver).remote_handle();
         tokio::spawn(fut.with_current_subscriber());
 
@@ -217,6 +462,7 @@ impl NodeConnectionPool {
             _refiller_handle: Arc::new(refiller_handle),
             pool_updated_notify,
             endpoint: arced_endpoint,
+            health,
         }
     }
 
@@ -233,10 +479,10 @@ impl NodeConnectionPool {
     }
 
     pub fn connection_for_token(&self, token: Token) -> Result<Arc<Connection>, QueryError> {
-        trace!(token = token.value, "Selecting connection for token");
+        trace!(token = %token, "Selecting connection for token");
         self.with_connections(|pool_conns| match pool_conns {
             PoolConnections::NotSharded(conns) => {
-                Self::choose_random_connection_from_slice(conns).unwrap()
+                Self::choose_connection_from_slice(conns).unwrap()
             }
             PoolConnections::Sharded {
                 sharder,
@@ -247,61 +493,124 @@ impl NodeConnectionPool {
                     .try_into()
                     .expect("Shard number doesn't fit in u16");
                 trace!(shard = shard, "Selecting connection for token");
-                Self::connection_for_shard(shard, sharder.nr_shards, connections.as_slice())
+                Self::connection_for_shard(shard, connections.as_slice())
             }
         })
     }
 
+    /// Number of requests awaiting a response on the connection which `connection_for_token`
+    /// would choose for the given token, or on all connections if no token is given.
+    /// Returns `None` if the pool has no connections.
+    pub(crate) fn in_flight_requests(&self, token: Option<&Token>) -> Option<usize> {
+        let conns = self.conns.load();
+        let pool_conns = match &**conns {
+            MaybePoolConnections::Ready(pool_conns) => pool_conns,
+            _ => return None,
+        };
+        let load = |conn: &Arc<Connection>| conn.in_flight_requests();
+
+        match (pool_conns, token) {
+            (PoolConnections::NotSharded(conns), Some(_)) => conns.iter().map(load).min(),
+            (
+                PoolConnections::Sharded {
+                    sharder,
+                    connections,
+                },
+                Some(token),
+            ) => {
+                let shard = sharder.shard_of(token.clone()) as usize;
+                Self::load_of_connection_for_shard(shard, connections, load)
+            }
+            (PoolConnections::NotSharded(conns), None) => {
+                (!conns.is_empty()).then(|| conns.iter().map(load).sum())
+            }
+            (PoolConnections::Sharded { connections, .. }, None) => {
+                let mut conns = connections.iter().flatten().peekable();
+                conns.peek()?;
+                Some(conns.map(load).sum())
+            }
+        }
+    }
+
+    // The load of the connection which `connection_for_shard` would choose.
+    fn load_of_connection_for_shard<T>(
+        shard: usize,
+        shard_conns: &[Vec<T>],
+        load: impl Fn(&T) -> usize,
+    ) -> Option<usize> {
+        shard_conns
+            .get(shard)
+            .and_then(|conns| conns.iter().map(&load).min())
+            .or_else(|| shard_conns.iter().flatten().map(&load).min())
+    }
+
+    // Unlike `connection_for_token`, doesn't fall back to other shards
+    pub(crate) fn connection_to_shard(&self, shard: Shard) -> Result<Arc<Connection>, QueryError> {
+        trace!(shard = shard, "Selecting connection to a chosen shard");
+        self.with_connections(|pool_conns| match pool_conns {
+            PoolConnections::NotSharded(conns) => {
+                if shard != 0 {
+                    return Err(QueryError::BadQuery(BadQuery::Other(format!(
+                        "Node is not sharded, shard {} can't be targeted",
+                        shard
+                    ))));
+                }
+                Ok(Self::choose_connection_from_slice(conns).unwrap())
+            }
+            PoolConnections::Sharded {
+                sharder,
+                connections,
+            } => {
+                let shard_conns = connections.get(shard as usize).ok_or_else(|| {
+                    QueryError::BadQuery(BadQuery::Other(format!(
+                        "Shard {} doesn't exist, the node has {} shards",
+                        shard, sharder.nr_shards
+                    )))
+                })?;
+                Self::choose_connection_from_slice(shard_conns).ok_or_else(|| {
+                    QueryError::IoError(Arc::new(std::io::Error::new(
+                        ErrorKind::Other,
+                        format!("No connections to shard {} in the pool", shard),
+                    )))
+                })
+            }
+        })?
+    }
+
     pub fn random_connection(&self) -> Result<Arc<Connection>, QueryError> {
         trace!("Selecting random connection");
         self.with_connections(|pool_conns| match pool_conns {
             PoolConnections::NotSharded(conns) => {
-                Self::choose_random_connection_from_slice(conns).unwrap()
+                Self::choose_connection_from_slice(conns).unwrap()
             }
             PoolConnections::Sharded {
                 sharder,
                 connections,
             } => {
                 let shard: u16 = rand::thread_rng().gen_range(0..sharder.nr_shards.get());
-                Self::connection_for_shard(shard, sharder.nr_shards, connections.as_slice())
+                Self::connection_for_shard(shard, connections.as_slice())
             }
         })
     }
 
-    // Tries to get a connection to given shard, if it's broken returns any working connection
-    fn connection_for_shard(
-        shard: u16,
-        nr_shards: ShardCount,
-        shard_conns: &[Vec<Arc<Connection>>],
-    ) -> Arc<Connection> {
+    // Tries to get a connection to given shard, if it's broken returns
+    // the least loaded working connection to any other shard
+    fn connection_for_shard(shard: u16, shard_conns: &[Vec<Arc<Connection>>]) -> Arc<Connection> {
         // Try getting the desired connection
-        if let Some(conn) = Self::choose_random_connection_from_slice(&shard_conns[shard as usize])
-        {
+        if let Some(conn) = Self::choose_connection_from_slice(&shard_conns[shard as usize]) {
             trace!(shard = shard, "Found connection for the target shard");
             return conn;
         }
 
-        // If this fails try getting any other in random order
-        let mut shards_to_try: Vec<u16> = (0..shard).chain(shard + 1..nr_shards.get()).collect();
-
-        let orig_shard = shard;
-        while !shards_to_try.is_empty() {
-            let idx = rand::thread_rng().gen_range(0..shards_to_try.len());
-            let shard = shards_to_try.swap_remove(idx);
-
-            if let Some(conn) =
-                Self::choose_random_connection_from_slice(&shard_conns[shard as usize])
-            {
-                trace!(
-                    orig_shard = orig_shard,
-                    shard = shard,
-                    "Choosing connection for a different shard"
-                );
-                return conn;
-            }
-        }
-
-        unreachable!("could not find any connection in supposedly non-empty pool")
+        // If this fails choose among the connections to all other shards
+        let other_conns: Vec<Arc<Connection>> = shard_conns.iter().flatten().cloned().collect();
+        let conn = Self::choose_connection_from_slice(&other_conns)
+            .expect("could not find any connection in supposedly non-empty pool");
+        trace!(
+            orig_shard = shard,
+            "Choosing connection for a different shard"
+        );
+        conn
     }
 
     pub async fn use_keyspace(
@@ -345,7 +654,54 @@ impl NodeConnectionPool {
         })
     }
 
-    fn choose_random_connection_from_slice(v: &[Arc<Connection>]) -> Option<Arc<Connection>> {
+    pub(crate) fn snapshot(&self) -> PoolSnapshot {
+        let conns = self.conns.load_full();
+        let health = self.health.lock().unwrap();
+
+        let state = match &*conns {
+            MaybePoolConnections::Initializing => PoolState::Initializing,
+            MaybePoolConnections::Broken(_) => PoolState::Broken,
+            MaybePoolConnections::Ready(_) => PoolState::Ready,
+        };
+        let shard_conns: &[Vec<Arc<Connection>>] = match &*conns {
+            MaybePoolConnections::Ready(PoolConnections::NotSharded(conns)) => {
+                std::slice::from_ref(conns)
+            }
+            MaybePoolConnections::Ready(PoolConnections::Sharded { connections, .. }) => {
+                connections
+            }
+            _ => &[],
+        };
+
+        let shard_count = std::cmp::max(shard_conns.len(), health.shards.len()).max(1);
+        let shards = (0..shard_count)
+            .map(|shard| {
+                let conns = shard_conns.get(shard).map_or(&[][..], Vec::as_slice);
+                let shard_health = health.shards.get(shard).cloned().unwrap_or_default();
+                ShardPoolSnapshot {
+                    shard: shard as Shard,
+                    open_connections: conns.len(),
+                    in_flight_requests: conns.iter().map(|c| c.in_flight_requests()).sum(),
+                    orphaned_streams: conns.iter().map(|c| c.orphaned_streams()).sum(),
+                    connection_errors: shard_health.connection_errors,
+                    last_error: shard_health.last_error,
+                }
+            })
+            .collect();
+
+        PoolSnapshot {
+            state,
+            uses_shard_aware_port: health.uses_shard_aware_port,
+            connection_errors: health.connection_errors,
+            last_error: health.last_error.clone(),
+            last_refill: health.last_refill,
+            shards,
+        }
+    }
+
+    // Chooses the connection with the fewest requests in flight.
+    // Ties are broken randomly, so that an idle pool spreads requests evenly.
+    fn choose_connection_from_slice(v: &[Arc<Connection>]) -> Option<Arc<Connection>> {
         trace!(
             connections = v
                 .iter()
@@ -360,7 +716,11 @@ impl NodeConnectionPool {
         } else if v.len() == 1 {
             Some(v[0].clone())
         } else {
-            let idx = rand::thread_rng().gen_range(0..v.len());
+            let start = rand::thread_rng().gen_range(0..v.len());
+            let idx = (0..v.len())
+                .map(|i| (start + i) % v.len())
+                .min_by_key(|&i| v[i].in_flight_requests())
+                .unwrap();
             Some(v[idx].clone())
         }
     }
@@ -390,20 +750,31 @@ impl NodeConnectionPool {
 
 const EXCESS_CONNECTION_BOUND_PER_SHARD_MULTIPLIER: usize = 10;
 
-// TODO: Make it configurable through a policy (issue #184)
-const MIN_FILL_BACKOFF: Duration = Duration::from_millis(50);
-const MAX_FILL_BACKOFF: Duration = Duration::from_secs(10);
-const FILL_BACKOFF_MULTIPLIER: u32 = 2;
-
-// A simple exponential strategy for pool fill backoffs.
+// Computes pool fill backoffs according to the configured reconnection policy.
+// The schedule is restarted after each successful fill.
+// Shards which the shard-aware port failed to connect to get their own schedules,
+// and are skipped by shard-aware fills until their delay passes.
 struct RefillDelayStrategy {
+    policy: Arc<dyn ReconnectionPolicy>,
+    schedule: Box<dyn ReconnectionSchedule>,
     current_delay: Duration,
+    shard_backoffs: HashMap<Shard, ShardBackoff>,
+}
+
+struct ShardBackoff {
+    schedule: Box<dyn ReconnectionSchedule>,
+    retry_at: tokio::time::Instant,
 }
 
 impl RefillDelayStrategy {
-    fn new() -> Self {
+    fn new(policy: Arc<dyn ReconnectionPolicy>, endpoint: &UntranslatedEndpoint) -> Self {
+        let mut schedule = policy.new_schedule(endpoint, None);
+        let current_delay = schedule.next_delay();
         Self {
-            current_delay: MIN_FILL_BACKOFF,
+            policy,
+            schedule,
+            current_delay,
+            shard_backoffs: HashMap::new(),
         }
     }
 
@@ -411,15 +782,51 @@ impl RefillDelayStrategy {
         self.current_delay
     }
 
-    fn on_successful_fill(&mut self) {
-        self.current_delay = MIN_FILL_BACKOFF;
+    fn on_successful_fill(&mut self, endpoint: &UntranslatedEndpoint) {
+        self.schedule = self.policy.new_schedule(endpoint, None);
+        self.current_delay = self.schedule.next_delay();
     }
 
     fn on_fill_error(&mut self) {
-        self.current_delay = std::cmp::min(
-            MAX_FILL_BACKOFF,
-            self.current_delay * FILL_BACKOFF_MULTIPLIER,
-        );
+        self.current_delay = self.schedule.next_delay();
+    }
+
+    fn on_shard_error(
+        &mut self,
+        endpoint: &UntranslatedEndpoint,
+        shard: Shard,
+        now: tokio::time::Instant,
+    ) {
+        let policy = &self.policy;
+        let backoff = self
+            .shard_backoffs
+            .entry(shard)
+            .or_insert_with(|| ShardBackoff {
+                schedule: policy.new_schedule(endpoint, Some(shard)),
+                retry_at: now,
+            });
+        backoff.retry_at = now + backoff.schedule.next_delay();
+    }
+
+    fn on_shard_connected(&mut self, shard: Shard) {
+        self.shard_backoffs.remove(&shard);
+    }
+
+    // Returns the time at which the shard may be connected to again,
+    // if it is still backed off.
+    fn shard_retry_time(
+        &self,
+        shard: Shard,
+        now: tokio::time::Instant,
+    ) -> Option<tokio::time::Instant> {
+        self.shard_backoffs
+            .get(&shard)
+            .map(|backoff| backoff.retry_at)
+            .filter(|retry_at| *retry_at > now)
+    }
+
+    fn clear_shard_backoffs(&mut self) {
+        self.shard_backoffs.clear();
     }
 }
 
@@ -472,6 +879,12 @@ struct PoolRefiller {
 
     // Signaled when the connection pool is updated
     pool_updated_notify: Arc<Notify>,
+
+    // Exposed through `NodeConnectionPool::snapshot`
+    health: Arc<Mutex<PoolHealth>>,
+
+    // Used only with `PoolSize::Adaptive`, one entry per shard
+    adaptive_shards: Vec<AdaptiveShardState>,
 }
 
 #[derive(Debug)]
@@ -492,6 +905,13 @@ impl PoolRefiller {
         let conns = vec![Vec::new()];
         let shared_conns = Arc::new(ArcSwap::new(Arc::new(MaybePoolConnections::Initializing)));
 
+        let refill_delay_strategy = RefillDelayStrategy::new(
+            pool_config.reconnection_policy.clone(),
+            &endpoint.read().unwrap(),
+        );
+
+        let adaptive_shards = Self::initial_adaptive_shards(&pool_config.pool_size, 1);
+
         Self {
             endpoint,
             pool_config,
@@ -503,7 +923,7 @@ impl PoolRefiller {
             conns,
 
             had_error_since_last_refill: false,
-            refill_delay_strategy: RefillDelayStrategy::new(),
+            refill_delay_strategy,
 
             ready_connections: FuturesUnordered::new(),
             connection_errors: FuturesUnordered::new(),
@@ -513,6 +933,29 @@ impl PoolRefiller {
             current_keyspace,
 
             pool_updated_notify,
+
+            health: Arc::new(Mutex::new(PoolHealth {
+                shards: vec![ShardHealth::default()],
+                ..Default::default()
+            })),
+
+            adaptive_shards,
+        }
+    }
+
+    fn initial_adaptive_shards(
+        pool_size: &PoolSize,
+        shard_count: usize,
+    ) -> Vec<AdaptiveShardState> {
+        match pool_size {
+            PoolSize::Adaptive(params) => vec![
+                AdaptiveShardState {
+                    target: params.min_per_shard.get(),
+                    low_load_since: None,
+                };
+                shard_count
+            ],
+            _ => Vec::new(),
         }
     }
 
@@ -537,8 +980,30 @@ impl PoolRefiller {
         let mut next_refill_time = tokio::time::Instant::now();
         let mut refill_scheduled = true;
 
+        let tls_reload_check_interval = self.pool_config.tls_reload_check_interval;
+        let mut next_tls_reload_check =
+            tokio::time::Instant::now() + tls_reload_check_interval.unwrap_or_default();
+
+        let adaptive_pool_size = match self.pool_config.pool_size {
+            PoolSize::Adaptive(params) => Some(params),
+            _ => None,
+        };
+        let mut next_load_check = tokio::time::Instant::now() + ADAPTIVE_POOL_LOAD_CHECK_INTERVAL;
+
         loop {
             tokio::select! {
+                _ = tokio::time::sleep_until(next_load_check), if adaptive_pool_size.is_some() => {
+                    if let Some(params) = &adaptive_pool_size {
+                        self.adjust_adaptive_pool_size(params);
+                    }
+                    next_load_check = tokio::time::Instant::now() + ADAPTIVE_POOL_LOAD_CHECK_INTERVAL;
+                }
+
+                _ = tokio::time::sleep_until(next_tls_reload_check), if tls_reload_check_interval.is_some() => {
+                    self.start_replacing_stale_tls_connections();
+                    next_tls_reload_check = tokio::time::Instant::now() + tls_reload_check_interval.unwrap_or_default();
+                }
+
                 _ = tokio::time::sleep_until(next_refill_time), if refill_scheduled => {
                     self.had_error_since_last_refill = false;
                     self.start_filling();
@@ -561,6 +1026,8 @@ impl PoolRefiller {
                 evt = self.connection_errors.select_next_some(), if !self.connection_errors.is_empty() => {
                     if let Some(conn) = evt.connection.upgrade() {
                         debug!("[{}] Got error for connection {:p}: {:?}", self.endpoint_description(), Arc::as_ptr(&conn), evt.error);
+                        let shard_id = conn.get_shard_info().as_ref().map(|s| s.shard as usize);
+                        self.health.lock().unwrap().record_error(shard_id.or(Some(0)), &evt.error);
                         self.remove_connection(conn, evt.error);
                     }
                 }
@@ -587,9 +1054,13 @@ impl PoolRefiller {
                 if self.had_error_since_last_refill {
                     self.refill_delay_strategy.on_fill_error();
                 } else {
-                    self.refill_delay_strategy.on_successful_fill();
+                    let endpoint = self.endpoint.read().unwrap().clone();
+                    self.refill_delay_strategy.on_successful_fill(&endpoint);
+                }
+                let mut delay = self.refill_delay_strategy.get_delay();
+                if let Some(shard_delay) = self.shard_backoff_delay() {
+                    delay = std::cmp::max(delay, shard_delay);
                 }
-                let delay = self.refill_delay_strategy.get_delay();
                 debug!(
                     "[{}] Scheduling next refill in {} ms",
                     self.endpoint_description(),
@@ -609,10 +1080,77 @@ impl PoolRefiller {
     fn is_full(&self) -> bool {
         match self.pool_config.pool_size {
             PoolSize::PerHost(target) => self.active_connection_count() >= target.get(),
-            PoolSize::PerShard(target) => {
-                self.conns.iter().all(|conns| conns.len() >= target.get())
+            PoolSize::PerShard(_) | PoolSize::Adaptive(_) => self
+                .conns
+                .iter()
+                .enumerate()
+                .all(|(shard_id, conns)| conns.len() >= self.shard_target(shard_id)),
+        }
+    }
+
+    // How many connections the pool aims to keep to the given shard.
+    // Meaningless in the PerHost mode.
+    fn shard_target(&self, shard_id: usize) -> usize {
+        match self.pool_config.pool_size {
+            PoolSize::PerHost(target) | PoolSize::PerShard(target) => target.get(),
+            PoolSize::Adaptive(params) => self
+                .adaptive_shards
+                .get(shard_id)
+                .map_or(params.min_per_shard.get(), |s| s.target),
+        }
+    }
+
+    // Opens or closes connections according to the load, in the adaptive mode.
+    fn adjust_adaptive_pool_size(&mut self, params: &AdaptivePoolSize) {
+        let now = tokio::time::Instant::now();
+        let mut closed_any = false;
+
+        for shard_id in 0..self.conns.len() {
+            let in_flight = self.conns[shard_id]
+                .iter()
+                .map(|conn| conn.in_flight_requests())
+                .sum::<usize>();
+            let open_connections = self.conns[shard_id].len();
+            let state = &mut self.adaptive_shards[shard_id];
+
+            match params.decide(state, open_connections, in_flight, now) {
+                AdaptiveDecision::Grow => {
+                    state.target += 1;
+                    let target = state.target;
+                    debug!(
+                        "[{}] {} requests in flight on {} connections to shard {}, growing to {} connections",
+                        self.endpoint_description(),
+                        in_flight,
+                        open_connections,
+                        shard_id,
+                        target,
+                    );
+                }
+                AdaptiveDecision::Shrink => {
+                    state.target = open_connections - 1;
+                    let conns = &mut self.conns[shard_id];
+                    let idx = (0..conns.len())
+                        .min_by_key(|&i| conns[i].in_flight_requests())
+                        .unwrap();
+                    // Requests in flight hold their own references to the connection,
+                    // so it is closed only after they complete
+                    let conn = conns.swap_remove(idx);
+                    debug!(
+                        "[{}] Load of shard {} is low, closing connection {:p}",
+                        self.endpoint_description(),
+                        shard_id,
+                        Arc::as_ptr(&conn),
+                    );
+                    closed_any = true;
+                }
+                AdaptiveDecision::Keep => {}
             }
         }
+
+        if closed_any {
+            // At least `min_per_shard` connections are kept, so the pool isn't empty
+            self.update_shared_conns(None);
+        }
     }
 
     fn is_empty(&self) -> bool {
@@ -623,6 +1161,33 @@ impl PoolRefiller {
         !self.is_filling() && !self.is_full()
     }
 
+    // If every shard which lacks connections is backed off, returns how long
+    // to wait until the first of them may be connected to again.
+    fn shard_backoff_delay(&self) -> Option<Duration> {
+        if self.is_empty()
+            || !self.can_use_shard_aware_port()
+            || matches!(self.pool_config.pool_size, PoolSize::PerHost(_))
+        {
+            return None;
+        }
+
+        let now = tokio::time::Instant::now();
+        let mut earliest: Option<tokio::time::Instant> = None;
+        for (shard_id, conns) in self.conns.iter().enumerate() {
+            if conns.len() >= self.shard_target(shard_id) {
+                continue;
+            }
+            let retry_at = self
+                .refill_delay_strategy
+                .shard_retry_time(shard_id as Shard, now)?;
+            earliest = Some(match earliest {
+                Some(earliest) => std::cmp::min(earliest, retry_at),
+                None => retry_at,
+            });
+        }
+        earliest.map(|earliest| earliest.saturating_duration_since(now))
+    }
+
     fn can_use_shard_aware_port(&self) -> bool {
         self.sharder.is_some()
             && self.shard_aware_port.is_some()
@@ -633,6 +1198,12 @@ impl PoolRefiller {
     // Futures which open the connections are pushed to the `ready_connections`
     // FuturesUnordered structure, and their results are processed in the main loop.
     fn start_filling(&mut self) {
+        {
+            let mut health = self.health.lock().unwrap();
+            health.last_refill = Some(Instant::now());
+            health.uses_shard_aware_port = self.can_use_shard_aware_port();
+        }
+
         if self.is_empty() {
             // If the pool is empty, it might mean that the node is not alive.
             // It is more likely than not that the next connection attempt will
@@ -646,14 +1217,29 @@ impl PoolRefiller {
         }
 
         if self.can_use_shard_aware_port() {
-            // Only use the shard-aware port if we have a PerShard strategy
-            if let PoolSize::PerShard(target) = self.pool_config.pool_size {
-                // Try to fill up each shard up to `target` connections
+            // Only use the shard-aware port if we have a per-shard strategy
+            if !matches!(self.pool_config.pool_size, PoolSize::PerHost(_)) {
+                // Try to fill up each shard up to its target number of connections
+                let now = tokio::time::Instant::now();
                 for (shard_id, shard_conns) in self.conns.iter().enumerate() {
-                    let to_open_count = target.get().saturating_sub(shard_conns.len());
+                    let to_open_count = self
+                        .shard_target(shard_id)
+                        .saturating_sub(shard_conns.len());
                     if to_open_count == 0 {
                         continue;
                     }
+                    if self
+                        .refill_delay_strategy
+                        .shard_retry_time(shard_id as Shard, now)
+                        .is_some()
+                    {
+                        trace!(
+                            "[{}] Shard {} is backed off, not opening connections to it",
+                            self.endpoint_description(),
+                            shard_id,
+                        );
+                        continue;
+                    }
                     trace!(
                         "[{}] Will open {} connections to shard {}",
                         self.endpoint_description(),
@@ -673,10 +1259,11 @@ impl PoolRefiller {
             PoolSize::PerHost(target) => {
                 target.get().saturating_sub(self.active_connection_count())
             }
-            PoolSize::PerShard(target) => self
+            PoolSize::PerShard(_) | PoolSize::Adaptive(_) => self
                 .conns
                 .iter()
-                .map(|conns| target.get().saturating_sub(conns.len()))
+                .enumerate()
+                .map(|(shard_id, conns)| self.shard_target(shard_id).saturating_sub(conns.len()))
                 .sum::<usize>(),
         };
         // When connecting to Scylla through non-shard-aware port,
@@ -698,7 +1285,19 @@ impl PoolRefiller {
     fn handle_ready_connection(&mut self, evt: OpenedConnectionEvent) {
         match evt.result {
             Err(err) => {
-                if evt.requested_shard.is_some() {
+                self.health
+                    .lock()
+                    .unwrap()
+                    .record_error(evt.requested_shard.map(|s| s as usize), &err);
+                if let Some(shard) = evt.requested_shard {
+                    // The shard is backed off according to its own schedule.
+                    let endpoint = self.endpoint.read().unwrap().clone();
+                    self.refill_delay_strategy.on_shard_error(
+                        &endpoint,
+                        shard,
+                        tokio::time::Instant::now(),
+                    );
+
                     // If we failed to connect to a shard-aware port,
                     // fall back to the non-shard-aware port.
                     // Don't set `had_error_since_last_refill` here;
@@ -740,6 +1339,8 @@ impl PoolRefiller {
                 let sharder = shard_info.map(|s| s.get_sharder());
                 let shard_id = shard_info.map_or(0, |s| s.shard as usize);
                 self.maybe_reshard(sharder);
+                self.refill_delay_strategy
+                    .on_shard_connected(shard_id as Shard);
 
                 // Update the shard-aware port
                 if self.shard_aware_port != connection.get_shard_aware_port() {
@@ -749,6 +1350,8 @@ impl PoolRefiller {
                         connection.get_shard_aware_port(),
                     );
                     self.shard_aware_port = connection.get_shard_aware_port();
+                    self.health.lock().unwrap().uses_shard_aware_port =
+                        self.can_use_shard_aware_port();
                 }
 
                 // Before the connection can be put to the pool, we need
@@ -773,9 +1376,13 @@ impl PoolRefiller {
                 // the pool filling strategy
                 let can_be_accepted = match self.pool_config.pool_size {
                     PoolSize::PerHost(target) => self.active_connection_count() < target.get(),
-                    PoolSize::PerShard(target) => self.conns[shard_id].len() < target.get(),
+                    PoolSize::PerShard(_) | PoolSize::Adaptive(_) => {
+                        self.conns[shard_id].len() < self.shard_target(shard_id)
+                    }
                 };
 
+                let stale_connection_idx = self.stale_tls_connection_index(shard_id);
+
                 if can_be_accepted {
                     // Don't complain and just put the connection to the pool.
                     // If this was a shard-aware port connection which missed
@@ -795,6 +1402,24 @@ impl PoolRefiller {
                         .push(wait_for_error(Arc::downgrade(&conn), error_receiver).boxed());
                     self.conns[shard_id].push(conn);
 
+                    self.update_shared_conns(None);
+                } else if let Some(idx) = stale_connection_idx {
+                    // The shard is full, but one of its connections uses an outdated
+                    // TLS context. Swap it for the new connection. Requests which
+                    // already use the old connection keep it alive until they complete.
+                    let conn = Arc::new(connection);
+                    debug!(
+                        "[{}] Replacing connection {:p} with outdated TLS context in shard {} with {:p}",
+                        self.endpoint_description(),
+                        Arc::as_ptr(&self.conns[shard_id][idx]),
+                        shard_id,
+                        Arc::as_ptr(&conn),
+                    );
+
+                    self.connection_errors
+                        .push(wait_for_error(Arc::downgrade(&conn), error_receiver).boxed());
+                    self.conns[shard_id][idx] = conn;
+
                     self.update_shared_conns(None);
                 } else if evt.requested_shard.is_some() {
                     // This indicates that some shard-aware connections
@@ -842,7 +1467,7 @@ impl PoolRefiller {
         }
     }
 
-    #[cfg(not(feature = "cloud"))]
+    #[cfg(not(any(feature = "cloud", feature = "cloud-rustls")))]
     fn maybe_translate_for_serverless(
         &self,
         endpoint: UntranslatedEndpoint,
@@ -851,7 +1476,7 @@ impl PoolRefiller {
         async move { endpoint }
     }
 
-    #[cfg(feature = "cloud")]
+    #[cfg(any(feature = "cloud", feature = "cloud-rustls"))]
     fn maybe_translate_for_serverless(
         &self,
         mut endpoint: UntranslatedEndpoint,
@@ -973,6 +1598,13 @@ impl PoolRefiller {
         self.conns.resize_with(shard_count, Vec::new);
 
         self.excess_connections.clear();
+        self.refill_delay_strategy.clear_shard_backoffs();
+        self.adaptive_shards =
+            Self::initial_adaptive_shards(&self.pool_config.pool_size, shard_count);
+
+        let mut health = self.health.lock().unwrap();
+        health.shards = vec![ShardHealth::default(); shard_count];
+        health.uses_shard_aware_port = self.can_use_shard_aware_port();
     }
 
     // Updates `shared_conns` based on `conns`.
@@ -1167,6 +1799,58 @@ impl PoolRefiller {
         );
     }
 
+    // Returns the index of a connection in the given shard which was opened
+    // with an older TLS context than the current one, if there is any.
+    fn stale_tls_connection_index(&self, shard_id: usize) -> Option<usize> {
+        self.pool_config.tls_reload_check_interval?;
+        let current_generation = self.pool_config.connection_config.tls_generation()?;
+        self.conns[shard_id]
+            .iter()
+            .position(|conn| conn.tls_generation() != Some(current_generation))
+    }
+
+    // Opens a replacement connection for each connection opened with an outdated TLS context.
+    // Stale connections stay in the pool until their replacements are ready.
+    fn start_replacing_stale_tls_connections(&mut self) {
+        if self.is_filling() {
+            // Replacements will be opened in one of the next checks
+            return;
+        }
+
+        let current_generation = self.pool_config.connection_config.tls_generation();
+        let stale_per_shard: Vec<usize> = self
+            .conns
+            .iter()
+            .map(|conns| {
+                conns
+                    .iter()
+                    .filter(|conn| conn.tls_generation() != current_generation)
+                    .count()
+            })
+            .collect();
+
+        let stale_count = stale_per_shard.iter().sum::<usize>();
+        if stale_count == 0 {
+            return;
+        }
+        debug!(
+            "[{}] TLS context changed, replacing {} connections",
+            self.endpoint_description(),
+            stale_count,
+        );
+
+        let use_shard_aware_port = self.can_use_shard_aware_port();
+        for (shard_id, stale) in stale_per_shard.into_iter().enumerate() {
+            for _ in 0..stale {
+                if use_shard_aware_port {
+                    self.start_opening_connection(Some(shard_id as Shard));
+                } else {
+                    self.start_opening_connection(None);
+                }
+            }
+        }
+    }
+
     fn has_connections(&self) -> bool {
         self.conns.iter().any(|v| !v.is_empty())
     }
@@ -1177,7 +1861,7 @@ impl PoolRefiller {
 
     fn excess_connection_limit(&self) -> usize {
         match self.pool_config.pool_size {
-            PoolSize::PerShard(_) => {
+            PoolSize::PerShard(_) | PoolSize::Adaptive(_) => {
                 EXCESS_CONNECTION_BOUND_PER_SHARD_MULTIPLIER
                     * self
                         .sharder
@@ -1246,10 +1930,14 @@ async fn open_connection_to_shard_aware_port(
 
 #[cfg(test)]
 mod tests {
-    use super::open_connection_to_shard_aware_port;
+    use super::{
+        open_connection_to_shard_aware_port, AdaptiveDecision, AdaptivePoolSize,
+        AdaptiveShardState, NodeConnectionPool, PoolHealth, ShardHealth,
+    };
     use crate::routing::{ShardCount, Sharder};
     use crate::transport::cluster::ContactPoint;
     use crate::transport::connection::ConnectionConfig;
+    use crate::transport::errors::QueryError;
     use crate::transport::topology::UntranslatedEndpoint;
     use std::net::{SocketAddr, ToSocketAddrs};
 
@@ -1271,7 +1959,7 @@ mod tests {
         let connection_config = ConnectionConfig {
             compression: None,
             tcp_nodelay: true,
-            #[cfg(feature = "ssl")]
+            #[cfg(any(feature = "ssl", feature = "rustls"))]
             ssl_config: None,
             ..Default::default()
         };
@@ -1289,6 +1977,7 @@ mod tests {
                 UntranslatedEndpoint::ContactPoint(ContactPoint {
                     address: connect_address,
                     datacenter: None,
+                    hostname: None,
                 }),
                 0,
                 sharder.clone(),
@@ -1303,4 +1992,219 @@ mod tests {
             res.unwrap();
         }
     }
+
+    #[test]
+    fn pool_health_records_errors() {
+        let mut health = PoolHealth {
+            shards: vec![ShardHealth::default(); 2],
+            ..Default::default()
+        };
+
+        health.record_error(None, &QueryError::TimeoutError);
+        health.record_error(Some(1), &QueryError::ProtocolError("test"));
+        // Errors for shards which are out of range only count towards the node
+        health.record_error(Some(5), &QueryError::TimeoutError);
+
+        assert_eq!(health.connection_errors, 3);
+        assert!(matches!(health.last_error, Some(QueryError::TimeoutError)));
+        assert_eq!(health.shards[0].connection_errors, 0);
+        assert!(health.shards[0].last_error.is_none());
+        assert_eq!(health.shards[1].connection_errors, 1);
+        assert!(matches!(
+            health.shards[1].last_error,
+            Some(QueryError::ProtocolError("test"))
+        ));
+    }
+
+    #[test]
+    fn load_of_connection_for_shard_falls_back_to_other_shards() {
+        let load = |in_flight: &usize| *in_flight;
+        let shard_conns = vec![vec![7, 3], vec![], vec![5, 4]];
+
+        // The least loaded connection to the shard is used
+        assert_eq!(
+            NodeConnectionPool::load_of_connection_for_shard(0, &shard_conns, load),
+            Some(3)
+        );
+        assert_eq!(
+            NodeConnectionPool::load_of_connection_for_shard(2, &shard_conns, load),
+            Some(4)
+        );
+
+        // Without connections to the shard, the least loaded connection to any shard is used
+        assert_eq!(
+            NodeConnectionPool::load_of_connection_for_shard(1, &shard_conns, load),
+            Some(3)
+        );
+
+        let no_conns: Vec<Vec<usize>> = vec![vec![], vec![]];
+        assert_eq!(
+            NodeConnectionPool::load_of_connection_for_shard(1, &no_conns, load),
+            None
+        );
+    }
+
+    #[test]
+    fn adaptive_pool_size_decisions() {
+        use std::num::NonZeroUsize;
+        use std::time::Duration;
+
+        let params = AdaptivePoolSize {
+            min_per_shard: NonZeroUsize::new(1).unwrap(),
+            max_per_shard: NonZeroUsize::new(3).unwrap(),
+            in_flight_threshold: 100,
+            idle_timeout: Duration::from_secs(10),
+        };
+        let mut state = AdaptiveShardState {
+            target: 1,
+            low_load_since: None,
+        };
+        let start = tokio::time::Instant::now();
+
+        // Moderate load is fine
+        assert_eq!(
+            params.decide(&mut state, 1, 100, start),
+            AdaptiveDecision::Keep
+        );
+
+        // Overloaded connection, grow
+        assert_eq!(
+            params.decide(&mut state, 1, 101, start),
+            AdaptiveDecision::Grow
+        );
+        state.target = 2;
+
+        // The new connection is not open yet, wait for it
+        assert_eq!(
+            params.decide(&mut state, 1, 500, start),
+            AdaptiveDecision::Keep
+        );
+        assert_eq!(
+            params.decide(&mut state, 2, 201, start),
+            AdaptiveDecision::Grow
+        );
+        state.target = 3;
+
+        // Maximum reached
+        assert_eq!(
+            params.decide(&mut state, 3, 1000, start),
+            AdaptiveDecision::Keep
+        );
+
+        // Low load has to last for the idle timeout
+        assert_eq!(
+            params.decide(&mut state, 3, 100, start),
+            AdaptiveDecision::Keep
+        );
+        let later = start + Duration::from_secs(5);
+        assert_eq!(
+            params.decide(&mut state, 3, 100, later),
+            AdaptiveDecision::Keep
+        );
+        let later = start + Duration::from_secs(10);
+        assert_eq!(
+            params.decide(&mut state, 3, 100, later),
+            AdaptiveDecision::Shrink
+        );
+        state.target = 2;
+
+        // A load spike resets the idle period
+        assert_eq!(
+            params.decide(&mut state, 2, 50, later),
+            AdaptiveDecision::Keep
+        );
+        let later = later + Duration::from_secs(5);
+        assert_eq!(
+            params.decide(&mut state, 2, 80, later),
+            AdaptiveDecision::Keep
+        );
+        let later = later + Duration::from_secs(6);
+        assert_eq!(
+            params.decide(&mut state, 2, 50, later),
+            AdaptiveDecision::Keep
+        );
+        let later = later + Duration::from_secs(10);
+        assert_eq!(
+            params.decide(&mut state, 2, 50, later),
+            AdaptiveDecision::Shrink
+        );
+        state.target = 1;
+
+        // Never shrink below the minimum
+        let later = later + Duration::from_secs(100);
+        assert_eq!(
+            params.decide(&mut state, 1, 0, later),
+            AdaptiveDecision::Keep
+        );
+    }
+
+    #[tokio::test]
+    async fn refill_delay_strategy_backs_off_shards() {
+        use super::RefillDelayStrategy;
+        use crate::routing::Shard;
+        use crate::transport::reconnection_policy::{ReconnectionPolicy, ReconnectionSchedule};
+        use std::sync::{Arc, Mutex};
+        use std::time::Duration;
+
+        // Delays grow by a second with each attempt, and the shards asked about are recorded
+        #[derive(Debug, Default)]
+        struct RecordingPolicy {
+            shards: Mutex<Vec<Option<Shard>>>,
+        }
+
+        struct LinearSchedule(Duration);
+
+        impl ReconnectionSchedule for LinearSchedule {
+            fn next_delay(&mut self) -> Duration {
+                self.0 += Duration::from_secs(1);
+                self.0
+            }
+        }
+
+        impl ReconnectionPolicy for RecordingPolicy {
+            fn new_schedule(
+                &self,
+                _endpoint: &UntranslatedEndpoint,
+                shard: Option<Shard>,
+            ) -> Box<dyn ReconnectionSchedule> {
+                self.shards.lock().unwrap().push(shard);
+                Box::new(LinearSchedule(Duration::ZERO))
+            }
+        }
+
+        let endpoint = UntranslatedEndpoint::ContactPoint(ContactPoint {
+            address: "127.0.0.1:9042".parse().unwrap(),
+            datacenter: None,
+            hostname: None,
+        });
+        let policy = Arc::new(RecordingPolicy::default());
+        let mut strategy = RefillDelayStrategy::new(policy.clone(), &endpoint);
+        assert_eq!(strategy.get_delay(), Duration::from_secs(1));
+
+        let now = tokio::time::Instant::now();
+        assert_eq!(strategy.shard_retry_time(2, now), None);
+
+        // Each failure of a shard prolongs its own backoff only.
+        strategy.on_shard_error(&endpoint, 2, now);
+        assert_eq!(
+            strategy.shard_retry_time(2, now),
+            Some(now + Duration::from_secs(1))
+        );
+        strategy.on_shard_error(&endpoint, 2, now);
+        assert_eq!(
+            strategy.shard_retry_time(2, now),
+            Some(now + Duration::from_secs(2))
+        );
+        assert_eq!(strategy.shard_retry_time(1, now), None);
+        assert_eq!(
+            strategy.shard_retry_time(2, now + Duration::from_secs(2)),
+            None
+        );
+
+        // A connection to the shard resets its backoff.
+        strategy.on_shard_connected(2);
+        assert_eq!(strategy.shard_retry_time(2, now), None);
+
+        assert_eq!(*policy.shards.lock().unwrap(), vec![None, Some(2)]);
+    }
 }
@@ -4,10 +4,17 @@
 //! [`Peer`](crate::transport::topology::Peer)s. Currently, they are used
 //! by the [`Session`](crate::transport::session::Session) to determine whether
 //! connections should be opened to a given node or not.
+//!
+//! Filters can be combined with [`AndHostFilter`], [`OrHostFilter`] and
+//! [`NotHostFilter`]. To change the filter while the session is running, wrap it
+//! in an [`UpdatableHostFilter`].
 
 use std::collections::HashSet;
 use std::io::Error;
 use std::net::{SocketAddr, ToSocketAddrs};
+use std::sync::{Arc, RwLock};
+
+use uuid::Uuid;
 
 use crate::transport::topology::Peer;
 
@@ -40,16 +47,24 @@ impl AllowListHostFilter {
         I: IntoIterator<Item = A>,
         A: ToSocketAddrs,
     {
-        // I couldn't get the iterator combinators to work
-        let mut allowed = HashSet::new();
-        for item in allowed_iter {
-            for addr in item.to_socket_addrs()? {
-                allowed.insert(addr);
-            }
-        }
+        Ok(Self {
+            allowed: resolve_addresses(allowed_iter)?,
+        })
+    }
+}
 
-        Ok(Self { allowed })
+fn resolve_addresses<I, A>(addr_iter: I) -> Result<HashSet<SocketAddr>, Error>
+where
+    I: IntoIterator<Item = A>,
+    A: ToSocketAddrs,
+{
+    let mut addresses = HashSet::new();
+    for item in addr_iter {
+        for addr in item.to_socket_addrs()? {
+            addresses.insert(addr);
+        }
     }
+    Ok(addresses)
 }
 
 impl HostFilter for AllowListHostFilter {
@@ -82,3 +97,314 @@ impl HostFilter for DcHostFilter {
         peer.datacenter.as_ref() == Some(&self.local_dc)
     }
 }
+
+/// Rejects nodes whose addresses are present in the deny list provided
+/// during filter's construction, and accepts all the others.
+pub struct DenyListHostFilter {
+    denied: HashSet<SocketAddr>,
+}
+
+impl DenyListHostFilter {
+    /// Creates a new `DenyListHostFilter` which rejects nodes from the list.
+    pub fn new<I, A>(denied_iter: I) -> Result<Self, Error>
+    where
+        I: IntoIterator<Item = A>,
+        A: ToSocketAddrs,
+    {
+        Ok(Self {
+            denied: resolve_addresses(denied_iter)?,
+        })
+    }
+}
+
+impl HostFilter for DenyListHostFilter {
+    fn accept(&self, peer: &Peer) -> bool {
+        match peer.address {
+            super::NodeAddr::Translatable(addr) => !self.denied.contains(&addr),
+            // See the comment in `AllowListHostFilter::accept`.
+            super::NodeAddr::Untranslatable(_) => true,
+        }
+    }
+}
+
+/// Accepts nodes from given rack of given DC.
+pub struct RackHostFilter {
+    local_dc: String,
+    local_rack: String,
+}
+
+impl RackHostFilter {
+    /// Creates a new `RackHostFilter` that accepts nodes only from the
+    /// `local_rack` of the `local_dc`.
+    pub fn new(local_dc: String, local_rack: String) -> Self {
+        Self {
+            local_dc,
+            local_rack,
+        }
+    }
+}
+
+impl HostFilter for RackHostFilter {
+    fn accept(&self, peer: &Peer) -> bool {
+        peer.datacenter.as_ref() == Some(&self.local_dc)
+            && peer.rack.as_ref() == Some(&self.local_rack)
+    }
+}
+
+/// Accepts nodes whose host ids are present in the list provided
+/// during filter's construction.
+pub struct HostIdHostFilter {
+    host_ids: HashSet<Uuid>,
+}
+
+impl HostIdHostFilter {
+    /// Creates a new `HostIdHostFilter` which only accepts nodes with the given host ids.
+    pub fn new(host_ids: impl IntoIterator<Item = Uuid>) -> Self {
+        Self {
+            host_ids: host_ids.into_iter().collect(),
+        }
+    }
+}
+
+impl HostFilter for HostIdHostFilter {
+    fn accept(&self, peer: &Peer) -> bool {
+        self.host_ids.contains(&peer.host_id)
+    }
+}
+
+/// Accepts nodes accepted by all of the given filters.
+/// Accepts all nodes if no filters are given.
+pub struct AndHostFilter {
+    filters: Vec<Arc<dyn HostFilter>>,
+}
+
+impl AndHostFilter {
+    /// Creates a new `AndHostFilter` which accepts nodes accepted by all of `filters`.
+    pub fn new(filters: impl IntoIterator<Item = Arc<dyn HostFilter>>) -> Self {
+        Self {
+            filters: filters.into_iter().collect(),
+        }
+    }
+}
+
+impl HostFilter for AndHostFilter {
+    fn accept(&self, peer: &Peer) -> bool {
+        self.filters.iter().all(|filter| filter.accept(peer))
+    }
+}
+
+/// Accepts nodes accepted by any of the given filters.
+/// Rejects all nodes if no filters are given.
+pub struct OrHostFilter {
+    filters: Vec<Arc<dyn HostFilter>>,
+}
+
+impl OrHostFilter {
+    /// Creates a new `OrHostFilter` which accepts nodes accepted by any of `filters`.
+    pub fn new(filters: impl IntoIterator<Item = Arc<dyn HostFilter>>) -> Self {
+        Self {
+            filters: filters.into_iter().collect(),
+        }
+    }
+}
+
+impl HostFilter for OrHostFilter {
+    fn accept(&self, peer: &Peer) -> bool {
+        self.filters.iter().any(|filter| filter.accept(peer))
+    }
+}
+
+/// Accepts nodes rejected by the given filter.
+pub struct NotHostFilter {
+    filter: Arc<dyn HostFilter>,
+}
+
+impl NotHostFilter {
+    /// Creates a new `NotHostFilter` which negates `filter`.
+    pub fn new(filter: Arc<dyn HostFilter>) -> Self {
+        Self { filter }
+    }
+}
+
+impl HostFilter for NotHostFilter {
+    fn accept(&self, peer: &Peer) -> bool {
+        !self.filter.accept(peer)
+    }
+}
+
+/// A filter which can be replaced while the session is running.
+///
+/// Pass a clone of the `Arc` holding it to
+/// [`SessionBuilder::host_filter`](crate::transport::session_builder::GenericSessionBuilder::host_filter)
+/// and keep the other one to call [`set`](Self::set) later. The new filter takes effect on
+/// the next topology refresh: connections are opened to nodes which became accepted,
+/// and the pools of nodes which became rejected are closed. Call
+/// [`Session::refresh_metadata`](crate::Session::refresh_metadata) to apply it immediately.
+///
+/// # Example
+/// ```rust
+/// # use scylla::{Session, SessionBuilder};
+/// # async fn example() -> Result<(), Box<dyn std::error::Error>> {
+/// use scylla::transport::host_filter::{
+///     AcceptAllHostFilter, DenyListHostFilter, UpdatableHostFilter,
+/// };
+/// use std::sync::Arc;
+///
+/// let host_filter = Arc::new(UpdatableHostFilter::new(Arc::new(AcceptAllHostFilter)));
+/// let session: Session = SessionBuilder::new()
+///     .known_node("127.0.0.1:9042")
+///     .host_filter(host_filter.clone())
+///     .build()
+///     .await?;
+///
+/// // Drain a node before maintenance.
+/// host_filter.set(Arc::new(DenyListHostFilter::new(["127.0.0.2:9042"])?));
+/// session.refresh_metadata().await?;
+/// # Ok(())
+/// # }
+/// ```
+pub struct UpdatableHostFilter {
+    filter: RwLock<Arc<dyn HostFilter>>,
+}
+
+impl UpdatableHostFilter {
+    /// Creates a new `UpdatableHostFilter` which initially behaves like `filter`.
+    pub fn new(filter: Arc<dyn HostFilter>) -> Self {
+        Self {
+            filter: RwLock::new(filter),
+        }
+    }
+
+    /// Replaces the current filter with `filter`.
+    pub fn set(&self, filter: Arc<dyn HostFilter>) {
+        *self.filter.write().unwrap() = filter;
+    }
+
+    /// Returns the current filter.
+    pub fn get(&self) -> Arc<dyn HostFilter> {
+        self.filter.read().unwrap().clone()
+    }
+}
+
+impl HostFilter for UpdatableHostFilter {
+    fn accept(&self, peer: &Peer) -> bool {
+        self.get().accept(peer)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::transport::cluster::ClusterData;
+    use crate::transport::locator::test::mock_metadata_for_token_aware_tests;
+    use crate::transport::NodeAddr;
+    use std::collections::HashMap;
+
+    fn peer(port: u16, dc: &str, rack: &str) -> Peer {
+        Peer {
+            host_id: Uuid::from_u128(port as u128),
+            address: NodeAddr::Translatable(SocketAddr::from(([127, 0, 0, 1], port))),
+            tokens: Vec::new(),
+            datacenter: Some(dc.to_owned()),
+            rack: Some(rack.to_owned()),
+            release_version: None,
+            schema_version: None,
+        }
+    }
+
+    #[test]
+    fn test_host_filters() {
+        let a = peer(1, "eu", "r1");
+        let b = peer(2, "eu", "r2");
+        let c = peer(3, "us", "r1");
+
+        let accepted = |filter: &dyn HostFilter| -> Vec<u16> {
+            [&a, &b, &c]
+                .into_iter()
+                .filter(|peer| filter.accept(peer))
+                .map(|peer| peer.address.into_inner().port())
+                .collect()
+        };
+
+        let deny = DenyListHostFilter::new(["127.0.0.1:2"]).unwrap();
+        assert_eq!(accepted(&deny), vec![1, 3]);
+
+        let rack = RackHostFilter::new("eu".to_owned(), "r1".to_owned());
+        assert_eq!(accepted(&rack), vec![1]);
+
+        let host_ids = HostIdHostFilter::new([Uuid::from_u128(2), Uuid::from_u128(3)]);
+        assert_eq!(accepted(&host_ids), vec![2, 3]);
+
+        let eu: Arc<dyn HostFilter> = Arc::new(DcHostFilter::new("eu".to_owned()));
+        let r1_anywhere: Arc<dyn HostFilter> = Arc::new(OrHostFilter::new([
+            Arc::new(RackHostFilter::new("eu".to_owned(), "r1".to_owned())) as _,
+            Arc::new(RackHostFilter::new("us".to_owned(), "r1".to_owned())) as _,
+        ]));
+
+        assert_eq!(accepted(&NotHostFilter::new(eu.clone())), vec![3]);
+        assert_eq!(
+            accepted(&AndHostFilter::new([eu.clone(), r1_anywhere])),
+            vec![1]
+        );
+        assert_eq!(accepted(&AndHostFilter::new([])), vec![1, 2, 3]);
+        assert_eq!(accepted(&OrHostFilter::new([])), Vec::<u16>::new());
+
+        let updatable = UpdatableHostFilter::new(eu);
+        assert_eq!(accepted(&updatable), vec![1, 2]);
+        updatable.set(Arc::new(deny));
+        assert_eq!(accepted(&updatable), vec![1, 3]);
+    }
+
+    #[tokio::test]
+    async fn test_updated_host_filter_applies_on_refresh() {
+        let eu: Arc<dyn HostFilter> = Arc::new(DcHostFilter::new("eu".to_owned()));
+        let host_filter = UpdatableHostFilter::new(eu.clone());
+        let metadata = mock_metadata_for_token_aware_tests();
+        let host_ids: Vec<Uuid> = metadata.peers.iter().map(|peer| peer.host_id).collect();
+
+        let cluster = ClusterData::new(
+            metadata,
+            &Default::default(),
+            &HashMap::new(),
+            &None,
+            Some(&host_filter),
+            &HashMap::new(),
+        )
+        .await;
+        for node in cluster.get_nodes_info() {
+            assert_eq!(node.is_enabled(), node.datacenter.as_deref() == Some("eu"));
+        }
+
+        // Keep the "eu" nodes of rack "r1" only.
+        host_filter.set(Arc::new(AndHostFilter::new([
+            eu,
+            Arc::new(RackHostFilter::new("eu".to_owned(), "r1".to_owned())) as _,
+        ])));
+        // The mock generates random host ids, so the refreshed metadata reuses the old ones.
+        let mut metadata = mock_metadata_for_token_aware_tests();
+        for (peer, host_id) in metadata.peers.iter_mut().zip(host_ids) {
+            peer.host_id = host_id;
+        }
+        let refreshed = ClusterData::new(
+            metadata,
+            &Default::default(),
+            &cluster.known_peers,
+            &None,
+            Some(&host_filter),
+            &HashMap::new(),
+        )
+        .await;
+        for node in refreshed.get_nodes_info() {
+            let is_enabled =
+                node.datacenter.as_deref() == Some("eu") && node.rack.as_deref() == Some("r1");
+            assert_eq!(node.is_enabled(), is_enabled);
+
+            // Nodes whose state didn't change are reused, the others are recreated.
+            let old_node = &cluster.known_peers[&node.host_id];
+            assert_eq!(
+                Arc::ptr_eq(old_node, node),
+                old_node.is_enabled() == is_enabled
+            );
+        }
+    }
+}
@@ -32,8 +32,8 @@ use crate::frame::{
 };
 use crate::history::{self, HistoryListener};
 use crate::routing::Token;
-use crate::statement::Consistency;
 use crate::statement::{prepared_statement::PreparedStatement, query::Query};
+use crate::statement::{Consistency, NodeTarget};
 use crate::transport::cluster::ClusterData;
 use crate::transport::connection::{Connection, NonErrorQueryResponse, QueryResponse};
 use crate::transport::load_balancing::{self, RoutingInfo};
@@ -133,6 +133,7 @@ impl RowIterator {
     pub(crate) async fn new_for_query(
         mut query: Query,
         values: SerializedValues,
+        token: Option<Token>,
         execution_profile: Arc<ExecutionProfileInner>,
         cluster_data: Arc<ClusterData>,
         metrics: Arc<Metrics>,
@@ -162,7 +163,13 @@ impl RowIterator {
             let query_ref = &query;
             let values_ref = &values;
 
-            let choose_connection = |node: Arc<Node>| async move { node.random_connection().await };
+            let token_ref = &token;
+            let choose_connection = |node: Arc<Node>| async move {
+                match token_ref {
+                    Some(token) => node.connection_for_token(token.clone()).await,
+                    None => node.random_connection().await,
+                }
+            };
 
             let page_query = |connection: Arc<Connection>,
                               consistency: Consistency,
@@ -188,7 +195,8 @@ impl RowIterator {
                 sender: sender.into(),
                 choose_connection,
                 page_query,
-                statement_info: RoutingInfo::default(),
+                statement_info: query.routing_info(token.clone(), consistency),
+                node_target: query.config.node_target.clone(),
                 query_is_idempotent: query.config.is_idempotent,
                 query_consistency: consistency,
                 retry_session,
@@ -238,7 +246,7 @@ impl RowIterator {
             let statement_info = RoutingInfo {
                 consistency,
                 serial_consistency: config.prepared.get_serial_consistency(),
-                token: config.token,
+                token: config.token.clone(),
                 keyspace: config.prepared.get_keyspace_name(),
                 is_confirmed_lwt: config.prepared.is_confirmed_lwt(),
             };
@@ -248,9 +256,10 @@ impl RowIterator {
             let partition_key = config.partition_key;
             let token = config.token;
 
+            let token_ref = &token;
             let choose_connection = |node: Arc<Node>| async move {
-                match token {
-                    Some(token) => node.connection_for_token(token).await,
+                match token_ref {
+                    Some(token) => node.connection_for_token(token.clone()).await,
                     None => node.random_connection().await,
                 }
             };
@@ -272,9 +281,10 @@ impl RowIterator {
             let serialized_values_size = config.values.size();
 
             let replicas: Option<smallvec::SmallVec<[_; 8]>> =
-                if let (Some(keyspace), Some(token)) =
-                    (statement_info.keyspace.as_ref(), statement_info.token)
-                {
+                if let (Some(keyspace), Some(token)) = (
+                    statement_info.keyspace.as_ref(),
+                    statement_info.token.clone(),
+                ) {
                     Some(
                         config
                             .cluster_data
@@ -289,7 +299,7 @@ impl RowIterator {
             let span_creator = move || {
                 let span = RequestSpan::new_prepared(
                     partition_key.as_ref(),
-                    token,
+                    token_ref.as_ref(),
                     serialized_values_size,
                 );
                 if let Some(replicas) = replicas.as_ref() {
@@ -303,6 +313,7 @@ impl RowIterator {
                 choose_connection,
                 page_query,
                 statement_info,
+                node_target: config.prepared.config.node_target.clone(),
                 query_is_idempotent: config.prepared.config.is_idempotent,
                 query_consistency: consistency,
                 retry_session,
@@ -468,6 +479,7 @@ struct RowIteratorWorker<'a, ConnFunc, QueryFunc, SpanCreatorFunc> {
     page_query: QueryFunc,
 
     statement_info: RoutingInfo<'a>,
+    node_target: Option<NodeTarget>,
     query_is_idempotent: bool,
     query_consistency: Consistency,
     retry_session: Box<dyn RetrySession>,
@@ -497,8 +509,17 @@ where
     async fn work(mut self, cluster_data: Arc<ClusterData>) -> PageSendAttemptedProof {
         let load_balancer = self.execution_profile.load_balancing_policy.clone();
         let statement_info = self.statement_info.clone();
-        let query_plan =
-            load_balancing::Plan::new(load_balancer.as_ref(), &statement_info, &cluster_data);
+        // A statement sent directly to a node bypasses the load balancer
+        let node_target = self.node_target.clone();
+        let query_plan = match &node_target {
+            Some(target) => itertools::Either::Left(std::iter::once(&target.node)),
+            None => itertools::Either::Right(load_balancing::Plan::new(
+                load_balancer.as_ref(),
+                &statement_info,
+                &cluster_data,
+            )),
+        };
+        let target_shard = node_target.as_ref().and_then(|target| target.shard);
 
         let mut last_error: QueryError =
             QueryError::ProtocolError("Empty query plan - driver bug!");
@@ -511,10 +532,19 @@ where
                 trace_span!(parent: &self.parent_span, "Executing query", node = %node.address);
             // For each node in the plan choose a connection to use
             // This connection will be reused for same node retries to preserve paging cache on the shard
-            let connection: Arc<Connection> = match (self.choose_connection)(node.clone())
-                .instrument(span.clone())
-                .await
-            {
+            let connection_result = match target_shard {
+                Some(shard) => {
+                    node.connection_to_shard(shard)
+                        .instrument(span.clone())
+                        .await
+                }
+                None => {
+                    (self.choose_connection)(node.clone())
+                        .instrument(span.clone())
+                        .await
+                }
+            };
+            let connection: Arc<Connection> = match connection_result {
                 Ok(connection) => connection,
                 Err(e) => {
                     trace!(
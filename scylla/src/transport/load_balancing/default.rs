@@ -1,7 +1,13 @@
+pub use self::circuit_breaker::CircuitBreakerBuilder;
+use self::circuit_breaker::{CircuitBreaker, IteratorWithTrippedNodesLast};
+use self::dc_failover::DcFailover;
+pub use self::dc_failover::{
+    DcFailoverBuilder, DcFailoverEvent, DcFailoverListener, DcFailoverReason,
+};
 use self::latency_awareness::LatencyAwareness;
 pub use self::latency_awareness::LatencyAwarenessBuilder;
 
-use super::{FallbackPlan, LoadBalancingPolicy, NodeRef, RoutingInfo};
+use super::{FallbackPlan, LoadBalancingPolicy, NodeExplanation, NodeRef, RoutingInfo};
 use crate::{
     routing::Token,
     transport::{cluster::ClusterData, locator::ReplicaSet, node::Node, topology::Strategy},
@@ -10,7 +16,7 @@ use itertools::{Either, Itertools};
 use rand::{prelude::SliceRandom, thread_rng, Rng};
 use rand_pcg::Pcg32;
 use scylla_cql::{errors::QueryError, frame::types::SerialConsistency, Consistency};
-use std::{fmt, sync::Arc, time::Duration};
+use std::{cmp, fmt, sync::Arc, time::Duration};
 use tracing::warn;
 
 #[derive(Clone, Copy)]
@@ -44,7 +50,6 @@ impl ReplicaLocationPreference {
         }
     }
 
-    #[allow(unused)]
     fn rack(&self) -> Option<&str> {
         match self {
             Self::Any | Self::Datacenter(_) => None,
@@ -65,17 +70,28 @@ enum StatementType {
     NonLwt,
 }
 
+type NodePredicate = Box<dyn Fn(&NodeRef) -> bool + Send + Sync>;
+
 /// The default load balancing policy.
 ///
 /// It can be configured to be datacenter-aware and token-aware.
 /// Datacenter failover for queries with non local consistency mode is also supported.
 /// Latency awareness is available, althrough not recommended.
+/// Replicas can also be chosen by the number of requests in flight, see
+/// [`DefaultPolicyBuilder::least_outstanding_requests`].
+/// Unhealthy nodes can be deprioritized by a circuit breaker, see
+/// [`DefaultPolicyBuilder::circuit_breaker`].
 pub struct DefaultPolicy {
     preferences: ReplicaLocationPreference,
     is_token_aware: bool,
     permit_dc_failover: bool,
-    pick_predicate: Box<dyn Fn(&NodeRef) -> bool + Send + Sync>,
+    pick_predicate: NodePredicate,
+    // Like `pick_predicate`, but also accepts nodes which may be probed by the circuit breaker
+    probe_pick_predicate: Option<NodePredicate>,
     latency_awareness: Option<LatencyAwareness>,
+    least_outstanding_requests: bool,
+    dc_failover: Option<DcFailover>,
+    circuit_breaker: Option<Arc<CircuitBreaker>>,
     fixed_shuffle_seed: Option<u64>,
 }
 
@@ -86,6 +102,12 @@ impl fmt::Debug for DefaultPolicy {
             .field("is_token_aware", &self.is_token_aware)
             .field("permit_dc_failover", &self.permit_dc_failover)
             .field("latency_awareness", &self.latency_awareness)
+            .field(
+                "least_outstanding_requests",
+                &self.least_outstanding_requests,
+            )
+            .field("dc_failover", &self.dc_failover)
+            .field("circuit_breaker", &self.circuit_breaker)
             .field("fixed_shuffle_seed", &self.fixed_shuffle_seed)
             .finish_non_exhaustive()
     }
@@ -93,114 +115,54 @@ impl fmt::Debug for DefaultPolicy {
 
 impl LoadBalancingPolicy for DefaultPolicy {
     fn pick<'a>(&'a self, query: &'a RoutingInfo, cluster: &'a ClusterData) -> Option<NodeRef<'a>> {
-        let routing_info = self.routing_info(query, cluster);
-        if let Some(ref token_with_strategy) = routing_info.token_with_strategy {
-            if self.preferences.datacenter().is_some()
-                && !self.permit_dc_failover
-                && matches!(
-                    token_with_strategy.strategy,
-                    Strategy::SimpleStrategy { .. }
-                )
-            {
-                warn!("\
-Combining SimpleStrategy with preferred_datacenter set to Some and disabled datacenter failover may lead to empty query plans for some tokens.\
-It is better to give up using one of them: either operate in a keyspace with NetworkTopologyStrategy, which explicitly states\
-how many replicas there are in each datacenter (you probably want at least 1 to avoid empty plans while preferring that datacenter), \
-or refrain from preferring datacenters (which may ban all other datacenters, if datacenter failover happens to be not possible)."
-                );
-            }
-        }
-        let statement_type = if query.is_confirmed_lwt {
-            StatementType::Lwt
-        } else {
-            StatementType::NonLwt
-        };
-        if let Some(ts) = &routing_info.token_with_strategy {
-            if let ReplicaLocationPreference::DatacenterAndRack(dc, rack) = &self.preferences {
-                // Try to pick some alive local rack random replica.
-                let local_rack_picked = self.pick_replica(
-                    ts,
-                    ReplicaLocationCriteria::DatacenterAndRack(dc, rack),
-                    &self.pick_predicate,
-                    cluster,
-                    statement_type,
-                );
-
-                if let Some(alive_local_rack_replica) = local_rack_picked {
-                    return Some(alive_local_rack_replica);
-                }
-            }
-
-            if let ReplicaLocationPreference::DatacenterAndRack(dc, _)
-            | ReplicaLocationPreference::Datacenter(dc) = &self.preferences
-            {
-                // Try to pick some alive local random replica.
-                let picked = self.pick_replica(
-                    ts,
-                    ReplicaLocationCriteria::Datacenter(dc),
-                    &self.pick_predicate,
+        if let Some(dc_failover) = self.dc_failover.as_ref() {
+            dc_failover.update(cluster);
+
+            // While failed over, some requests probe the preferred datacenter.
+            // The fallback plan still consists of the backup datacenter.
+            if dc_failover.try_start_probe() {
+                match self.pick_with_preferences(
+                    query,
                     cluster,
-                    statement_type,
-                );
-
-                if let Some(alive_local_replica) = picked {
-                    return Some(alive_local_replica);
-                }
-            }
-
-            // If preferred datacenter is not specified, or if datacenter failover is possible, loosen restriction about locality.
-            if self.preferences.datacenter().is_none()
-                || self.is_datacenter_failover_possible(&routing_info)
-            {
-                // Try to pick some alive random replica.
-                let picked = self.pick_replica(
-                    ts,
-                    ReplicaLocationCriteria::Any,
+                    &self.preferences,
                     &self.pick_predicate,
-                    cluster,
-                    statement_type,
-                );
-                if let Some(alive_remote_replica) = picked {
-                    return Some(alive_remote_replica);
+                ) {
+                    Some(node) if dc_failover.is_preferred(node) => return Some(node),
+                    _ => dc_failover.cancel_probe(),
                 }
             }
-        };
-
-        // If no token was available (or all the replicas for that token are down), try to pick
-        // some alive local node.
-        // If there was no preferred datacenter specified, all nodes are treated as local.
-        let nodes = self.preferred_node_set(cluster);
-        let picked = Self::pick_node(nodes, &self.pick_predicate);
-        if let Some(alive_local) = picked {
-            return Some(alive_local);
-        }
-
-        let all_nodes = cluster.replica_locator().unique_nodes_in_global_ring();
-        // If a datacenter failover is possible, loosen restriction about locality.
-        if self.is_datacenter_failover_possible(&routing_info) {
-            let picked = Self::pick_node(all_nodes, &self.pick_predicate);
-            if let Some(alive_maybe_remote) = picked {
-                return Some(alive_maybe_remote);
-            }
         }
 
-        // Previous checks imply that every node we could have selected is down.
-        // Let's try to return a down node that wasn't disabled.
-        let picked = Self::pick_node(nodes, |node| node.is_enabled());
-        if let Some(down_but_enabled_local_node) = picked {
-            return Some(down_but_enabled_local_node);
+        if let Some(circuit_breaker) = self.circuit_breaker.as_deref() {
+            circuit_breaker.cleanup(cluster);
         }
 
-        // If a datacenter failover is possible, loosen restriction about locality.
-        if self.is_datacenter_failover_possible(&routing_info) {
-            let picked = Self::pick_node(all_nodes, |node| node.is_enabled());
-            if let Some(down_but_enabled_maybe_remote_node) = picked {
-                return Some(down_but_enabled_maybe_remote_node);
+        // Nodes with an open circuit breaker are picked only as probes.
+        if let (Some(circuit_breaker), Some(probe_pick_predicate)) = (
+            self.circuit_breaker.as_deref(),
+            self.probe_pick_predicate.as_ref(),
+        ) {
+            match self.pick_with_preferences(
+                query,
+                cluster,
+                self.effective_preferences(),
+                probe_pick_predicate,
+            ) {
+                Some(node) if circuit_breaker.is_open(node) => {
+                    if circuit_breaker.try_start_probe(node) {
+                        return Some(node);
+                    }
+                }
+                picked => return picked,
             }
         }
 
-        // Every node is disabled. This could be due to a bad host filter - configuration error.
-        nodes.first()
+        self.pick_with_preferences(
+            query,
+            cluster,
+            self.effective_preferences(),
+            &self.pick_predicate,
+        )
     }
 
     fn fallback<'a>(
@@ -218,7 +180,9 @@ or refrain from preferring datacenters (which may ban all other datacenters, if
         // If token is available, get a shuffled list of alive replicas.
         let maybe_replicas = if let Some(ts) = &routing_info.token_with_strategy {
             let maybe_local_rack_replicas =
-                if let ReplicaLocationPreference::DatacenterAndRack(dc, rack) = &self.preferences {
+                if let ReplicaLocationPreference::DatacenterAndRack(dc, rack) =
+                    routing_info.preferences
+                {
                     let local_rack_replicas = self.fallback_replicas(
                         ts,
                         ReplicaLocationCriteria::DatacenterAndRack(dc, rack),
@@ -233,7 +197,7 @@ or refrain from preferring datacenters (which may ban all other datacenters, if
 
             let maybe_local_replicas = if let ReplicaLocationPreference::DatacenterAndRack(dc, _)
             | ReplicaLocationPreference::Datacenter(dc) =
-                &self.preferences
+                routing_info.preferences
             {
                 let local_replicas = self.fallback_replicas(
                     ts,
@@ -248,7 +212,7 @@ or refrain from preferring datacenters (which may ban all other datacenters, if
             };
 
             // If no datacenter is preferred, or datacenter failover is possible, loosen restriction about locality.
-            let maybe_remote_replicas = if self.preferences.datacenter().is_none()
+            let maybe_remote_replicas = if routing_info.preferences.datacenter().is_none()
                 || self.is_datacenter_failover_possible(&routing_info)
             {
                 let remote_replicas = self.fallback_replicas(
@@ -275,7 +239,7 @@ or refrain from preferring datacenters (which may ban all other datacenters, if
         };
 
         // Get a list of all local alive nodes, and apply a round robin to it
-        let local_nodes = self.preferred_node_set(cluster);
+        let local_nodes = Self::preferred_node_set(routing_info.preferences, cluster);
         let robined_local_nodes = Self::round_robin_nodes(local_nodes, Self::is_alive);
 
         let all_nodes = cluster.replica_locator().unique_nodes_in_global_ring();
@@ -307,10 +271,17 @@ or refrain from preferring datacenters (which may ban all other datacenters, if
             .chain(maybe_down_nodes)
             .unique();
 
-        if let Some(latency_awareness) = self.latency_awareness.as_ref() {
-            Box::new(latency_awareness.wrap(plan))
+        let plan: FallbackPlan<'a> =
+            if let Some(latency_awareness) = self.latency_awareness.as_ref() {
+                Box::new(latency_awareness.wrap(plan))
+            } else {
+                Box::new(plan)
+            };
+
+        if let Some(circuit_breaker) = self.circuit_breaker.as_deref() {
+            Box::new(IteratorWithTrippedNodesLast::new(circuit_breaker, plan))
         } else {
-            Box::new(plan)
+            plan
         }
     }
 
@@ -318,10 +289,47 @@ or refrain from preferring datacenters (which may ban all other datacenters, if
         "DefaultPolicy".to_string()
     }
 
+    fn explain_node(&self, _query: &RoutingInfo, node: NodeRef<'_>) -> NodeExplanation {
+        let preferences = self.effective_preferences();
+        NodeExplanation {
+            is_local_datacenter: preferences
+                .datacenter()
+                .map(|dc| node.datacenter.as_deref() == Some(dc)),
+            is_local_rack: preferences
+                .rack()
+                .map(|rack| node.rack.as_deref() == Some(rack)),
+            is_penalised: match self.latency_awareness.as_ref() {
+                Some(latency_awareness) => !latency_awareness.generate_predicate()(node),
+                None => false,
+            } || match self.circuit_breaker.as_ref() {
+                Some(circuit_breaker) => circuit_breaker.is_open(node),
+                None => false,
+            },
+        }
+    }
+
+    fn consistency_for_node(
+        &self,
+        _query: &RoutingInfo,
+        node: NodeRef<'_>,
+        consistency: Consistency,
+    ) -> Consistency {
+        match self.dc_failover.as_ref() {
+            Some(dc_failover) => dc_failover.consistency_for_node(node, consistency),
+            None => consistency,
+        }
+    }
+
     fn on_query_success(&self, _routing_info: &RoutingInfo, latency: Duration, node: NodeRef<'_>) {
         if let Some(latency_awareness) = self.latency_awareness.as_ref() {
             latency_awareness.report_query(node, latency);
         }
+        if let Some(dc_failover) = self.dc_failover.as_ref() {
+            dc_failover.report_success(node);
+        }
+        if let Some(circuit_breaker) = self.circuit_breaker.as_ref() {
+            circuit_breaker.report_success(node);
+        }
     }
 
     fn on_query_failure(
@@ -336,6 +344,12 @@ or refrain from preferring datacenters (which may ban all other datacenters, if
                 latency_awareness.report_query(node, latency);
             }
         }
+        if let Some(dc_failover) = self.dc_failover.as_ref() {
+            dc_failover.report_failure(node, error);
+        }
+        if let Some(circuit_breaker) = self.circuit_breaker.as_ref() {
+            circuit_breaker.report_failure(node, error);
+        }
     }
 }
 
@@ -345,12 +359,29 @@ impl DefaultPolicy {
         DefaultPolicyBuilder::new()
     }
 
+    // While failed over to a backup datacenter, it is preferred instead of the configured one.
+    fn effective_preferences(&self) -> &ReplicaLocationPreference {
+        self.dc_failover
+            .as_ref()
+            .and_then(|dc_failover| dc_failover.backup_preferences())
+            .unwrap_or(&self.preferences)
+    }
+
     fn routing_info<'a>(
         &'a self,
         query: &'a RoutingInfo,
         cluster: &'a ClusterData,
     ) -> ProcessedRoutingInfo<'a> {
-        let mut routing_info = ProcessedRoutingInfo::new(query, cluster);
+        self.routing_info_with_preferences(query, cluster, self.effective_preferences())
+    }
+
+    fn routing_info_with_preferences<'a>(
+        &'a self,
+        query: &'a RoutingInfo,
+        cluster: &'a ClusterData,
+        preferences: &'a ReplicaLocationPreference,
+    ) -> ProcessedRoutingInfo<'a> {
+        let mut routing_info = ProcessedRoutingInfo::new(query, cluster, preferences);
 
         if !self.is_token_aware {
             routing_info.token_with_strategy = None;
@@ -359,8 +390,129 @@ impl DefaultPolicy {
         routing_info
     }
 
-    fn preferred_node_set<'a>(&'a self, cluster: &'a ClusterData) -> &'a [Arc<Node>] {
-        if let Some(preferred_datacenter) = self.preferences.datacenter() {
+    fn pick_with_preferences<'a>(
+        &'a self,
+        query: &'a RoutingInfo,
+        cluster: &'a ClusterData,
+        preferences: &'a ReplicaLocationPreference,
+        predicate: &'a impl Fn(&NodeRef<'a>) -> bool,
+    ) -> Option<NodeRef<'a>> {
+        let routing_info = self.routing_info_with_preferences(query, cluster, preferences);
+        if let Some(ref token_with_strategy) = routing_info.token_with_strategy {
+            if routing_info.preferences.datacenter().is_some()
+                && !self.permit_dc_failover
+                && matches!(
+                    token_with_strategy.strategy,
+                    Strategy::SimpleStrategy { .. }
+                )
+            {
+                warn!("\
+Combining SimpleStrategy with preferred_datacenter set to Some and disabled datacenter failover may lead to empty query plans for some tokens.\
+It is better to give up using one of them: either operate in a keyspace with NetworkTopologyStrategy, which explicitly states\
+how many replicas there are in each datacenter (you probably want at least 1 to avoid empty plans while preferring that datacenter), \
+or refrain from preferring datacenters (which may ban all other datacenters, if datacenter failover happens to be not possible)."
+                );
+            }
+        }
+        let statement_type = if query.is_confirmed_lwt {
+            StatementType::Lwt
+        } else {
+            StatementType::NonLwt
+        };
+        if let Some(ts) = &routing_info.token_with_strategy {
+            if let ReplicaLocationPreference::DatacenterAndRack(dc, rack) = routing_info.preferences
+            {
+                // Try to pick some alive local rack random replica.
+                let local_rack_picked = self.pick_replica(
+                    ts,
+                    ReplicaLocationCriteria::DatacenterAndRack(dc, rack),
+                    predicate,
+                    cluster,
+                    statement_type,
+                );
+
+                if let Some(alive_local_rack_replica) = local_rack_picked {
+                    return Some(alive_local_rack_replica);
+                }
+            }
+
+            if let ReplicaLocationPreference::DatacenterAndRack(dc, _)
+            | ReplicaLocationPreference::Datacenter(dc) = routing_info.preferences
+            {
+                // Try to pick some alive local random replica.
+                let picked = self.pick_replica(
+                    ts,
+                    ReplicaLocationCriteria::Datacenter(dc),
+                    predicate,
+                    cluster,
+                    statement_type,
+                );
+
+                if let Some(alive_local_replica) = picked {
+                    return Some(alive_local_replica);
+                }
+            }
+
+            // If preferred datacenter is not specified, or if datacenter failover is possible, loosen restriction about locality.
+            if routing_info.preferences.datacenter().is_none()
+                || self.is_datacenter_failover_possible(&routing_info)
+            {
+                // Try to pick some alive random replica.
+                let picked = self.pick_replica(
+                    ts,
+                    ReplicaLocationCriteria::Any,
+                    predicate,
+                    cluster,
+                    statement_type,
+                );
+                if let Some(alive_remote_replica) = picked {
+                    return Some(alive_remote_replica);
+                }
+            }
+        };
+
+        // If no token was available (or all the replicas for that token are down), try to pick
+        // some alive local node.
+        // If there was no preferred datacenter specified, all nodes are treated as local.
+        let nodes = Self::preferred_node_set(routing_info.preferences, cluster);
+        let picked = Self::pick_node(nodes, predicate);
+        if let Some(alive_local) = picked {
+            return Some(alive_local);
+        }
+
+        let all_nodes = cluster.replica_locator().unique_nodes_in_global_ring();
+        // If a datacenter failover is possible, loosen restriction about locality.
+        if self.is_datacenter_failover_possible(&routing_info) {
+            let picked = Self::pick_node(all_nodes, predicate);
+            if let Some(alive_maybe_remote) = picked {
+                return Some(alive_maybe_remote);
+            }
+        }
+
+        // Previous checks imply that every node we could have selected is down.
+        // Let's try to return a down node that wasn't disabled.
+        let picked = Self::pick_node(nodes, |node| node.is_enabled());
+        if let Some(down_but_enabled_local_node) = picked {
+            return Some(down_but_enabled_local_node);
+        }
+
+        // If a datacenter failover is possible, loosen restriction about locality.
+        if self.is_datacenter_failover_possible(&routing_info) {
+            let picked = Self::pick_node(all_nodes, |node| node.is_enabled());
+            if let Some(down_but_enabled_maybe_remote_node) = picked {
+                return Some(down_but_enabled_maybe_remote_node);
+            }
+        }
+
+        // Every node is disabled. This could be due to a bad host filter - configuration error.
+        nodes.first()
+    }
+
+    fn preferred_node_set<'a>(
+        preferences: &ReplicaLocationPreference,
+        cluster: &'a ClusterData,
+    ) -> &'a [Arc<Node>] {
+        if let Some(preferred_datacenter) = preferences.datacenter() {
             if let Some(nodes) = cluster
                 .replica_locator()
                 .unique_nodes_in_datacenter_ring(preferred_datacenter)
@@ -389,7 +541,7 @@ impl DefaultPolicy {
 
         cluster
             .replica_locator()
-            .replicas_for_token(ts.token, ts.strategy, datacenter)
+            .replicas_for_token(ts.token.clone(), ts.strategy, datacenter)
     }
 
     /// Wraps the provided predicate, adding the requirement for rack to match.
@@ -441,6 +593,9 @@ impl DefaultPolicy {
     ) -> Option<NodeRef<'a>> {
         match statement_type {
             StatementType::Lwt => self.pick_first_replica(ts, replica_location, predicate, cluster),
+            StatementType::NonLwt if self.least_outstanding_requests => {
+                self.pick_least_loaded_replica(ts, replica_location, predicate, cluster)
+            }
             StatementType::NonLwt => {
                 self.pick_random_replica(ts, replica_location, predicate, cluster)
             }
@@ -524,6 +679,61 @@ impl DefaultPolicy {
         }
     }
 
+    // Power of two choices: out of two random replicas satisfying the predicate, the one
+    // with fewer requests in flight on the connection used for the token is picked.
+    fn pick_least_loaded_replica<'a>(
+        &'a self,
+        ts: &TokenWithStrategy<'a>,
+        replica_location: ReplicaLocationCriteria<'a>,
+        predicate: &'a impl Fn(&NodeRef<'a>) -> bool,
+        cluster: &'a ClusterData,
+    ) -> Option<NodeRef<'a>> {
+        let predicate = Self::make_rack_predicate(predicate, replica_location);
+
+        let candidates: Vec<NodeRef<'a>> = self
+            .nonfiltered_replica_set(ts, replica_location, cluster)
+            .into_iter()
+            .filter(|node| predicate(node))
+            .collect();
+        let load = |node: NodeRef<'_>| Self::in_flight_requests(node, ts.token);
+
+        if let Some(fixed) = self.fixed_shuffle_seed {
+            let mut gen = Pcg32::new(fixed, 0);
+            Self::choose_less_loaded(&candidates, load, &mut gen)
+        } else {
+            Self::choose_less_loaded(&candidates, load, &mut thread_rng())
+        }
+    }
+
+    fn choose_less_loaded<'a, R>(
+        candidates: &[NodeRef<'a>],
+        load: impl Fn(NodeRef<'a>) -> usize,
+        rng: &mut R,
+    ) -> Option<NodeRef<'a>>
+    where
+        R: Rng + ?Sized,
+    {
+        match candidates.len() {
+            0 => None,
+            1 => Some(candidates[0]),
+            len => {
+                let first = rng.gen_range(0..len);
+                // Adding a nonzero offset guarantees choosing two different replicas.
+                let second = (first + rng.gen_range(1..len)) % len;
+                Some(cmp::min_by_key(
+                    candidates[first],
+                    candidates[second],
+                    |node| load(node),
+                ))
+            }
+        }
+    }
+
+    // Nodes without connections are treated as the most loaded ones.
+    fn in_flight_requests(node: NodeRef<'_>, token: &Token) -> usize {
+        node.in_flight_requests(Some(token)).unwrap_or(usize::MAX)
+    }
+
     fn fallback_replicas<'a>(
         &'a self,
         ts: &TokenWithStrategy<'a>,
@@ -543,7 +753,14 @@ impl DefaultPolicy {
             // As an LWT optimisation: in order to reduce contention caused by Paxos conflicts,
             //  we always try to query replicas in the same order.
             StatementType::Lwt => Either::Left(replicas),
-            StatementType::NonLwt => Either::Right(self.shuffle(replicas)),
+            StatementType::NonLwt if self.least_outstanding_requests => {
+                // Shuffling first makes equally loaded replicas appear in random order.
+                let token = ts.token;
+                let mut replicas: Vec<NodeRef<'a>> = self.shuffle(replicas).collect();
+                replicas.sort_by_cached_key(|node| Self::in_flight_requests(node, token));
+                Either::Right(Either::Left(replicas.into_iter()))
+            }
+            StatementType::NonLwt => Either::Right(Either::Right(self.shuffle(replicas))),
         }
     }
 
@@ -601,7 +818,7 @@ impl DefaultPolicy {
     }
 
     fn is_datacenter_failover_possible(&self, routing_info: &ProcessedRoutingInfo) -> bool {
-        self.preferences.datacenter().is_some()
+        routing_info.preferences.datacenter().is_some()
             && self.permit_dc_failover
             && !routing_info.local_consistency
     }
@@ -614,7 +831,11 @@ impl Default for DefaultPolicy {
             is_token_aware: true,
             permit_dc_failover: false,
             pick_predicate: Box::new(Self::is_alive),
+            probe_pick_predicate: None,
             latency_awareness: None,
+            least_outstanding_requests: false,
+            dc_failover: None,
+            circuit_breaker: None,
             fixed_shuffle_seed: None,
         }
     }
@@ -641,6 +862,9 @@ pub struct DefaultPolicyBuilder {
     is_token_aware: bool,
     permit_dc_failover: bool,
     latency_awareness: Option<LatencyAwarenessBuilder>,
+    least_outstanding_requests: bool,
+    dc_failover: Option<DcFailoverBuilder>,
+    circuit_breaker: Option<CircuitBreakerBuilder>,
     enable_replica_shuffle: bool,
 }
 
@@ -653,6 +877,9 @@ impl DefaultPolicyBuilder {
             is_token_aware: true,
             permit_dc_failover: false,
             latency_awareness: None,
+            least_outstanding_requests: false,
+            dc_failover: None,
+            circuit_breaker: None,
             enable_replica_shuffle: true,
         }
     }
@@ -668,9 +895,44 @@ impl DefaultPolicyBuilder {
             Box::new(DefaultPolicy::is_alive)
         };
 
-        // As the case of providing preferred rack without providing datacenter is invalid, the rack is then ignored.
+        let circuit_breaker = self
+            .circuit_breaker
+            .map(|builder| Arc::new(builder.build()));
+        let (pick_predicate, probe_pick_predicate) =
+            if let Some(ref circuit_breaker) = circuit_breaker {
+                let pick_predicate: Arc<dyn Fn(&NodeRef) -> bool + Send + Sync> =
+                    Arc::from(pick_predicate);
+                let strict_predicate = {
+                    let pick_predicate = pick_predicate.clone();
+                    let circuit_breaker = circuit_breaker.clone();
+                    Box::new(move |node: &NodeRef| {
+                        pick_predicate(node) && !circuit_breaker.is_open(node)
+                    }) as Box<dyn Fn(&NodeRef) -> bool + Send + Sync + 'static>
+                };
+                let probe_predicate = {
+                    let circuit_breaker = circuit_breaker.clone();
+                    Box::new(move |node: &NodeRef| {
+                        pick_predicate(node)
+                            && (!circuit_breaker.is_open(node) || circuit_breaker.can_probe(node))
+                    }) as Box<dyn Fn(&NodeRef) -> bool + Send + Sync + 'static>
+                };
+                (strict_predicate, Some(probe_predicate))
+            } else {
+                (pick_predicate, None)
+            };
+
+        // As the case of providing preferred rack without providing datacenter is invalid, the rack is then ignored.
         // According to the principle “Make illegal states unrepresentable”, in the next major release we will
         // alter the `DefaultPolicyBuilder`'s API so that it is impossible for the user to create such state.
+        let dc_failover = match (self.dc_failover, &self.preferred_datacenter) {
+            (Some(builder), Some(datacenter)) => Some(builder.build(datacenter.clone())),
+            (Some(_), None) => {
+                warn!("Automatic datacenter failover has effect only if a preferred datacenter is set. Ignoring it.");
+                None
+            }
+            (None, _) => None,
+        };
+
         let preferences = match (self.preferred_datacenter, self.preferred_rack) {
             (None, None) => ReplicaLocationPreference::Any,
             (None, Some(_)) => {
@@ -689,7 +951,11 @@ impl DefaultPolicyBuilder {
             is_token_aware: self.is_token_aware,
             permit_dc_failover: self.permit_dc_failover,
             pick_predicate,
+            probe_pick_predicate,
             latency_awareness,
+            least_outstanding_requests: self.least_outstanding_requests,
+            dc_failover,
+            circuit_breaker,
             fixed_shuffle_seed: (!self.enable_replica_shuffle).then(rand::random),
         })
     }
@@ -770,6 +1036,31 @@ impl DefaultPolicyBuilder {
         self
     }
 
+    /// Enables automatic datacenter failover and failback, driven by the health
+    /// of the preferred datacenter.
+    ///
+    /// Unlike [`permit_dc_failover`](Self::permit_dc_failover), which statically adds remote
+    /// nodes to the end of every plan, the policy switches to the first available backup
+    /// datacenter when all nodes of the preferred datacenter are down, or when its nodes
+    /// repeatedly return `Unavailable` errors. The backup datacenter is then treated as
+    /// the local one: its replicas and nodes come first, and `LOCAL_*` consistency levels
+    /// are satisfied by its replicas. The rack preference is not applied to it.
+    /// Once the preferred datacenter has had a node up for the configured period,
+    /// some requests are sent to it as probes, and the policy switches back to it
+    /// after enough of them succeed.
+    ///
+    /// By default, requests keep their consistency: a `LOCAL_*` consistency level is
+    /// evaluated by the coordinator in the backup datacenter, while others, like
+    /// `EACH_QUORUM`, keep their meaning. The consistency of requests sent to a backup
+    /// datacenter can be changed with [`DcFailoverBuilder::remap_consistency`].
+    ///
+    /// Each switch is logged and reported to the listener, if one is set.
+    /// Has effect only if a preferred datacenter is set.
+    pub fn dc_failover(mut self, dc_failover_builder: DcFailoverBuilder) -> Self {
+        self.dc_failover = Some(dc_failover_builder);
+        self
+    }
+
     /// Latency awareness is a mechanism that penalises nodes whose measured
     /// recent average latency classifies it as falling behind the others.
     ///
@@ -789,6 +1080,51 @@ impl DefaultPolicyBuilder {
         self
     }
 
+    /// Enables a per-node circuit breaker, which deprioritizes unhealthy nodes.
+    ///
+    /// The policy tracks the outcomes of requests sent to each node. When the rate of errors
+    /// indicating a problem with the node (e.g. `Overloaded`, server errors, broken connections)
+    /// or the rate of timeouts exceeds the configured threshold, the node's breaker opens:
+    /// the node is not picked and is moved to the end of query plans. After a while the breaker
+    /// becomes half-open: the node stays deprioritized, but a bounded number of requests at
+    /// a time is sent to it as probes. If enough probes succeed, the breaker closes; if one
+    /// of them fails, it opens again.
+    ///
+    /// Errors caused by the request itself, e.g. syntax errors or `Unavailable`, are not counted.
+    /// The circuit breaker is disabled by default.
+    pub fn circuit_breaker(mut self, circuit_breaker_builder: CircuitBreakerBuilder) -> Self {
+        self.circuit_breaker = Some(circuit_breaker_builder);
+        self
+    }
+
+    /// Sets whether this policy chooses replicas by the number of requests in flight.
+    ///
+    /// When enabled, the policy picks two random replicas and sends the request to the one
+    /// with fewer requests awaiting a response on the connection it would use for the token,
+    /// i.e. to the shard owning the token or, if that shard has no connections, to another
+    /// shard ("power of two choices"). The remaining replicas are tried in the order
+    /// of increasing number of requests in flight. Unlike latency awareness, this reacts
+    /// immediately to a node that stalls, e.g. due to a GC pause or heavy compaction.
+    ///
+    /// Datacenter and rack preferences are respected, and LWT statements are still
+    /// sent to replicas in the ring order. Non-replica nodes are not affected.
+    ///
+    /// This option is disabled by default.
+    ///
+    /// # Example
+    /// ```
+    /// use scylla::load_balancing::DefaultPolicy;
+    ///
+    /// let default_policy = DefaultPolicy::builder()
+    ///     .prefer_datacenter("dc1".to_string())
+    ///     .least_outstanding_requests(true)
+    ///     .build();
+    /// ```
+    pub fn least_outstanding_requests(mut self, enable: bool) -> Self {
+        self.least_outstanding_requests = enable;
+        self
+    }
+
     /// Sets whether this policy should shuffle replicas when token-awareness
     /// is enabled. Shuffling can help distribute the load over replicas, but
     /// can reduce the effectiveness of caching on the database side (e.g.
@@ -812,12 +1148,19 @@ impl Default for DefaultPolicyBuilder {
 struct ProcessedRoutingInfo<'a> {
     token_with_strategy: Option<TokenWithStrategy<'a>>,
 
+    // Preferred datacenter and rack, possibly replaced by automatic datacenter failover
+    preferences: &'a ReplicaLocationPreference,
+
     // True if one of LOCAL_ONE, LOCAL_QUORUM, LOCAL_SERIAL was requested
     local_consistency: bool,
 }
 
 impl<'a> ProcessedRoutingInfo<'a> {
-    fn new(query: &'a RoutingInfo, cluster: &'a ClusterData) -> ProcessedRoutingInfo<'a> {
+    fn new(
+        query: &'a RoutingInfo,
+        cluster: &'a ClusterData,
+        preferences: &'a ReplicaLocationPreference,
+    ) -> ProcessedRoutingInfo<'a> {
         let local_consistency = matches!(
             (query.consistency, query.serial_consistency),
             (Consistency::LocalQuorum, _)
@@ -827,6 +1170,7 @@ impl<'a> ProcessedRoutingInfo<'a> {
 
         Self {
             token_with_strategy: TokenWithStrategy::new(query, cluster),
+            preferences,
             local_consistency,
         }
     }
@@ -834,12 +1178,12 @@ impl<'a> ProcessedRoutingInfo<'a> {
 
 struct TokenWithStrategy<'a> {
     strategy: &'a Strategy,
-    token: Token,
+    token: &'a Token,
 }
 
 impl<'a> TokenWithStrategy<'a> {
     fn new(query: &'a RoutingInfo, cluster: &'a ClusterData) -> Option<TokenWithStrategy<'a>> {
-        let token = query.token?;
+        let token = query.token.as_ref()?;
         let keyspace_name = query.keyspace?;
         let keyspace = cluster.get_keyspace_info().get(keyspace_name)?;
         let strategy = &keyspace.strategy;
@@ -848,7 +1192,7 @@ impl<'a> TokenWithStrategy<'a> {
 }
 
 #[cfg(test)]
-mod tests {
+pub(super) mod tests {
     use scylla_cql::{frame::types::SerialConsistency, Consistency};
 
     use self::framework::{
@@ -866,7 +1210,8 @@ mod tests {
         },
     };
 
-    use super::{DefaultPolicy, ReplicaLocationPreference};
+    use super::{DefaultPolicy, NodeRef, ReplicaLocationPreference};
+    use std::collections::HashSet;
 
     pub(crate) mod framework {
         use std::collections::{HashMap, HashSet};
@@ -1072,7 +1417,15 @@ mod tests {
         // based on locator mock cluster
         pub(crate) async fn mock_cluster_data_for_token_aware_tests() -> ClusterData {
             let metadata = mock_metadata_for_token_aware_tests();
-            ClusterData::new(metadata, &Default::default(), &HashMap::new(), &None, None).await
+            ClusterData::new(
+                metadata,
+                &Default::default(),
+                &HashMap::new(),
+                &None,
+                None,
+                &HashMap::new(),
+            )
+            .await
         }
 
         // creates ClusterData with info about 5 nodes living in 2 different datacenters
@@ -1084,19 +1437,28 @@ mod tests {
                     datacenter: Some(dc.to_string()),
                     rack: None,
                     address: id_to_invalid_addr(*id),
-                    tokens: vec![Token {
-                        value: *id as i64 * 100,
-                    }],
+                    tokens: vec![Token::new(*id as i64 * 100)],
                     host_id: Uuid::new_v4(),
+                    release_version: None,
+                    schema_version: None,
                 })
                 .collect::<Vec<_>>();
 
             let info = Metadata {
                 peers,
                 keyspaces: HashMap::new(),
+                partitioner: None,
             };
 
-            ClusterData::new(info, &Default::default(), &HashMap::new(), &None, None).await
+            ClusterData::new(
+                info,
+                &Default::default(),
+                &HashMap::new(),
+                &None,
+                None,
+                &HashMap::new(),
+            )
+            .await
         }
 
         pub(crate) fn get_plan_and_collect_node_identifiers(
@@ -1200,7 +1562,7 @@ mod tests {
                     ..Default::default()
                 },
                 routing_info: RoutingInfo {
-                    token: Some(Token { value: 160 }),
+                    token: Some(Token::new(160)),
                     keyspace: Some(KEYSPACE_NTS_RF_2),
                     consistency: Consistency::Two,
                     ..Default::default()
@@ -1225,7 +1587,7 @@ mod tests {
                     ..Default::default()
                 },
                 routing_info: RoutingInfo {
-                    token: Some(Token { value: 160 }),
+                    token: Some(Token::new(160)),
                     keyspace: Some(KEYSPACE_NTS_RF_2),
                     consistency: Consistency::Two,
                     ..Default::default()
@@ -1249,7 +1611,7 @@ mod tests {
                     ..Default::default()
                 },
                 routing_info: RoutingInfo {
-                    token: Some(Token { value: 160 }),
+                    token: Some(Token::new(160)),
                     keyspace: Some(KEYSPACE_NTS_RF_2),
                     consistency: Consistency::LocalOne, // local Consistency forbids datacenter failover
                     ..Default::default()
@@ -1271,7 +1633,7 @@ mod tests {
                     ..Default::default()
                 },
                 routing_info: RoutingInfo {
-                    token: Some(Token { value: 160 }),
+                    token: Some(Token::new(160)),
                     keyspace: Some(KEYSPACE_NTS_RF_2),
                     consistency: Consistency::One,
                     ..Default::default()
@@ -1293,7 +1655,7 @@ mod tests {
                     ..Default::default()
                 },
                 routing_info: RoutingInfo {
-                    token: Some(Token { value: 160 }),
+                    token: Some(Token::new(160)),
                     keyspace: Some(KEYSPACE_NTS_RF_3),
                     consistency: Consistency::Quorum,
                     ..Default::default()
@@ -1318,7 +1680,7 @@ mod tests {
                     ..Default::default()
                 },
                 routing_info: RoutingInfo {
-                    token: Some(Token { value: 160 }),
+                    token: Some(Token::new(160)),
                     keyspace: Some(KEYSPACE_NTS_RF_3),
                     consistency: Consistency::Quorum,
                     ..Default::default()
@@ -1342,7 +1704,7 @@ mod tests {
                     ..Default::default()
                 },
                 routing_info: RoutingInfo {
-                    token: Some(Token { value: 160 }),
+                    token: Some(Token::new(160)),
                     keyspace: Some(KEYSPACE_NTS_RF_3),
                     consistency: Consistency::Quorum,
                     ..Default::default()
@@ -1364,7 +1726,7 @@ mod tests {
                     ..Default::default()
                 },
                 routing_info: RoutingInfo {
-                    token: Some(Token { value: 160 }),
+                    token: Some(Token::new(160)),
                     keyspace: Some(KEYSPACE_SS_RF_2),
                     consistency: Consistency::Two,
                     ..Default::default()
@@ -1388,7 +1750,7 @@ mod tests {
                     ..Default::default()
                 },
                 routing_info: RoutingInfo {
-                    token: Some(Token { value: 160 }),
+                    token: Some(Token::new(160)),
                     keyspace: Some(KEYSPACE_SS_RF_2),
                     consistency: Consistency::LocalOne, // local Consistency forbids datacenter failover
                     ..Default::default()
@@ -1429,7 +1791,7 @@ mod tests {
                     ..Default::default()
                 },
                 routing_info: RoutingInfo {
-                    token: Some(Token { value: 160 }),
+                    token: Some(Token::new(160)),
                     keyspace: None, // no keyspace
                     consistency: Consistency::Quorum,
                     ..Default::default()
@@ -1448,7 +1810,7 @@ mod tests {
                     ..Default::default()
                 },
                 routing_info: RoutingInfo {
-                    token: Some(Token { value: 160 }),
+                    token: Some(Token::new(160)),
                     keyspace: Some(KEYSPACE_NTS_RF_2),
                     consistency: Consistency::Quorum,
                     ..Default::default()
@@ -1470,7 +1832,7 @@ mod tests {
                     ..Default::default()
                 },
                 routing_info: RoutingInfo {
-                    token: Some(Token { value: 160 }),
+                    token: Some(Token::new(160)),
                     keyspace: Some(KEYSPACE_NTS_RF_2),
                     consistency: Consistency::Quorum,
                     ..Default::default()
@@ -1489,7 +1851,7 @@ mod tests {
                     ..Default::default()
                 },
                 routing_info: RoutingInfo {
-                    token: Some(Token { value: 160 }),
+                    token: Some(Token::new(160)),
                     keyspace: Some(KEYSPACE_NTS_RF_2),
                     consistency: Consistency::Quorum,
                     ..Default::default()
@@ -1511,7 +1873,7 @@ mod tests {
                     ..Default::default()
                 },
                 routing_info: RoutingInfo {
-                    token: Some(Token { value: 160 }),
+                    token: Some(Token::new(160)),
                     keyspace: Some(KEYSPACE_NTS_RF_2),
                     consistency: Consistency::Quorum,
                     ..Default::default()
@@ -1536,7 +1898,36 @@ mod tests {
                     ..Default::default()
                 },
                 routing_info: RoutingInfo {
-                    token: Some(Token { value: 160 }),
+                    token: Some(Token::new(160)),
+                    keyspace: Some(KEYSPACE_NTS_RF_3),
+                    consistency: Consistency::One,
+                    ..Default::default()
+                },
+                // going through the ring, we get order: F , A , C , D , G , B , E
+                //                                      us  eu  eu  us  eu  eu  us
+                //                                      r2  r1  r1  r1  r2  r1  r1
+                expected_groups: ExpectedGroupsBuilder::new()
+                    .group([A, C]) // pick local rack replicas
+                    .group([G]) // local DC replicas
+                    .group([F, D, E]) // remote replicas
+                    .group([B]) // local nodes
+                    .build(),
+            },
+            // Keyspace NTS with RF=3 with enabled DC failover, rack-awareness and choosing
+            // replicas by the number of requests in flight
+            Test {
+                policy: DefaultPolicy {
+                    preferences: ReplicaLocationPreference::DatacenterAndRack(
+                        "eu".to_owned(),
+                        "r1".to_owned(),
+                    ),
+                    is_token_aware: true,
+                    permit_dc_failover: true,
+                    least_outstanding_requests: true,
+                    ..Default::default()
+                },
+                routing_info: RoutingInfo {
+                    token: Some(Token::new(160)),
                     keyspace: Some(KEYSPACE_NTS_RF_3),
                     consistency: Consistency::One,
                     ..Default::default()
@@ -1564,7 +1955,7 @@ mod tests {
                     ..Default::default()
                 },
                 routing_info: RoutingInfo {
-                    token: Some(Token { value: 560 }),
+                    token: Some(Token::new(560)),
                     keyspace: Some(KEYSPACE_SS_RF_2),
                     consistency: Consistency::Two,
                     ..Default::default()
@@ -1590,7 +1981,7 @@ mod tests {
                     ..Default::default()
                 },
                 routing_info: RoutingInfo {
-                    token: Some(Token { value: 160 }),
+                    token: Some(Token::new(160)),
                     keyspace: Some(KEYSPACE_SS_RF_2),
                     consistency: Consistency::One,
                     ..Default::default()
@@ -1642,7 +2033,34 @@ mod tests {
                     ..Default::default()
                 },
                 routing_info: RoutingInfo {
-                    token: Some(Token { value: 160 }),
+                    token: Some(Token::new(160)),
+                    keyspace: Some(KEYSPACE_NTS_RF_2),
+                    consistency: Consistency::Two,
+                    is_confirmed_lwt: true,
+                    ..Default::default()
+                },
+                // going through the ring, we get order: F , A , C , D , G , B , E
+                //                                      us  eu  eu  us  eu  eu  us
+                //                                      r2  r1  r1  r1  r2  r1  r1
+                expected_groups: ExpectedGroupsBuilder::new()
+                    .ordered([A, G]) // pick + fallback local replicas
+                    .ordered([F, D]) // remote replicas
+                    .group([C, B]) // local nodes
+                    .group([E]) // remote nodes
+                    .build(),
+            },
+            // Keyspace NTS with RF=2 with enabled DC failover, choosing replicas by the number
+            // of requests in flight doesn't affect LWT
+            Test {
+                policy: DefaultPolicy {
+                    preferences: ReplicaLocationPreference::Datacenter("eu".to_owned()),
+                    is_token_aware: true,
+                    permit_dc_failover: true,
+                    least_outstanding_requests: true,
+                    ..Default::default()
+                },
+                routing_info: RoutingInfo {
+                    token: Some(Token::new(160)),
                     keyspace: Some(KEYSPACE_NTS_RF_2),
                     consistency: Consistency::Two,
                     is_confirmed_lwt: true,
@@ -1668,7 +2086,7 @@ mod tests {
                     ..Default::default()
                 },
                 routing_info: RoutingInfo {
-                    token: Some(Token { value: 160 }),
+                    token: Some(Token::new(160)),
                     keyspace: Some(KEYSPACE_NTS_RF_2),
                     consistency: Consistency::Two,
                     is_confirmed_lwt: true,
@@ -1693,7 +2111,7 @@ mod tests {
                     ..Default::default()
                 },
                 routing_info: RoutingInfo {
-                    token: Some(Token { value: 160 }),
+                    token: Some(Token::new(160)),
                     keyspace: Some(KEYSPACE_NTS_RF_2),
                     consistency: Consistency::LocalOne, // local Consistency forbids datacenter failover
                     is_confirmed_lwt: true,
@@ -1716,7 +2134,7 @@ mod tests {
                     ..Default::default()
                 },
                 routing_info: RoutingInfo {
-                    token: Some(Token { value: 160 }),
+                    token: Some(Token::new(160)),
                     keyspace: Some(KEYSPACE_NTS_RF_2),
                     consistency: Consistency::One,
                     is_confirmed_lwt: true,
@@ -1739,7 +2157,7 @@ mod tests {
                     ..Default::default()
                 },
                 routing_info: RoutingInfo {
-                    token: Some(Token { value: 160 }),
+                    token: Some(Token::new(160)),
                     keyspace: Some(KEYSPACE_NTS_RF_3),
                     consistency: Consistency::Quorum,
                     is_confirmed_lwt: true,
@@ -1765,7 +2183,7 @@ mod tests {
                     ..Default::default()
                 },
                 routing_info: RoutingInfo {
-                    token: Some(Token { value: 160 }),
+                    token: Some(Token::new(160)),
                     keyspace: Some(KEYSPACE_NTS_RF_3),
                     consistency: Consistency::Quorum,
                     is_confirmed_lwt: true,
@@ -1790,7 +2208,7 @@ mod tests {
                     ..Default::default()
                 },
                 routing_info: RoutingInfo {
-                    token: Some(Token { value: 160 }),
+                    token: Some(Token::new(160)),
                     keyspace: Some(KEYSPACE_NTS_RF_3),
                     consistency: Consistency::Quorum,
                     is_confirmed_lwt: true,
@@ -1813,7 +2231,7 @@ mod tests {
                     ..Default::default()
                 },
                 routing_info: RoutingInfo {
-                    token: Some(Token { value: 160 }),
+                    token: Some(Token::new(160)),
                     keyspace: Some(KEYSPACE_SS_RF_2),
                     consistency: Consistency::Two,
                     is_confirmed_lwt: true,
@@ -1838,7 +2256,7 @@ mod tests {
                     ..Default::default()
                 },
                 routing_info: RoutingInfo {
-                    token: Some(Token { value: 160 }),
+                    token: Some(Token::new(160)),
                     keyspace: Some(KEYSPACE_SS_RF_2),
                     consistency: Consistency::LocalOne, // local Consistency forbids datacenter failover
                     is_confirmed_lwt: true,
@@ -1881,7 +2299,7 @@ mod tests {
                     ..Default::default()
                 },
                 routing_info: RoutingInfo {
-                    token: Some(Token { value: 160 }),
+                    token: Some(Token::new(160)),
                     keyspace: None, // no keyspace
                     consistency: Consistency::Quorum,
                     is_confirmed_lwt: true,
@@ -1901,7 +2319,7 @@ mod tests {
                     ..Default::default()
                 },
                 routing_info: RoutingInfo {
-                    token: Some(Token { value: 160 }),
+                    token: Some(Token::new(160)),
                     keyspace: Some(KEYSPACE_NTS_RF_2),
                     consistency: Consistency::Quorum,
                     is_confirmed_lwt: true,
@@ -1924,7 +2342,7 @@ mod tests {
                     ..Default::default()
                 },
                 routing_info: RoutingInfo {
-                    token: Some(Token { value: 160 }),
+                    token: Some(Token::new(160)),
                     keyspace: Some(KEYSPACE_NTS_RF_2),
                     consistency: Consistency::Quorum,
                     is_confirmed_lwt: true,
@@ -1944,7 +2362,7 @@ mod tests {
                     ..Default::default()
                 },
                 routing_info: RoutingInfo {
-                    token: Some(Token { value: 160 }),
+                    token: Some(Token::new(160)),
                     keyspace: Some(KEYSPACE_NTS_RF_2),
                     consistency: Consistency::Quorum,
                     is_confirmed_lwt: true,
@@ -1967,7 +2385,7 @@ mod tests {
                     ..Default::default()
                 },
                 routing_info: RoutingInfo {
-                    token: Some(Token { value: 160 }),
+                    token: Some(Token::new(160)),
                     keyspace: Some(KEYSPACE_NTS_RF_2),
                     consistency: Consistency::Quorum,
                     is_confirmed_lwt: true,
@@ -1993,7 +2411,7 @@ mod tests {
                     ..Default::default()
                 },
                 routing_info: RoutingInfo {
-                    token: Some(Token { value: 160 }),
+                    token: Some(Token::new(160)),
                     keyspace: Some(KEYSPACE_NTS_RF_3),
                     consistency: Consistency::One,
                     is_confirmed_lwt: true,
@@ -2022,7 +2440,7 @@ mod tests {
                     ..Default::default()
                 },
                 routing_info: RoutingInfo {
-                    token: Some(Token { value: 760 }),
+                    token: Some(Token::new(760)),
                     keyspace: Some(KEYSPACE_SS_RF_2),
                     consistency: Consistency::Two,
                     is_confirmed_lwt: true,
@@ -2049,7 +2467,7 @@ mod tests {
                     ..Default::default()
                 },
                 routing_info: RoutingInfo {
-                    token: Some(Token { value: 160 }),
+                    token: Some(Token::new(160)),
                     keyspace: Some(KEYSPACE_SS_RF_2),
                     consistency: Consistency::One,
                     is_confirmed_lwt: true,
@@ -2080,6 +2498,48 @@ mod tests {
             .await;
         }
     }
+
+    #[tokio::test]
+    async fn test_choose_less_loaded() {
+        use crate::transport::locator::test::{A, B, C};
+        use rand::SeedableRng;
+        use rand_pcg::Pcg32;
+
+        let cluster = mock_cluster_data_for_token_aware_tests().await;
+        let nodes = cluster.replica_locator().unique_nodes_in_global_ring();
+        let node = |id: u16| nodes.iter().find(|node| node.address.port() == id).unwrap();
+        // Node A has the most requests in flight, node C the fewest.
+        let load = |node: NodeRef<'_>| match node.address.port() {
+            A => 100,
+            B => 10,
+            _ => 1,
+        };
+        let mut rng = Pcg32::seed_from_u64(42);
+
+        assert_eq!(DefaultPolicy::choose_less_loaded(&[], load, &mut rng), None);
+        assert_eq!(
+            DefaultPolicy::choose_less_loaded(&[node(A)], load, &mut rng)
+                .map(|node| node.address.port()),
+            Some(A)
+        );
+
+        // Out of two candidates, the less loaded one is always chosen.
+        for _ in 0..32 {
+            let chosen = DefaultPolicy::choose_less_loaded(&[node(A), node(B)], load, &mut rng);
+            assert_eq!(chosen.map(|node| node.address.port()), Some(B));
+        }
+
+        // The most loaded of three candidates is never chosen,
+        // but each of the other two is chosen sometimes.
+        let mut chosen = HashSet::new();
+        for _ in 0..64 {
+            let node =
+                DefaultPolicy::choose_less_loaded(&[node(A), node(B), node(C)], load, &mut rng)
+                    .unwrap();
+            chosen.insert(node.address.port());
+        }
+        assert_eq!(chosen, [B, C].into_iter().collect());
+    }
 }
 
 mod latency_awareness {
@@ -2695,7 +3155,11 @@ mod latency_awareness {
                 permit_dc_failover: true,
                 is_token_aware: true,
                 pick_predicate,
+                probe_pick_predicate: None,
                 latency_awareness: Some(latency_awareness),
+                least_outstanding_requests: false,
+                dc_failover: None,
+                circuit_breaker: None,
                 fixed_shuffle_seed: None,
             }
         }
@@ -3223,7 +3687,7 @@ mod latency_awareness {
                         (E, too_few_measurements_slow()),
                     ],
                     routing_info: RoutingInfo {
-                        token: Some(Token { value: 160 }),
+                        token: Some(Token::new(160)),
                         keyspace: Some(KEYSPACE_NTS_RF_3),
                         consistency: Consistency::Quorum,
                         ..Default::default()
@@ -3243,7 +3707,7 @@ mod latency_awareness {
                     // Latency-awareness has old minimum average cached, so does not fire.
                     preset_min_avg: Some(100 * min_avg),
                     routing_info: RoutingInfo {
-                        token: Some(Token { value: 160 }),
+                        token: Some(Token::new(160)),
                         keyspace: Some(KEYSPACE_NTS_RF_3),
                         consistency: Consistency::Quorum,
                         ..Default::default()
@@ -3272,7 +3736,7 @@ mod latency_awareness {
                         (C, too_few_measurements_fast_leader()),
                     ],
                     routing_info: RoutingInfo {
-                        token: Some(Token { value: 160 }),
+                        token: Some(Token::new(160)),
                         keyspace: Some(KEYSPACE_NTS_RF_2),
                         consistency: Consistency::Quorum,
                         ..Default::default()
@@ -3291,7 +3755,7 @@ mod latency_awareness {
                     // No latency stats, so latency-awareness is a no-op.
                     preset_min_avg: None,
                     routing_info: RoutingInfo {
-                        token: Some(Token { value: 160 }),
+                        token: Some(Token::new(160)),
                         keyspace: Some("invalid"),
                         consistency: Consistency::Quorum,
                         ..Default::default()
@@ -3362,3 +3826,1541 @@ mod latency_awareness {
         }
     }
 }
+
+mod probes {
+    use std::time::{Duration, Instant};
+
+    // A probe whose outcome is never reported, e.g. because the request was cancelled,
+    // stops occupying its slot after this long.
+    const PROBE_TIMEOUT: Duration = Duration::from_secs(60);
+
+    /// Requests sent to check whether an unhealthy node or datacenter has recovered.
+    /// At most `max_in_flight` of them are in flight at a time.
+    #[derive(Debug, Clone)]
+    pub(super) struct ProbeSlots {
+        max_in_flight: usize,
+        started: Vec<Instant>,
+    }
+
+    impl ProbeSlots {
+        pub(super) fn new(max_in_flight: usize) -> Self {
+            Self {
+                max_in_flight,
+                started: Vec::new(),
+            }
+        }
+
+        pub(super) fn has_free_slot(&self, now: Instant) -> bool {
+            let in_flight = self
+                .started
+                .iter()
+                .filter(|started| now.saturating_duration_since(**started) < PROBE_TIMEOUT)
+                .count();
+            in_flight < self.max_in_flight
+        }
+
+        /// Returns `true` if a probe may be sent, and counts it as in flight.
+        pub(super) fn try_acquire(&mut self, now: Instant) -> bool {
+            self.started
+                .retain(|started| now.saturating_duration_since(*started) < PROBE_TIMEOUT);
+            if self.started.len() < self.max_in_flight {
+                self.started.push(now);
+                true
+            } else {
+                false
+            }
+        }
+
+        /// Frees the slot of the oldest probe in flight, if there is one.
+        pub(super) fn release(&mut self) {
+            if !self.started.is_empty() {
+                self.started.remove(0);
+            }
+        }
+
+        pub(super) fn clear(&mut self) {
+            self.started.clear();
+        }
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::{ProbeSlots, PROBE_TIMEOUT};
+        use std::time::{Duration, Instant};
+
+        #[test]
+        fn test_probe_slots() {
+            let mut slots = ProbeSlots::new(2);
+            let now = Instant::now();
+
+            assert!(slots.try_acquire(now));
+            assert!(slots.try_acquire(now));
+            assert!(!slots.has_free_slot(now));
+            assert!(!slots.try_acquire(now));
+
+            slots.release();
+            assert!(slots.has_free_slot(now));
+            assert!(slots.try_acquire(now));
+
+            // Probes which were never reported expire.
+            let later = now + PROBE_TIMEOUT + Duration::from_secs(1);
+            assert!(slots.has_free_slot(later));
+            assert!(slots.try_acquire(later));
+            assert!(slots.try_acquire(later));
+            assert!(!slots.try_acquire(later));
+        }
+    }
+}
+
+mod periodic {
+    use std::{
+        sync::atomic::{AtomicU64, Ordering},
+        time::{Duration, Instant},
+    };
+
+    /// Limits how often a check done while picking nodes, and thus called for every
+    /// request, actually runs. Lock-free, so that it's cheap when the check isn't due.
+    #[derive(Debug)]
+    pub(super) struct PeriodicCheck {
+        interval: Duration,
+        created: Instant,
+        // Milliseconds since `created` at which the next check is due
+        next_check: AtomicU64,
+    }
+
+    impl PeriodicCheck {
+        pub(super) fn new(interval: Duration) -> Self {
+            Self {
+                interval,
+                created: Instant::now(),
+                next_check: AtomicU64::new(0),
+            }
+        }
+
+        /// Returns `true` if the check is due, in which case the next one is scheduled,
+        /// so that concurrent callers don't repeat it.
+        pub(super) fn try_start(&self, now: Instant) -> bool {
+            let now_ms = Self::as_millis(now.saturating_duration_since(self.created));
+            let next_check = self.next_check.load(Ordering::Relaxed);
+            now_ms >= next_check
+                && self
+                    .next_check
+                    .compare_exchange(
+                        next_check,
+                        now_ms.saturating_add(Self::as_millis(self.interval)),
+                        Ordering::Relaxed,
+                        Ordering::Relaxed,
+                    )
+                    .is_ok()
+        }
+
+        /// Makes the check due immediately.
+        pub(super) fn schedule_now(&self) {
+            self.next_check.store(0, Ordering::Relaxed);
+        }
+
+        fn as_millis(duration: Duration) -> u64 {
+            duration.as_millis().try_into().unwrap_or(u64::MAX)
+        }
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::PeriodicCheck;
+        use std::time::Duration;
+
+        #[test]
+        fn test_periodic_check() {
+            let check = PeriodicCheck::new(Duration::from_secs(1));
+            let now = check.created;
+
+            assert!(check.try_start(now));
+            assert!(!check.try_start(now));
+            assert!(!check.try_start(now + Duration::from_millis(999)));
+            assert!(check.try_start(now + Duration::from_secs(1)));
+            assert!(!check.try_start(now + Duration::from_secs(1)));
+
+            check.schedule_now();
+            assert!(check.try_start(now + Duration::from_secs(1)));
+        }
+    }
+}
+
+mod dc_failover {
+    use scylla_cql::errors::{DbError, QueryError};
+    use scylla_cql::Consistency;
+    use tracing::warn;
+
+    use super::circuit_breaker::CircuitBreaker;
+    use super::periodic::PeriodicCheck;
+    use super::probes::ProbeSlots;
+    use super::ReplicaLocationPreference;
+    use crate::transport::{cluster::ClusterData, NodeRef};
+    use std::{
+        collections::HashMap,
+        fmt,
+        sync::{
+            atomic::{AtomicUsize, Ordering},
+            Arc, RwLock,
+        },
+        time::{Duration, Instant},
+    };
+
+    /// Why the policy stopped using a datacenter, see [`DcFailoverEvent`].
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    #[non_exhaustive]
+    pub enum DcFailoverReason {
+        /// All nodes of the datacenter are down or disabled.
+        AllNodesDown,
+        /// Nodes of the datacenter repeatedly returned `Unavailable` errors.
+        RepeatedUnavailable,
+    }
+
+    /// A change of the datacenter used as the local one by `DefaultPolicy`
+    /// with automatic datacenter failover.
+    #[derive(Debug, Clone, PartialEq, Eq)]
+    #[non_exhaustive]
+    pub enum DcFailoverEvent {
+        /// The policy switched from the datacenter `from` to the backup datacenter `to`.
+        FailedOver {
+            from: String,
+            to: String,
+            reason: DcFailoverReason,
+        },
+        /// The preferred datacenter has been up long enough and successfully answered
+        /// probe requests, so the policy switched back to it from the backup datacenter `from`.
+        FailedBack { from: String, to: String },
+    }
+
+    /// Receives events of automatic datacenter failover, see [`DcFailoverBuilder::listener`].
+    pub trait DcFailoverListener: Send + Sync + fmt::Debug {
+        fn on_event(&self, event: &DcFailoverEvent);
+    }
+
+    #[derive(Debug)]
+    struct FailoverState {
+        // Index of the backup datacenter in use, None if the preferred datacenter is used
+        active_backup: Option<usize>,
+        // Since when the preferred datacenter is healthy while a backup datacenter is used
+        healthy_since: Option<Instant>,
+        // Successful requests to the preferred datacenter while a backup datacenter is used
+        probe_successes: usize,
+        probes: ProbeSlots,
+    }
+
+    /// Automatic datacenter failover and failback of `DefaultPolicy`.
+    #[derive(Debug)]
+    pub(super) struct DcFailover {
+        preferred_datacenter: String,
+        backup_preferences: Vec<ReplicaLocationPreference>,
+        unavailable_threshold: usize,
+        failback_delay: Duration,
+        failback_probes: usize,
+        // Consistency levels replaced for requests sent to a backup datacenter, by its name
+        consistency_remappings: HashMap<String, Vec<(Consistency, Consistency)>>,
+        listener: Option<Arc<dyn DcFailoverListener>>,
+
+        // Unavailable errors returned by nodes of the preferred datacenter in a row
+        consecutive_unavailable: AtomicUsize,
+        health_check: PeriodicCheck,
+        state: RwLock<FailoverState>,
+    }
+
+    impl DcFailover {
+        /// Returns the preferences to use instead of the configured ones,
+        /// if the policy has failed over to a backup datacenter.
+        pub(super) fn backup_preferences(&self) -> Option<&ReplicaLocationPreference> {
+            let active_backup = self.state.read().unwrap().active_backup?;
+            self.backup_preferences.get(active_backup)
+        }
+
+        /// Returns the consistency to send a request to the node with. While the policy
+        /// has failed over, requests sent to a backup datacenter use its remapped consistency.
+        pub(super) fn consistency_for_node(
+            &self,
+            node: NodeRef<'_>,
+            consistency: Consistency,
+        ) -> Consistency {
+            if self.consistency_remappings.is_empty()
+                || self.state.read().unwrap().active_backup.is_none()
+            {
+                return consistency;
+            }
+            node.datacenter
+                .as_deref()
+                .and_then(|datacenter| self.consistency_remappings.get(datacenter))
+                .and_then(|remapping| remapping.iter().find(|(from, _)| *from == consistency))
+                .map(|(_, to)| *to)
+                .unwrap_or(consistency)
+        }
+
+        /// Checks the health of the preferred datacenter and fails over or back if needed.
+        /// As it's called for every request, the check is done at most once
+        /// per `health_check_interval`.
+        pub(super) fn update(&self, cluster: &ClusterData) {
+            let now = Instant::now();
+            if self.health_check.try_start(now) {
+                self.update_at(cluster, now)
+            }
+        }
+
+        fn update_at(&self, cluster: &ClusterData, now: Instant) {
+            let preferred_is_up = Self::is_datacenter_up(cluster, &self.preferred_datacenter);
+            let too_many_unavailable =
+                self.consecutive_unavailable.load(Ordering::Relaxed) >= self.unavailable_threshold;
+
+            // Fast path: the preferred datacenter is in use and healthy.
+            if self.state.read().unwrap().active_backup.is_none()
+                && preferred_is_up
+                && !too_many_unavailable
+            {
+                return;
+            }
+
+            let mut state = self.state.write().unwrap();
+            match state.active_backup {
+                None if !preferred_is_up || too_many_unavailable => {
+                    let reason = if preferred_is_up {
+                        DcFailoverReason::RepeatedUnavailable
+                    } else {
+                        DcFailoverReason::AllNodesDown
+                    };
+                    if let Some(backup) = self.choose_backup(cluster, None) {
+                        state.active_backup = Some(backup);
+                        state.healthy_since = None;
+                        self.emit(DcFailoverEvent::FailedOver {
+                            from: self.preferred_datacenter.clone(),
+                            to: self.backup_name(backup).to_owned(),
+                            reason,
+                        });
+                    }
+                }
+                None => (),
+                Some(active) => {
+                    // Failing back requires successful probes, see `try_start_probe`.
+                    if preferred_is_up {
+                        state.healthy_since.get_or_insert(now);
+                        return;
+                    }
+
+                    state.healthy_since = None;
+                    state.probe_successes = 0;
+                    if !Self::is_datacenter_up(cluster, self.backup_name(active)) {
+                        // The backup datacenter went down as well, try the next one.
+                        if let Some(backup) = self.choose_backup(cluster, Some(active)) {
+                            state.active_backup = Some(backup);
+                            self.emit(DcFailoverEvent::FailedOver {
+                                from: self.backup_name(active).to_owned(),
+                                to: self.backup_name(backup).to_owned(),
+                                reason: DcFailoverReason::AllNodesDown,
+                            });
+                        }
+                    }
+                }
+            }
+        }
+
+        /// Returns `true` if the next request should probe the preferred datacenter,
+        /// while a backup datacenter is used. This is the case once the preferred
+        /// datacenter has had a node up for `failback_delay`, as long as fewer than
+        /// the allowed number of probes are in flight.
+        pub(super) fn try_start_probe(&self) -> bool {
+            self.try_start_probe_at(Instant::now())
+        }
+
+        fn try_start_probe_at(&self, now: Instant) -> bool {
+            if self.state.read().unwrap().active_backup.is_none() {
+                return false;
+            }
+
+            let mut state = self.state.write().unwrap();
+            match state.healthy_since {
+                Some(healthy_since)
+                    if state.active_backup.is_some()
+                        && now.saturating_duration_since(healthy_since) >= self.failback_delay =>
+                {
+                    state.probes.try_acquire(now)
+                }
+                _ => false,
+            }
+        }
+
+        /// Frees the slot of a probe which won't be sent.
+        pub(super) fn cancel_probe(&self) {
+            self.state.write().unwrap().probes.release();
+        }
+
+        pub(super) fn report_success(&self, node: NodeRef<'_>) {
+            if !self.is_preferred(node) {
+                return;
+            }
+            self.consecutive_unavailable.store(0, Ordering::Relaxed);
+
+            if self.state.read().unwrap().active_backup.is_none() {
+                return;
+            }
+            let mut state = self.state.write().unwrap();
+            if let Some(active) = state.active_backup {
+                state.probes.release();
+                state.probe_successes += 1;
+                if state.probe_successes >= self.failback_probes {
+                    state.active_backup = None;
+                    state.healthy_since = None;
+                    state.probe_successes = 0;
+                    state.probes.clear();
+                    self.consecutive_unavailable.store(0, Ordering::Relaxed);
+                    self.emit(DcFailoverEvent::FailedBack {
+                        from: self.backup_name(active).to_owned(),
+                        to: self.preferred_datacenter.clone(),
+                    });
+                }
+            }
+        }
+
+        pub(super) fn report_failure(&self, node: NodeRef<'_>, error: &QueryError) {
+            self.report_failure_at(node, error, Instant::now())
+        }
+
+        fn report_failure_at(&self, node: NodeRef<'_>, error: &QueryError, now: Instant) {
+            if !self.is_preferred(node) {
+                return;
+            }
+            let is_unavailable =
+                matches!(error, QueryError::DbError(DbError::Unavailable { .. }, _));
+            if is_unavailable {
+                let count = self.consecutive_unavailable.fetch_add(1, Ordering::Relaxed) + 1;
+                if count == self.unavailable_threshold {
+                    // Don't wait for the next scheduled health check to fail over.
+                    self.health_check.schedule_now();
+                }
+            }
+
+            if self.state.read().unwrap().active_backup.is_none() {
+                return;
+            }
+            let mut state = self.state.write().unwrap();
+            if state.active_backup.is_some() {
+                state.probes.release();
+                // Errors caused by the request itself say nothing about the datacenter's health.
+                if is_unavailable || CircuitBreaker::classify(error).is_some() {
+                    // The preferred datacenter has to prove itself healthy from scratch.
+                    state.probe_successes = 0;
+                    state.healthy_since = Some(now);
+                }
+            }
+        }
+
+        /// Returns `true` if the node belongs to the preferred datacenter.
+        pub(super) fn is_preferred(&self, node: NodeRef<'_>) -> bool {
+            node.datacenter.as_deref() == Some(self.preferred_datacenter.as_str())
+        }
+
+        // Chooses the first backup datacenter, other than `current`, with a node up.
+        // If there is none, the first backup datacenter is chosen when failing over
+        // from the preferred one, as it can't be worse than a datacenter without nodes up.
+        fn choose_backup(&self, cluster: &ClusterData, current: Option<usize>) -> Option<usize> {
+            let up = (0..self.backup_preferences.len()).find(|&i| {
+                Some(i) != current && Self::is_datacenter_up(cluster, self.backup_name(i))
+            });
+            match (up, current) {
+                (Some(i), _) => Some(i),
+                (None, None) if !self.backup_preferences.is_empty() => Some(0),
+                (None, _) => None,
+            }
+        }
+
+        fn backup_name(&self, index: usize) -> &str {
+            self.backup_preferences[index]
+                .datacenter()
+                .expect("backup preferences always name a datacenter")
+        }
+
+        fn is_datacenter_up(cluster: &ClusterData, datacenter: &str) -> bool {
+            cluster
+                .replica_locator()
+                .unique_nodes_in_datacenter_ring(datacenter)
+                .unwrap_or(&[])
+                .iter()
+                .any(|node| node.is_enabled() && !node.is_down())
+        }
+
+        fn emit(&self, event: DcFailoverEvent) {
+            warn!("Datacenter failover: {:?}", event);
+            if let Some(listener) = &self.listener {
+                listener.on_event(&event);
+            }
+        }
+    }
+
+    /// Builds the automatic datacenter failover of `DefaultPolicy`,
+    /// see [`DefaultPolicyBuilder::dc_failover`](super::DefaultPolicyBuilder::dc_failover).
+    ///
+    /// # Example
+    /// ```
+    /// # fn example() {
+    /// use scylla::load_balancing::{DcFailoverBuilder, DefaultPolicy};
+    /// use scylla::statement::Consistency;
+    /// use std::time::Duration;
+    ///
+    /// let dc_failover = DcFailoverBuilder::new(vec!["dc2".to_string(), "dc3".to_string()])
+    ///     .unavailable_threshold(10)
+    ///     .failback_delay(Duration::from_secs(120))
+    ///     .failback_probes(5)
+    ///     .remap_consistency("dc3".to_string(), Consistency::LocalQuorum, Consistency::Quorum);
+    ///
+    /// let policy = DefaultPolicy::builder()
+    ///     .prefer_datacenter("dc1".to_string())
+    ///     .dc_failover(dc_failover)
+    ///     .build();
+    /// # }
+    /// ```
+    #[derive(Debug, Clone)]
+    pub struct DcFailoverBuilder {
+        backup_datacenters: Vec<String>,
+        unavailable_threshold: usize,
+        failback_delay: Duration,
+        failback_probes: usize,
+        max_probes_in_flight: usize,
+        health_check_interval: Duration,
+        consistency_remappings: HashMap<String, Vec<(Consistency, Consistency)>>,
+        listener: Option<Arc<dyn DcFailoverListener>>,
+    }
+
+    impl DcFailoverBuilder {
+        /// Creates a builder of automatic datacenter failover to the given backup datacenters,
+        /// in the order of preference.
+        pub fn new(backup_datacenters: Vec<String>) -> Self {
+            Self {
+                backup_datacenters,
+                unavailable_threshold: 5,
+                failback_delay: Duration::from_secs(60),
+                failback_probes: 3,
+                max_probes_in_flight: 1,
+                health_check_interval: Duration::from_secs(1),
+                consistency_remappings: HashMap::new(),
+                listener: None,
+            }
+        }
+
+        /// Sets how many `Unavailable` errors in a row, returned by nodes of the preferred
+        /// datacenter, make the policy fail over. Any successful request to the preferred
+        /// datacenter resets the count.
+        /// The default is **5**.
+        pub fn unavailable_threshold(self, unavailable_threshold: usize) -> Self {
+            Self {
+                unavailable_threshold,
+                ..self
+            }
+        }
+
+        /// Sets how long the preferred datacenter has to have a node up before the policy
+        /// starts probing it, see [`failback_probes`](Self::failback_probes).
+        /// The default is **60 seconds**.
+        pub fn failback_delay(self, failback_delay: Duration) -> Self {
+            Self {
+                failback_delay,
+                ..self
+            }
+        }
+
+        /// Sets how many requests sent to the preferred datacenter have to succeed
+        /// before the policy switches back to it.
+        ///
+        /// While a backup datacenter is used and the preferred one has had a node up for
+        /// [`failback_delay`](Self::failback_delay), some requests are sent to the preferred
+        /// datacenter as probes, with the backup datacenter as the fallback. A probe failing
+        /// with an error indicating a problem with the node, e.g. `Unavailable`, `Overloaded`
+        /// or a timeout, resets the count and the delay.
+        /// The default is **3**.
+        pub fn failback_probes(self, failback_probes: usize) -> Self {
+            Self {
+                failback_probes,
+                ..self
+            }
+        }
+
+        /// Sets how many probe requests may be sent to the preferred datacenter at a time,
+        /// see [`failback_probes`](Self::failback_probes).
+        /// The default is **1**.
+        pub fn max_probes_in_flight(self, max_probes_in_flight: usize) -> Self {
+            Self {
+                max_probes_in_flight,
+                ..self
+            }
+        }
+
+        /// Sets how often the policy checks whether all nodes of the datacenter in use
+        /// are down, which makes it fail over or try the next backup datacenter.
+        /// Reaching the [`unavailable_threshold`](Self::unavailable_threshold) triggers
+        /// the check immediately.
+        /// The default is **1 second**.
+        pub fn health_check_interval(self, health_check_interval: Duration) -> Self {
+            Self {
+                health_check_interval,
+                ..self
+            }
+        }
+
+        /// Makes requests with the consistency `from` use the consistency `to` instead
+        /// when sent to the backup datacenter `backup_datacenter` while the policy
+        /// has failed over. Requests sent to the preferred datacenter, including probes,
+        /// keep their consistency.
+        ///
+        /// For example, remapping `LOCAL_QUORUM` to `QUORUM` for a backup datacenter which
+        /// should only coordinate requests, or `EACH_QUORUM` to `LOCAL_QUORUM` for
+        /// a backup datacenter which should keep working without the preferred one.
+        /// Setting a remapping of the same consistency again replaces it.
+        /// By default, no consistency is remapped.
+        pub fn remap_consistency(
+            mut self,
+            backup_datacenter: String,
+            from: Consistency,
+            to: Consistency,
+        ) -> Self {
+            let remapping = self
+                .consistency_remappings
+                .entry(backup_datacenter)
+                .or_default();
+            remapping.retain(|(remapped, _)| *remapped != from);
+            remapping.push((from, to));
+            self
+        }
+
+        /// Sets the listener notified whenever the policy fails over or back.
+        pub fn listener(self, listener: Arc<dyn DcFailoverListener>) -> Self {
+            Self {
+                listener: Some(listener),
+                ..self
+            }
+        }
+
+        pub(super) fn build(self, preferred_datacenter: String) -> DcFailover {
+            let mut consistency_remappings = self.consistency_remappings;
+            consistency_remappings.retain(|datacenter, _| {
+                let is_backup = self.backup_datacenters.contains(datacenter);
+                if !is_backup {
+                    warn!(
+                        "Consistency remapping is set for datacenter {}, which is not a backup datacenter. Ignoring it.",
+                        datacenter
+                    );
+                }
+                is_backup
+            });
+
+            DcFailover {
+                preferred_datacenter,
+                backup_preferences: self
+                    .backup_datacenters
+                    .into_iter()
+                    .map(ReplicaLocationPreference::Datacenter)
+                    .collect(),
+                unavailable_threshold: self.unavailable_threshold,
+                failback_delay: self.failback_delay,
+                failback_probes: self.failback_probes,
+                consistency_remappings,
+                listener: self.listener,
+                consecutive_unavailable: AtomicUsize::new(0),
+                health_check: PeriodicCheck::new(self.health_check_interval),
+                state: RwLock::new(FailoverState {
+                    active_backup: None,
+                    healthy_since: None,
+                    probe_successes: 0,
+                    probes: ProbeSlots::new(self.max_probes_in_flight),
+                }),
+            }
+        }
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::{DcFailoverBuilder, DcFailoverEvent, DcFailoverListener, DcFailoverReason};
+        use crate::load_balancing::default::tests::framework::mock_cluster_data_for_token_aware_tests;
+        use crate::load_balancing::default::ReplicaLocationPreference;
+        use crate::transport::ClusterData;
+        use scylla_cql::errors::{DbError, QueryError};
+        use scylla_cql::frame::types::LegacyConsistency;
+        use scylla_cql::Consistency;
+        use std::sync::{Arc, Mutex};
+        use std::time::{Duration, Instant};
+
+        #[derive(Debug, Default)]
+        struct RecordingListener {
+            events: Mutex<Vec<DcFailoverEvent>>,
+        }
+
+        impl DcFailoverListener for RecordingListener {
+            fn on_event(&self, event: &DcFailoverEvent) {
+                self.events.lock().unwrap().push(event.clone());
+            }
+        }
+
+        fn mark_datacenter(cluster: &ClusterData, datacenter: &str, is_down: bool) {
+            for node in cluster.get_nodes_info() {
+                if node.datacenter.as_deref() == Some(datacenter) {
+                    node.change_down_marker(is_down);
+                }
+            }
+        }
+
+        #[tokio::test]
+        async fn test_failover_and_failback() {
+            let cluster = mock_cluster_data_for_token_aware_tests().await;
+            let listener = Arc::new(RecordingListener::default());
+            let failover = DcFailoverBuilder::new(vec!["unknown".to_owned(), "us".to_owned()])
+                .failback_delay(Duration::from_secs(10))
+                .listener(listener.clone())
+                .build("eu".to_owned());
+
+            let now = Instant::now();
+            failover.update_at(&cluster, now);
+            assert!(failover.backup_preferences().is_none());
+
+            // All nodes of "eu" go down, "unknown" has no nodes, so "us" is chosen.
+            mark_datacenter(&cluster, "eu", true);
+            failover.update_at(&cluster, now);
+            assert!(matches!(
+                failover.backup_preferences(),
+                Some(ReplicaLocationPreference::Datacenter(dc)) if dc == "us"
+            ));
+
+            // "eu" comes back, but it is probed only after the delay.
+            mark_datacenter(&cluster, "eu", false);
+            failover.update_at(&cluster, now + Duration::from_secs(1));
+            failover.update_at(&cluster, now + Duration::from_secs(5));
+            assert!(!failover.try_start_probe_at(now + Duration::from_secs(5)));
+            assert!(failover.try_start_probe_at(now + Duration::from_secs(11)));
+            // Only one probe is in flight at a time by default.
+            assert!(!failover.try_start_probe_at(now + Duration::from_secs(11)));
+
+            // A failed probe restarts the delay.
+            let eu_node = cluster
+                .get_nodes_info()
+                .iter()
+                .find(|node| node.datacenter.as_deref() == Some("eu"))
+                .unwrap();
+            failover.report_failure_at(
+                eu_node,
+                &QueryError::TimeoutError,
+                now + Duration::from_secs(11),
+            );
+            assert!(!failover.try_start_probe_at(now + Duration::from_secs(12)));
+            assert!(failover.backup_preferences().is_some());
+
+            // The policy fails back after enough successful probes.
+            for _ in 0..3 {
+                assert!(failover.backup_preferences().is_some());
+                assert!(failover.try_start_probe_at(now + Duration::from_secs(21)));
+                failover.report_success(eu_node);
+            }
+            assert!(failover.backup_preferences().is_none());
+            assert!(!failover.try_start_probe_at(now + Duration::from_secs(21)));
+
+            assert_eq!(
+                *listener.events.lock().unwrap(),
+                vec![
+                    DcFailoverEvent::FailedOver {
+                        from: "eu".to_owned(),
+                        to: "us".to_owned(),
+                        reason: DcFailoverReason::AllNodesDown,
+                    },
+                    DcFailoverEvent::FailedBack {
+                        from: "us".to_owned(),
+                        to: "eu".to_owned(),
+                    },
+                ]
+            );
+        }
+
+        #[tokio::test]
+        async fn test_default_policy_with_dc_failover() {
+            use crate::load_balancing::default::tests::{
+                framework::ExpectedGroupsBuilder,
+                test_default_policy_with_given_cluster_and_routing_info,
+            };
+            use crate::load_balancing::{DefaultPolicy, LoadBalancingPolicy, RoutingInfo};
+            use crate::routing::Token;
+            use crate::transport::locator::test::{A, B, C, D, E, F, G, KEYSPACE_NTS_RF_2};
+
+            let cluster = mock_cluster_data_for_token_aware_tests().await;
+            let policy = DefaultPolicy {
+                preferences: ReplicaLocationPreference::Datacenter("eu".to_owned()),
+                dc_failover: Some(
+                    DcFailoverBuilder::new(vec!["us".to_owned()])
+                        .failback_delay(Duration::ZERO)
+                        .failback_probes(1)
+                        .health_check_interval(Duration::ZERO)
+                        .remap_consistency("us".to_owned(), Consistency::LocalOne, Consistency::One)
+                        .build("eu".to_owned()),
+                ),
+                ..Default::default()
+            };
+            // Local consistency doesn't prevent switching to the backup datacenter.
+            let routing_info = RoutingInfo {
+                token: Some(Token::new(160)),
+                keyspace: Some(KEYSPACE_NTS_RF_2),
+                consistency: Consistency::LocalOne,
+                ..Default::default()
+            };
+
+            // going through the ring, we get order: F , A , C , D , G , B , E
+            //                                      us  eu  eu  us  eu  eu  us
+            //                                      r2  r1  r1  r1  r2  r1  r1
+            let eu_groups = ExpectedGroupsBuilder::new()
+                .group([A, G]) // local replicas
+                .group([C, B]) // local nodes
+                .build();
+            let us_groups = ExpectedGroupsBuilder::new()
+                .group([F, D]) // backup datacenter replicas
+                .group([E]) // backup datacenter nodes
+                .build();
+
+            let consistency_for_node =
+                |node| policy.consistency_for_node(&routing_info, node, routing_info.consistency);
+
+            test_default_policy_with_given_cluster_and_routing_info(
+                &policy,
+                &cluster,
+                &routing_info,
+                &eu_groups,
+            )
+            .await;
+            // Consistency is remapped only after failing over.
+            let us_node = cluster
+                .get_nodes_info()
+                .iter()
+                .find(|node| node.datacenter.as_deref() == Some("us"))
+                .unwrap();
+            assert_eq!(consistency_for_node(us_node), Consistency::LocalOne);
+
+            mark_datacenter(&cluster, "eu", true);
+            test_default_policy_with_given_cluster_and_routing_info(
+                &policy,
+                &cluster,
+                &routing_info,
+                &us_groups,
+            )
+            .await;
+            assert_eq!(consistency_for_node(us_node), Consistency::One);
+
+            // The preferred datacenter is probed, with the backup datacenter as the fallback.
+            mark_datacenter(&cluster, "eu", false);
+            let probe = policy.pick(&routing_info, &cluster).unwrap();
+            assert_eq!(probe.datacenter.as_deref(), Some("eu"));
+            assert_eq!(consistency_for_node(probe), Consistency::LocalOne);
+            assert!(policy
+                .fallback(&routing_info, &cluster)
+                .all(|node| node.datacenter.as_deref() == Some("us")));
+
+            // A successful probe makes the policy fail back.
+            policy.on_query_success(&routing_info, Duration::ZERO, probe);
+            test_default_policy_with_given_cluster_and_routing_info(
+                &policy,
+                &cluster,
+                &routing_info,
+                &eu_groups,
+            )
+            .await;
+        }
+
+        #[tokio::test]
+        async fn test_failover_on_repeated_unavailable() {
+            let cluster = mock_cluster_data_for_token_aware_tests().await;
+            let failover = DcFailoverBuilder::new(vec!["us".to_owned()])
+                .unavailable_threshold(3)
+                .build("eu".to_owned());
+            let eu_node = cluster
+                .get_nodes_info()
+                .iter()
+                .find(|node| node.datacenter.as_deref() == Some("eu"))
+                .unwrap();
+            let unavailable = QueryError::DbError(
+                DbError::Unavailable {
+                    consistency: LegacyConsistency::Regular(Consistency::LocalQuorum),
+                    required: 2,
+                    alive: 1,
+                },
+                "Unavailable".to_owned(),
+            );
+
+            // A success resets the count of Unavailable errors.
+            failover.report_failure(eu_node, &unavailable);
+            failover.report_failure(eu_node, &unavailable);
+            failover.report_success(eu_node);
+            failover.report_failure(eu_node, &unavailable);
+            failover.update(&cluster);
+            assert!(failover.backup_preferences().is_none());
+
+            // Reaching the threshold triggers a health check before the scheduled one.
+            failover.report_failure(eu_node, &unavailable);
+            failover.report_failure(eu_node, &unavailable);
+            failover.update(&cluster);
+            assert!(matches!(
+                failover.backup_preferences(),
+                Some(ReplicaLocationPreference::Datacenter(dc)) if dc == "us"
+            ));
+        }
+
+        #[test]
+        fn test_remap_consistency_replaces_previous_remapping() {
+            let failover = DcFailoverBuilder::new(vec!["us".to_owned()])
+                .remap_consistency("us".to_owned(), Consistency::LocalQuorum, Consistency::One)
+                .remap_consistency(
+                    "us".to_owned(),
+                    Consistency::LocalQuorum,
+                    Consistency::Quorum,
+                )
+                .remap_consistency("eu".to_owned(), Consistency::LocalOne, Consistency::One)
+                .build("eu".to_owned());
+
+            assert_eq!(
+                failover.consistency_remappings.get("us"),
+                Some(&vec![(Consistency::LocalQuorum, Consistency::Quorum)])
+            );
+            // Remappings for datacenters other than the backup ones are ignored.
+            assert!(!failover.consistency_remappings.contains_key("eu"));
+        }
+    }
+}
+
+mod circuit_breaker {
+    use scylla_cql::errors::{DbError, QueryError};
+    use tracing::{debug, warn};
+    use uuid::Uuid;
+
+    use super::periodic::PeriodicCheck;
+    use super::probes::ProbeSlots;
+    use crate::transport::{cluster::ClusterData, NodeRef};
+    use std::{
+        collections::HashMap,
+        sync::{
+            atomic::{AtomicBool, AtomicUsize, Ordering},
+            Mutex, RwLock,
+        },
+        time::{Duration, Instant},
+    };
+
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    pub(super) enum Failure {
+        Error,
+        Timeout,
+    }
+
+    #[derive(Debug, Clone)]
+    enum BreakerState {
+        // Requests are counted in windows of `window` length.
+        Closed {
+            window_start: Instant,
+            requests: usize,
+            errors: usize,
+            timeouts: usize,
+        },
+        // The node is deprioritized; it may be probed after the given instant.
+        Open {
+            until: Instant,
+        },
+        // The node is still deprioritized, but a bounded number of probe requests
+        // is sent to it; a failure opens the breaker, enough successes close it.
+        HalfOpen {
+            successes: usize,
+            probes: ProbeSlots,
+        },
+    }
+
+    impl BreakerState {
+        fn closed(now: Instant) -> Self {
+            BreakerState::Closed {
+                window_start: now,
+                requests: 0,
+                errors: 0,
+                timeouts: 0,
+            }
+        }
+    }
+
+    #[derive(Debug)]
+    struct NodeBreaker {
+        // Mirrors whether `state` is `Closed`, so that it can be checked without locking.
+        closed: AtomicBool,
+        state: Mutex<BreakerState>,
+    }
+
+    /// A per-node circuit breaker of `DefaultPolicy`. Nodes whose recent requests
+    /// mostly failed or timed out are moved to the end of query plans for a while.
+    #[derive(Debug)]
+    pub(super) struct CircuitBreaker {
+        error_rate_threshold: f64,
+        timeout_rate_threshold: f64,
+        minimum_requests: usize,
+        window: Duration,
+        open_duration: Duration,
+        half_open_successes: usize,
+        max_probes_in_flight: usize,
+
+        states: RwLock<HashMap<Uuid, NodeBreaker>>,
+        // Number of breakers which are not closed; if zero, no locks have to be taken
+        // to check a node.
+        not_closed: AtomicUsize,
+        cleanup: PeriodicCheck,
+    }
+
+    impl CircuitBreaker {
+        /// Returns `true` if the breaker of the node is not closed, i.e. the node should be
+        /// avoided except for probe requests. Doesn't change the state of the breaker.
+        pub(super) fn is_open(&self, node: NodeRef<'_>) -> bool {
+            if self.not_closed.load(Ordering::Relaxed) == 0 {
+                return false;
+            }
+            let states = self.states.read().unwrap();
+            match states.get(&node.host_id) {
+                Some(breaker) => !breaker.closed.load(Ordering::Relaxed),
+                None => false,
+            }
+        }
+
+        /// Removes the breakers of nodes which are no longer in the cluster, and closed
+        /// breakers whose window has passed, as they carry no information.
+        /// As it's called for every request, this is done at most once per window.
+        pub(super) fn cleanup(&self, cluster: &ClusterData) {
+            let now = Instant::now();
+            if self.cleanup.try_start(now) {
+                self.cleanup_at(cluster, now)
+            }
+        }
+
+        fn cleanup_at(&self, cluster: &ClusterData, now: Instant) {
+            let mut states = self.states.write().unwrap();
+            states.retain(|host_id, breaker| {
+                let state = breaker.state.get_mut().unwrap();
+                let keep = cluster.known_peers.contains_key(host_id)
+                    && match state {
+                        BreakerState::Closed { window_start, .. } => {
+                            now.saturating_duration_since(*window_start) < self.window
+                        }
+                        _ => true,
+                    };
+                if !keep && !breaker.closed.load(Ordering::Relaxed) {
+                    self.not_closed.fetch_sub(1, Ordering::Relaxed);
+                }
+                keep
+            });
+        }
+
+        /// Returns `true` if a probe request may be sent to the node, i.e. its breaker
+        /// has been open long enough and not too many probes are in flight.
+        /// Doesn't change the state of the breaker.
+        pub(super) fn can_probe(&self, node: NodeRef<'_>) -> bool {
+            self.can_probe_at(node, Instant::now())
+        }
+
+        fn can_probe_at(&self, node: NodeRef<'_>, now: Instant) -> bool {
+            if self.not_closed.load(Ordering::Relaxed) == 0 {
+                return false;
+            }
+            let states = self.states.read().unwrap();
+            let state = match states.get(&node.host_id) {
+                Some(breaker) if !breaker.closed.load(Ordering::Relaxed) => {
+                    breaker.state.lock().unwrap()
+                }
+                _ => return false,
+            };
+            match &*state {
+                BreakerState::Closed { .. } => false,
+                BreakerState::Open { until } => now >= *until && self.max_probes_in_flight > 0,
+                BreakerState::HalfOpen { probes, .. } => probes.has_free_slot(now),
+            }
+        }
+
+        /// Counts a request about to be sent to the node as a probe. Returns `false`
+        /// if the node may not be probed now, see [`can_probe`](Self::can_probe).
+        pub(super) fn try_start_probe(&self, node: NodeRef<'_>) -> bool {
+            self.try_start_probe_at(node, Instant::now())
+        }
+
+        fn try_start_probe_at(&self, node: NodeRef<'_>, now: Instant) -> bool {
+            if self.not_closed.load(Ordering::Relaxed) == 0 {
+                return false;
+            }
+            let states = self.states.read().unwrap();
+            let mut state = match states.get(&node.host_id) {
+                Some(breaker) if !breaker.closed.load(Ordering::Relaxed) => {
+                    breaker.state.lock().unwrap()
+                }
+                _ => return false,
+            };
+            match &mut *state {
+                BreakerState::Closed { .. } => false,
+                BreakerState::Open { until } if now < *until => false,
+                BreakerState::Open { .. } => {
+                    let mut probes = ProbeSlots::new(self.max_probes_in_flight);
+                    if !probes.try_acquire(now) {
+                        return false;
+                    }
+                    debug!("Circuit breaker: probing node {} (half-open)", node.address);
+                    *state = BreakerState::HalfOpen {
+                        successes: 0,
+                        probes,
+                    };
+                    true
+                }
+                BreakerState::HalfOpen { probes, .. } => probes.try_acquire(now),
+            }
+        }
+
+        pub(super) fn report_success(&self, node: NodeRef<'_>) {
+            self.report_at(node, None, Instant::now())
+        }
+
+        pub(super) fn report_failure(&self, node: NodeRef<'_>, error: &QueryError) {
+            // Errors caused by the request itself say nothing about the node's health.
+            if let Some(failure) = Self::classify(error) {
+                self.report_at(node, Some(failure), Instant::now())
+            }
+        }
+
+        fn report_at(&self, node: NodeRef<'_>, failure: Option<Failure>, now: Instant) {
+            {
+                let states = self.states.read().unwrap();
+                if let Some(breaker) = states.get(&node.host_id) {
+                    self.update_breaker(node, breaker, failure, now);
+                    return;
+                }
+            }
+
+            let mut states = self.states.write().unwrap();
+            let breaker = states.entry(node.host_id).or_insert_with(|| NodeBreaker {
+                closed: AtomicBool::new(true),
+                state: Mutex::new(BreakerState::closed(now)),
+            });
+            self.update_breaker(node, breaker, failure, now);
+        }
+
+        fn update_breaker(
+            &self,
+            node: NodeRef<'_>,
+            breaker: &NodeBreaker,
+            failure: Option<Failure>,
+            now: Instant,
+        ) {
+            let mut state = breaker.state.lock().unwrap();
+            self.update_state(node, &mut state, failure, now);
+
+            // Updated while holding the lock, so that it can't be overwritten with a stale value.
+            let closed = matches!(*state, BreakerState::Closed { .. });
+            if breaker.closed.swap(closed, Ordering::Relaxed) != closed {
+                if closed {
+                    self.not_closed.fetch_sub(1, Ordering::Relaxed);
+                } else {
+                    self.not_closed.fetch_add(1, Ordering::Relaxed);
+                }
+            }
+        }
+
+        fn update_state(
+            &self,
+            node: NodeRef<'_>,
+            state: &mut BreakerState,
+            failure: Option<Failure>,
+            now: Instant,
+        ) {
+            match state {
+                BreakerState::Closed {
+                    window_start,
+                    requests,
+                    errors,
+                    timeouts,
+                } => {
+                    if now.saturating_duration_since(*window_start) >= self.window {
+                        *state = BreakerState::closed(now);
+                        return self.update_state(node, state, failure, now);
+                    }
+
+                    *requests += 1;
+                    match failure {
+                        Some(Failure::Error) => *errors += 1,
+                        Some(Failure::Timeout) => *timeouts += 1,
+                        None => (),
+                    }
+
+                    if *requests >= self.minimum_requests {
+                        let error_rate = *errors as f64 / *requests as f64;
+                        let timeout_rate = *timeouts as f64 / *requests as f64;
+                        if error_rate >= self.error_rate_threshold
+                            || timeout_rate >= self.timeout_rate_threshold
+                        {
+                            warn!(
+                                "Circuit breaker: deprioritizing node {} for {:?}, error rate: {:.2}, timeout rate: {:.2}",
+                                node.address, self.open_duration, error_rate, timeout_rate
+                            );
+                            *state = BreakerState::Open {
+                                until: now + self.open_duration,
+                            };
+                        }
+                    }
+                }
+                // Responses to requests sent before the breaker opened.
+                BreakerState::Open { .. } => (),
+                BreakerState::HalfOpen { successes, probes } => match failure {
+                    Some(_) => {
+                        warn!(
+                            "Circuit breaker: probe of node {} failed, deprioritizing it for {:?}",
+                            node.address, self.open_duration
+                        );
+                        *state = BreakerState::Open {
+                            until: now + self.open_duration,
+                        };
+                    }
+                    None => {
+                        probes.release();
+                        *successes += 1;
+                        if *successes >= self.half_open_successes {
+                            debug!("Circuit breaker: node {} is healthy again", node.address);
+                            *state = BreakerState::closed(now);
+                        }
+                    }
+                },
+            }
+        }
+
+        pub(super) fn classify(error: &QueryError) -> Option<Failure> {
+            match error {
+                QueryError::TimeoutError
+                | QueryError::RequestTimeout(_)
+                | QueryError::DbError(DbError::ReadTimeout { .. }, _)
+                | QueryError::DbError(DbError::WriteTimeout { .. }, _) => Some(Failure::Timeout),
+
+                QueryError::IoError(_)
+                | QueryError::TooManyOrphanedStreamIds(_)
+                | QueryError::UnableToAllocStreamId
+                | QueryError::DbError(DbError::Overloaded, _)
+                | QueryError::DbError(DbError::IsBootstrapping, _)
+                | QueryError::DbError(DbError::ServerError, _)
+                | QueryError::DbError(DbError::ReadFailure { .. }, _)
+                | QueryError::DbError(DbError::WriteFailure { .. }, _) => Some(Failure::Error),
+
+                _ => None,
+            }
+        }
+    }
+
+    /// Moves nodes whose circuit breaker is open to the end of the plan.
+    pub(super) struct IteratorWithTrippedNodesLast<'a, I> {
+        breaker: &'a CircuitBreaker,
+        fallback: I,
+        tripped: Vec<NodeRef<'a>>,
+        tripped_index: usize,
+    }
+
+    impl<'a, I> IteratorWithTrippedNodesLast<'a, I>
+    where
+        I: Iterator<Item = NodeRef<'a>>,
+    {
+        pub(super) fn new(breaker: &'a CircuitBreaker, fallback: I) -> Self {
+            Self {
+                breaker,
+                fallback,
+                tripped: Vec::new(),
+                tripped_index: 0,
+            }
+        }
+    }
+
+    impl<'a, I> Iterator for IteratorWithTrippedNodesLast<'a, I>
+    where
+        I: Iterator<Item = NodeRef<'a>>,
+    {
+        type Item = NodeRef<'a>;
+
+        fn next(&mut self) -> Option<Self::Item> {
+            for node in self.fallback.by_ref() {
+                if !self.breaker.is_open(node) {
+                    return Some(node);
+                }
+                self.tripped.push(node);
+            }
+
+            let node = self.tripped.get(self.tripped_index).copied();
+            self.tripped_index += 1;
+            node
+        }
+    }
+
+    /// Builds the per-node circuit breaker of `DefaultPolicy`,
+    /// see [`DefaultPolicyBuilder::circuit_breaker`](super::DefaultPolicyBuilder::circuit_breaker).
+    ///
+    /// # Example
+    /// ```
+    /// # fn example() {
+    /// use scylla::load_balancing::{CircuitBreakerBuilder, DefaultPolicy};
+    /// use std::time::Duration;
+    ///
+    /// let circuit_breaker = CircuitBreakerBuilder::new()
+    ///     .error_rate_threshold(0.3)
+    ///     .open_duration(Duration::from_secs(10));
+    ///
+    /// let policy = DefaultPolicy::builder()
+    ///     .circuit_breaker(circuit_breaker)
+    ///     .build();
+    /// # }
+    /// ```
+    #[derive(Debug, Clone)]
+    pub struct CircuitBreakerBuilder {
+        error_rate_threshold: f64,
+        timeout_rate_threshold: f64,
+        minimum_requests: usize,
+        window: Duration,
+        open_duration: Duration,
+        half_open_successes: usize,
+        max_probes_in_flight: usize,
+    }
+
+    impl CircuitBreakerBuilder {
+        /// Creates a builder of the circuit breaker of DefaultPolicy.
+        pub fn new() -> Self {
+            Self {
+                error_rate_threshold: 0.5,
+                timeout_rate_threshold: 0.5,
+                minimum_requests: 20,
+                window: Duration::from_secs(10),
+                open_duration: Duration::from_secs(30),
+                half_open_successes: 3,
+                max_probes_in_flight: 1,
+            }
+        }
+
+        /// Sets the fraction of requests to a node which have to fail within a window
+        /// for the breaker to open. Failures are errors indicating a problem with the node,
+        /// e.g. `Overloaded`, server errors or broken connections, but not timeouts.
+        /// The default is **0.5**.
+        pub fn error_rate_threshold(self, error_rate_threshold: f64) -> Self {
+            Self {
+                error_rate_threshold,
+                ..self
+            }
+        }
+
+        /// Sets the fraction of requests to a node which have to time out within a window
+        /// for the breaker to open. Both client-side and server-side timeouts are counted.
+        /// The default is **0.5**.
+        pub fn timeout_rate_threshold(self, timeout_rate_threshold: f64) -> Self {
+            Self {
+                timeout_rate_threshold,
+                ..self
+            }
+        }
+
+        /// Sets how many requests have to be sent to a node within a window
+        /// before the error and timeout rates are taken into account.
+        /// The default is **20**.
+        pub fn minimum_requests(self, minimum_requests: usize) -> Self {
+            Self {
+                minimum_requests,
+                ..self
+            }
+        }
+
+        /// Sets the length of the window in which the requests are counted.
+        /// The default is **10 seconds**.
+        pub fn window(self, window: Duration) -> Self {
+            Self { window, ..self }
+        }
+
+        /// Sets how long a node is deprioritized after its breaker opens.
+        /// After that, the breaker becomes half-open: the node stays deprioritized, but
+        /// some requests are sent to it as probes, see
+        /// [`max_probes_in_flight`](Self::max_probes_in_flight). A failed probe opens
+        /// the breaker again, and enough successful ones close it.
+        /// The default is **30 seconds**.
+        pub fn open_duration(self, open_duration: Duration) -> Self {
+            Self {
+                open_duration,
+                ..self
+            }
+        }
+
+        /// Sets how many successful requests close a half-open breaker.
+        /// The default is **3**.
+        pub fn half_open_successes(self, half_open_successes: usize) -> Self {
+            Self {
+                half_open_successes,
+                ..self
+            }
+        }
+
+        /// Sets how many probe requests may be sent to a node with a half-open breaker
+        /// at a time. Other requests keep avoiding the node.
+        /// The default is **1**.
+        pub fn max_probes_in_flight(self, max_probes_in_flight: usize) -> Self {
+            Self {
+                max_probes_in_flight,
+                ..self
+            }
+        }
+
+        pub(super) fn build(self) -> CircuitBreaker {
+            CircuitBreaker {
+                error_rate_threshold: self.error_rate_threshold,
+                timeout_rate_threshold: self.timeout_rate_threshold,
+                minimum_requests: self.minimum_requests,
+                window: self.window,
+                open_duration: self.open_duration,
+                half_open_successes: self.half_open_successes,
+                max_probes_in_flight: self.max_probes_in_flight,
+                states: RwLock::new(HashMap::new()),
+                not_closed: AtomicUsize::new(0),
+                cleanup: PeriodicCheck::new(self.window),
+            }
+        }
+    }
+
+    impl Default for CircuitBreakerBuilder {
+        fn default() -> Self {
+            Self::new()
+        }
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::{CircuitBreakerBuilder, Failure, IteratorWithTrippedNodesLast};
+        use crate::load_balancing::default::tests::framework::mock_cluster_data_for_token_aware_tests;
+        use scylla_cql::errors::{BadQuery, DbError, QueryError};
+        use std::sync::atomic::Ordering;
+        use std::time::{Duration, Instant};
+
+        #[test]
+        fn test_failure_classification() {
+            use super::CircuitBreaker;
+
+            assert_eq!(
+                CircuitBreaker::classify(&QueryError::RequestTimeout("timeout".to_owned())),
+                Some(Failure::Timeout)
+            );
+            assert_eq!(
+                CircuitBreaker::classify(&QueryError::DbError(
+                    DbError::Overloaded,
+                    "overloaded".to_owned()
+                )),
+                Some(Failure::Error)
+            );
+            assert_eq!(
+                CircuitBreaker::classify(&QueryError::DbError(
+                    DbError::SyntaxError,
+                    "syntax".to_owned()
+                )),
+                None
+            );
+            assert_eq!(
+                CircuitBreaker::classify(&QueryError::BadQuery(BadQuery::Other("bad".to_owned()))),
+                None
+            );
+        }
+
+        #[tokio::test]
+        async fn test_circuit_breaker_transitions() {
+            let cluster = mock_cluster_data_for_token_aware_tests().await;
+            let nodes = cluster.get_nodes_info();
+            let (sick, healthy) = (&nodes[0], &nodes[1]);
+
+            let breaker = CircuitBreakerBuilder::new()
+                .minimum_requests(10)
+                .timeout_rate_threshold(0.5)
+                .window(Duration::from_secs(10))
+                .open_duration(Duration::from_secs(30))
+                .half_open_successes(2)
+                .build();
+            let now = Instant::now();
+
+            // Below the minimum number of requests, failures don't open the breaker.
+            for _ in 0..5 {
+                breaker.report_at(sick, Some(Failure::Timeout), now);
+                breaker.report_at(healthy, None, now);
+            }
+            assert!(!breaker.is_open(sick));
+
+            // A new window starts, so the earlier requests are forgotten.
+            let now = now + Duration::from_secs(10);
+            for _ in 0..4 {
+                breaker.report_at(sick, Some(Failure::Timeout), now);
+            }
+            for _ in 0..5 {
+                breaker.report_at(sick, None, now);
+            }
+            assert!(!breaker.is_open(sick));
+
+            // 5 timeouts out of 10 requests open the breaker.
+            breaker.report_at(sick, Some(Failure::Timeout), now);
+            assert!(breaker.is_open(sick));
+            assert!(!breaker.is_open(healthy));
+            assert!(!breaker.can_probe_at(sick, now));
+            assert!(!breaker.try_start_probe_at(sick, now));
+
+            // The tripped node is moved to the end of the plan.
+            let plan: Vec<_> =
+                IteratorWithTrippedNodesLast::new(&breaker, [sick, healthy].into_iter()).collect();
+            assert!(std::ptr::eq(plan[0], healthy));
+            assert!(std::ptr::eq(plan[1], sick));
+
+            // After the open duration, the node may be probed, but only by a bounded
+            // number of requests; it stays deprioritized otherwise.
+            let now = now + Duration::from_secs(30);
+            assert!(breaker.is_open(sick));
+            assert!(breaker.can_probe_at(sick, now));
+            assert!(breaker.can_probe_at(sick, now));
+            assert!(breaker.try_start_probe_at(sick, now));
+            assert!(!breaker.can_probe_at(sick, now));
+            assert!(!breaker.try_start_probe_at(sick, now));
+            assert!(breaker.is_open(sick));
+
+            // A failed probe opens the breaker again.
+            breaker.report_at(sick, Some(Failure::Error), now);
+            assert!(breaker.is_open(sick));
+            assert!(!breaker.can_probe_at(sick, now));
+
+            // Enough successful probes close the breaker.
+            let now = now + Duration::from_secs(30);
+            assert!(breaker.try_start_probe_at(sick, now));
+            breaker.report_at(sick, None, now);
+            assert!(breaker.is_open(sick));
+            assert!(breaker.try_start_probe_at(sick, now));
+            breaker.report_at(sick, None, now);
+            assert!(!breaker.is_open(sick));
+            for _ in 0..9 {
+                breaker.report_at(sick, Some(Failure::Timeout), now);
+            }
+            // The counting starts over after closing, 9 requests are below the minimum.
+            assert!(!breaker.is_open(sick));
+        }
+
+        #[tokio::test]
+        async fn test_circuit_breaker_cleanup() {
+            let cluster = mock_cluster_data_for_token_aware_tests().await;
+            let nodes = cluster.get_nodes_info();
+            let (sick, healthy) = (&nodes[0], &nodes[1]);
+
+            let breaker = CircuitBreakerBuilder::new()
+                .minimum_requests(1)
+                .window(Duration::from_secs(10))
+                .build();
+            let now = Instant::now();
+
+            breaker.report_at(sick, Some(Failure::Error), now);
+            breaker.report_at(healthy, None, now);
+            assert!(breaker.is_open(sick));
+            assert_eq!(breaker.states.read().unwrap().len(), 2);
+
+            // Closed breakers are removed once their window has passed.
+            breaker.cleanup_at(&cluster, now);
+            assert_eq!(breaker.states.read().unwrap().len(), 2);
+            breaker.cleanup_at(&cluster, now + Duration::from_secs(10));
+            assert_eq!(breaker.states.read().unwrap().len(), 1);
+            assert!(breaker.is_open(sick));
+            assert!(!breaker.is_open(healthy));
+
+            // Breakers of nodes which are no longer in the cluster are removed.
+            let other_cluster = mock_cluster_data_for_token_aware_tests().await;
+            breaker.cleanup_at(&other_cluster, now + Duration::from_secs(10));
+            assert!(breaker.states.read().unwrap().is_empty());
+            assert_eq!(breaker.not_closed.load(Ordering::Relaxed), 0);
+            assert!(!breaker.is_open(sick));
+        }
+    }
+}
@@ -0,0 +1,198 @@
+use std::sync::Arc;
+
+use super::{LoadBalancingPolicy, Plan, RoutingInfo};
+use crate::routing::{Shard, Token};
+use crate::statement::NodeTarget;
+use crate::transport::{ClusterData, Node};
+
+/// What a load balancing policy knows about a node of its plan,
+/// returned by [`LoadBalancingPolicy::explain_node`].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct NodeExplanation {
+    /// Whether the node is in the datacenter preferred by the policy.
+    /// `None` if the policy doesn't prefer any datacenter.
+    pub is_local_datacenter: Option<bool>,
+    /// Whether the node is in the rack preferred by the policy.
+    /// `None` if the policy doesn't prefer any rack.
+    pub is_local_rack: Option<bool>,
+    /// Whether the policy moved the node towards the end of the plan
+    /// for being too slow, e.g. by latency awareness.
+    pub is_penalised: bool,
+}
+
+/// A node of an explained plan, see [`PlanExplanation`].
+#[derive(Debug, Clone)]
+#[non_exhaustive]
+pub struct PlannedNode {
+    pub node: Arc<Node>,
+    /// The shard the request would be sent to. `None` if the node isn't sharded,
+    /// or if no token was computed and a random connection would be used.
+    pub shard: Option<Shard>,
+    /// Whether the node is a replica of the token.
+    pub is_replica: bool,
+    /// Whether the node is marked as down.
+    pub is_down: bool,
+    /// `false` if the node was filtered out by the host filter, so no connections are kept to it.
+    pub is_enabled: bool,
+    /// Annotations provided by the load balancing policy.
+    pub policy: NodeExplanation,
+}
+
+/// The load balancing plan a statement would be sent according to,
+/// returned by [`Session::explain_plan`](crate::Session::explain_plan).
+///
+/// The plan reflects the current state of the cluster and the policy. It may differ from
+/// the one used by the next execution, e.g. because the policy shuffles replicas.
+#[derive(Debug, Clone)]
+#[non_exhaustive]
+pub struct PlanExplanation {
+    /// Name of the load balancing policy of the statement's execution profile.
+    pub policy_name: String,
+    /// `true` if the statement targets a chosen node (see [`NodeTarget`]),
+    /// so the load balancing policy is not used.
+    pub bypasses_load_balancing: bool,
+    pub keyspace: Option<String>,
+    /// The token computed from the partition key. `None` if the statement isn't token-aware.
+    pub token: Option<Token>,
+    pub is_confirmed_lwt: bool,
+    /// Replicas of the token, in no particular order.
+    pub replicas: Vec<Arc<Node>>,
+    /// Nodes in the order in which they would be tried.
+    pub plan: Vec<PlannedNode>,
+}
+
+pub(crate) fn explain_plan(
+    policy: &dyn LoadBalancingPolicy,
+    routing_info: &RoutingInfo,
+    cluster: &ClusterData,
+    node_target: Option<&NodeTarget>,
+) -> PlanExplanation {
+    let replicas = match (routing_info.keyspace, &routing_info.token) {
+        (Some(keyspace), Some(token)) => cluster.get_token_endpoints(keyspace, token.clone()),
+        _ => Vec::new(),
+    };
+
+    let plan = match node_target {
+        Some(target) => vec![explain_node(
+            policy,
+            routing_info,
+            &target.node,
+            target.shard,
+            &replicas,
+        )],
+        None => Plan::new(policy, routing_info, cluster)
+            .map(|node| {
+                let shard = routing_info
+                    .token
+                    .as_ref()
+                    .and_then(|token| Some(node.sharder()?.shard_of(token.clone())));
+                explain_node(policy, routing_info, node, shard, &replicas)
+            })
+            .collect(),
+    };
+
+    PlanExplanation {
+        policy_name: policy.name(),
+        bypasses_load_balancing: node_target.is_some(),
+        keyspace: routing_info.keyspace.map(ToOwned::to_owned),
+        token: routing_info.token.clone(),
+        is_confirmed_lwt: routing_info.is_confirmed_lwt,
+        replicas,
+        plan,
+    }
+}
+
+fn explain_node(
+    policy: &dyn LoadBalancingPolicy,
+    routing_info: &RoutingInfo,
+    node: &Arc<Node>,
+    shard: Option<Shard>,
+    replicas: &[Arc<Node>],
+) -> PlannedNode {
+    PlannedNode {
+        node: node.clone(),
+        shard,
+        is_replica: replicas.iter().any(|replica| Arc::ptr_eq(replica, node)),
+        is_down: node.is_down(),
+        is_enabled: node.is_enabled(),
+        policy: policy.explain_node(routing_info, node),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::explain_plan;
+    use crate::load_balancing::default::tests::framework::mock_cluster_data_for_token_aware_tests;
+    use crate::load_balancing::{DefaultPolicy, RoutingInfo};
+    use crate::routing::Token;
+    use crate::statement::NodeTarget;
+    use crate::transport::locator::test::{A, C, G, KEYSPACE_NTS_RF_2};
+    use scylla_cql::Consistency;
+
+    #[tokio::test]
+    async fn test_explain_plan() {
+        let cluster = mock_cluster_data_for_token_aware_tests().await;
+        let policy = DefaultPolicy::builder()
+            .prefer_datacenter("eu".to_owned())
+            .prefer_rack("r1".to_owned())
+            .build();
+        let routing_info = RoutingInfo {
+            token: Some(Token::new(160)),
+            keyspace: Some(KEYSPACE_NTS_RF_2),
+            consistency: Consistency::LocalOne,
+            ..Default::default()
+        };
+
+        let explanation = explain_plan(policy.as_ref(), &routing_info, &cluster, None);
+        assert_eq!(explanation.policy_name, "DefaultPolicy");
+        assert!(!explanation.bypasses_load_balancing);
+        assert_eq!(explanation.keyspace.as_deref(), Some(KEYSPACE_NTS_RF_2));
+        assert_eq!(explanation.token, Some(Token::new(160)));
+        assert_eq!(explanation.replicas.len(), 4);
+
+        // Local consistency forbids datacenter failover, so the plan consists of nodes
+        // from "eu" only. The local rack replica is the first, then the other local replica.
+        // going through the ring, we get order: F , A , C , D , G , B , E
+        //                                      us  eu  eu  us  eu  eu  us
+        //                                      r2  r1  r1  r1  r2  r1  r1
+        let ports: Vec<u16> = explanation
+            .plan
+            .iter()
+            .map(|planned| planned.node.address.port())
+            .collect();
+        assert_eq!(&ports[..2], &[A, G]);
+        assert_eq!(ports.len(), 4);
+
+        for planned in &explanation.plan {
+            assert_eq!(planned.policy.is_local_datacenter, Some(true));
+            assert_eq!(
+                planned.policy.is_local_rack,
+                Some(planned.node.rack.as_deref() == Some("r1"))
+            );
+            assert!(!planned.policy.is_penalised);
+            assert_eq!(
+                planned.is_replica,
+                [A, G].contains(&planned.node.address.port())
+            );
+            assert!(planned.is_enabled);
+            // Mock nodes are not sharded
+            assert_eq!(planned.shard, None);
+        }
+
+        // A statement sent to a chosen node bypasses the policy.
+        let node_c = cluster
+            .get_nodes_info()
+            .iter()
+            .find(|node| node.address.port() == C)
+            .unwrap()
+            .clone();
+        let target = NodeTarget::shard(node_c, 3);
+        let explanation = explain_plan(policy.as_ref(), &routing_info, &cluster, Some(&target));
+        assert!(explanation.bypasses_load_balancing);
+        assert_eq!(explanation.plan.len(), 1);
+        assert_eq!(explanation.plan[0].node.address.port(), C);
+        assert_eq!(explanation.plan[0].shard, Some(3));
+        assert!(!explanation.plan[0].is_replica);
+    }
+}
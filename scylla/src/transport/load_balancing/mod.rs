@@ -9,8 +9,14 @@ use scylla_cql::{errors::QueryError, frame::types};
 use std::time::Duration;
 
 mod default;
+mod explain;
 mod plan;
-pub use default::{DefaultPolicy, DefaultPolicyBuilder, LatencyAwarenessBuilder};
+pub use default::{
+    CircuitBreakerBuilder, DcFailoverBuilder, DcFailoverEvent, DcFailoverListener,
+    DcFailoverReason, DefaultPolicy, DefaultPolicyBuilder, LatencyAwarenessBuilder,
+};
+pub(crate) use explain::explain_plan;
+pub use explain::{NodeExplanation, PlanExplanation, PlannedNode};
 pub use plan::Plan;
 
 /// Represents info about statement that can be used by load balancing policies.
@@ -81,6 +87,28 @@ pub trait LoadBalancingPolicy: Send + Sync + std::fmt::Debug {
     ) {
     }
 
+    /// Returns the consistency to send a request to the given node with, based on
+    /// the consistency requested for it, e.g. to relax `LOCAL_*` consistency levels
+    /// for a node which is remote from the policy's point of view.
+    ///
+    /// The default implementation returns the requested consistency.
+    fn consistency_for_node(
+        &self,
+        _query: &RoutingInfo,
+        _node: NodeRef<'_>,
+        consistency: types::Consistency,
+    ) -> types::Consistency {
+        consistency
+    }
+
     /// Returns the name of load balancing policy.
     fn name(&self) -> String;
+
+    /// Describes why the policy placed the given node in the plan for a given query.
+    /// Used by [`Session::explain_plan`](crate::Session::explain_plan).
+    ///
+    /// The default implementation returns no information.
+    fn explain_node(&self, _query: &RoutingInfo, _node: NodeRef<'_>) -> NodeExplanation {
+        NodeExplanation::default()
+    }
 }
@@ -1,11 +1,13 @@
 mod precomputed_replicas;
 mod replicas;
 mod replication_info;
+mod replication_strategy;
 #[cfg(test)]
 pub(crate) mod test;
 mod token_ring;
 
 use rand::{seq::IteratorRandom, Rng};
+pub use replication_strategy::ReplicationStrategy;
 pub use token_ring::TokenRing;
 
 use super::{topology::Strategy, Node, NodeRef};
@@ -32,6 +34,9 @@ pub struct ReplicaLocator {
     precomputed_replicas: PrecomputedReplicas,
 
     datacenters: Vec<String>,
+
+    /// user-provided implementations of replication strategies, by strategy class name.
+    custom_strategies: HashMap<String, Arc<dyn ReplicationStrategy>>,
 }
 
 impl ReplicaLocator {
@@ -41,6 +46,16 @@ impl ReplicaLocator {
     pub fn new<'a>(
         ring_iter: impl Iterator<Item = (Token, Arc<Node>)>,
         precompute_replica_sets_for: impl Iterator<Item = &'a Strategy>,
+    ) -> Self {
+        Self::with_custom_strategies(ring_iter, precompute_replica_sets_for, HashMap::new())
+    }
+
+    /// Like [`ReplicaLocator::new`], but keyspaces using a strategy class found in
+    /// `custom_strategies` get their replica sets from the corresponding [`ReplicationStrategy`].
+    pub fn with_custom_strategies<'a>(
+        ring_iter: impl Iterator<Item = (Token, Arc<Node>)>,
+        precompute_replica_sets_for: impl Iterator<Item = &'a Strategy>,
+        custom_strategies: HashMap<String, Arc<dyn ReplicationStrategy>>,
     ) -> Self {
         let replication_data = ReplicationInfo::new(ring_iter);
         let precomputed_replicas =
@@ -57,6 +72,7 @@ impl ReplicaLocator {
             replication_data,
             precomputed_replicas,
             datacenters,
+            custom_strategies,
         }
     }
 
@@ -65,9 +81,13 @@ impl ReplicaLocator {
     /// from that datacenter. If a specified datacenter name does not correspond to a valid
     /// datacenter, an empty set will be returned.
     ///
-    /// Supported replication strategies: `SimpleStrategy`, 'NetworkTopologyStrategy',
-    /// 'LocalStrategy'. If other is specified, it is treated as the `SimpleStrategy` with
-    /// replication factor equal to 1.
+    /// Supported replication strategies: `SimpleStrategy`, `NetworkTopologyStrategy`,
+    /// `EverywhereStrategy` and `LocalStrategy`. Every node is a replica of `EverywhereStrategy`
+    /// keyspaces and, as each node keeps its own copy of the data, of `LocalStrategy` keyspaces.
+    /// Other strategies are looked up among the custom strategies passed to
+    /// `Self::with_custom_strategies`, by the full class name and then by the name after
+    /// the last dot. If no implementation is found, the strategy is treated as the
+    /// `SimpleStrategy` with replication factor equal to 1.
     ///
     /// If a provided replication strategy did not appear in `precompute_replica_sets_for`
     /// parameter of `Self::new`, invocation of this function will trigger a computation of the
@@ -82,7 +102,7 @@ impl ReplicaLocator {
         match strategy {
             Strategy::SimpleStrategy { replication_factor } => {
                 if let Some(datacenter) = datacenter {
-                    let replicas = self.get_simple_strategy_replicas(token, *replication_factor);
+                    let replicas = self.get_simple_strategy_replicas(&token, *replication_factor);
 
                     return ReplicaSetInner::FilteredSimple {
                         replicas,
@@ -91,7 +111,7 @@ impl ReplicaLocator {
                     .into();
                 } else {
                     return ReplicaSetInner::Plain(
-                        self.get_simple_strategy_replicas(token, *replication_factor),
+                        self.get_simple_strategy_replicas(&token, *replication_factor),
                     )
                     .into();
                 }
@@ -102,7 +122,7 @@ impl ReplicaLocator {
                 if let Some(dc) = datacenter {
                     if let Some(repfactor) = datacenter_repfactors.get(dc) {
                         return ReplicaSetInner::Plain(
-                            self.get_network_strategy_replicas(token, dc, *repfactor),
+                            self.get_network_strategy_replicas(&token, dc, *repfactor),
                         )
                         .into();
                     } else {
@@ -118,10 +138,35 @@ impl ReplicaLocator {
                     .into();
                 }
             }
-            Strategy::Other { name, .. } => {
+            Strategy::EverywhereStrategy | Strategy::LocalStrategy => {
+                if let Some(datacenter) = datacenter {
+                    return ReplicaSetInner::Plain(
+                        self.unique_nodes_in_datacenter_ring(datacenter)
+                            .map(ReplicasArray::from)
+                            .unwrap_or(EMPTY_REPLICAS),
+                    )
+                    .into();
+                } else {
+                    return ReplicaSetInner::Plain(self.unique_nodes_in_global_ring().into())
+                        .into();
+                }
+            }
+            Strategy::Other { name, data } => {
+                if let Some(custom_strategy) = self.custom_strategy(name) {
+                    let replicas =
+                        ReplicasArray::Owned(custom_strategy.replicas(token, self, data));
+                    if let Some(datacenter) = datacenter {
+                        return ReplicaSetInner::FilteredSimple {
+                            replicas,
+                            datacenter,
+                        }
+                        .into();
+                    } else {
+                        return ReplicaSetInner::Plain(replicas).into();
+                    }
+                }
                 debug!("Unknown strategy ({}), falling back to SimpleStrategy with replication_factor = 1", name)
             }
-            _ => (),
         }
 
         // Fallback to simple strategy with replication factor = 1.
@@ -159,9 +204,19 @@ impl ReplicaLocator {
             .unique_nodes_in_datacenter_ring(datacenter_name)
     }
 
+    fn custom_strategy(&self, class_name: &str) -> Option<&dyn ReplicationStrategy> {
+        self.custom_strategies
+            .get(class_name)
+            .or_else(|| {
+                let short_name = class_name.rsplit('.').next()?;
+                self.custom_strategies.get(short_name)
+            })
+            .map(|strategy| strategy.as_ref())
+    }
+
     fn get_simple_strategy_replicas(
         &self,
-        token: Token,
+        token: &Token,
         replication_factor: usize,
     ) -> ReplicasArray<'_> {
         if replication_factor == 0 {
@@ -183,7 +238,7 @@ impl ReplicaLocator {
 
     fn get_network_strategy_replicas<'a>(
         &'a self,
-        token: Token,
+        token: &Token,
         datacenter: &str,
         datacenter_replication_factor: usize,
     ) -> ReplicasArray<'a> {
@@ -339,7 +394,7 @@ impl<'a> ReplicaSet<'a> {
 
                         if nodes_to_skip < repfactor {
                             return locator
-                                .get_network_strategy_replicas(*token, datacenter, repfactor)
+                                .get_network_strategy_replicas(token, datacenter, repfactor)
                                 .get(nodes_to_skip);
                         }
 
@@ -384,7 +439,7 @@ impl<'a> IntoIterator for ReplicaSet<'a> {
                     let repfactor = *datacenter_repfactors.get(datacenter.as_str()).unwrap_or(&0);
                     ReplicaSetIteratorInner::ChainedNTS {
                         replicas: locator
-                            .get_network_strategy_replicas(token, datacenter, repfactor),
+                            .get_network_strategy_replicas(&token, datacenter, repfactor),
                         replicas_idx: 0,
 
                         locator,
@@ -492,8 +547,7 @@ impl<'a> Iterator for ReplicaSetIterator<'a> {
 
                     let datacenter = &locator.datacenters[*datacenter_idx];
                     let repfactor = *datacenter_repfactors.get(datacenter).unwrap_or(&0);
-                    *replicas =
-                        locator.get_network_strategy_replicas(*token, datacenter, repfactor);
+                    *replicas = locator.get_network_strategy_replicas(token, datacenter, repfactor);
 
                     self.next()
                 } else {
@@ -617,10 +671,13 @@ impl<'a> Iterator for ReplicasOrderedNTSIterator<'a> {
             Self::FreshForPick {
                 datacenter_repfactors,
                 locator,
-                token,
+                ref token,
             } => {
                 // We're going to find the primary replica for the given token.
-                let nodes_on_ring = locator.replication_data.get_global_ring().ring_range(token);
+                let nodes_on_ring = locator
+                    .replication_data
+                    .get_global_ring()
+                    .ring_range(token.clone());
                 for node in nodes_on_ring {
                     // If this node's DC has some replicas in this NTS...
                     if let Some(dc) = &node.datacenter {
@@ -629,7 +686,7 @@ impl<'a> Iterator for ReplicasOrderedNTSIterator<'a> {
                             *self = Self::Picked {
                                 datacenter_repfactors,
                                 locator,
-                                token,
+                                token: token.clone(),
                                 picked: node,
                             };
                             return Some(node);
@@ -641,7 +698,7 @@ impl<'a> Iterator for ReplicasOrderedNTSIterator<'a> {
             Self::Picked {
                 datacenter_repfactors,
                 locator,
-                token,
+                ref token,
                 picked,
             } => {
                 // Clippy can't check that in Eq and Hash impls we don't actually use any field with interior mutability
@@ -660,7 +717,10 @@ impl<'a> Iterator for ReplicasOrderedNTSIterator<'a> {
                 all_replicas.remove(picked);
 
                 let mut replicas_ordered = vec![];
-                let nodes_on_ring = locator.replication_data.get_global_ring().ring_range(token);
+                let nodes_on_ring = locator
+                    .replication_data
+                    .get_global_ring()
+                    .ring_range(token.clone());
                 for node in nodes_on_ring {
                     if all_replicas.is_empty() {
                         // All replicas were put in order.
@@ -752,8 +812,7 @@ mod tests {
         // For each case (token, limit_to_dc, strategy), we are checking
         // that ReplicasOrdered yields replicas in the expected order.
         let check = |token, limit_to_dc, strategy, expected| {
-            let replica_set =
-                locator.replicas_for_token(Token { value: token }, strategy, limit_to_dc);
+            let replica_set = locator.replicas_for_token(Token::new(token), strategy, limit_to_dc);
             let replicas_ordered = replica_set.into_replicas_ordered();
             let ids: Vec<_> = replicas_ordered
                 .into_iter()
@@ -103,17 +103,18 @@ impl PrecomputedReplicas {
                         repfactors.insert(*dc_repfactor);
                     }
                 }
-                Strategy::LocalStrategy => {} // RF=1
-                Strategy::Other { .. } => {}  // Can't precompute for custom strategies
+                Strategy::EverywhereStrategy => {} // All nodes, no need to precompute
+                Strategy::LocalStrategy => {}      // All nodes, no need to precompute
+                Strategy::Other { .. } => {}       // Can't precompute for custom strategies
             }
         }
 
         let global_replicas_iter = replication_data.get_global_ring().iter().map(|(token, _)| {
             let cur_replicas: Replicas = replication_data
-                .simple_strategy_replicas(*token, max_global_repfactor)
+                .simple_strategy_replicas(token, max_global_repfactor)
                 .cloned()
                 .collect();
-            (*token, cur_replicas)
+            (token.clone(), cur_replicas)
         });
         let global_replicas = PrecomputedReplicasRing {
             replicas_for_token: TokenRing::new(global_replicas_iter),
@@ -135,10 +136,10 @@ impl PrecomputedReplicas {
             let produce_replica_ring_iter = |rf| {
                 let ring_iter = dc_rep_data.get_dc_ring().iter().map(|(token, _)| {
                     let cur_replicas: Replicas = replication_data
-                        .nts_replicas_in_datacenter(*token, dc_name, rf)
+                        .nts_replicas_in_datacenter(token, dc_name, rf)
                         .cloned()
                         .collect();
-                    (*token, cur_replicas)
+                    (token.clone(), cur_replicas)
                 });
 
                 TokenRing::new(ring_iter)
@@ -173,7 +174,7 @@ impl PrecomputedReplicas {
     /// precomputation stage), `None` is returned.
     pub(crate) fn get_precomputed_simple_strategy_replicas(
         &self,
-        token: Token,
+        token: &Token,
         replication_factor: usize,
     ) -> Option<&[Arc<Node>]> {
         if replication_factor > self.global_replicas.max_rep_factor {
@@ -183,7 +184,7 @@ impl PrecomputedReplicas {
         let precomputed_token_replicas = self
             .global_replicas
             .replicas_for_token
-            .get_elem_for_token(token)?;
+            .get_elem_for_token(token.clone())?;
         let result_len: usize = cmp::min(precomputed_token_replicas.len(), replication_factor);
         Some(&precomputed_token_replicas[..result_len])
     }
@@ -194,7 +195,7 @@ impl PrecomputedReplicas {
     /// If a provided datacenter does not exist, `None` is returned.
     pub(crate) fn get_precomputed_network_strategy_replicas(
         &self,
-        token: Token,
+        token: &Token,
         dc_name: &str,
         dc_replication_factor: usize,
     ) -> Option<&[Arc<Node>]> {
@@ -203,7 +204,7 @@ impl PrecomputedReplicas {
             .get(dc_name)?
             .get_replica_ring_for_rf(dc_replication_factor)?;
 
-        let precomputed_replicas = precomputed_replicas_ring.get_elem_for_token(token)?;
+        let precomputed_replicas = precomputed_replicas_ring.get_elem_for_token(token.clone())?;
         let result_len: usize = cmp::min(precomputed_replicas.len(), dc_replication_factor);
 
         Some(&precomputed_replicas[..result_len])
@@ -253,10 +254,8 @@ mod tests {
         );
 
         let check = |token, replication_factor, expected_node_ids| {
-            let replicas = precomputed_replicas.get_precomputed_simple_strategy_replicas(
-                Token { value: token },
-                replication_factor,
-            );
+            let replicas = precomputed_replicas
+                .get_precomputed_simple_strategy_replicas(&Token::new(token), replication_factor);
 
             let ids: Vec<u16> = replicas
                 .unwrap()
@@ -271,7 +270,7 @@ mod tests {
         check(160, 1, vec![F]);
         check(160, 2, vec![F, A]);
         assert_eq!(
-            precomputed_replicas.get_precomputed_simple_strategy_replicas(Token { value: 160 }, 3),
+            precomputed_replicas.get_precomputed_simple_strategy_replicas(&Token::new(160), 3),
             None
         );
 
@@ -297,7 +296,7 @@ mod tests {
 
         let check = |token, dc, replication_factor, expected_node_ids| {
             let replicas = precomputed_replicas.get_precomputed_network_strategy_replicas(
-                Token { value: token },
+                &Token::new(token),
                 dc,
                 replication_factor,
             );
@@ -317,7 +316,7 @@ mod tests {
         check(160, "eu", 3, vec![A, C, G]);
         assert_eq!(
             precomputed_replicas.get_precomputed_network_strategy_replicas(
-                Token { value: 160 },
+                &Token::new(160),
                 "eu",
                 4
             ),
@@ -330,7 +329,7 @@ mod tests {
         check(160, "us", 3, vec![F, D, E]);
         assert_eq!(
             precomputed_replicas.get_precomputed_network_strategy_replicas(
-                Token { value: 160 },
+                &Token::new(160),
                 "us",
                 4
             ),
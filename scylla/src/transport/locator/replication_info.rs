@@ -74,7 +74,7 @@ impl ReplicationInfo {
                 datacenter_nodes
                     .entry(datacenter_name)
                     .or_default()
-                    .push((*token, node.clone()));
+                    .push((token.clone(), node.clone()));
             }
         }
 
@@ -110,13 +110,13 @@ impl ReplicationInfo {
     /// The iterator computes consecutive replicas lazily as needed.
     pub(crate) fn simple_strategy_replicas(
         &self,
-        token: Token,
+        token: &Token,
         replication_factor: usize,
     ) -> impl Iterator<Item = &Arc<Node>> {
         let num_to_take = cmp::min(replication_factor, self.unique_nodes_in_global_ring.len());
 
         self.global_ring
-            .ring_range(token)
+            .ring_range(token.clone())
             .unique()
             .take(num_to_take)
     }
@@ -125,7 +125,7 @@ impl ReplicationInfo {
     /// The iterator computes consecutive replicas lazily as needed.
     pub(crate) fn nts_replicas_in_datacenter<'a>(
         &'a self,
-        token: Token,
+        token: &Token,
         datacenter_name: &str,
         replication_factor: usize,
     ) -> impl Iterator<Item = &'a Arc<Node>> {
@@ -135,7 +135,7 @@ impl ReplicationInfo {
             .unwrap_or(&EMPTY_DATACENTER_NODES);
 
         let num_to_take = cmp::min(replication_factor, dc_lb_data.unique_nodes_in_dc_ring.len());
-        let unique_dc_nodes = dc_lb_data.dc_ring.ring_range(token).unique();
+        let unique_dc_nodes = dc_lb_data.dc_ring.ring_range(token.clone()).unique();
 
         NtsReplicasInDatacenterIterator {
             replicas_left_to_find: num_to_take,
@@ -218,8 +218,8 @@ mod tests {
         let replication_info = ReplicationInfo::new(ring);
 
         let check = |token, replication_factor, expected_node_ids| {
-            let replicas = replication_info
-                .simple_strategy_replicas(Token { value: token }, replication_factor);
+            let replicas =
+                replication_info.simple_strategy_replicas(&Token::new(token), replication_factor);
             let ids: Vec<u16> = replicas.map(|node| node.address.port()).collect();
 
             assert_eq!(ids, expected_node_ids);
@@ -252,8 +252,7 @@ mod tests {
         let replication_info = ReplicationInfo::new(ring);
 
         let check = |token, dc, rf, expected| {
-            let replicas =
-                replication_info.nts_replicas_in_datacenter(Token { value: token }, dc, rf);
+            let replicas = replication_info.nts_replicas_in_datacenter(&Token::new(token), dc, rf);
             let ids: Vec<u16> = replicas.map(|node| node.address.port()).collect();
 
             assert_eq!(ids, expected);
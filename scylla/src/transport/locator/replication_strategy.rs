@@ -0,0 +1,62 @@
+use super::ReplicaLocator;
+use crate::routing::Token;
+use crate::transport::NodeRef;
+use std::collections::HashMap;
+use std::fmt::Debug;
+
+/// Computes replica sets for keyspaces using a replication strategy class the driver doesn't know.
+///
+/// Implementations are registered for a strategy class name with
+/// [`SessionBuilder::replication_strategy`](crate::transport::session_builder::GenericSessionBuilder::replication_strategy).
+/// When a keyspace uses the registered class, the replica locator asks the implementation for
+/// the replicas of a token, which makes token-aware routing work for that keyspace.
+/// Replica sets of custom strategies are not precomputed, they are computed on every call.
+///
+/// # Example
+/// ```rust
+/// # use scylla::routing::Token;
+/// # use scylla::transport::locator::{ReplicaLocator, ReplicationStrategy};
+/// # use scylla::transport::NodeRef;
+/// # use std::collections::HashMap;
+/// /// Keeps `replication_factor` replicas, like `SimpleStrategy`, but only in datacenter `dc`.
+/// #[derive(Debug)]
+/// struct SingleDcStrategy;
+///
+/// impl ReplicationStrategy for SingleDcStrategy {
+///     fn replicas<'a>(
+///         &self,
+///         token: Token,
+///         locator: &'a ReplicaLocator,
+///         options: &HashMap<String, String>,
+///     ) -> Vec<NodeRef<'a>> {
+///         let dc = options.get("dc");
+///         let rf: usize = options
+///             .get("replication_factor")
+///             .and_then(|rf| rf.parse().ok())
+///             .unwrap_or(1);
+///
+///         let mut replicas: Vec<NodeRef<'a>> = Vec::new();
+///         for node in locator.ring().ring_range(token) {
+///             if replicas.len() == rf {
+///                 break;
+///             }
+///             if node.datacenter.as_ref() == dc && !replicas.contains(&node) {
+///                 replicas.push(node);
+///             }
+///         }
+///         replicas
+///     }
+/// }
+/// ```
+pub trait ReplicationStrategy: Send + Sync + Debug {
+    /// Returns the replicas owning `token`, the primary replica first.
+    ///
+    /// `options` are the replication options of the keyspace, without the `class` entry.
+    /// The locator gives access to the token ring and the datacenters of the cluster.
+    fn replicas<'a>(
+        &self,
+        token: Token,
+        locator: &'a ReplicaLocator,
+        options: &HashMap<String, String>,
+    ) -> Vec<NodeRef<'a>>;
+}
@@ -2,7 +2,7 @@ use rand::SeedableRng;
 use rand_chacha::ChaCha8Rng;
 use uuid::Uuid;
 
-use super::{ReplicaLocator, ReplicaSet};
+use super::{ReplicaLocator, ReplicaSet, ReplicationStrategy};
 use crate::routing::Token;
 use crate::transport::{
     connection_pool::PoolConfig,
@@ -51,68 +51,70 @@ pub(crate) fn mock_metadata_for_token_aware_tests() -> Metadata {
             datacenter: Some("eu".into()),
             rack: Some("r1".to_owned()),
             address: id_to_invalid_addr(1),
-            tokens: vec![
-                Token { value: 50 },
-                Token { value: 250 },
-                Token { value: 400 },
-            ],
+            tokens: vec![Token::new(50), Token::new(250), Token::new(400)],
             host_id: Uuid::new_v4(),
+            release_version: None,
+            schema_version: None,
         },
         Peer {
             // B
             datacenter: Some("eu".into()),
             rack: Some("r1".to_owned()),
             address: id_to_invalid_addr(2),
-            tokens: vec![
-                Token { value: 100 },
-                Token { value: 600 },
-                Token { value: 900 },
-            ],
+            tokens: vec![Token::new(100), Token::new(600), Token::new(900)],
             host_id: Uuid::new_v4(),
+            release_version: None,
+            schema_version: None,
         },
         Peer {
             // C
             datacenter: Some("eu".into()),
             rack: Some("r1".to_owned()),
             address: id_to_invalid_addr(3),
-            tokens: vec![
-                Token { value: 300 },
-                Token { value: 650 },
-                Token { value: 700 },
-            ],
+            tokens: vec![Token::new(300), Token::new(650), Token::new(700)],
             host_id: Uuid::new_v4(),
+            release_version: None,
+            schema_version: None,
         },
         Peer {
             // D
             datacenter: Some("us".into()),
             rack: Some("r1".to_owned()),
             address: id_to_invalid_addr(4),
-            tokens: vec![Token { value: 350 }, Token { value: 550 }],
+            tokens: vec![Token::new(350), Token::new(550)],
             host_id: Uuid::new_v4(),
+            release_version: None,
+            schema_version: None,
         },
         Peer {
             // E
             datacenter: Some("us".into()),
             rack: Some("r1".to_owned()),
             address: id_to_invalid_addr(5),
-            tokens: vec![Token { value: 150 }, Token { value: 750 }],
+            tokens: vec![Token::new(150), Token::new(750)],
             host_id: Uuid::new_v4(),
+            release_version: None,
+            schema_version: None,
         },
         Peer {
             // F
             datacenter: Some("us".into()),
             rack: Some("r2".to_owned()),
             address: id_to_invalid_addr(6),
-            tokens: vec![Token { value: 200 }, Token { value: 450 }],
+            tokens: vec![Token::new(200), Token::new(450)],
             host_id: Uuid::new_v4(),
+            release_version: None,
+            schema_version: None,
         },
         Peer {
             // G
             datacenter: Some("eu".into()),
             rack: Some("r2".to_owned()),
             address: id_to_invalid_addr(7),
-            tokens: vec![Token { value: 500 }, Token { value: 800 }],
+            tokens: vec![Token::new(500), Token::new(800)],
             host_id: Uuid::new_v4(),
+            release_version: None,
+            schema_version: None,
         },
     ];
 
@@ -162,6 +164,7 @@ pub(crate) fn mock_metadata_for_token_aware_tests() -> Metadata {
     Metadata {
         peers: Vec::from(peers),
         keyspaces,
+        partitioner: None,
     }
 }
 
@@ -191,7 +194,7 @@ pub(crate) fn create_ring(metadata: &Metadata) -> impl Iterator<Item = (Token, A
         ));
 
         for token in &peer.tokens {
-            ring.push((*token, node.clone()));
+            ring.push((token.clone(), node.clone()));
         }
     }
 
@@ -212,6 +215,7 @@ async fn test_locator() {
     test_datacenter_info(&locator);
     test_simple_strategy_replicas(&locator);
     test_network_topology_strategy_replicas(&locator);
+    test_everywhere_and_local_strategy_replicas(&locator);
     test_replica_set_len(&locator);
     test_replica_set_choose(&locator);
     test_replica_set_choose_filtered(&locator);
@@ -250,7 +254,7 @@ fn test_datacenter_info(locator: &ReplicaLocator) {
 fn test_simple_strategy_replicas(locator: &ReplicaLocator) {
     assert_replica_set_equal_to(
         locator.replicas_for_token(
-            Token { value: 450 },
+            Token::new(450),
             &Strategy::SimpleStrategy {
                 replication_factor: 3,
             },
@@ -261,7 +265,7 @@ fn test_simple_strategy_replicas(locator: &ReplicaLocator) {
 
     assert_replica_set_equal_to(
         locator.replicas_for_token(
-            Token { value: 450 },
+            Token::new(450),
             &Strategy::SimpleStrategy {
                 replication_factor: 4,
             },
@@ -272,7 +276,7 @@ fn test_simple_strategy_replicas(locator: &ReplicaLocator) {
 
     assert_replica_set_equal_to(
         locator.replicas_for_token(
-            Token { value: 201 },
+            Token::new(201),
             &Strategy::SimpleStrategy {
                 replication_factor: 4,
             },
@@ -283,7 +287,7 @@ fn test_simple_strategy_replicas(locator: &ReplicaLocator) {
 
     assert_replica_set_equal_to(
         locator.replicas_for_token(
-            Token { value: 201 },
+            Token::new(201),
             &Strategy::SimpleStrategy {
                 replication_factor: 0,
             },
@@ -296,7 +300,7 @@ fn test_simple_strategy_replicas(locator: &ReplicaLocator) {
     // in that dc.
     assert_replica_set_equal_to(
         locator.replicas_for_token(
-            Token { value: 50 },
+            Token::new(50),
             &Strategy::SimpleStrategy {
                 replication_factor: 1,
             },
@@ -307,7 +311,7 @@ fn test_simple_strategy_replicas(locator: &ReplicaLocator) {
 
     assert_replica_set_equal_to(
         locator.replicas_for_token(
-            Token { value: 50 },
+            Token::new(50),
             &Strategy::SimpleStrategy {
                 replication_factor: 3,
             },
@@ -318,7 +322,7 @@ fn test_simple_strategy_replicas(locator: &ReplicaLocator) {
 
     assert_replica_set_equal_to(
         locator.replicas_for_token(
-            Token { value: 50 },
+            Token::new(50),
             &Strategy::SimpleStrategy {
                 replication_factor: 3,
             },
@@ -331,7 +335,7 @@ fn test_simple_strategy_replicas(locator: &ReplicaLocator) {
 fn test_network_topology_strategy_replicas(locator: &ReplicaLocator) {
     assert_replica_set_equal_to(
         locator.replicas_for_token(
-            Token { value: 75 },
+            Token::new(75),
             &Strategy::NetworkTopologyStrategy {
                 datacenter_repfactors: [("eu".to_owned(), 1), ("us".to_owned(), 1)]
                     .into_iter()
@@ -344,7 +348,7 @@ fn test_network_topology_strategy_replicas(locator: &ReplicaLocator) {
 
     assert_replica_set_equal_to(
         locator.replicas_for_token(
-            Token { value: 75 },
+            Token::new(75),
             &Strategy::NetworkTopologyStrategy {
                 datacenter_repfactors: [("eu".to_owned(), 1), ("us".to_owned(), 1)]
                     .into_iter()
@@ -357,7 +361,7 @@ fn test_network_topology_strategy_replicas(locator: &ReplicaLocator) {
 
     assert_replica_set_equal_to(
         locator.replicas_for_token(
-            Token { value: 75 },
+            Token::new(75),
             &Strategy::NetworkTopologyStrategy {
                 datacenter_repfactors: [("eu".to_owned(), 1), ("us".to_owned(), 1)]
                     .into_iter()
@@ -370,7 +374,7 @@ fn test_network_topology_strategy_replicas(locator: &ReplicaLocator) {
 
     assert_replica_set_equal_to(
         locator.replicas_for_token(
-            Token { value: 75 },
+            Token::new(75),
             &Strategy::NetworkTopologyStrategy {
                 datacenter_repfactors: [("eu".to_owned(), 2), ("us".to_owned(), 1)]
                     .into_iter()
@@ -386,7 +390,7 @@ fn test_network_topology_strategy_replicas(locator: &ReplicaLocator) {
 
     assert_replica_set_equal_to(
         locator.replicas_for_token(
-            Token { value: 75 },
+            Token::new(75),
             &Strategy::NetworkTopologyStrategy {
                 datacenter_repfactors: [("unknown".to_owned(), 2), ("us".to_owned(), 1)]
                     .into_iter()
@@ -399,7 +403,7 @@ fn test_network_topology_strategy_replicas(locator: &ReplicaLocator) {
 
     assert_replica_set_equal_to(
         locator.replicas_for_token(
-            Token { value: 800 },
+            Token::new(800),
             &Strategy::NetworkTopologyStrategy {
                 datacenter_repfactors: [("eu".to_owned(), 1), ("us".to_owned(), 1)]
                     .into_iter()
@@ -411,10 +415,100 @@ fn test_network_topology_strategy_replicas(locator: &ReplicaLocator) {
     );
 }
 
+fn test_everywhere_and_local_strategy_replicas(locator: &ReplicaLocator) {
+    for strategy in [Strategy::EverywhereStrategy, Strategy::LocalStrategy] {
+        assert_replica_set_equal_to(
+            locator.replicas_for_token(Token::new(450), &strategy, None),
+            &[A, B, C, D, E, F, G],
+        );
+
+        assert_replica_set_equal_to(
+            locator.replicas_for_token(Token::new(450), &strategy, Some("us")),
+            &[D, E, F],
+        );
+
+        assert_replica_set_equal_to(
+            locator.replicas_for_token(Token::new(450), &strategy, Some("unknown_dc_name")),
+            &[],
+        );
+    }
+}
+
+// Behaves like SimpleStrategy, with the replication factor taken from the options.
+#[derive(Debug)]
+struct TestSimpleStrategy;
+
+impl ReplicationStrategy for TestSimpleStrategy {
+    fn replicas<'a>(
+        &self,
+        token: Token,
+        locator: &'a ReplicaLocator,
+        options: &HashMap<String, String>,
+    ) -> Vec<NodeRef<'a>> {
+        let rf: usize = options["replication_factor"].parse().unwrap();
+        let mut replicas: Vec<NodeRef<'a>> = Vec::new();
+        for node in locator.ring().ring_range(token) {
+            if replicas.len() == rf {
+                break;
+            }
+            if !replicas.contains(&node) {
+                replicas.push(node);
+            }
+        }
+        replicas
+    }
+}
+
+#[tokio::test]
+async fn test_custom_replication_strategy() {
+    let metadata = mock_metadata_for_token_aware_tests();
+    let custom_strategies: HashMap<String, Arc<dyn ReplicationStrategy>> = [(
+        "TestSimpleStrategy".to_owned(),
+        Arc::new(TestSimpleStrategy) as Arc<dyn ReplicationStrategy>,
+    )]
+    .into_iter()
+    .collect();
+    let locator = ReplicaLocator::with_custom_strategies(
+        create_ring(&metadata),
+        metadata.keyspaces.values().map(|ks| &ks.strategy),
+        custom_strategies,
+    );
+
+    let strategy_with_name = |name: &str| Strategy::Other {
+        name: name.to_owned(),
+        data: [("replication_factor".to_owned(), "3".to_owned())]
+            .into_iter()
+            .collect(),
+    };
+
+    // The strategy is found both by the full class name and by the short one.
+    for name in ["TestSimpleStrategy", "com.example.TestSimpleStrategy"] {
+        let strategy = strategy_with_name(name);
+        assert_replica_set_equal_to(
+            locator.replicas_for_token(Token::new(450), &strategy, None),
+            &[F, G, D],
+        );
+        assert_replica_set_equal_to(
+            locator.replicas_for_token(Token::new(450), &strategy, Some("us")),
+            &[F, D],
+        );
+    }
+
+    // Unknown strategies fall back to SimpleStrategy with replication factor 1.
+    assert_replica_set_equal_to(
+        locator.replicas_for_token(
+            Token::new(450),
+            &strategy_with_name("com.example.UnknownStrategy"),
+            None,
+        ),
+        &[F],
+    );
+}
+
 fn test_replica_set_len(locator: &ReplicaLocator) {
     let merged_nts_len = locator
         .replicas_for_token(
-            Token { value: 75 },
+            Token::new(75),
             &Strategy::NetworkTopologyStrategy {
                 datacenter_repfactors: [("eu".to_owned(), 2), ("us".to_owned(), 1)]
                     .into_iter()
@@ -429,7 +523,7 @@ fn test_replica_set_len(locator: &ReplicaLocator) {
     // replica set length was limited.
     let capped_merged_nts_len = locator
         .replicas_for_token(
-            Token { value: 75 },
+            Token::new(75),
             &Strategy::NetworkTopologyStrategy {
                 datacenter_repfactors: [("eu".to_owned(), 69), ("us".to_owned(), 1)]
                     .into_iter()
@@ -442,7 +536,7 @@ fn test_replica_set_len(locator: &ReplicaLocator) {
 
     let filtered_nts_len = locator
         .replicas_for_token(
-            Token { value: 450 },
+            Token::new(450),
             &Strategy::NetworkTopologyStrategy {
                 datacenter_repfactors: [("eu".to_owned(), 2), ("us".to_owned(), 1)]
                     .into_iter()
@@ -455,7 +549,7 @@ fn test_replica_set_len(locator: &ReplicaLocator) {
 
     let ss_len = locator
         .replicas_for_token(
-            Token { value: 75 },
+            Token::new(75),
             &Strategy::SimpleStrategy {
                 replication_factor: 3,
             },
@@ -467,7 +561,7 @@ fn test_replica_set_len(locator: &ReplicaLocator) {
     // Test if the replica set length was capped when a datacenter name was provided.
     let filtered_ss_len = locator
         .replicas_for_token(
-            Token { value: 75 },
+            Token::new(75),
             &Strategy::SimpleStrategy {
                 replication_factor: 3,
             },
@@ -492,8 +586,7 @@ fn test_replica_set_choose(locator: &ReplicaLocator) {
     let mut rng = ChaCha8Rng::seed_from_u64(69);
 
     for strategy in strategies {
-        let replica_set_generator =
-            || locator.replicas_for_token(Token { value: 75 }, &strategy, None);
+        let replica_set_generator = || locator.replicas_for_token(Token::new(75), &strategy, None);
 
         // Verify that after a certain number of random selections, the set of selected replicas
         // will contain all nodes in the ring (replica set was created usin a strategy with
@@ -533,8 +626,7 @@ fn test_replica_set_choose_filtered(locator: &ReplicaLocator) {
     let mut rng = ChaCha8Rng::seed_from_u64(69);
 
     for strategy in strategies {
-        let replica_set_generator =
-            || locator.replicas_for_token(Token { value: 75 }, &strategy, None);
+        let replica_set_generator = || locator.replicas_for_token(Token::new(75), &strategy, None);
 
         // Verify that after a certain number of random selections with a dc filter, the set of
         // selected replicas will contain all nodes in the specified dc ring.
@@ -561,7 +653,7 @@ fn test_replica_set_choose_filtered(locator: &ReplicaLocator) {
     // Check that choosing from an empty set yields no value.
     let empty = locator
         .replicas_for_token(
-            Token { value: 75 },
+            Token::new(75),
             &Strategy::LocalStrategy,
             Some("unknown_dc_name"),
         )
@@ -4,7 +4,7 @@ use crate::routing::Token;
 /// onto the ring and then walking the ring in one direction.
 /// Cassandra and Scylla use it for determining data ownership which allows for efficient load balancing.
 /// The token ring is used by the driver to find the replicas for a given token.
-/// Each ring member has a token (i64 number) which defines the member's position on the ring.
+/// Each ring member has a token which defines the member's position on the ring.
 /// The ring is circular and can be traversed in the order of increasing tokens.
 /// `TokenRing` makes it easy and efficient to traverse the ring starting at a given token.
 #[derive(Debug, Clone)]
@@ -77,117 +77,117 @@ mod tests {
     #[test]
     fn test_token_ring() {
         let ring_data = [
-            (Token { value: -30 }, -3),
-            (Token { value: -20 }, -2),
-            (Token { value: -10 }, -1),
-            (Token { value: 0 }, 0),
-            (Token { value: 10 }, 1),
-            (Token { value: 20 }, 2),
-            (Token { value: 30 }, 3),
+            (Token::new(-30), -3),
+            (Token::new(-20), -2),
+            (Token::new(-10), -1),
+            (Token::new(0), 0),
+            (Token::new(10), 1),
+            (Token::new(20), 2),
+            (Token::new(30), 3),
         ];
 
         let ring: TokenRing<i32> = TokenRing::new(ring_data.into_iter());
 
         assert_eq!(
-            ring.ring_range(Token { value: -35 })
+            ring.ring_range(Token::new(-35))
                 .cloned()
                 .collect::<Vec<i32>>(),
             vec![-3, -2, -1, 0, 1, 2, 3]
         );
 
         assert_eq!(
-            ring.ring_range(Token { value: -30 })
+            ring.ring_range(Token::new(-30))
                 .cloned()
                 .collect::<Vec<i32>>(),
             vec![-3, -2, -1, 0, 1, 2, 3]
         );
 
         assert_eq!(
-            ring.ring_range(Token { value: -25 })
+            ring.ring_range(Token::new(-25))
                 .cloned()
                 .collect::<Vec<i32>>(),
             vec![-2, -1, 0, 1, 2, 3, -3]
         );
 
         assert_eq!(
-            ring.ring_range(Token { value: -20 })
+            ring.ring_range(Token::new(-20))
                 .cloned()
                 .collect::<Vec<i32>>(),
             vec![-2, -1, 0, 1, 2, 3, -3]
         );
 
         assert_eq!(
-            ring.ring_range(Token { value: -15 })
+            ring.ring_range(Token::new(-15))
                 .cloned()
                 .collect::<Vec<i32>>(),
             vec![-1, 0, 1, 2, 3, -3, -2]
         );
 
         assert_eq!(
-            ring.ring_range(Token { value: -10 })
+            ring.ring_range(Token::new(-10))
                 .cloned()
                 .collect::<Vec<i32>>(),
             vec![-1, 0, 1, 2, 3, -3, -2]
         );
 
         assert_eq!(
-            ring.ring_range(Token { value: -5 })
+            ring.ring_range(Token::new(-5))
                 .cloned()
                 .collect::<Vec<i32>>(),
             vec![0, 1, 2, 3, -3, -2, -1]
         );
 
         assert_eq!(
-            ring.ring_range(Token { value: 0 })
+            ring.ring_range(Token::new(0))
                 .cloned()
                 .collect::<Vec<i32>>(),
             vec![0, 1, 2, 3, -3, -2, -1]
         );
 
         assert_eq!(
-            ring.ring_range(Token { value: 5 })
+            ring.ring_range(Token::new(5))
                 .cloned()
                 .collect::<Vec<i32>>(),
             vec![1, 2, 3, -3, -2, -1, 0]
         );
 
         assert_eq!(
-            ring.ring_range(Token { value: 10 })
+            ring.ring_range(Token::new(10))
                 .cloned()
                 .collect::<Vec<i32>>(),
             vec![1, 2, 3, -3, -2, -1, 0]
         );
 
         assert_eq!(
-            ring.ring_range(Token { value: 15 })
+            ring.ring_range(Token::new(15))
                 .cloned()
                 .collect::<Vec<i32>>(),
             vec![2, 3, -3, -2, -1, 0, 1]
         );
 
         assert_eq!(
-            ring.ring_range(Token { value: 20 })
+            ring.ring_range(Token::new(20))
                 .cloned()
                 .collect::<Vec<i32>>(),
             vec![2, 3, -3, -2, -1, 0, 1]
         );
 
         assert_eq!(
-            ring.ring_range(Token { value: 25 })
+            ring.ring_range(Token::new(25))
                 .cloned()
                 .collect::<Vec<i32>>(),
             vec![3, -3, -2, -1, 0, 1, 2]
         );
 
         assert_eq!(
-            ring.ring_range(Token { value: 30 })
+            ring.ring_range(Token::new(30))
                 .cloned()
                 .collect::<Vec<i32>>(),
             vec![3, -3, -2, -1, 0, 1, 2]
         );
 
         assert_eq!(
-            ring.ring_range(Token { value: 35 })
+            ring.ring_range(Token::new(35))
                 .cloned()
                 .collect::<Vec<i32>>(),
             vec![-3, -2, -1, 0, 1, 2, 3]
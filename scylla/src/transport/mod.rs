@@ -10,12 +10,16 @@ pub mod load_balancing;
 pub mod locator;
 pub(crate) mod metrics;
 mod node;
+pub mod partition_routing;
 pub mod partitioner;
+pub mod proxy;
 pub mod query_result;
+pub mod reconnection_policy;
 pub mod retry_policy;
 pub mod session;
 pub mod session_builder;
 pub mod speculative_execution;
+pub mod token_range_scan;
 pub mod topology;
 
 pub use crate::frame::{Authenticator, Compression};
@@ -35,4 +39,5 @@ mod cql_types_test;
 mod cql_value_test;
 
 pub use cluster::ClusterData;
-pub use node::{Node, NodeAddr, NodeRef};
+pub use connection_pool::{PoolSnapshot, PoolState, ShardPoolSnapshot};
+pub use node::{Node, NodeAddr, NodeCapabilities, NodeRef};
@@ -1,10 +1,11 @@
 use uuid::Uuid;
 
+use crate::frame::protocol_features::ProtocolFeatures;
 /// Node represents a cluster node along with it's data and connections
-use crate::routing::{Sharder, Token};
+use crate::routing::{Shard, Sharder, Token};
 use crate::transport::connection::Connection;
 use crate::transport::connection::VerifiedKeyspaceName;
-use crate::transport::connection_pool::{NodeConnectionPool, PoolConfig};
+use crate::transport::connection_pool::{NodeConnectionPool, PoolConfig, PoolSnapshot};
 use crate::transport::errors::QueryError;
 
 use std::fmt::Display;
@@ -14,11 +15,11 @@ use std::{
     net::SocketAddr,
     sync::{
         atomic::{AtomicBool, Ordering},
-        Arc,
+        Arc, RwLock,
     },
 };
 
-use super::topology::{PeerEndpoint, UntranslatedEndpoint};
+use super::topology::{Peer, PeerEndpoint, UntranslatedEndpoint};
 
 /// This enum is introduced to support address translation only in PoolRefiller,
 /// as well as to cope with the bug in older Cassandra and Scylla releases.
@@ -61,6 +62,35 @@ impl Display for NodeAddr {
     }
 }
 
+/// Capabilities of a node, learned from the handshake
+/// of one of the connections to it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct NodeCapabilities {
+    /// Sharding parameters. Only Scylla nodes are sharded.
+    pub sharder: Option<Sharder>,
+    /// Port on which the node accepts shard-aware connections, if any.
+    pub shard_aware_port: Option<u16>,
+    /// Protocol extensions negotiated with the node.
+    pub protocol_features: ProtocolFeatures,
+}
+
+impl NodeCapabilities {
+    /// Returns true if the node is a Scylla node, false if it is a Cassandra node.
+    pub fn is_scylla(&self) -> bool {
+        self.sharder.is_some()
+    }
+}
+
+// Information about the node fetched from system tables,
+// refreshed on every topology refresh.
+#[derive(Debug, Default)]
+struct NodeMetadata {
+    tokens: Vec<Token>,
+    release_version: Option<String>,
+    schema_version: Option<Uuid>,
+}
+
 /// Node represents a cluster node along with it's data and connections
 ///
 /// Note: if a Node changes its broadcast address, then it is not longer
@@ -77,6 +107,8 @@ pub struct Node {
     pool: Option<NodeConnectionPool>,
 
     down_marker: AtomicBool,
+
+    metadata: RwLock<NodeMetadata>,
 }
 
 /// A way that Nodes are often passed and accessed in the driver's code.
@@ -111,6 +143,7 @@ impl Node {
             rack,
             pool,
             down_marker: false.into(),
+            metadata: Default::default(),
         }
     }
 
@@ -134,9 +167,51 @@ impl Node {
             rack: node.rack.clone(),
             host_id: node.host_id,
             pool: node.pool.clone(),
+            metadata: Default::default(),
         }
     }
 
+    /// Updates information about the node with data fetched during topology refresh.
+    pub(crate) fn update_metadata(&self, peer: &Peer) {
+        let mut metadata = self.metadata.write().unwrap();
+        metadata.tokens = peer.tokens.clone();
+        metadata.release_version = peer.release_version.clone();
+        metadata.schema_version = peer.schema_version;
+    }
+
+    /// Tokens owned by the node, as of the last topology refresh.
+    pub fn tokens(&self) -> Vec<Token> {
+        self.metadata.read().unwrap().tokens.clone()
+    }
+
+    /// Release version reported by the node in `system.local` or `system.peers`,
+    /// as of the last topology refresh.
+    pub fn release_version(&self) -> Option<String> {
+        self.metadata.read().unwrap().release_version.clone()
+    }
+
+    /// Schema version reported by the node, as of the last topology refresh.
+    ///
+    /// Use [`Session::fetch_schema_versions`](crate::Session::fetch_schema_versions)
+    /// to read the current schema versions.
+    pub fn schema_version(&self) -> Option<Uuid> {
+        self.metadata.read().unwrap().schema_version
+    }
+
+    /// Returns capabilities of the node, taken from one of the connections to it.
+    /// Returns `None` if the node is disabled or has no working connections.
+    pub fn capabilities(&self) -> Option<NodeCapabilities> {
+        let connection = self.pool.as_ref()?.random_connection().ok()?;
+        Some(NodeCapabilities {
+            sharder: connection
+                .get_shard_info()
+                .as_ref()
+                .map(|info| info.get_sharder()),
+            shard_aware_port: connection.get_shard_aware_port(),
+            protocol_features: *connection.get_protocol_features(),
+        })
+    }
+
     pub fn sharder(&self) -> Option<Sharder> {
         self.pool.as_ref()?.sharder()
     }
@@ -150,6 +225,14 @@ impl Node {
         self.get_pool()?.connection_for_token(token)
     }
 
+    /// Get connection to the given shard, failing if there is none
+    pub(crate) async fn connection_to_shard(
+        &self,
+        shard: Shard,
+    ) -> Result<Arc<Connection>, QueryError> {
+        self.get_pool()?.connection_to_shard(shard)
+    }
+
     /// Get random connection
     pub(crate) async fn random_connection(&self) -> Result<Arc<Connection>, QueryError> {
         self.get_pool()?.random_connection()
@@ -166,6 +249,21 @@ impl Node {
         self.pool.is_some()
     }
 
+    /// Returns a snapshot of the state of this node's connection pool:
+    /// open connections, in-flight requests and connection errors, per shard.
+    /// Returns `None` if the node is disabled, as it has no pool.
+    pub fn pool_snapshot(&self) -> Option<PoolSnapshot> {
+        self.pool.as_ref().map(NodeConnectionPool::snapshot)
+    }
+
+    /// Returns the number of requests sent to this node which still await a response.
+    /// If `token` is given, only the connection which would be used for the token is counted:
+    /// the least loaded one to the shard owning the token or, if there are none, to any other shard.
+    /// Returns `None` if the node has no open connections, e.g. if it is disabled.
+    pub fn in_flight_requests(&self, token: Option<&Token>) -> Option<usize> {
+        self.pool.as_ref()?.in_flight_requests(token)
+    }
+
     pub(crate) fn change_down_marker(&self, is_down: bool) {
         self.down_marker.store(is_down, Ordering::Relaxed);
     }
@@ -232,7 +330,41 @@ mod tests {
                 rack,
                 pool: None,
                 down_marker: false.into(),
+                metadata: Default::default(),
             }
         }
     }
+
+    #[test]
+    fn node_metadata_is_updated() {
+        let node = Node::new_for_test(
+            NodeAddr::Translatable(SocketAddr::from(([127, 0, 0, 1], 9042))),
+            None,
+            None,
+        );
+        assert!(node.tokens().is_empty());
+        assert_eq!(node.release_version(), None);
+        assert_eq!(node.schema_version(), None);
+        // Disabled nodes have no connections to learn capabilities from
+        assert_eq!(node.capabilities(), None);
+
+        let schema_version = Uuid::new_v4();
+        let peer = Peer {
+            host_id: node.host_id,
+            address: node.address,
+            tokens: vec![Token::new(42), Token::new(-7)],
+            datacenter: None,
+            rack: None,
+            release_version: Some("3.0.8".to_owned()),
+            schema_version: Some(schema_version),
+        };
+        node.update_metadata(&peer);
+
+        assert_eq!(
+            node.tokens(),
+            vec![Token::new(42), Token::new(-7)]
+        );
+        assert_eq!(node.release_version().as_deref(), Some("3.0.8"));
+        assert_eq!(node.schema_version(), Some(schema_version));
+    }
 }
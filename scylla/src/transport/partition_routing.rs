@@ -0,0 +1,524 @@
+//! Routing of partitions given by the names and values of their partition key columns,
+//! see [`ClusterData::compute_partition_routing`].
+
+use std::collections::{BTreeMap, HashMap};
+use std::hash::BuildHasher;
+use std::sync::Arc;
+
+use bytes::{BufMut, Bytes, BytesMut};
+use thiserror::Error;
+
+use crate::frame::response::result::CqlValue;
+use crate::frame::value::Value;
+use crate::routing::{Shard, Token};
+use crate::transport::cluster::ClusterData;
+use crate::transport::topology::{CollectionType, CqlType, NativeType, Table};
+use crate::transport::Node;
+
+/// Values of partition key columns, looked up by column name.
+///
+/// Implemented for maps and slices of pairs. It can be implemented for a struct
+/// holding the partition key of a table.
+///
+/// # Example
+/// ```rust
+/// use scylla::frame::response::result::CqlValue;
+/// use scylla::transport::partition_routing::PartitionKeyValues;
+///
+/// struct EventKey {
+///     user_id: i64,
+///     day: String,
+/// }
+///
+/// impl PartitionKeyValues for EventKey {
+///     fn named_values(&self) -> Vec<(&str, CqlValue)> {
+///         vec![
+///             ("user_id", CqlValue::BigInt(self.user_id)),
+///             ("day", CqlValue::Text(self.day.clone())),
+///         ]
+///     }
+/// }
+/// ```
+pub trait PartitionKeyValues {
+    /// Returns pairs of column names and their values.
+    fn named_values(&self) -> Vec<(&str, CqlValue)>;
+}
+
+impl<S: BuildHasher> PartitionKeyValues for HashMap<String, CqlValue, S> {
+    fn named_values(&self) -> Vec<(&str, CqlValue)> {
+        self.iter()
+            .map(|(name, value)| (name.as_str(), value.clone()))
+            .collect()
+    }
+}
+
+impl PartitionKeyValues for BTreeMap<String, CqlValue> {
+    fn named_values(&self) -> Vec<(&str, CqlValue)> {
+        self.iter()
+            .map(|(name, value)| (name.as_str(), value.clone()))
+            .collect()
+    }
+}
+
+impl PartitionKeyValues for [(&str, CqlValue)] {
+    fn named_values(&self) -> Vec<(&str, CqlValue)> {
+        self.iter()
+            .map(|(name, value)| (*name, value.clone()))
+            .collect()
+    }
+}
+
+impl<const N: usize> PartitionKeyValues for [(&str, CqlValue); N] {
+    fn named_values(&self) -> Vec<(&str, CqlValue)> {
+        self[..].named_values()
+    }
+}
+
+impl PartitionKeyValues for Vec<(&str, CqlValue)> {
+    fn named_values(&self) -> Vec<(&str, CqlValue)> {
+        self[..].named_values()
+    }
+}
+
+/// A replica of a partition and the shard owning the partition on it.
+#[derive(Debug, Clone)]
+#[non_exhaustive]
+pub struct ReplicaShard {
+    pub node: Arc<Node>,
+    /// `None` if the node isn't sharded, or if no connection to it has been
+    /// established yet, so its sharding parameters are unknown.
+    pub shard: Option<Shard>,
+}
+
+/// Where a partition lives, returned by [`ClusterData::compute_partition_routing`].
+#[derive(Debug, Clone)]
+#[non_exhaustive]
+pub struct PartitionRouting {
+    pub token: Token,
+    /// The serialized partition key, which the token is computed from.
+    pub routing_key: Bytes,
+    /// Replicas of the partition according to the replication strategy of the keyspace,
+    /// in no particular order.
+    pub replicas: Vec<ReplicaShard>,
+}
+
+impl PartitionRouting {
+    /// Groups the replicas by their datacenters. Replicas with unknown datacenter are skipped.
+    pub fn replicas_by_datacenter(&self) -> HashMap<&str, Vec<&ReplicaShard>> {
+        let mut replicas: HashMap<&str, Vec<&ReplicaShard>> = HashMap::new();
+        for replica in &self.replicas {
+            if let Some(datacenter) = replica.node.datacenter.as_deref() {
+                replicas.entry(datacenter).or_default().push(replica);
+            }
+        }
+        replicas
+    }
+}
+
+/// An error of [`ClusterData::compute_partition_routing`].
+#[derive(Error, Debug, Clone)]
+#[non_exhaustive]
+pub enum PartitionRoutingError {
+    #[error("Table {keyspace}.{table} not found in the schema metadata")]
+    UnknownTable { keyspace: String, table: String },
+    #[error("Missing value of partition key column {0}")]
+    MissingColumn(String),
+    #[error("Column {0} is not a partition key column of the table")]
+    NotPartitionKeyColumn(String),
+    #[error("Value of column {0} given more than once")]
+    DuplicateColumn(String),
+    #[error("Value {value:?} of partition key column {column} doesn't match its type {type_:?}")]
+    TypeMismatch {
+        column: String,
+        type_: Box<CqlType>,
+        value: Box<CqlValue>,
+    },
+    #[error(
+        "Partition key column {column} has type {type_:?}, whose serialized form can't be \
+        computed exactly (sets, maps and user defined types missing from the metadata)"
+    )]
+    UnsupportedType { column: String, type_: Box<CqlType> },
+    #[error("Value of partition key column {0} is too long")]
+    ValueTooLong(String),
+}
+
+enum TypeCheck {
+    Matches,
+    Mismatch,
+    Unsupported,
+}
+
+impl TypeCheck {
+    fn all(checks: impl IntoIterator<Item = TypeCheck>) -> TypeCheck {
+        let mut result = TypeCheck::Matches;
+        for check in checks {
+            match check {
+                TypeCheck::Matches => (),
+                TypeCheck::Mismatch => return TypeCheck::Mismatch,
+                TypeCheck::Unsupported => result = TypeCheck::Unsupported,
+            }
+        }
+        result
+    }
+}
+
+fn check_type(value: &CqlValue, type_: &CqlType) -> TypeCheck {
+    match (type_, value) {
+        (CqlType::Native(native), value) => {
+            if native_type_matches(native, value) {
+                TypeCheck::Matches
+            } else {
+                TypeCheck::Mismatch
+            }
+        }
+        (
+            CqlType::Collection {
+                type_: CollectionType::List(element_type),
+                ..
+            },
+            CqlValue::List(elements),
+        ) => TypeCheck::all(
+            elements
+                .iter()
+                .map(|element| check_type(element, element_type)),
+        ),
+        // The database sorts elements of sets and maps, so the serialized value
+        // depends on the ordering of the element type.
+        (
+            CqlType::Collection {
+                type_: CollectionType::Set(_) | CollectionType::Map(_, _),
+                ..
+            },
+            _,
+        ) => TypeCheck::Unsupported,
+        (CqlType::Tuple(types), CqlValue::Tuple(values)) if types.len() == values.len() => {
+            TypeCheck::all(
+                values
+                    .iter()
+                    .zip(types)
+                    .filter_map(|(value, type_)| Some(check_type(value.as_ref()?, type_))),
+            )
+        }
+        (
+            CqlType::UserDefinedType {
+                definition: Ok(definition),
+                ..
+            },
+            CqlValue::UserDefinedType {
+                keyspace,
+                type_name,
+                fields,
+            },
+        ) => {
+            if *keyspace != definition.keyspace
+                || *type_name != definition.name
+                || fields.len() != definition.field_types.len()
+                || fields
+                    .iter()
+                    .zip(&definition.field_types)
+                    .any(|((name, _), (field_name, _))| name != field_name)
+            {
+                return TypeCheck::Mismatch;
+            }
+            TypeCheck::all(
+                fields.iter().zip(&definition.field_types).filter_map(
+                    |((_, value), (_, type_))| Some(check_type(value.as_ref()?, type_)),
+                ),
+            )
+        }
+        (
+            CqlType::UserDefinedType {
+                definition: Err(_), ..
+            },
+            _,
+        ) => TypeCheck::Unsupported,
+        _ => TypeCheck::Mismatch,
+    }
+}
+
+fn native_type_matches(type_: &NativeType, value: &CqlValue) -> bool {
+    match (type_, value) {
+        // Both are serialized as their UTF-8 bytes.
+        (NativeType::Ascii, CqlValue::Text(text)) => text.is_ascii(),
+        (NativeType::Text, CqlValue::Ascii(_)) => true,
+        // A time UUID is a valid UUID.
+        (NativeType::Uuid, CqlValue::Timeuuid(_)) => true,
+        (NativeType::Ascii, CqlValue::Ascii(_))
+        | (NativeType::Boolean, CqlValue::Boolean(_))
+        | (NativeType::Blob, CqlValue::Blob(_))
+        | (NativeType::Counter, CqlValue::Counter(_))
+        | (NativeType::Date, CqlValue::Date(_))
+        | (NativeType::Decimal, CqlValue::Decimal(_))
+        | (NativeType::Double, CqlValue::Double(_))
+        | (NativeType::Duration, CqlValue::Duration(_))
+        | (NativeType::Float, CqlValue::Float(_))
+        | (NativeType::Int, CqlValue::Int(_))
+        | (NativeType::BigInt, CqlValue::BigInt(_))
+        | (NativeType::Text, CqlValue::Text(_))
+        | (NativeType::Timestamp, CqlValue::Timestamp(_))
+        | (NativeType::Inet, CqlValue::Inet(_))
+        | (NativeType::SmallInt, CqlValue::SmallInt(_))
+        | (NativeType::TinyInt, CqlValue::TinyInt(_))
+        | (NativeType::Time, CqlValue::Time(_))
+        | (NativeType::Timeuuid, CqlValue::Timeuuid(_))
+        | (NativeType::Uuid, CqlValue::Uuid(_))
+        | (NativeType::Varint, CqlValue::Varint(_)) => true,
+        _ => false,
+    }
+}
+
+/// Validates the values against the partition key of the table and serializes them
+/// into the routing key, in the order of the partition key columns.
+pub(crate) fn compute_routing_key(
+    table: &Table,
+    values: &(impl PartitionKeyValues + ?Sized),
+) -> Result<Bytes, PartitionRoutingError> {
+    let mut key_values: Vec<Option<CqlValue>> = vec![None; table.partition_key.len()];
+    for (name, value) in values.named_values() {
+        let idx = table
+            .partition_key
+            .iter()
+            .position(|column| column == name)
+            .ok_or_else(|| PartitionRoutingError::NotPartitionKeyColumn(name.to_owned()))?;
+        if key_values[idx].replace(value).is_some() {
+            return Err(PartitionRoutingError::DuplicateColumn(name.to_owned()));
+        }
+    }
+
+    let mut serialized_values = Vec::with_capacity(key_values.len());
+    for (column, value) in table.partition_key.iter().zip(key_values) {
+        let value = value.ok_or_else(|| PartitionRoutingError::MissingColumn(column.clone()))?;
+        let type_ = match table.columns.get(column) {
+            Some(column) => &column.type_,
+            None => return Err(PartitionRoutingError::MissingColumn(column.clone())),
+        };
+        match check_type(&value, type_) {
+            TypeCheck::Matches => (),
+            TypeCheck::Mismatch => {
+                return Err(PartitionRoutingError::TypeMismatch {
+                    column: column.clone(),
+                    type_: Box::new(type_.clone()),
+                    value: Box::new(value),
+                })
+            }
+            TypeCheck::Unsupported => {
+                return Err(PartitionRoutingError::UnsupportedType {
+                    column: column.clone(),
+                    type_: Box::new(type_.clone()),
+                })
+            }
+        }
+
+        let mut buf = Vec::new();
+        value
+            .serialize(&mut buf)
+            .map_err(|_| PartitionRoutingError::ValueTooLong(column.clone()))?;
+        // Skip the length of the value.
+        serialized_values.push((column, buf.split_off(4)));
+    }
+
+    if let [(_, value)] = &serialized_values[..] {
+        return Ok(Bytes::copy_from_slice(value));
+    }
+    // Composite partition keys are serialized like in `PreparedStatement::compute_partition_key`.
+    let mut routing_key = BytesMut::new();
+    for (column, value) in serialized_values {
+        let len: u16 = value
+            .len()
+            .try_into()
+            .map_err(|_| PartitionRoutingError::ValueTooLong(column.clone()))?;
+        routing_key.put_u16(len);
+        routing_key.extend_from_slice(&value);
+        routing_key.put_u8(0);
+    }
+    Ok(routing_key.freeze())
+}
+
+pub(crate) fn compute_partition_routing(
+    cluster_data: &ClusterData,
+    keyspace: &str,
+    table: &str,
+    values: &(impl PartitionKeyValues + ?Sized),
+) -> Result<PartitionRouting, PartitionRoutingError> {
+    let table_metadata = cluster_data
+        .get_keyspace_info()
+        .get(keyspace)
+        .and_then(|k| k.tables.get(table))
+        .ok_or_else(|| PartitionRoutingError::UnknownTable {
+            keyspace: keyspace.to_owned(),
+            table: table.to_owned(),
+        })?;
+
+    let routing_key = compute_routing_key(table_metadata, values)?;
+    let token = cluster_data.compute_token_from_routing_key(keyspace, table, &routing_key);
+    let replicas = cluster_data
+        .get_token_endpoints_iter(keyspace, token.clone())
+        .map(|node| ReplicaShard {
+            node: node.clone(),
+            shard: node
+                .sharder()
+                .map(|sharder| sharder.shard_of(token.clone())),
+        })
+        .collect();
+
+    Ok(PartitionRouting {
+        token,
+        routing_key,
+        replicas,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{PartitionKeyValues, PartitionRoutingError};
+    use crate::frame::response::result::CqlValue;
+    use crate::transport::locator::test::{mock_metadata_for_token_aware_tests, KEYSPACE_NTS_RF_3};
+    use crate::transport::topology::{
+        CollectionType, Column, ColumnKind, CqlType, NativeType, Table,
+    };
+    use crate::transport::ClusterData;
+    use assert_matches::assert_matches;
+    use std::collections::{BTreeMap, HashMap};
+
+    fn column(type_: CqlType, kind: ColumnKind) -> Column {
+        Column { type_, kind }
+    }
+
+    async fn cluster_with_table() -> ClusterData {
+        let mut metadata = mock_metadata_for_token_aware_tests();
+        let table = Table {
+            columns: HashMap::from([
+                (
+                    "user".to_owned(),
+                    column(CqlType::Native(NativeType::Text), ColumnKind::PartitionKey),
+                ),
+                (
+                    "day".to_owned(),
+                    column(CqlType::Native(NativeType::Int), ColumnKind::PartitionKey),
+                ),
+                (
+                    "tags".to_owned(),
+                    column(
+                        CqlType::Collection {
+                            frozen: true,
+                            type_: CollectionType::Set(Box::new(CqlType::Native(NativeType::Text))),
+                        },
+                        ColumnKind::PartitionKey,
+                    ),
+                ),
+                (
+                    "seq".to_owned(),
+                    column(CqlType::Native(NativeType::Int), ColumnKind::Clustering),
+                ),
+            ]),
+            partition_key: vec!["user".to_owned(), "day".to_owned()],
+            clustering_key: vec!["seq".to_owned()],
+            partitioner: None,
+        };
+        let mut by_tags = table.clone();
+        by_tags.partition_key = vec!["tags".to_owned()];
+
+        let keyspace = metadata.keyspaces.get_mut(KEYSPACE_NTS_RF_3).unwrap();
+        keyspace.tables.insert("events".to_owned(), table);
+        keyspace.tables.insert("by_tags".to_owned(), by_tags);
+
+        ClusterData::new(
+            metadata,
+            &Default::default(),
+            &HashMap::new(),
+            &None,
+            None,
+            &HashMap::new(),
+        )
+        .await
+    }
+
+    #[tokio::test]
+    async fn test_compute_partition_routing() {
+        let cluster = cluster_with_table().await;
+        let expected_token = cluster
+            .compute_token(KEYSPACE_NTS_RF_3, "events", ("alice", 20230101))
+            .unwrap();
+
+        // The order in which the columns are given doesn't matter.
+        let values = [
+            ("day", CqlValue::Int(20230101)),
+            ("user", CqlValue::Text("alice".to_owned())),
+        ];
+        let map: HashMap<String, CqlValue> = values
+            .iter()
+            .map(|(name, value)| (name.to_string(), value.clone()))
+            .collect();
+        let btree_map: BTreeMap<String, CqlValue> = map.clone().into_iter().collect();
+        let inputs: [&dyn PartitionKeyValues; 3] = [&values, &map, &btree_map];
+        for input in inputs {
+            let routing = cluster
+                .compute_partition_routing(KEYSPACE_NTS_RF_3, "events", input)
+                .unwrap();
+            assert_eq!(routing.token, expected_token);
+            assert_eq!(
+                cluster.compute_token_from_routing_key(
+                    KEYSPACE_NTS_RF_3,
+                    "events",
+                    &routing.routing_key
+                ),
+                expected_token
+            );
+
+            // 3 replicas in each of the two datacenters.
+            assert_eq!(routing.replicas.len(), 6);
+            let by_datacenter = routing.replicas_by_datacenter();
+            assert_eq!(by_datacenter["eu"].len(), 3);
+            assert_eq!(by_datacenter["us"].len(), 3);
+            // Mock nodes are not sharded
+            assert!(routing
+                .replicas
+                .iter()
+                .all(|replica| replica.shard.is_none()));
+        }
+    }
+
+    #[tokio::test]
+    async fn test_partition_routing_validation() {
+        let cluster = cluster_with_table().await;
+        let route = |table: &str, values: &[(&str, CqlValue)]| {
+            cluster.compute_partition_routing(KEYSPACE_NTS_RF_3, table, values)
+        };
+        let alice = || CqlValue::Text("alice".to_owned());
+
+        assert_matches!(
+            route("no_such_table", &[]),
+            Err(PartitionRoutingError::UnknownTable { .. })
+        );
+        assert_matches!(
+            route("events", &[("user", alice())]),
+            Err(PartitionRoutingError::MissingColumn(column)) if column == "day"
+        );
+        assert_matches!(
+            route("events", &[("user", alice()), ("day", CqlValue::Int(1)), ("seq", CqlValue::Int(1))]),
+            Err(PartitionRoutingError::NotPartitionKeyColumn(column)) if column == "seq"
+        );
+        assert_matches!(
+            route("events", &[("user", alice()), ("user", alice()), ("day", CqlValue::Int(1))]),
+            Err(PartitionRoutingError::DuplicateColumn(column)) if column == "user"
+        );
+        assert_matches!(
+            route("events", &[("user", alice()), ("day", CqlValue::BigInt(1))]),
+            Err(PartitionRoutingError::TypeMismatch { column, .. }) if column == "day"
+        );
+        assert_matches!(
+            route("by_tags", &[("tags", CqlValue::Set(vec![alice()]))]),
+            Err(PartitionRoutingError::UnsupportedType { column, .. }) if column == "tags"
+        );
+
+        // Ascii values are valid text values.
+        assert!(route(
+            "events",
+            &[
+                ("user", CqlValue::Ascii("alice".to_owned())),
+                ("day", CqlValue::Int(1))
+            ]
+        )
+        .is_ok());
+    }
+}
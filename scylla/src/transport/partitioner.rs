@@ -9,6 +9,8 @@ pub(crate) enum PartitionerName {
     #[default]
     Murmur3,
     CDC,
+    Random,
+    ByteOrdered,
 }
 
 impl PartitionerName {
@@ -17,6 +19,10 @@ impl PartitionerName {
             Some(PartitionerName::Murmur3)
         } else if name.ends_with("CDCPartitioner") {
             Some(PartitionerName::CDC)
+        } else if name.ends_with("RandomPartitioner") {
+            Some(PartitionerName::Random)
+        } else if name.ends_with("ByteOrderedPartitioner") {
+            Some(PartitionerName::ByteOrdered)
         } else {
             None
         }
@@ -26,8 +32,36 @@ impl PartitionerName {
         match self {
             PartitionerName::Murmur3 => Murmur3Partitioner::hash(pk),
             PartitionerName::CDC => CDCPartitioner::hash(pk),
+            PartitionerName::Random => RandomPartitioner::hash(pk),
+            PartitionerName::ByteOrdered => ByteOrderedPartitioner::hash(pk),
         }
     }
+
+    /// Parses the string representation of a token, as found in `system.local` and `system.peers`.
+    pub(crate) fn parse_token(&self, token: &str) -> Option<Token> {
+        match self {
+            PartitionerName::Murmur3 | PartitionerName::CDC => token.parse().ok().map(Token::new),
+            PartitionerName::Random => {
+                // Tokens range from -1 up to 2^127, which doesn't fit in i128
+                let value: i128 = match token.strip_prefix('-') {
+                    Some(abs) => -i128::try_from(abs.parse::<u128>().ok()?).ok()?,
+                    None => i128::try_from(token.parse::<u128>().ok()?).unwrap_or(i128::MAX),
+                };
+                Some(Token::from_i128(value))
+            }
+            PartitionerName::ByteOrdered => parse_hex(token).map(Token::from_bytes),
+        }
+    }
+}
+
+fn parse_hex(s: &str) -> Option<Vec<u8>> {
+    s.as_bytes()
+        .chunks(2)
+        .map(|digits| match digits {
+            [_, _] => u8::from_str_radix(std::str::from_utf8(digits).ok()?, 16).ok(),
+            _ => None,
+        })
+        .collect()
 }
 
 pub trait Partitioner {
@@ -36,12 +70,14 @@ pub trait Partitioner {
 
 pub struct Murmur3Partitioner;
 pub struct CDCPartitioner;
+pub struct RandomPartitioner;
+pub struct ByteOrderedPartitioner;
 
 impl Murmur3Partitioner {
     // An implementation of MurmurHash3 ported from Scylla. Please note that this
     // is not a "correct" implementation of MurmurHash3 - it replicates the same
     // bugs made in the original Cassandra implementation in order to be compatible.
-    fn hash3_x64_128(mut data: &[u8]) -> i128 {
+    pub(crate) fn hash3_x64_128(mut data: &[u8]) -> i128 {
         let length = data.len();
 
         let c1: Wrapping<i64> = Wrapping(0x87c3_7b91_1142_53d5_u64 as i64);
@@ -134,25 +170,46 @@ impl Murmur3Partitioner {
 
 impl Partitioner for Murmur3Partitioner {
     fn hash(pk: &[u8]) -> Token {
-        Token {
-            value: Self::hash3_x64_128(pk) as i64,
-        }
+        Token::new(Self::hash3_x64_128(pk) as i64)
     }
 }
 
 impl Partitioner for CDCPartitioner {
     fn hash(mut pk: &[u8]) -> Token {
         let value = if pk.len() < 8 { i64::MIN } else { pk.get_i64() };
-        Token { value }
+        Token::new(value)
+    }
+}
+
+impl Partitioner for RandomPartitioner {
+    // The token is the absolute value of the MD5 digest, read as a signed big-endian integer.
+    // The digest of i128::MIN would give 2^127, which is clamped to i128::MAX.
+    fn hash(pk: &[u8]) -> Token {
+        let digest = md5::compute(pk);
+        let value = i128::from_be_bytes(digest.0)
+            .checked_abs()
+            .unwrap_or(i128::MAX);
+        Token::from_i128(value)
+    }
+}
+
+impl Partitioner for ByteOrderedPartitioner {
+    // Tokens of this partitioner are the partition keys themselves, compared as byte strings.
+    fn hash(pk: &[u8]) -> Token {
+        Token::from_bytes(pk)
     }
 }
 
 #[cfg(test)]
 mod tests {
-    use super::{CDCPartitioner, Murmur3Partitioner, Partitioner};
+    use super::{
+        ByteOrderedPartitioner, CDCPartitioner, Murmur3Partitioner, Partitioner, PartitionerName,
+        RandomPartitioner,
+    };
+    use crate::routing::Token;
 
     fn assert_correct_murmur3_hash(pk: &'static str, expected_hash: i64) {
-        let hash = Murmur3Partitioner::hash(pk.as_bytes()).value;
+        let hash = Murmur3Partitioner::hash(pk.as_bytes()).as_i64().unwrap();
         assert_eq!(hash, expected_hash);
     }
 
@@ -169,7 +226,7 @@ mod tests {
     }
 
     fn assert_correct_cdc_hash(pk: &'static str, expected_hash: i64) {
-        let hash = CDCPartitioner::hash(pk.as_bytes()).value;
+        let hash = CDCPartitioner::hash(pk.as_bytes()).as_i64().unwrap();
         assert_eq!(hash, expected_hash);
     }
 
@@ -184,4 +241,85 @@ mod tests {
             assert_correct_cdc_hash(s.0, s.1);
         }
     }
+
+    #[test]
+    fn test_random_partitioner() {
+        for (pk, expected_hash) in [
+            ("test", 12707736894140473154801792860916528374),
+            ("xd", 169066033079904544943465057344980629343),
+            ("primary_key", 7963137704637686617557505077267557904),
+            ("kremówki", 167725585181102827933860415579428130527),
+            // MD5 digest with the most significant bit set
+            ("", 58332598431525814501020785164969033090),
+        ] {
+            assert_eq!(
+                RandomPartitioner::hash(pk.as_bytes()).as_i128(),
+                Some(expected_hash)
+            );
+        }
+    }
+
+    #[test]
+    fn test_byte_ordered_partitioner_keeps_order() {
+        let keys: [&[u8]; 10] = [
+            b"",
+            b"\x00",
+            b"\x00\x00",
+            b"a",
+            b"a\x00",
+            b"ab",
+            b"abcdefghijklmno",
+            b"abcdefghijklmnop",
+            b"abcdefghijklmnoq",
+            b"\xff",
+        ];
+        for pair in keys.windows(2) {
+            assert!(
+                ByteOrderedPartitioner::hash(pair[0]) < ByteOrderedPartitioner::hash(pair[1]),
+                "{:?} should precede {:?}",
+                pair[0],
+                pair[1]
+            );
+        }
+
+        assert_eq!(
+            ByteOrderedPartitioner::hash(b"key").as_bytes(),
+            Some(&b"key"[..])
+        );
+    }
+
+    #[test]
+    fn test_parse_token() {
+        let murmur3 = PartitionerName::Murmur3;
+        assert_eq!(
+            murmur3.parse_token("-6017608668500074083"),
+            Some(Token::new(-6017608668500074083))
+        );
+        assert_eq!(murmur3.parse_token("9223372036854775808"), None);
+
+        let random = PartitionerName::Random;
+        assert_eq!(random.parse_token("-1"), Some(Token::new(-1)));
+        assert_eq!(
+            random.parse_token("12707736894140473154801792860916528374"),
+            Some(RandomPartitioner::hash(b"test"))
+        );
+        // 2^127
+        assert_eq!(
+            random.parse_token("170141183460469231731687303715884105728"),
+            Some(Token::from_i128(i128::MAX))
+        );
+        assert_eq!(random.parse_token("abc"), None);
+
+        let byte_ordered = PartitionerName::ByteOrdered;
+        assert_eq!(
+            byte_ordered.parse_token("6b65790a"),
+            Some(ByteOrderedPartitioner::hash(b"key\n"))
+        );
+        assert_eq!(
+            byte_ordered.parse_token(""),
+            Some(ByteOrderedPartitioner::hash(b""))
+        );
+        assert_eq!(byte_ordered.parse_token("6b6"), None);
+        assert_eq!(byte_ordered.parse_token("zz"), None);
+    }
 }
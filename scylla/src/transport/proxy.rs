@@ -0,0 +1,451 @@
+//! Outbound proxy configuration\
+//! In environments where nodes can't be reached directly, the driver can open
+//! all its connections through a SOCKS5 or HTTP CONNECT proxy.
+//! The proxy is configured with [`SessionBuilder::proxy`](crate::transport::session_builder::GenericSessionBuilder::proxy).
+//!
+//! Connections are tunneled after address translation, so the proxy is asked to connect
+//! to the translated address. TLS, if configured, is established through the tunnel.
+//! The driver can't choose source ports of connections going through a proxy,
+//! so the shard-aware port is not used.
+
+use std::io::{Error, ErrorKind};
+use std::net::SocketAddr;
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+use tokio::net::TcpStream;
+
+/// The protocol used to talk to the proxy.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum ProxyKind {
+    /// SOCKS5 (RFC 1928), with optional username/password authentication (RFC 1929)
+    Socks5,
+    /// HTTP CONNECT tunneling, with optional basic authentication
+    HttpConnect,
+}
+
+/// Username and password used to authenticate to the proxy.
+#[derive(Clone, PartialEq, Eq)]
+pub struct ProxyCredentials {
+    pub username: String,
+    pub password: String,
+}
+
+// Don't leak the password into logs
+impl std::fmt::Debug for ProxyCredentials {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ProxyCredentials")
+            .field("username", &self.username)
+            .field("password", &"<hidden>")
+            .finish()
+    }
+}
+
+/// Describes the proxy which all connections are opened through.
+///
+/// # Example
+/// ```
+/// # use scylla::transport::proxy::ProxyConfig;
+/// let proxy = ProxyConfig::socks5("10.0.0.1:1080".parse().unwrap())
+///     .with_credentials("user", "pass");
+/// ```
+#[derive(Debug, Clone)]
+pub struct ProxyConfig {
+    pub kind: ProxyKind,
+    pub address: SocketAddr,
+    pub credentials: Option<ProxyCredentials>,
+}
+
+impl ProxyConfig {
+    /// Creates a config of a SOCKS5 proxy listening on the given address.
+    pub fn socks5(address: SocketAddr) -> Self {
+        ProxyConfig {
+            kind: ProxyKind::Socks5,
+            address,
+            credentials: None,
+        }
+    }
+
+    /// Creates a config of an HTTP proxy supporting the CONNECT method, listening on the given address.
+    pub fn http_connect(address: SocketAddr) -> Self {
+        ProxyConfig {
+            kind: ProxyKind::HttpConnect,
+            address,
+            credentials: None,
+        }
+    }
+
+    /// Sets the credentials used to authenticate to the proxy.
+    pub fn with_credentials(
+        mut self,
+        username: impl Into<String>,
+        password: impl Into<String>,
+    ) -> Self {
+        self.credentials = Some(ProxyCredentials {
+            username: username.into(),
+            password: password.into(),
+        });
+        self
+    }
+
+    /// Connects to the proxy and asks it to open a tunnel to `target`.
+    pub(crate) async fn connect(&self, target: SocketAddr) -> Result<TcpStream, Error> {
+        let mut stream = TcpStream::connect(self.address).await?;
+        self.handshake(&mut stream, target).await?;
+        Ok(stream)
+    }
+
+    async fn handshake(
+        &self,
+        stream: &mut (impl AsyncRead + AsyncWrite + Unpin),
+        target: SocketAddr,
+    ) -> Result<(), Error> {
+        match self.kind {
+            ProxyKind::Socks5 => socks5_handshake(stream, target, self.credentials.as_ref()).await,
+            ProxyKind::HttpConnect => {
+                http_connect_handshake(stream, target, self.credentials.as_ref()).await
+            }
+        }
+    }
+}
+
+fn proxy_error(msg: String) -> Error {
+    Error::new(ErrorKind::Other, msg)
+}
+
+const SOCKS5_VERSION: u8 = 0x05;
+const SOCKS5_NO_AUTH: u8 = 0x00;
+const SOCKS5_USERNAME_PASSWORD: u8 = 0x02;
+const SOCKS5_NO_ACCEPTABLE_METHODS: u8 = 0xFF;
+const SOCKS5_CMD_CONNECT: u8 = 0x01;
+const SOCKS5_ATYP_IPV4: u8 = 0x01;
+const SOCKS5_ATYP_DOMAIN: u8 = 0x03;
+const SOCKS5_ATYP_IPV6: u8 = 0x04;
+
+async fn socks5_handshake(
+    stream: &mut (impl AsyncRead + AsyncWrite + Unpin),
+    target: SocketAddr,
+    credentials: Option<&ProxyCredentials>,
+) -> Result<(), Error> {
+    // Method negotiation
+    let greeting: &[u8] = match credentials {
+        Some(_) => &[SOCKS5_VERSION, 2, SOCKS5_NO_AUTH, SOCKS5_USERNAME_PASSWORD],
+        None => &[SOCKS5_VERSION, 1, SOCKS5_NO_AUTH],
+    };
+    stream.write_all(greeting).await?;
+
+    let mut method_selection = [0u8; 2];
+    stream.read_exact(&mut method_selection).await?;
+    if method_selection[0] != SOCKS5_VERSION {
+        return Err(proxy_error(format!(
+            "SOCKS5 proxy replied with unsupported version {}",
+            method_selection[0]
+        )));
+    }
+
+    match (method_selection[1], credentials) {
+        (SOCKS5_NO_AUTH, _) => {}
+        (SOCKS5_USERNAME_PASSWORD, Some(credentials)) => {
+            socks5_authenticate(stream, credentials).await?
+        }
+        (SOCKS5_NO_ACCEPTABLE_METHODS, _) => {
+            return Err(proxy_error(
+                "SOCKS5 proxy accepted none of the offered authentication methods".to_string(),
+            ))
+        }
+        (method, _) => {
+            return Err(proxy_error(format!(
+                "SOCKS5 proxy selected an authentication method which wasn't offered: {}",
+                method
+            )))
+        }
+    }
+
+    // Connect request
+    let mut request = vec![SOCKS5_VERSION, SOCKS5_CMD_CONNECT, 0x00];
+    match target {
+        SocketAddr::V4(addr) => {
+            request.push(SOCKS5_ATYP_IPV4);
+            request.extend_from_slice(&addr.ip().octets());
+        }
+        SocketAddr::V6(addr) => {
+            request.push(SOCKS5_ATYP_IPV6);
+            request.extend_from_slice(&addr.ip().octets());
+        }
+    }
+    request.extend_from_slice(&target.port().to_be_bytes());
+    stream.write_all(&request).await?;
+
+    let mut reply = [0u8; 4];
+    stream.read_exact(&mut reply).await?;
+    if reply[1] != 0x00 {
+        return Err(proxy_error(format!(
+            "SOCKS5 proxy failed to connect to {}: {}",
+            target,
+            socks5_reply_description(reply[1])
+        )));
+    }
+
+    // Skip the address the proxy bound for the tunnel
+    let bound_address_len = match reply[3] {
+        SOCKS5_ATYP_IPV4 => 4,
+        SOCKS5_ATYP_IPV6 => 16,
+        SOCKS5_ATYP_DOMAIN => stream.read_u8().await? as usize,
+        atyp => {
+            return Err(proxy_error(format!(
+                "SOCKS5 proxy replied with unknown address type {}",
+                atyp
+            )))
+        }
+    };
+    let mut bound_address = vec![0u8; bound_address_len + 2];
+    stream.read_exact(&mut bound_address).await?;
+
+    Ok(())
+}
+
+async fn socks5_authenticate(
+    stream: &mut (impl AsyncRead + AsyncWrite + Unpin),
+    credentials: &ProxyCredentials,
+) -> Result<(), Error> {
+    let username = credentials.username.as_bytes();
+    let password = credentials.password.as_bytes();
+    if username.len() > 255 || password.len() > 255 {
+        return Err(Error::new(
+            ErrorKind::InvalidInput,
+            "SOCKS5 username and password can't be longer than 255 bytes",
+        ));
+    }
+
+    let mut request = Vec::with_capacity(3 + username.len() + password.len());
+    request.push(0x01);
+    request.push(username.len() as u8);
+    request.extend_from_slice(username);
+    request.push(password.len() as u8);
+    request.extend_from_slice(password);
+    stream.write_all(&request).await?;
+
+    let mut response = [0u8; 2];
+    stream.read_exact(&mut response).await?;
+    if response[1] != 0x00 {
+        return Err(proxy_error(
+            "SOCKS5 proxy rejected the credentials".to_string(),
+        ));
+    }
+    Ok(())
+}
+
+fn socks5_reply_description(reply: u8) -> &'static str {
+    match reply {
+        0x01 => "general SOCKS server failure",
+        0x02 => "connection not allowed by ruleset",
+        0x03 => "network unreachable",
+        0x04 => "host unreachable",
+        0x05 => "connection refused",
+        0x06 => "TTL expired",
+        0x07 => "command not supported",
+        0x08 => "address type not supported",
+        _ => "unknown error",
+    }
+}
+
+// Standard base64 with padding (RFC 4648), used for basic authentication.
+fn base64_encode(data: &[u8]) -> String {
+    const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+    let mut encoded = String::with_capacity(data.len() / 3 * 4 + 4);
+    for chunk in data.chunks(3) {
+        let bytes = [
+            chunk[0],
+            *chunk.get(1).unwrap_or(&0),
+            *chunk.get(2).unwrap_or(&0),
+        ];
+        let n = u32::from_be_bytes([0, bytes[0], bytes[1], bytes[2]]);
+        for i in 0..4 {
+            if i <= chunk.len() {
+                encoded.push(ALPHABET[((n >> (18 - 6 * i)) & 0x3f) as usize] as char);
+            } else {
+                encoded.push('=');
+            }
+        }
+    }
+    encoded
+}
+
+// Limits how much the driver reads while waiting for the end of the proxy's response headers
+const HTTP_MAX_RESPONSE_HEADER_LEN: usize = 8192;
+
+async fn http_connect_handshake(
+    stream: &mut (impl AsyncRead + AsyncWrite + Unpin),
+    target: SocketAddr,
+    credentials: Option<&ProxyCredentials>,
+) -> Result<(), Error> {
+    let mut request = format!("CONNECT {0} HTTP/1.1\r\nHost: {0}\r\n", target);
+    if let Some(credentials) = credentials {
+        let token =
+            base64_encode(format!("{}:{}", credentials.username, credentials.password).as_bytes());
+        request.push_str(&format!("Proxy-Authorization: Basic {}\r\n", token));
+    }
+    request.push_str("\r\n");
+    stream.write_all(request.as_bytes()).await?;
+
+    // Read byte by byte, so that nothing sent through the tunnel is consumed
+    let mut response = Vec::new();
+    while !response.ends_with(b"\r\n\r\n") {
+        if response.len() >= HTTP_MAX_RESPONSE_HEADER_LEN {
+            return Err(proxy_error(
+                "HTTP proxy response headers are too long".to_string(),
+            ));
+        }
+        response.push(stream.read_u8().await?);
+    }
+
+    let response = String::from_utf8_lossy(&response);
+    let status_line = response.lines().next().unwrap_or_default();
+    let status_code = status_line
+        .split_whitespace()
+        .nth(1)
+        .and_then(|code| code.parse::<u16>().ok());
+    match status_code {
+        Some(code) if (200..300).contains(&code) => Ok(()),
+        _ => Err(proxy_error(format!(
+            "HTTP proxy failed to connect to {}: {}",
+            target, status_line
+        ))),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{base64_encode, ProxyConfig};
+    use std::net::SocketAddr;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    fn proxy_address() -> SocketAddr {
+        "10.0.0.1:1080".parse().unwrap()
+    }
+
+    fn target() -> SocketAddr {
+        "192.168.1.2:9042".parse().unwrap()
+    }
+
+    #[tokio::test]
+    async fn socks5_handshake_with_credentials() {
+        let (mut client, mut server) = tokio::io::duplex(1024);
+        let proxy = ProxyConfig::socks5(proxy_address()).with_credentials("user", "pass");
+
+        let server = tokio::spawn(async move {
+            let mut greeting = [0u8; 4];
+            server.read_exact(&mut greeting).await.unwrap();
+            assert_eq!(greeting, [5, 2, 0, 2]);
+            server.write_all(&[5, 2]).await.unwrap();
+
+            let mut auth = [0u8; 11];
+            server.read_exact(&mut auth).await.unwrap();
+            assert_eq!(&auth, b"\x01\x04user\x04pass");
+            server.write_all(&[1, 0]).await.unwrap();
+
+            let mut request = [0u8; 10];
+            server.read_exact(&mut request).await.unwrap();
+            assert_eq!(request, [5, 1, 0, 1, 192, 168, 1, 2, 0x23, 0x52]);
+            server
+                .write_all(&[5, 0, 0, 1, 10, 0, 0, 1, 0x04, 0x38])
+                .await
+                .unwrap();
+
+            // Data sent through the tunnel is not consumed by the handshake
+            server.write_all(b"cql").await.unwrap();
+        });
+
+        proxy.handshake(&mut client, target()).await.unwrap();
+        server.await.unwrap();
+
+        let mut data = [0u8; 3];
+        client.read_exact(&mut data).await.unwrap();
+        assert_eq!(&data, b"cql");
+    }
+
+    #[tokio::test]
+    async fn socks5_connect_failure() {
+        let (mut client, mut server) = tokio::io::duplex(1024);
+        let proxy = ProxyConfig::socks5(proxy_address());
+
+        tokio::spawn(async move {
+            let mut greeting = [0u8; 3];
+            server.read_exact(&mut greeting).await.unwrap();
+            assert_eq!(greeting, [5, 1, 0]);
+            server.write_all(&[5, 0]).await.unwrap();
+
+            let mut request = [0u8; 10];
+            server.read_exact(&mut request).await.unwrap();
+            server
+                .write_all(&[5, 5, 0, 1, 0, 0, 0, 0, 0, 0])
+                .await
+                .unwrap();
+        });
+
+        let err = proxy.handshake(&mut client, target()).await.unwrap_err();
+        assert!(err.to_string().contains("connection refused"), "{}", err);
+    }
+
+    #[test]
+    fn base64_encoding() {
+        let cases = [
+            ("", ""),
+            ("f", "Zg=="),
+            ("fo", "Zm8="),
+            ("foo", "Zm9v"),
+            ("foob", "Zm9vYg=="),
+            ("fooba", "Zm9vYmE="),
+            ("foobar", "Zm9vYmFy"),
+            ("user:p\u{e4}ss?>", "dXNlcjpww6Rzcz8+"),
+        ];
+        for (data, expected) in cases {
+            assert_eq!(base64_encode(data.as_bytes()), expected);
+        }
+    }
+
+    #[tokio::test]
+    async fn http_connect_handshake() {
+        let (mut client, mut server) = tokio::io::duplex(1024);
+        let proxy = ProxyConfig::http_connect(proxy_address()).with_credentials("user", "pass");
+
+        let server = tokio::spawn(async move {
+            let expected = "CONNECT 192.168.1.2:9042 HTTP/1.1\r\n\
+                Host: 192.168.1.2:9042\r\n\
+                Proxy-Authorization: Basic dXNlcjpwYXNz\r\n\r\n";
+            let mut request = vec![0u8; expected.len()];
+            server.read_exact(&mut request).await.unwrap();
+            assert_eq!(String::from_utf8(request).unwrap(), expected);
+
+            server
+                .write_all(b"HTTP/1.1 200 Connection established\r\n\r\ncql")
+                .await
+                .unwrap();
+        });
+
+        proxy.handshake(&mut client, target()).await.unwrap();
+        server.await.unwrap();
+
+        let mut data = [0u8; 3];
+        client.read_exact(&mut data).await.unwrap();
+        assert_eq!(&data, b"cql");
+    }
+
+    #[tokio::test]
+    async fn http_connect_failure() {
+        let (mut client, mut server) = tokio::io::duplex(1024);
+        let proxy = ProxyConfig::http_connect(proxy_address());
+
+        tokio::spawn(async move {
+            let mut buf = [0u8; 256];
+            let _ = server.read(&mut buf).await.unwrap();
+            server
+                .write_all(b"HTTP/1.1 403 Forbidden\r\nContent-Length: 0\r\n\r\n")
+                .await
+                .unwrap();
+        });
+
+        let err = proxy.handshake(&mut client, target()).await.unwrap_err();
+        assert!(err.to_string().contains("403 Forbidden"), "{}", err);
+    }
+}
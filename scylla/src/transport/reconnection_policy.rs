@@ -0,0 +1,191 @@
+//! Reconnection delays configuration\
+//! When connections to a node break or cannot be opened, the driver tries
+//! to reopen them in the background. The `Session` decides how long to wait
+//! between the attempts using an object implementing the `ReconnectionPolicy` trait.
+
+use rand::Rng;
+use std::time::Duration;
+
+use crate::routing::Shard;
+use crate::transport::topology::UntranslatedEndpoint;
+
+/// Specifies a policy used to decide how long to wait between attempts
+/// to reopen connections to a node.
+///
+/// The policy is used by every per-node connection pool and by the control connection.
+/// Each of them asks for a separate schedule, so the delays can depend on the node
+/// and on the shard.
+pub trait ReconnectionPolicy: std::fmt::Debug + Send + Sync {
+    /// Called when a pool starts reconnecting, either after being created
+    /// or after a successful fill. The returned schedule is then used
+    /// until the pool fills successfully again.
+    ///
+    /// `endpoint` is the node the pool connects to. `shard` is `None` if the schedule
+    /// covers opening connections to the node as a whole, and `Some` if it covers
+    /// only reopening connections to the given shard through the shard-aware port.
+    fn new_schedule(
+        &self,
+        endpoint: &UntranslatedEndpoint,
+        shard: Option<Shard>,
+    ) -> Box<dyn ReconnectionSchedule>;
+}
+
+/// Used by a single connection pool to compute the delays between consecutive
+/// reconnection attempts.
+pub trait ReconnectionSchedule: Send + Sync {
+    /// Returns the delay before the next reconnection attempt.
+    /// The first call returns the delay used when no attempt has failed yet.
+    fn next_delay(&mut self) -> Duration;
+}
+
+/// Waits the same amount of time before each reconnection attempt.
+#[derive(Debug, Clone)]
+pub struct ConstantReconnectionPolicy {
+    /// The delay between reconnection attempts
+    pub delay: Duration,
+}
+
+impl ReconnectionPolicy for ConstantReconnectionPolicy {
+    fn new_schedule(
+        &self,
+        _endpoint: &UntranslatedEndpoint,
+        _shard: Option<Shard>,
+    ) -> Box<dyn ReconnectionSchedule> {
+        Box::new(ConstantReconnectionSchedule { delay: self.delay })
+    }
+}
+
+struct ConstantReconnectionSchedule {
+    delay: Duration,
+}
+
+impl ReconnectionSchedule for ConstantReconnectionSchedule {
+    fn next_delay(&mut self) -> Duration {
+        self.delay
+    }
+}
+
+/// Doubles the delay after each failed reconnection attempt, up to `max_delay`.
+///
+/// Each delay is shortened by a random fraction of itself, bounded by `jitter`.
+/// This prevents many clients from reconnecting in lockstep, e.g. after a node restart.
+#[derive(Debug, Clone)]
+pub struct ExponentialReconnectionPolicy {
+    /// The delay before the first attempt
+    pub base_delay: Duration,
+
+    /// The upper bound of the delay between attempts
+    pub max_delay: Duration,
+
+    /// The maximum fraction of the delay which is randomly subtracted from it,
+    /// between 0.0 (no jitter) and 1.0. The default is 0.5.
+    pub jitter: f64,
+}
+
+impl Default for ExponentialReconnectionPolicy {
+    fn default() -> Self {
+        Self {
+            base_delay: Duration::from_millis(50),
+            max_delay: Duration::from_secs(10),
+            jitter: 0.5,
+        }
+    }
+}
+
+impl ReconnectionPolicy for ExponentialReconnectionPolicy {
+    fn new_schedule(
+        &self,
+        _endpoint: &UntranslatedEndpoint,
+        _shard: Option<Shard>,
+    ) -> Box<dyn ReconnectionSchedule> {
+        Box::new(ExponentialReconnectionSchedule {
+            next_delay: self.base_delay,
+            max_delay: self.max_delay,
+            jitter: self.jitter.clamp(0.0, 1.0),
+        })
+    }
+}
+
+const EXPONENTIAL_BACKOFF_MULTIPLIER: u32 = 2;
+
+struct ExponentialReconnectionSchedule {
+    next_delay: Duration,
+    max_delay: Duration,
+    jitter: f64,
+}
+
+impl ReconnectionSchedule for ExponentialReconnectionSchedule {
+    fn next_delay(&mut self) -> Duration {
+        let delay = std::cmp::min(self.next_delay, self.max_delay);
+        self.next_delay = delay.saturating_mul(EXPONENTIAL_BACKOFF_MULTIPLIER);
+
+        if self.jitter > 0.0 {
+            let fraction = rand::thread_rng().gen_range(0.0..=self.jitter);
+            delay.mul_f64(1.0 - fraction)
+        } else {
+            delay
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{ConstantReconnectionPolicy, ExponentialReconnectionPolicy, ReconnectionPolicy};
+    use crate::transport::cluster::ContactPoint;
+    use crate::transport::topology::UntranslatedEndpoint;
+    use std::time::Duration;
+
+    fn endpoint() -> UntranslatedEndpoint {
+        UntranslatedEndpoint::ContactPoint(ContactPoint {
+            address: "127.0.0.1:9042".parse().unwrap(),
+            datacenter: None,
+            hostname: None,
+        })
+    }
+
+    #[test]
+    fn constant_policy() {
+        let policy = ConstantReconnectionPolicy {
+            delay: Duration::from_millis(300),
+        };
+        let mut schedule = policy.new_schedule(&endpoint(), None);
+        for _ in 0..5 {
+            assert_eq!(schedule.next_delay(), Duration::from_millis(300));
+        }
+    }
+
+    #[test]
+    fn exponential_policy() {
+        let policy = ExponentialReconnectionPolicy {
+            base_delay: Duration::from_millis(50),
+            max_delay: Duration::from_millis(500),
+            jitter: 0.0,
+        };
+        let mut schedule = policy.new_schedule(&endpoint(), None);
+        let delays: Vec<u128> = (0..6).map(|_| schedule.next_delay().as_millis()).collect();
+        assert_eq!(delays, vec![50, 100, 200, 400, 500, 500]);
+
+        // A new schedule starts from the base delay again
+        assert_eq!(
+            policy.new_schedule(&endpoint(), None).next_delay(),
+            Duration::from_millis(50)
+        );
+    }
+
+    #[test]
+    fn exponential_policy_with_jitter() {
+        let policy = ExponentialReconnectionPolicy {
+            base_delay: Duration::from_millis(100),
+            max_delay: Duration::from_secs(1),
+            jitter: 0.5,
+        };
+        let mut schedule = policy.new_schedule(&endpoint(), None);
+        let mut upper_bound = Duration::from_millis(100);
+        for _ in 0..10 {
+            let delay = schedule.next_delay();
+            assert!(delay <= upper_bound);
+            assert!(delay >= upper_bound / 2);
+            upper_bound = std::cmp::min(upper_bound * 2, Duration::from_secs(1));
+        }
+    }
+}
@@ -1,7 +1,7 @@
 //! `Session` is the main object used in the driver.\
 //! It manages all connections to the cluster and allows to perform queries.
 
-#[cfg(feature = "cloud")]
+#[cfg(any(feature = "cloud", feature = "cloud-rustls"))]
 use crate::cloud::CloudConfig;
 
 use crate::frame::types::LegacyConsistency;
@@ -11,16 +11,18 @@ use crate::retry_policy::RetryPolicy;
 use arc_swap::ArcSwapOption;
 use async_trait::async_trait;
 use bytes::Bytes;
-use futures::future::join_all;
 use futures::future::try_join_all;
+use futures::future::{self, join_all};
 pub use scylla_cql::errors::TranslationError;
 use scylla_cql::frame::response::result::Rows;
 use scylla_cql::frame::response::NonErrorResponse;
 use std::borrow::Borrow;
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::fmt::Display;
 use std::future::Future;
 use std::net::SocketAddr;
+#[cfg(unix)]
+use std::path::PathBuf;
 use std::str::FromStr;
 use std::sync::atomic::AtomicUsize;
 use std::sync::atomic::Ordering;
@@ -34,13 +36,13 @@ use uuid::Uuid;
 use super::cluster::ContactPoint;
 use super::connection::NonErrorQueryResponse;
 use super::connection::QueryResponse;
-#[cfg(feature = "ssl")]
+#[cfg(any(feature = "ssl", feature = "rustls"))]
 use super::connection::SslConfig;
 use super::errors::{BadQuery, NewSessionError, QueryError};
 use super::execution_profile::{ExecutionProfile, ExecutionProfileHandle, ExecutionProfileInner};
 use super::partitioner::PartitionerName;
 use super::topology::UntranslatedPeer;
-use super::NodeRef;
+use super::{NodeAddr, NodeRef};
 use crate::cql_to_rust::FromRow;
 use crate::frame::response::cql_to_rust::FromRowError;
 use crate::frame::response::result;
@@ -48,28 +50,40 @@ use crate::frame::value::{
     BatchValues, BatchValuesFirstSerialized, BatchValuesIterator, SerializedValues, ValueList,
 };
 use crate::prepared_statement::{PartitionKeyError, PreparedStatement};
-use crate::query::Query;
+use crate::query::{Query, RoutingHint};
 use crate::routing::Token;
 use crate::statement::{Consistency, SerialConsistency};
 use crate::tracing::{GetTracingConfig, TracingEvent, TracingInfo};
 use crate::transport::cluster::{Cluster, ClusterData, ClusterNeatDebug};
+#[cfg(unix)]
+use crate::transport::connection::unix_socket_node_address;
 use crate::transport::connection::{Connection, ConnectionConfig, VerifiedKeyspaceName};
 use crate::transport::connection_pool::PoolConfig;
 use crate::transport::host_filter::HostFilter;
 use crate::transport::iterator::{PreparedIteratorConfig, RowIterator};
-use crate::transport::load_balancing::{self, RoutingInfo};
+use crate::transport::load_balancing::{self, PlanExplanation, RoutingInfo};
+use crate::transport::locator::ReplicationStrategy;
 use crate::transport::metrics::Metrics;
 use crate::transport::node::Node;
+use crate::transport::proxy::ProxyConfig;
 use crate::transport::query_result::QueryResult;
+use crate::transport::reconnection_policy::{ExponentialReconnectionPolicy, ReconnectionPolicy};
 use crate::transport::retry_policy::{QueryInfo, RetryDecision, RetrySession};
 use crate::transport::speculative_execution;
+use crate::transport::token_range_scan::{
+    self, ScanConfig, TokenRangeScanIterator, TokenRangeScanOptions,
+};
 use crate::transport::Compression;
 use crate::{
     batch::{Batch, BatchStatement},
     statement::StatementConfig,
 };
 
-pub use crate::transport::connection_pool::PoolSize;
+pub use crate::transport::connection::{is_reserved_startup_option, ClientIdentity};
+pub use crate::transport::connection_pool::{AdaptivePoolSize, PoolSize};
+
+#[cfg(any(feature = "ssl", feature = "rustls"))]
+pub use crate::transport::connection::{ReloadableTlsContext, TlsContext, TlsContextProvider};
 
 use crate::authentication::AuthenticatorProvider;
 #[cfg(feature = "ssl")]
@@ -125,6 +139,7 @@ pub struct Session {
     auto_await_schema_agreement_timeout: Option<Duration>,
     refresh_metadata_on_auto_schema_agreement: bool,
     keyspace_name: ArcSwapOption<String>,
+    schema_agreement_scope: SchemaAgreementScope,
 }
 
 /// This implementation deliberately omits some details from Cluster in order
@@ -143,6 +158,7 @@ impl std::fmt::Debug for Session {
                 "auto_await_schema_agreement_timeout",
                 &self.auto_await_schema_agreement_timeout,
             )
+            .field("schema_agreement_scope", &self.schema_agreement_scope)
             .finish()
     }
 }
@@ -173,15 +189,44 @@ pub struct SessionConfig {
     #[cfg(feature = "ssl")]
     pub ssl_context: Option<SslContext>,
 
+    /// Provide our Session with TLS, using either the OpenSSL or the rustls backend.
+    /// Takes precedence over `ssl_context` if both are set.
+    #[cfg(any(feature = "ssl", feature = "rustls"))]
+    pub tls_context: Option<TlsContext>,
+
+    /// Provides the TLS context each time a connection is opened, which allows
+    /// to change it at runtime. Takes precedence over `tls_context` and `ssl_context`.
+    #[cfg(any(feature = "ssl", feature = "rustls"))]
+    pub tls_context_provider: Option<Arc<dyn TlsContextProvider>>,
+
+    /// If set, connection pools check with this interval whether the generation
+    /// of `tls_context_provider` changed, and gradually replace connections opened
+    /// with an older TLS context. A connection is closed only after a replacement
+    /// is ready and the requests already sent on it are completed.
+    /// If `None` (the default), open connections keep their TLS context.
+    #[cfg(any(feature = "ssl", feature = "rustls"))]
+    pub tls_reload_check_interval: Option<Duration>,
+
     pub authenticator: Option<Arc<dyn AuthenticatorProvider>>,
 
     pub schema_agreement_interval: Duration,
+
+    /// Nodes taken into account when checking for schema agreement,
+    /// both explicitly and automatically after schema-altering statements.
+    /// The default is [`SchemaAgreementScope::ConnectedNodes`].
+    pub schema_agreement_scope: SchemaAgreementScope,
+
     pub connect_timeout: Duration,
 
     /// Size of the per-node connection pool, i.e. how many connections the driver should keep to each node.
     /// The default is `PerShard(1)`, which is the recommended setting for Scylla clusters.
     pub connection_pool_size: PoolSize,
 
+    /// Decides how long to wait between attempts to reopen connections to a node,
+    /// both in per-node connection pools and for the control connection.
+    /// The default is [`ExponentialReconnectionPolicy`] with 50 ms base delay and 10 s max delay.
+    pub reconnection_policy: Arc<dyn ReconnectionPolicy>,
+
     /// If true, prevents the driver from connecting to the shard-aware port, even if the node supports it.
     /// Generally, this options is best left as default (false).
     pub disallow_shard_aware_port: bool,
@@ -211,12 +256,32 @@ pub struct SessionConfig {
     /// re-establishing the control connection.
     pub host_filter: Option<Arc<dyn HostFilter>>,
 
+    /// How often the contact points given as hostnames are resolved again.
+    /// Newly resolved addresses replace the old ones and are used when the control connection
+    /// has to be re-established. Regardless of this setting, the hostnames are also resolved
+    /// again when the control connection cannot be re-established to any known node.
+    /// If `None` (the default), hostnames are not re-resolved periodically.
+    pub hostname_resolution_interval: Option<Duration>,
+
+    /// Implementations of replication strategies unknown to the driver, by strategy class name.
+    /// They are used to find replicas of keyspaces using these strategies.
+    pub replication_strategies: HashMap<String, Arc<dyn ReplicationStrategy>>,
+
+    /// If set, all connections are opened through this SOCKS5 or HTTP CONNECT proxy.
+    /// The shard-aware port is not used then, because source ports can't be chosen.
+    /// The default is `None`.
+    pub proxy: Option<ProxyConfig>,
+
+    /// Identification of the application, sent to the server when opening connections.
+    /// Empty by default.
+    pub client_identity: ClientIdentity,
+
     /// If true, full schema metadata is fetched after successfully reaching a schema agreement.
     /// It is true by default but can be disabled if successive schema-altering statements should be performed.
     pub refresh_metadata_on_auto_schema_agreement: bool,
 
     // If the driver is to connect to ScyllaCloud, there is a config for it.
-    #[cfg(feature = "cloud")]
+    #[cfg(any(feature = "cloud", feature = "cloud-rustls"))]
     pub(crate) cloud_config: Option<Arc<CloudConfig>>,
 
     /// If true, the driver will inject a small delay before flushing data
@@ -238,11 +303,16 @@ pub struct SessionConfig {
 pub enum KnownNode {
     Hostname(String),
     Address(SocketAddr),
-    #[cfg(feature = "cloud")]
+    #[cfg(any(feature = "cloud", feature = "cloud-rustls"))]
     CloudEndpoint(CloudEndpoint),
+    /// Path of a Unix domain socket of a single node, e.g. Scylla's maintenance socket.
+    /// It has to be the only known node. The driver connects only to this node,
+    /// without using TLS or the shard-aware port, and doesn't discover other nodes.
+    #[cfg(unix)]
+    UnixSocket(PathBuf),
 }
 
-#[cfg(feature = "cloud")]
+#[cfg(any(feature = "cloud", feature = "cloud-rustls"))]
 #[non_exhaustive]
 #[derive(Clone, PartialEq, Eq, PartialOrd, Ord, Debug)]
 pub struct CloudEndpoint {
@@ -268,15 +338,23 @@ impl SessionConfig {
             tcp_nodelay: true,
             tcp_keepalive_interval: None,
             schema_agreement_interval: Duration::from_millis(200),
+            schema_agreement_scope: SchemaAgreementScope::ConnectedNodes,
             default_execution_profile_handle: ExecutionProfile::new_from_inner(Default::default())
                 .into_handle(),
             used_keyspace: None,
             keyspace_case_sensitive: false,
             #[cfg(feature = "ssl")]
             ssl_context: None,
+            #[cfg(any(feature = "ssl", feature = "rustls"))]
+            tls_context: None,
+            #[cfg(any(feature = "ssl", feature = "rustls"))]
+            tls_context_provider: None,
+            #[cfg(any(feature = "ssl", feature = "rustls"))]
+            tls_reload_check_interval: None,
             authenticator: None,
             connect_timeout: Duration::from_secs(5),
             connection_pool_size: Default::default(),
+            reconnection_policy: Arc::new(ExponentialReconnectionPolicy::default()),
             disallow_shard_aware_port: false,
             keyspaces_to_fetch: Vec::new(),
             fetch_schema_metadata: true,
@@ -285,8 +363,12 @@ impl SessionConfig {
             auto_await_schema_agreement_timeout: Some(std::time::Duration::from_secs(60)),
             address_translator: None,
             host_filter: None,
+            hostname_resolution_interval: None,
+            replication_strategies: HashMap::new(),
+            proxy: None,
+            client_identity: ClientIdentity::default(),
             refresh_metadata_on_auto_schema_agreement: true,
-            #[cfg(feature = "cloud")]
+            #[cfg(any(feature = "cloud", feature = "cloud-rustls"))]
             cloud_config: None,
             enable_write_coalescing: true,
         }
@@ -318,6 +400,19 @@ impl SessionConfig {
         self.known_nodes.push(KnownNode::Address(node_addr));
     }
 
+    /// Adds a known database server reachable through a Unix domain socket.
+    /// See [`KnownNode::UnixSocket`].
+    /// # Example
+    /// ```
+    /// # use scylla::SessionConfig;
+    /// let mut config = SessionConfig::new();
+    /// config.add_known_node_unix_socket("/var/lib/scylla/cql.m");
+    /// ```
+    #[cfg(unix)]
+    pub fn add_known_node_unix_socket(&mut self, path: impl Into<PathBuf>) {
+        self.known_nodes.push(KnownNode::UnixSocket(path.into()));
+    }
+
     /// Adds a list of known database server with hostnames.
     /// If the port is not explicitly specified, 9042 is used as default
     /// # Example
@@ -354,6 +449,48 @@ impl SessionConfig {
     }
 }
 
+/// Determines which nodes have to agree on the schema version
+/// for the schema to be considered in agreement.
+///
+/// Nodes disabled by the [`HostFilter`] and nodes to which the driver has no working
+/// connection are never waited for.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum SchemaAgreementScope {
+    /// All nodes the session is connected to.
+    ConnectedNodes,
+    /// Only the nodes from the given datacenter.
+    /// Useful when a remote datacenter is unavailable and DDL should proceed anyway.
+    Datacenter(String),
+}
+
+impl SchemaAgreementScope {
+    fn includes(&self, node: &Node) -> bool {
+        match self {
+            SchemaAgreementScope::ConnectedNodes => true,
+            SchemaAgreementScope::Datacenter(dc) => node.datacenter.as_ref() == Some(dc),
+        }
+    }
+}
+
+/// Schema version reported by a single node, see [`Session::fetch_schema_versions`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct NodeSchemaVersion {
+    pub host_id: Uuid,
+    pub address: NodeAddr,
+    pub datacenter: Option<String>,
+    /// Schema version read from the node's `system.local` table,
+    /// or `None` if the node couldn't be reached.
+    pub version: Option<Uuid>,
+}
+
+impl NodeSchemaVersion {
+    pub fn is_reachable(&self) -> bool {
+        self.version.is_some()
+    }
+}
+
 /// Creates default [`SessionConfig`], same as [`SessionConfig::new`]
 impl Default for SessionConfig {
     fn default() -> Self {
@@ -426,7 +563,7 @@ impl Session {
     pub async fn connect(config: SessionConfig) -> Result<Session, NewSessionError> {
         let known_nodes = config.known_nodes;
 
-        #[cfg(feature = "cloud")]
+        #[cfg(any(feature = "cloud", feature = "cloud-rustls"))]
         let known_nodes = if let Some(cloud_servers) =
             config.cloud_config.as_ref().map(|cloud_config| {
                 cloud_config
@@ -450,6 +587,17 @@ impl Session {
             return Err(NewSessionError::EmptyKnownNodesList);
         }
 
+        #[cfg(unix)]
+        let unix_socket_path = match known_nodes.iter().find_map(|node| match node {
+            KnownNode::UnixSocket(path) => Some(path.clone()),
+            _ => None,
+        }) {
+            Some(_) if known_nodes.len() > 1 => {
+                return Err(NewSessionError::UnixSocketWithOtherKnownNodes)
+            }
+            path => path,
+        };
+
         // Find IP addresses of all known nodes passed in the config
         let mut initial_peers: Vec<ContactPoint> = Vec::with_capacity(known_nodes.len());
 
@@ -461,48 +609,96 @@ impl Session {
                 KnownNode::Address(address) => initial_peers.push(ContactPoint {
                     address,
                     datacenter: None,
+                    hostname: None,
                 }),
-                #[cfg(feature = "cloud")]
+                #[cfg(any(feature = "cloud", feature = "cloud-rustls"))]
                 KnownNode::CloudEndpoint(CloudEndpoint {
                     hostname,
                     datacenter,
                 }) => to_resolve.push((hostname, Some(datacenter))),
+                #[cfg(unix)]
+                KnownNode::UnixSocket(_) => initial_peers.push(ContactPoint {
+                    address: unix_socket_node_address(),
+                    datacenter: None,
+                    hostname: None,
+                }),
             };
         }
+        // Every address the hostname resolves to becomes a separate contact point
         let resolve_futures = to_resolve
             .into_iter()
             .map(|(hostname, datacenter)| async move {
-                Ok::<_, NewSessionError>(ContactPoint {
-                    address: resolve_hostname(&hostname).await?,
-                    datacenter,
-                })
+                let addresses = resolve_hostname_all(&hostname).await?;
+                Ok::<_, NewSessionError>(
+                    addresses
+                        .into_iter()
+                        .map(|address| ContactPoint {
+                            address,
+                            datacenter: datacenter.clone(),
+                            hostname: Some(hostname.clone()),
+                        })
+                        .collect::<Vec<_>>(),
+                )
             });
-        let resolved: Vec<ContactPoint> = futures::future::try_join_all(resolve_futures).await?;
-        initial_peers.extend(resolved);
+        let resolved: Vec<Vec<ContactPoint>> =
+            futures::future::try_join_all(resolve_futures).await?;
+        initial_peers.extend(resolved.into_iter().flatten());
+
+        // `tls_context` takes precedence over the OpenSSL-specific `ssl_context`
+        #[cfg(feature = "ssl")]
+        let tls_context = config
+            .tls_context
+            .or_else(|| config.ssl_context.map(TlsContext::from));
+        #[cfg(all(feature = "rustls", not(feature = "ssl")))]
+        let tls_context = config.tls_context;
+
+        #[cfg(any(feature = "ssl", feature = "rustls"))]
+        let ssl_config = config
+            .tls_context_provider
+            .map(SslConfig::new_with_provider)
+            .or_else(|| tls_context.map(SslConfig::new_with_global_context));
+        // The socket is local, so there is nothing to encrypt
+        #[cfg(all(unix, any(feature = "ssl", feature = "rustls")))]
+        let ssl_config = ssl_config.filter(|_| unix_socket_path.is_none());
 
         let connection_config = ConnectionConfig {
             compression: config.compression,
             tcp_nodelay: config.tcp_nodelay,
             tcp_keepalive_interval: config.tcp_keepalive_interval,
-            #[cfg(feature = "ssl")]
-            ssl_config: config.ssl_context.map(SslConfig::new_with_global_context),
+            #[cfg(any(feature = "ssl", feature = "rustls"))]
+            ssl_config,
             authenticator: config.authenticator.clone(),
             connect_timeout: config.connect_timeout,
             event_sender: None,
             default_consistency: Default::default(),
             address_translator: config.address_translator,
-            #[cfg(feature = "cloud")]
+            #[cfg(any(feature = "cloud", feature = "cloud-rustls"))]
             cloud_config: config.cloud_config,
             enable_write_coalescing: config.enable_write_coalescing,
             keepalive_interval: config.keepalive_interval,
             keepalive_timeout: config.keepalive_timeout,
+            #[cfg(unix)]
+            unix_socket_path,
+            proxy: config.proxy,
+            client_identity: config.client_identity,
         };
 
+        // All connections through the socket end up at the same port anyway,
+        // and a proxy doesn't let the driver choose the source port
+        let can_use_shard_aware_port = !config.disallow_shard_aware_port
+            && !connection_config.uses_unix_socket()
+            && connection_config.proxy.is_none();
+
         let pool_config = PoolConfig {
             connection_config,
             pool_size: config.connection_pool_size,
-            can_use_shard_aware_port: !config.disallow_shard_aware_port,
+            can_use_shard_aware_port,
             keepalive_interval: config.keepalive_interval,
+            reconnection_policy: config.reconnection_policy,
+            #[cfg(any(feature = "ssl", feature = "rustls"))]
+            tls_reload_check_interval: config.tls_reload_check_interval,
+            #[cfg(not(any(feature = "ssl", feature = "rustls")))]
+            tls_reload_check_interval: None,
         };
 
         let cluster = Cluster::new(
@@ -511,6 +707,8 @@ impl Session {
             config.keyspaces_to_fetch,
             config.fetch_schema_metadata,
             config.host_filter,
+            config.hostname_resolution_interval,
+            config.replication_strategies,
         )
         .await?;
 
@@ -525,6 +723,7 @@ impl Session {
             refresh_metadata_on_auto_schema_agreement: config
                 .refresh_metadata_on_auto_schema_agreement,
             keyspace_name: ArcSwapOption::default(), // will be set by use_keyspace
+            schema_agreement_scope: config.schema_agreement_scope,
         };
 
         if let Some(keyspace_name) = config.used_keyspace {
@@ -604,14 +803,21 @@ impl Session {
     ) -> Result<QueryResult, QueryError> {
         let query: Query = query.into();
         let serialized_values = values.serialized()?;
+        let token = self.calculate_query_token(&query)?;
+        let token_ref = &token;
 
         let span = RequestSpan::new_query(&query.contents, serialized_values.size());
         let run_query_result = self
             .run_query(
-                RoutingInfo::default(),
+                self.query_routing_info(&query, token.clone()),
                 &query.config,
                 query.get_retry_policy().map(|rp| &**rp),
-                |node: Arc<Node>| async move { node.random_connection().await },
+                |node: Arc<Node>| async move {
+                    match token_ref {
+                        Some(token) => node.connection_for_token(token.clone()).await,
+                        None => node.random_connection().await,
+                    }
+                },
                 |connection: Arc<Connection>,
                  consistency: Consistency,
                  execution_profile: &ExecutionProfileInner| {
@@ -743,6 +949,8 @@ impl Session {
     ) -> Result<RowIterator, QueryError> {
         let query: Query = query.into();
         let serialized_values = values.serialized()?;
+        let token = self.calculate_query_token(&query)?;
+        let cluster_data = self.cluster.get_data();
 
         let execution_profile = query
             .get_execution_profile_handle()
@@ -752,8 +960,9 @@ impl Session {
         RowIterator::new_for_query(
             query,
             serialized_values.into_owned(),
+            token,
             execution_profile,
-            self.cluster.get_data(),
+            cluster_data,
             self.metrics.clone(),
         )
         .await
@@ -834,10 +1043,11 @@ impl Session {
                 .extend(statement.prepare_tracing_ids);
         }
 
+        let cluster_data = self.cluster.get_data();
         prepared.set_partitioner_name(
-            self.extract_partitioner_name(&prepared, &self.cluster.get_data())
+            self.extract_partitioner_name(&prepared, &cluster_data)
                 .and_then(PartitionerName::from_str)
-                .unwrap_or_default(),
+                .unwrap_or_else(|| cluster_data.partitioner.clone()),
         );
 
         Ok(prepared)
@@ -922,25 +1132,21 @@ impl Session {
         let token = partition_key
             .as_ref()
             .map(|pk| prepared.get_partitioner_name().hash(pk));
+        let token_ref = &token;
 
-        let statement_info = RoutingInfo {
-            consistency: prepared
-                .get_consistency()
-                .unwrap_or(self.default_execution_profile_handle.access().consistency),
-            serial_consistency: prepared.get_serial_consistency(),
-            token,
-            keyspace: prepared.get_keyspace_name(),
-            is_confirmed_lwt: prepared.is_confirmed_lwt(),
-        };
+        let statement_info = self.prepared_routing_info(prepared, token.clone());
 
-        let span =
-            RequestSpan::new_prepared(partition_key.as_ref(), token, serialized_values.size());
+        let span = RequestSpan::new_prepared(
+            partition_key.as_ref(),
+            token.as_ref(),
+            serialized_values.size(),
+        );
 
         if !span.span().is_disabled() {
-            if let (Some(keyspace), Some(token)) = (statement_info.keyspace.as_ref(), token) {
+            if let (Some(keyspace), Some(token)) = (statement_info.keyspace.as_ref(), token_ref) {
                 let cluster_data = self.get_cluster_data();
                 let replicas: smallvec::SmallVec<[_; 8]> = cluster_data
-                    .get_token_endpoints_iter(keyspace, token)
+                    .get_token_endpoints_iter(keyspace, token.clone())
                     .collect();
                 span.record_replicas(&replicas)
             }
@@ -952,8 +1158,8 @@ impl Session {
                 &prepared.config,
                 prepared.get_retry_policy().map(|rp| &**rp),
                 |node: Arc<Node>| async move {
-                    match token {
-                        Some(token) => node.connection_for_token(token).await,
+                    match token_ref {
+                        Some(token) => node.connection_for_token(token.clone()).await,
                         None => node.random_connection().await,
                     }
                 },
@@ -1070,6 +1276,110 @@ impl Session {
         .await
     }
 
+    /// Reads all rows of a table, split into token ranges read concurrently.
+    ///
+    /// The token ring is split into ranges between the tokens of the nodes (vnodes), and by
+    /// default further at the shard boundaries of the nodes owning them. Each range is read with
+    /// a paged `SELECT ... WHERE token(pk) > ? AND token(pk) <= ?` query, routed to the replicas
+    /// owning the range. At most [`concurrency`](TokenRangeScanOptions::concurrency) ranges are
+    /// read at the same time, and rows are returned as they arrive, so they are not ordered.
+    ///
+    /// The partition key of the table is taken from the schema metadata, so schema metadata
+    /// fetching must be enabled. Only partitioners with 64-bit tokens (`Murmur3Partitioner`
+    /// and `CDCPartitioner`) are supported.
+    ///
+    /// The progress of the scan can be saved with [`TokenRangeScanIterator::checkpoint`]
+    /// and continued later with [`TokenRangeScanOptions::resume_from`].
+    ///
+    /// # Example
+    /// ```rust
+    /// # use scylla::Session;
+    /// # use std::error::Error;
+    /// # async fn check_only_compiles(session: &Session) -> Result<(), Box<dyn Error>> {
+    /// use futures::StreamExt;
+    /// use scylla::transport::token_range_scan::TokenRangeScanOptions;
+    ///
+    /// let options = TokenRangeScanOptions::new()
+    ///     .columns(vec!["a".to_string(), "b".to_string()])
+    ///     .concurrency(32);
+    /// let mut rows = session.scan_table::<(i32, i32)>("ks", "tab", options).await?;
+    ///
+    /// while let Some(row) = rows.next().await {
+    ///     let (a, b) = row?;
+    ///     println!("a, b: {}, {}", a, b);
+    /// }
+    /// assert!(rows.checkpoint().is_finished());
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn scan_table<RowT: FromRow>(
+        &self,
+        keyspace: &str,
+        table: &str,
+        options: TokenRangeScanOptions,
+    ) -> Result<TokenRangeScanIterator<RowT>, QueryError> {
+        let cluster_data = self.cluster.get_data();
+        let table_metadata = cluster_data
+            .get_keyspace_info()
+            .get(keyspace)
+            .and_then(|keyspace| keyspace.tables.get(table))
+            .ok_or_else(|| {
+                QueryError::BadQuery(BadQuery::Other(format!(
+                    "Table {}.{} not found in the schema metadata",
+                    keyspace, table
+                )))
+            })?;
+
+        let partitioner = cluster_data.table_partitioner(keyspace, table);
+        if !matches!(partitioner, PartitionerName::Murmur3 | PartitionerName::CDC) {
+            return Err(QueryError::BadQuery(BadQuery::Other(format!(
+                "Token range scans require a partitioner with 64-bit tokens, {}.{} uses {:?}",
+                keyspace, table, partitioner
+            ))));
+        }
+
+        let query = token_range_scan::scan_query(
+            keyspace,
+            table,
+            &table_metadata.partition_key,
+            options.columns.as_deref(),
+        );
+        let mut prepared = self.prepare(query).await?;
+        if let Some(page_size) = options.page_size {
+            prepared.set_page_size(page_size);
+        }
+        if let Some(consistency) = options.consistency {
+            prepared.set_consistency(consistency);
+        }
+        if let Some(handle) = options.execution_profile_handle.clone() {
+            prepared.set_execution_profile_handle(Some(handle));
+        }
+
+        let execution_profile = prepared
+            .get_execution_profile_handle()
+            .unwrap_or_else(|| self.get_default_execution_profile_handle())
+            .access();
+
+        let ranges = match options.resume_from {
+            Some(checkpoint) => checkpoint.ranges().to_vec(),
+            None => token_range_scan::token_ranges(
+                cluster_data.replica_locator(),
+                options.split_by_shards,
+            ),
+        };
+
+        Ok(TokenRangeScanIterator::new(
+            ScanConfig {
+                prepared,
+                execution_profile,
+                cluster_data: self.cluster.data_handle(),
+                metrics: self.metrics.clone(),
+            },
+            ranges,
+            options.concurrency,
+        ))
+    }
+
     /// Perform a batch query\
     /// Batch contains many `simple` or `prepared` queries which are executed at once\
     /// Batch doesn't return any rows
@@ -1135,7 +1445,8 @@ impl Session {
             }
             _ => RoutingInfo::default(),
         };
-        let first_value_token = statement_info.token;
+        let first_value_token = statement_info.token.clone();
+        let first_value_token_ref = &first_value_token;
 
         // Reuse first serialized value when serializing query, and delegate to `BatchValues::write_next_to_request`
         // directly for others (if they weren't already serialized, possibly don't even allocate the `SerializedValues`)
@@ -1150,9 +1461,9 @@ impl Session {
                 &batch.config,
                 batch.get_retry_policy().map(|rp| &**rp),
                 |node: Arc<Node>| async move {
-                    match first_value_token {
+                    match first_value_token_ref {
                         Some(first_value_token) => {
-                            node.connection_for_token(first_value_token).await
+                            node.connection_for_token(first_value_token.clone()).await
                         }
                         None => node.random_connection().await,
                     }
@@ -1322,6 +1633,12 @@ impl Session {
             .await
     }
 
+    /// Returns a reader of system and virtual tables, which returns their rows as structs.
+    /// See [`SystemTables`](crate::system::SystemTables).
+    pub fn system_tables(&self) -> crate::system::SystemTables<'_> {
+        crate::system::SystemTables::new(self)
+    }
+
     /// Gets the name of the keyspace that is currently set, or `None` if no
     /// keyspace was set.
     ///
@@ -1437,15 +1754,115 @@ impl Session {
     // will be returned.
     pub fn estimate_replicas_for_query(&self, statement: &RoutingInfo) -> Vec<Arc<Node>> {
         let cluster_data = self.cluster.get_data();
-        match statement.token {
+        match &statement.token {
             Some(token) => {
                 let cluster_data = self.cluster.get_data();
-                cluster_data.get_token_endpoints(statement.keyspace.unwrap_or(""), token)
+                cluster_data.get_token_endpoints(statement.keyspace.unwrap_or(""), token.clone())
             }
             None => cluster_data.get_nodes_info().to_owned(),
         }
     }
 
+    /// Explains how a prepared statement with the given values would be routed:
+    /// returns the computed token, the replicas and the load balancing plan of
+    /// the statement's execution profile, with annotations of each node of the plan.
+    ///
+    /// Nothing is sent to the cluster, the plan is computed from the current cluster metadata.
+    ///
+    /// # Example
+    /// ```rust
+    /// # use scylla::Session;
+    /// # use std::error::Error;
+    /// # async fn check_only_compiles(session: &Session) -> Result<(), Box<dyn Error>> {
+    /// let prepared = session
+    ///     .prepare("SELECT a, b FROM ks.tab WHERE a = ?")
+    ///     .await?;
+    ///
+    /// let explanation = session.explain_plan(&prepared, (42,))?;
+    /// println!("{} gives token {:?}", explanation.policy_name, explanation.token);
+    /// for planned in &explanation.plan {
+    ///     println!(
+    ///         "{} shard={:?} replica={} local_dc={:?} down={}",
+    ///         planned.node.address,
+    ///         planned.shard,
+    ///         planned.is_replica,
+    ///         planned.policy.is_local_datacenter,
+    ///         planned.is_down,
+    ///     );
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn explain_plan(
+        &self,
+        prepared: &PreparedStatement,
+        values: impl ValueList,
+    ) -> Result<PlanExplanation, QueryError> {
+        let serialized_values = values.serialized()?;
+        let token = self.calculate_token(prepared, &serialized_values)?;
+        let routing_info = self.prepared_routing_info(prepared, token);
+
+        Ok(self.explain_routing(&prepared.config, &routing_info))
+    }
+
+    /// Explains how an unprepared statement would be routed, like [`Session::explain_plan`].
+    /// Unprepared statements are token-aware only if they have a
+    /// [routing hint](crate::query::Query::set_routing_hint), otherwise no token
+    /// or replicas are computed.
+    pub fn explain_query_plan(&self, query: &Query) -> Result<PlanExplanation, QueryError> {
+        let token = self.calculate_query_token(query)?;
+        let routing_info = self.query_routing_info(query, token);
+
+        Ok(self.explain_routing(&query.config, &routing_info))
+    }
+
+    fn explain_routing(
+        &self,
+        statement_config: &StatementConfig,
+        routing_info: &RoutingInfo,
+    ) -> PlanExplanation {
+        let execution_profile = statement_config
+            .execution_profile_handle
+            .as_ref()
+            .unwrap_or_else(|| self.get_default_execution_profile_handle())
+            .access();
+        let cluster_data = self.cluster.get_data();
+
+        load_balancing::explain_plan(
+            execution_profile.load_balancing_policy.as_ref(),
+            routing_info,
+            &cluster_data,
+            statement_config.node_target.as_ref(),
+        )
+    }
+
+    fn query_routing_info<'q>(&self, query: &'q Query, token: Option<Token>) -> RoutingInfo<'q> {
+        let consistency = query.get_consistency().unwrap_or_else(|| {
+            query
+                .get_execution_profile_handle()
+                .unwrap_or_else(|| self.get_default_execution_profile_handle())
+                .access()
+                .consistency
+        });
+        query.routing_info(token, consistency)
+    }
+
+    fn prepared_routing_info<'p>(
+        &self,
+        prepared: &'p PreparedStatement,
+        token: Option<Token>,
+    ) -> RoutingInfo<'p> {
+        RoutingInfo {
+            consistency: prepared
+                .get_consistency()
+                .unwrap_or(self.default_execution_profile_handle.access().consistency),
+            serial_consistency: prepared.get_serial_consistency(),
+            token,
+            keyspace: prepared.get_keyspace_name(),
+            is_confirmed_lwt: prepared.is_confirmed_lwt(),
+        }
+    }
+
     // This method allows to easily run a query using load balancing, retry policy etc.
     // Requires some information about the query and two closures
     // First closure is used to choose a connection
@@ -1486,10 +1903,25 @@ impl Session {
 
         let load_balancer = &execution_profile.load_balancing_policy;
 
+        // A statement sent directly to a node bypasses the load balancer
+        let node_target = statement_config.node_target.as_ref();
+        let choose_connection = |node: Arc<Node>| match node_target.and_then(|t| t.shard) {
+            Some(shard) => {
+                future::Either::Left(async move { node.connection_to_shard(shard).await })
+            }
+            None => future::Either::Right(choose_connection(node)),
+        };
+
         let runner = async {
             let cluster_data = self.cluster.get_data();
-            let query_plan =
-                load_balancing::Plan::new(load_balancer.as_ref(), &statement_info, &cluster_data);
+            let query_plan = match node_target {
+                Some(target) => itertools::Either::Left(std::iter::once(&target.node)),
+                None => itertools::Either::Right(load_balancing::Plan::new(
+                    load_balancer.as_ref(),
+                    &statement_info,
+                    &cluster_data,
+                )),
+            };
 
             // If a speculative execution policy is used to run query, query_plan has to be shared
             // between different async functions. This struct helps to wrap query_plan in mutex so it
@@ -1676,8 +2108,11 @@ impl Session {
                 );
                 let attempt_id: Option<history::AttemptId> =
                     context.log_attempt_start(connection.get_connect_address());
+                let consistency = execution_profile
+                    .load_balancing_policy
+                    .consistency_for_node(context.query_info, node, current_consistency);
                 let query_result: Result<ResT, QueryError> =
-                    do_query(connection, current_consistency, execution_profile)
+                    do_query(connection, consistency, execution_profile)
                         .instrument(span.clone())
                         .await;
 
@@ -1750,20 +2185,46 @@ impl Session {
         last_error.map(Result::Err)
     }
 
+    /// Waits until the schema is in agreement, checking it every `schema_agreement_interval`.
+    /// Uses the [`SchemaAgreementScope`] configured for the session.
     pub async fn await_schema_agreement(&self) -> Result<(), QueryError> {
-        while !self.check_schema_agreement().await? {
+        self.await_schema_agreement_in_scope(&self.schema_agreement_scope)
+            .await
+    }
+
+    /// Same as [`Session::await_schema_agreement`], but only the nodes from `scope` are taken into account.
+    pub async fn await_schema_agreement_in_scope(
+        &self,
+        scope: &SchemaAgreementScope,
+    ) -> Result<(), QueryError> {
+        while !self.check_schema_agreement_in_scope(scope).await? {
             tokio::time::sleep(self.schema_agreement_interval).await
         }
         Ok(())
     }
 
+    /// Waits for schema agreement for at most `timeout_duration`.
+    /// Returns `false` if the agreement was not reached in time.
     pub async fn await_timed_schema_agreement(
         &self,
         timeout_duration: Duration,
     ) -> Result<bool, QueryError> {
-        timeout(timeout_duration, self.await_schema_agreement())
+        self.await_timed_schema_agreement_in_scope(&self.schema_agreement_scope, timeout_duration)
             .await
-            .map_or(Ok(false), |res| res.and(Ok(true)))
+    }
+
+    /// Same as [`Session::await_timed_schema_agreement`], but only the nodes from `scope` are taken into account.
+    pub async fn await_timed_schema_agreement_in_scope(
+        &self,
+        scope: &SchemaAgreementScope,
+        timeout_duration: Duration,
+    ) -> Result<bool, QueryError> {
+        timeout(
+            timeout_duration,
+            self.await_schema_agreement_in_scope(scope),
+        )
+        .await
+        .map_or(Ok(false), |res| res.and(Ok(true)))
     }
 
     async fn schema_agreement_auxiliary<ResT, QueryFut>(
@@ -1801,15 +2262,86 @@ impl Session {
         }
     }
 
+    /// Checks whether all nodes report the same schema version.
+    /// Uses the [`SchemaAgreementScope`] configured for the session.
     pub async fn check_schema_agreement(&self) -> Result<bool, QueryError> {
-        let connections = self.cluster.get_working_connections().await?;
+        self.check_schema_agreement_in_scope(&self.schema_agreement_scope)
+            .await
+    }
+
+    /// Checks whether all nodes from `scope` which the driver is connected to
+    /// report the same schema version.
+    ///
+    /// Nodes without a working connection are skipped. Fails if fetching the version
+    /// fails on any connected node, if none of the nodes in scope is connected,
+    /// or if there are no nodes in scope at all.
+    pub async fn check_schema_agreement_in_scope(
+        &self,
+        scope: &SchemaAgreementScope,
+    ) -> Result<bool, QueryError> {
+        let cluster_data = self.cluster.get_data();
+        let mut connections = Vec::new();
+        let mut last_error: Option<QueryError> = None;
+        for node in cluster_data
+            .get_nodes_info()
+            .iter()
+            .filter(|node| node.is_enabled() && scope.includes(node))
+        {
+            match node.random_connection().await {
+                Ok(connection) => connections.push(connection),
+                Err(e) => last_error = Some(e),
+            }
+        }
+
+        if connections.is_empty() {
+            return Err(last_error.unwrap_or_else(|| {
+                QueryError::BadQuery(BadQuery::Other(format!(
+                    "No nodes in schema agreement scope {:?}",
+                    scope
+                )))
+            }));
+        }
+
+        let versions = try_join_all(connections.iter().map(|c| c.fetch_schema_version())).await?;
+        Ok(versions.windows(2).all(|w| w[0] == w[1]))
+    }
+
+    /// Fetches the schema version reported by each node known to the driver.
+    ///
+    /// Nodes which couldn't be reached, including the ones disabled by the host filter,
+    /// are returned with `version` set to `None`.
+    pub async fn fetch_schema_versions(&self) -> Vec<NodeSchemaVersion> {
+        let cluster_data = self.cluster.get_data();
+        Self::fetch_node_schema_versions(cluster_data.get_nodes_info().iter()).await
+    }
 
-        let handles = connections.iter().map(|c| c.fetch_schema_version());
-        let versions = try_join_all(handles).await?;
+    async fn fetch_node_schema_versions<'a>(
+        nodes: impl Iterator<Item = &'a Arc<Node>>,
+    ) -> Vec<NodeSchemaVersion> {
+        let fetches = nodes.map(|node| async move {
+            let version = match node.random_connection().await {
+                Ok(connection) => match connection.fetch_schema_version().await {
+                    Ok(version) => Some(version),
+                    Err(e) => {
+                        debug!(
+                            "Failed to fetch schema version from node {}: {}",
+                            node.address, e
+                        );
+                        None
+                    }
+                },
+                Err(_) => None,
+            };
 
-        let local_version: Uuid = versions[0];
-        let in_agreement = versions.into_iter().all(|v| v == local_version);
-        Ok(in_agreement)
+            NodeSchemaVersion {
+                host_id: node.host_id,
+                address: node.address,
+                datacenter: node.datacenter.clone(),
+                version,
+            }
+        });
+
+        join_all(fetches).await
     }
 
     pub async fn fetch_schema_version(&self) -> Result<Uuid, QueryError> {
@@ -1849,6 +2381,28 @@ impl Session {
         }
     }
 
+    /// Computes the token of an unprepared query from its
+    /// [routing hint](crate::query::Query::set_routing_hint).
+    /// Returns `None` if the query has no routing hint.
+    pub fn calculate_query_token(&self, query: &Query) -> Result<Option<Token>, QueryError> {
+        let cluster_data = self.cluster.get_data();
+        let token = match query.get_routing_hint() {
+            None => return Ok(None),
+            Some(RoutingHint::Token { token, .. }) => token.clone(),
+            Some(RoutingHint::RoutingKey {
+                keyspace,
+                table,
+                routing_key,
+            }) => cluster_data.compute_token_from_routing_key(keyspace, table, routing_key),
+            Some(RoutingHint::PartitionKey {
+                keyspace,
+                table,
+                values,
+            }) => cluster_data.compute_token(keyspace, table, values)?,
+        };
+        Ok(Some(token))
+    }
+
     /// Retrieves the handle to execution profile that is used by this session
     /// by default, i.e. when an executed statement does not define its own handle.
     pub fn get_default_execution_profile_handle(&self) -> &ExecutionProfileHandle {
@@ -1874,13 +2428,10 @@ fn calculate_partition_key(
 // Resolve the given hostname using a DNS lookup if necessary.
 // The resolution may return multiple IPs and the function returns one of them.
 // It prefers to return IPv4s first, and only if there are none, IPv6s.
+#[cfg(any(feature = "cloud", feature = "cloud-rustls"))]
 pub(crate) async fn resolve_hostname(hostname: &str) -> Result<SocketAddr, NewSessionError> {
     let mut ret = None;
-    let addrs: Vec<SocketAddr> = match lookup_host(hostname).await {
-        Ok(addrs) => addrs.collect(),
-        // Use a default port in case of error, but propagate the original error on failure
-        Err(e) => lookup_host((hostname, 9042)).await.or(Err(e))?.collect(),
-    };
+    let addrs = lookup_hostname(hostname).await?;
     for a in addrs {
         match a {
             SocketAddr::V4(_) => return Ok(a),
@@ -1893,6 +2444,33 @@ pub(crate) async fn resolve_hostname(hostname: &str) -> Result<SocketAddr, NewSe
     ret.ok_or_else(|| NewSessionError::FailedToResolveAddress(hostname.to_string()))
 }
 
+/// Resolves the hostname to all of its addresses (both A and AAAA records),
+/// with duplicates removed and IPv4 addresses first.
+pub(crate) async fn resolve_hostname_all(
+    hostname: &str,
+) -> Result<Vec<SocketAddr>, NewSessionError> {
+    let mut addrs = lookup_hostname(hostname).await?;
+    // Stable sort keeps the order returned by the resolver within each address family
+    addrs.sort_by_key(|a| a.is_ipv6());
+    let mut seen = HashSet::new();
+    addrs.retain(|a| seen.insert(*a));
+
+    if addrs.is_empty() {
+        return Err(NewSessionError::FailedToResolveAddress(
+            hostname.to_string(),
+        ));
+    }
+    Ok(addrs)
+}
+
+async fn lookup_hostname(hostname: &str) -> Result<Vec<SocketAddr>, NewSessionError> {
+    Ok(match lookup_host(hostname).await {
+        Ok(addrs) => addrs.collect(),
+        // Use a default port in case of error, but propagate the original error on failure
+        Err(e) => lookup_host((hostname, 9042)).await.or(Err(e))?.collect(),
+    })
+}
+
 // run_query, execute_query, etc have a template type called ResT.
 // There was a bug where ResT was set to QueryResponse, which could
 // be an error response. This was not caught by retry policy which
@@ -1997,7 +2575,7 @@ impl RequestSpan {
 
     pub(crate) fn new_prepared(
         partition_key: Option<&Bytes>,
-        token: Option<Token>,
+        token: Option<&Token>,
         request_size: usize,
     ) -> Self {
         use crate::utils::pretty::HexBytes;
@@ -2024,7 +2602,10 @@ impl RequestSpan {
             );
         }
         if let Some(token) = token {
-            span.record("token", token.value);
+            match token.as_i64() {
+                Some(value) => span.record("token", value),
+                None => span.record("token", tracing::field::display(token)),
+            };
         }
 
         Self {
@@ -2,25 +2,34 @@
 
 use super::errors::NewSessionError;
 use super::execution_profile::ExecutionProfileHandle;
-use super::session::{AddressTranslator, Session, SessionConfig};
+use super::session::{AddressTranslator, SchemaAgreementScope, Session, SessionConfig};
+#[cfg(any(feature = "ssl", feature = "rustls"))]
+use super::session::{TlsContext, TlsContextProvider};
 use super::Compression;
 
-#[cfg(feature = "cloud")]
+#[cfg(any(feature = "cloud", feature = "cloud-rustls"))]
 use crate::cloud::{CloudConfig, CloudConfigError};
-#[cfg(feature = "cloud")]
+#[cfg(any(feature = "cloud", feature = "cloud-rustls"))]
 use crate::ExecutionProfile;
 
+use crate::transport::connection::is_reserved_startup_option;
 use crate::transport::connection_pool::PoolSize;
 use crate::transport::host_filter::HostFilter;
+use crate::transport::locator::ReplicationStrategy;
+use crate::transport::reconnection_policy::ReconnectionPolicy;
 use std::borrow::Borrow;
 use std::marker::PhantomData;
 use std::net::SocketAddr;
-#[cfg(feature = "cloud")]
+#[cfg(any(feature = "cloud", feature = "cloud-rustls"))]
 use std::path::Path;
+#[cfg(unix)]
+use std::path::PathBuf;
 use std::sync::Arc;
 use std::time::Duration;
+use uuid::Uuid;
 
-use crate::authentication::{AuthenticatorProvider, PlainTextAuthenticator};
+use crate::authentication::{AuthenticatorProvider, CredentialsProvider, PlainTextAuthenticator};
+use crate::transport::proxy::ProxyConfig;
 #[cfg(feature = "ssl")]
 use openssl::ssl::SslContext;
 use tracing::warn;
@@ -37,15 +46,15 @@ impl SessionBuilderKind for DefaultMode {}
 
 pub type SessionBuilder = GenericSessionBuilder<DefaultMode>;
 
-#[cfg(feature = "cloud")]
+#[cfg(any(feature = "cloud", feature = "cloud-rustls"))]
 #[derive(Clone)]
 pub enum CloudMode {}
-#[cfg(feature = "cloud")]
+#[cfg(any(feature = "cloud", feature = "cloud-rustls"))]
 impl sealed::Sealed for CloudMode {}
-#[cfg(feature = "cloud")]
+#[cfg(any(feature = "cloud", feature = "cloud-rustls"))]
 impl SessionBuilderKind for CloudMode {}
 
-#[cfg(feature = "cloud")]
+#[cfg(any(feature = "cloud", feature = "cloud-rustls"))]
 pub type CloudSessionBuilder = GenericSessionBuilder<CloudMode>;
 
 /// SessionBuilder is used to create new Session instances
@@ -121,6 +130,26 @@ impl SessionBuilder {
         self
     }
 
+    /// Add a known node reachable through a Unix domain socket, e.g. Scylla's maintenance socket.
+    /// It has to be the only known node. The session connects only to this node
+    /// and doesn't discover the rest of the cluster.
+    /// # Example
+    /// ```
+    /// # use scylla::{Session, SessionBuilder};
+    /// # async fn example() -> Result<(), Box<dyn std::error::Error>> {
+    /// let session: Session = SessionBuilder::new()
+    ///     .known_node_unix_socket("/var/lib/scylla/cql.m")
+    ///     .build()
+    ///     .await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[cfg(unix)]
+    pub fn known_node_unix_socket(mut self, path: impl Into<PathBuf>) -> Self {
+        self.config.add_known_node_unix_socket(path);
+        self
+    }
+
     /// Add a list of known nodes with hostnames
     /// # Example
     /// ```
@@ -187,6 +216,45 @@ impl SessionBuilder {
         self
     }
 
+    /// Authenticate with credentials fetched from the given provider, e.g. a secret manager.
+    ///
+    /// The credentials are cached until they expire. When the server rejects them,
+    /// they are fetched again and the connection attempt is retried once, so rotated
+    /// secrets are picked up by all new connections, including pool refills.
+    ///
+    /// # Example
+    /// ```
+    /// # use std::sync::Arc;
+    /// use async_trait::async_trait;
+    /// use scylla::authentication::{AuthError, Credentials, CredentialsProvider};
+    /// use scylla::{Session, SessionBuilder};
+    ///
+    /// struct EnvCredentials;
+    ///
+    /// #[async_trait]
+    /// impl CredentialsProvider for EnvCredentials {
+    ///     async fn credentials(&self) -> Result<Credentials, AuthError> {
+    ///         let password = std::env::var("SCYLLA_PASSWORD").map_err(|e| e.to_string())?;
+    ///         Ok(Credentials::new("cassandra", password))
+    ///     }
+    /// }
+    ///
+    /// # async fn example() -> Result<(), Box<dyn std::error::Error>> {
+    /// let session: Session = SessionBuilder::new()
+    ///     .known_node("127.0.0.1:9042")
+    ///     .credentials_provider(Arc::new(EnvCredentials))
+    ///     .build()
+    ///     .await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn credentials_provider(mut self, provider: Arc<dyn CredentialsProvider>) -> Self {
+        self.config.authenticator = Some(Arc::new(
+            PlainTextAuthenticator::with_credentials_provider(provider),
+        ));
+        self
+    }
+
     /// Set custom authenticator provider to create an authenticator instance during a session creation.
     ///
     /// # Example
@@ -329,7 +397,7 @@ impl SessionBuilder {
         self
     }
 }
-#[cfg(feature = "cloud")]
+#[cfg(any(feature = "cloud", feature = "cloud-rustls"))]
 impl CloudSessionBuilder {
     /// Creates a new SessionBuilder with default configuration,
     /// based on provided path to Scylla Cloud Config yaml.
@@ -397,6 +465,28 @@ impl<K: SessionBuilderKind> GenericSessionBuilder<K> {
         self
     }
 
+    /// Set which nodes have to agree on the schema version, both in explicit
+    /// schema agreement checks and after schema-altering statements.
+    /// The default is [`SchemaAgreementScope::ConnectedNodes`].
+    ///
+    /// # Example
+    /// ```
+    /// # use scylla::{Session, SessionBuilder};
+    /// # use scylla::transport::session::SchemaAgreementScope;
+    /// # async fn example() -> Result<(), Box<dyn std::error::Error>> {
+    /// let session: Session = SessionBuilder::new()
+    ///     .known_node("127.0.0.1:9042")
+    ///     .schema_agreement_scope(SchemaAgreementScope::Datacenter("my-local-dc".to_string()))
+    ///     .build()
+    ///     .await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn schema_agreement_scope(mut self, scope: SchemaAgreementScope) -> Self {
+        self.config.schema_agreement_scope = scope;
+        self
+    }
+
     /// Set the default execution profile using its handle
     ///
     /// # Example
@@ -529,6 +619,92 @@ impl<K: SessionBuilderKind> GenericSessionBuilder<K> {
         self
     }
 
+    /// ssl or rustls feature
+    /// Provide SessionBuilder with a TLS configuration that will be used
+    /// to create encrypted connections to the database. A [`TlsContext`] can be
+    /// created with `.into()` from an `SslContext` of the openssl crate (`ssl` feature)
+    /// or a `rustls::ClientConfig` (`rustls` feature).
+    /// Takes precedence over [`ssl_context`](Self::ssl_context).
+    /// If set to None TLS connection won't be used.
+    /// Default is None.
+    ///
+    /// # Example
+    /// ```
+    /// # #[cfg(feature = "rustls")]
+    /// # async fn example() -> Result<(), Box<dyn std::error::Error>> {
+    /// # use scylla::{Session, SessionBuilder};
+    /// let mut root_store = rustls::RootCertStore::empty();
+    /// # let ca_der: Vec<u8> = Vec::new();
+    /// root_store.add(&rustls::Certificate(ca_der))?;
+    /// let tls_config = rustls::ClientConfig::builder()
+    ///     .with_safe_defaults()
+    ///     .with_root_certificates(root_store)
+    ///     .with_no_client_auth();
+    ///
+    /// let session: Session = SessionBuilder::new()
+    ///     .known_node("127.0.0.1:9142")
+    ///     .tls_context(Some(tls_config.into()))
+    ///     .build()
+    ///     .await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[cfg(any(feature = "ssl", feature = "rustls"))]
+    pub fn tls_context(mut self, tls_context: Option<TlsContext>) -> Self {
+        self.config.tls_context = tls_context;
+        self
+    }
+
+    /// ssl or rustls feature
+    /// Provide SessionBuilder with a source of TLS configuration which is consulted
+    /// whenever a new connection is opened, so that e.g. client certificates can be
+    /// rotated without recreating the session. Takes precedence over
+    /// [`tls_context`](Self::tls_context) and [`ssl_context`](Self::ssl_context).
+    /// Default is None.
+    ///
+    /// # Example
+    /// ```
+    /// # #[cfg(feature = "ssl")]
+    /// # async fn example() -> Result<(), Box<dyn std::error::Error>> {
+    /// # use scylla::{Session, SessionBuilder};
+    /// use openssl::ssl::{SslContextBuilder, SslMethod};
+    /// use scylla::transport::session::ReloadableTlsContext;
+    /// use std::sync::Arc;
+    /// use std::time::Duration;
+    ///
+    /// let provider = Arc::new(ReloadableTlsContext::new(
+    ///     SslContextBuilder::new(SslMethod::tls())?.build(),
+    /// ));
+    /// let session: Session = SessionBuilder::new()
+    ///     .known_node("127.0.0.1:9142")
+    ///     .tls_context_provider(Some(provider.clone()))
+    ///     .tls_reload_check_interval(Some(Duration::from_secs(10)))
+    ///     .build()
+    ///     .await?;
+    ///
+    /// // New connections will use the new context, and the old ones will be replaced.
+    /// provider.reload(SslContextBuilder::new(SslMethod::tls())?.build());
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[cfg(any(feature = "ssl", feature = "rustls"))]
+    pub fn tls_context_provider(mut self, provider: Option<Arc<dyn TlsContextProvider>>) -> Self {
+        self.config.tls_context_provider = provider;
+        self
+    }
+
+    /// ssl or rustls feature
+    /// Sets how often connection pools check whether the TLS context provider
+    /// has a new context. If it has, connections opened with an older context
+    /// are gradually replaced with new ones.
+    /// If set to None, open connections keep their TLS context.
+    /// Default is None.
+    #[cfg(any(feature = "ssl", feature = "rustls"))]
+    pub fn tls_reload_check_interval(mut self, interval: Option<Duration>) -> Self {
+        self.config.tls_reload_check_interval = interval;
+        self
+    }
+
     /// Builds the Session after setting all the options
     ///
     /// # Example
@@ -578,7 +754,7 @@ impl<K: SessionBuilderKind> GenericSessionBuilder<K> {
     /// # use scylla::{Session, SessionBuilder};
     /// # async fn example() -> Result<(), Box<dyn std::error::Error>> {
     /// use std::num::NonZeroUsize;
-    /// use scylla::transport::session::PoolSize;
+    /// use scylla::transport::session::{AdaptivePoolSize, PoolSize};
     ///
     /// // This session will establish 4 connections to each node.
     /// // For Scylla clusters, this number will be divided across shards
@@ -587,6 +763,14 @@ impl<K: SessionBuilderKind> GenericSessionBuilder<K> {
     ///     .pool_size(PoolSize::PerHost(NonZeroUsize::new(4).unwrap()))
     ///     .build()
     ///     .await?;
+    ///
+    /// // This session will keep between 1 and 4 connections to each shard,
+    /// // depending on how many requests are in flight
+    /// let session: Session = SessionBuilder::new()
+    ///     .known_node("127.0.0.1:9042")
+    ///     .pool_size(PoolSize::Adaptive(AdaptivePoolSize::default()))
+    ///     .build()
+    ///     .await?;
     /// # Ok(())
     /// # }
     /// ```
@@ -595,6 +779,35 @@ impl<K: SessionBuilderKind> GenericSessionBuilder<K> {
         self
     }
 
+    /// Sets the policy deciding how long to wait between attempts to reopen connections to a node.
+    /// The default is exponential backoff from 50 ms up to 10 s, with a jitter of 0.5.
+    ///
+    /// # Example
+    /// ```
+    /// # use scylla::{Session, SessionBuilder};
+    /// # async fn example() -> Result<(), Box<dyn std::error::Error>> {
+    /// use std::sync::Arc;
+    /// use std::time::Duration;
+    /// use scylla::transport::reconnection_policy::ExponentialReconnectionPolicy;
+    ///
+    /// // Randomize delays so that many clients don't reconnect at the same time
+    /// let session: Session = SessionBuilder::new()
+    ///     .known_node("127.0.0.1:9042")
+    ///     .reconnection_policy(Arc::new(ExponentialReconnectionPolicy {
+    ///         base_delay: Duration::from_millis(100),
+    ///         max_delay: Duration::from_secs(30),
+    ///         jitter: 0.5,
+    ///     }))
+    ///     .build()
+    ///     .await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn reconnection_policy(mut self, policy: Arc<dyn ReconnectionPolicy>) -> Self {
+        self.config.reconnection_policy = policy;
+        self
+    }
+
     /// If true, prevents the driver from connecting to the shard-aware port, even if the node supports it.
     ///
     /// _This is a Scylla-specific option_. It has no effect on Cassandra clusters.
@@ -783,7 +996,10 @@ impl<K: SessionBuilderKind> GenericSessionBuilder<K> {
     ///
     /// See the [host filter](crate::transport::host_filter) module for a list
     /// of pre-defined filters. It is also possible to provide a custom filter
-    /// by implementing the HostFilter trait.
+    /// by implementing the HostFilter trait. The filter is consulted on every
+    /// topology refresh, so a filter whose decisions change over time (e.g.
+    /// [`UpdatableHostFilter`](crate::transport::host_filter::UpdatableHostFilter))
+    /// connects to and disconnects from nodes without restarting the session.
     ///
     /// # Example
     /// ```
@@ -809,6 +1025,196 @@ impl<K: SessionBuilderKind> GenericSessionBuilder<K> {
         self
     }
 
+    /// Registers an implementation of a replication strategy the driver doesn't know,
+    /// so that token-aware routing works for keyspaces using it.
+    /// `class_name` is the strategy class as stored in the schema, either the full name
+    /// (e.g. `com.example.MyStrategy`) or the part after the last dot (e.g. `MyStrategy`).
+    ///
+    /// `SimpleStrategy`, `NetworkTopologyStrategy`, `EverywhereStrategy` and `LocalStrategy`
+    /// are handled by the driver itself.
+    ///
+    /// # Example
+    /// ```
+    /// # use std::collections::HashMap;
+    /// # use std::sync::Arc;
+    /// # use scylla::{Session, SessionBuilder};
+    /// # use scylla::routing::Token;
+    /// # use scylla::transport::NodeRef;
+    /// use scylla::transport::locator::{ReplicaLocator, ReplicationStrategy};
+    ///
+    /// // Only the primary replica owns the data
+    /// #[derive(Debug)]
+    /// struct PrimaryOnlyStrategy;
+    ///
+    /// impl ReplicationStrategy for PrimaryOnlyStrategy {
+    ///     fn replicas<'a>(
+    ///         &self,
+    ///         token: Token,
+    ///         locator: &'a ReplicaLocator,
+    ///         _options: &HashMap<String, String>,
+    ///     ) -> Vec<NodeRef<'a>> {
+    ///         locator.ring().get_elem_for_token(token).into_iter().collect()
+    ///     }
+    /// }
+    ///
+    /// # async fn example() -> Result<(), Box<dyn std::error::Error>> {
+    /// let session: Session = SessionBuilder::new()
+    ///     .known_node("127.0.0.1:9042")
+    ///     .replication_strategy("com.example.PrimaryOnlyStrategy", Arc::new(PrimaryOnlyStrategy))
+    ///     .build()
+    ///     .await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn replication_strategy(
+        mut self,
+        class_name: impl Into<String>,
+        strategy: Arc<dyn ReplicationStrategy>,
+    ) -> Self {
+        self.config
+            .replication_strategies
+            .insert(class_name.into(), strategy);
+        self
+    }
+
+    /// Set how often the known nodes given as hostnames are resolved again.
+    /// This is useful when the addresses behind the hostnames change,
+    /// e.g. when the nodes run in Kubernetes and are rescheduled.
+    /// The new addresses are used when the control connection has to be re-established.
+    ///
+    /// Regardless of this setting, hostnames are resolved again
+    /// when the control connection can't be re-established to any known node.
+    /// The default is `None`, which disables the periodic re-resolution.
+    ///
+    /// # Example
+    /// ```
+    /// # use scylla::{Session, SessionBuilder};
+    /// # use std::time::Duration;
+    /// # async fn example() -> Result<(), Box<dyn std::error::Error>> {
+    /// let session: Session = SessionBuilder::new()
+    ///     .known_node("scylla.default.svc.cluster.local:9042")
+    ///     .hostname_resolution_interval(Some(Duration::from_secs(60)))
+    ///     .build()
+    ///     .await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn hostname_resolution_interval(mut self, interval: Option<Duration>) -> Self {
+        self.config.hostname_resolution_interval = interval;
+        self
+    }
+
+    /// Open all connections, including the control connection, through a SOCKS5
+    /// or HTTP CONNECT proxy. The proxy is asked to connect to node addresses
+    /// after address translation. TLS, if configured, is established through the tunnel.
+    ///
+    /// The driver can't choose the source port of a connection opened through a proxy,
+    /// so the shard-aware port is not used.
+    /// The default is `None`.
+    ///
+    /// # Example
+    /// ```
+    /// # use scylla::{Session, SessionBuilder};
+    /// use scylla::transport::proxy::ProxyConfig;
+    /// # async fn example() -> Result<(), Box<dyn std::error::Error>> {
+    /// let session: Session = SessionBuilder::new()
+    ///     .known_node("10.0.1.1:9042")
+    ///     .proxy(Some(ProxyConfig::socks5("127.0.0.1:1080".parse()?)))
+    ///     .build()
+    ///     .await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn proxy(mut self, proxy: Option<ProxyConfig>) -> Self {
+        self.config.proxy = proxy;
+        self
+    }
+
+    /// Set the name of the application, sent as `APPLICATION_NAME` in the STARTUP message
+    /// of every connection. Servers which list connected clients can show it
+    /// to attribute load to the application.
+    ///
+    /// # Example
+    /// ```
+    /// # use scylla::{Session, SessionBuilder};
+    /// # async fn example() -> Result<(), Box<dyn std::error::Error>> {
+    /// let session: Session = SessionBuilder::new()
+    ///     .known_node("127.0.0.1:9042")
+    ///     .application_name("billing-service")
+    ///     .application_version("2.4.1")
+    ///     .build()
+    ///     .await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn application_name(mut self, name: impl Into<String>) -> Self {
+        self.config.client_identity.application_name = Some(name.into());
+        self
+    }
+
+    /// Set the version of the application, sent as `APPLICATION_VERSION` in the STARTUP message
+    /// of every connection. See [`SessionBuilder::application_name`].
+    pub fn application_version(mut self, version: impl Into<String>) -> Self {
+        self.config.client_identity.application_version = Some(version.into());
+        self
+    }
+
+    /// Set the identifier of this instance of the application, sent as `CLIENT_ID`
+    /// in the STARTUP message of every connection.
+    ///
+    /// # Example
+    /// ```
+    /// # use scylla::{Session, SessionBuilder};
+    /// # async fn example() -> Result<(), Box<dyn std::error::Error>> {
+    /// use uuid::Uuid;
+    ///
+    /// let session: Session = SessionBuilder::new()
+    ///     .known_node("127.0.0.1:9042")
+    ///     .application_name("billing-service")
+    ///     .client_id(Uuid::new_v4())
+    ///     .build()
+    ///     .await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn client_id(mut self, client_id: Uuid) -> Self {
+        self.config.client_identity.client_id = Some(client_id);
+        self
+    }
+
+    /// Add an option to be sent as is in the STARTUP message of every connection,
+    /// e.g. the name of the deployment. Options reserved for the driver, like `CQL_VERSION`,
+    /// `COMPRESSION`, `DRIVER_NAME` or Scylla protocol extensions, are ignored with a warning,
+    /// see [`is_reserved_startup_option`](crate::transport::session::is_reserved_startup_option).
+    ///
+    /// # Example
+    /// ```
+    /// # use scylla::{Session, SessionBuilder};
+    /// # async fn example() -> Result<(), Box<dyn std::error::Error>> {
+    /// let session: Session = SessionBuilder::new()
+    ///     .known_node("127.0.0.1:9042")
+    ///     .startup_option("DEPLOYMENT", "eu-west-canary")
+    ///     .build()
+    ///     .await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn startup_option(mut self, key: impl Into<String>, value: impl Into<String>) -> Self {
+        let key = key.into();
+        if is_reserved_startup_option(&key) {
+            warn!(
+                "Ignoring startup option {}, which is reserved for the driver",
+                key
+            );
+            return self;
+        }
+        self.config
+            .client_identity
+            .extra_options
+            .insert(key, value.into());
+        self
+    }
+
     /// Set the refresh metadata on schema agreement flag.
     /// The default is true.
     ///
@@ -839,15 +1245,26 @@ impl Default for SessionBuilder {
 
 #[cfg(test)]
 mod tests {
+    use scylla_cql::errors::NewSessionError;
     use scylla_cql::frame::types::SerialConsistency;
     use scylla_cql::Consistency;
 
     use super::SessionBuilder;
+    use crate::routing::Token;
+    use crate::transport::cluster::ContactPoint;
     use crate::transport::execution_profile::{defaults, ExecutionProfile};
-    use crate::transport::session::KnownNode;
-    use crate::transport::Compression;
+    use crate::transport::locator::{ReplicaLocator, ReplicationStrategy};
+    use crate::transport::proxy::{ProxyConfig, ProxyKind};
+    use crate::transport::reconnection_policy::ConstantReconnectionPolicy;
+    use crate::transport::session::{ClientIdentity, KnownNode, SchemaAgreementScope};
+    use crate::transport::topology::UntranslatedEndpoint;
+    use crate::transport::{Compression, NodeRef};
+    use assert_matches::assert_matches;
+    use std::collections::HashMap;
     use std::net::{IpAddr, Ipv4Addr, SocketAddr};
+    use std::sync::Arc;
     use std::time::Duration;
+    use uuid::Uuid;
 
     #[test]
     fn default_session_builder() {
@@ -940,6 +1357,23 @@ mod tests {
         assert!(builder.config.tcp_nodelay);
     }
 
+    #[test]
+    #[cfg(feature = "rustls")]
+    fn tls_context() {
+        let mut builder = SessionBuilder::new();
+        assert!(builder.config.tls_context.is_none());
+
+        let tls_config = rustls::ClientConfig::builder()
+            .with_safe_defaults()
+            .with_root_certificates(rustls::RootCertStore::empty())
+            .with_no_client_auth();
+        builder = builder.tls_context(Some(tls_config.into()));
+        assert!(builder.config.tls_context.is_some());
+
+        builder = builder.tls_context(None);
+        assert!(builder.config.tls_context.is_none());
+    }
+
     #[test]
     fn use_keyspace() {
         let mut builder = SessionBuilder::new();
@@ -970,6 +1404,152 @@ mod tests {
         );
     }
 
+    #[test]
+    fn schema_agreement_scope() {
+        let mut builder = SessionBuilder::new();
+        assert_eq!(
+            builder.config.schema_agreement_scope,
+            SchemaAgreementScope::ConnectedNodes
+        );
+
+        builder = builder.schema_agreement_scope(SchemaAgreementScope::Datacenter("dc1".into()));
+        assert_eq!(
+            builder.config.schema_agreement_scope,
+            SchemaAgreementScope::Datacenter("dc1".into())
+        );
+    }
+
+    #[test]
+    fn reconnection_policy() {
+        let endpoint = UntranslatedEndpoint::ContactPoint(ContactPoint {
+            address: "127.0.0.1:9042".parse().unwrap(),
+            datacenter: None,
+            hostname: None,
+        });
+
+        // The default policy is exponential, with jitter
+        let mut builder = SessionBuilder::new();
+        let mut schedule = builder
+            .config
+            .reconnection_policy
+            .new_schedule(&endpoint, None);
+        let first = schedule.next_delay();
+        assert!(first <= Duration::from_millis(50) && first >= Duration::from_millis(25));
+        let second = schedule.next_delay();
+        assert!(second <= Duration::from_millis(100) && second >= Duration::from_millis(50));
+
+        builder = builder.reconnection_policy(Arc::new(ConstantReconnectionPolicy {
+            delay: Duration::from_secs(1),
+        }));
+        let mut schedule = builder
+            .config
+            .reconnection_policy
+            .new_schedule(&endpoint, Some(3));
+        assert_eq!(schedule.next_delay(), Duration::from_secs(1));
+        assert_eq!(schedule.next_delay(), Duration::from_secs(1));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn add_known_node_unix_socket() {
+        let builder = SessionBuilder::new().known_node_unix_socket("/var/lib/scylla/cql.m");
+        assert_eq!(
+            builder.config.known_nodes,
+            vec![KnownNode::UnixSocket("/var/lib/scylla/cql.m".into())]
+        );
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn unix_socket_has_to_be_the_only_known_node() {
+        let result = SessionBuilder::new()
+            .known_node_unix_socket("/var/lib/scylla/cql.m")
+            .known_node("127.0.0.1:9042")
+            .build()
+            .await;
+        assert_matches!(result, Err(NewSessionError::UnixSocketWithOtherKnownNodes));
+    }
+
+    #[test]
+    fn proxy() {
+        let mut builder = SessionBuilder::new();
+        assert!(builder.config.proxy.is_none());
+
+        builder = builder.proxy(Some(ProxyConfig::http_connect(
+            "127.0.0.1:3128".parse().unwrap(),
+        )));
+        let proxy = builder.config.proxy.unwrap();
+        assert_eq!(proxy.kind, ProxyKind::HttpConnect);
+        assert_eq!(proxy.address, "127.0.0.1:3128".parse().unwrap());
+        assert!(proxy.credentials.is_none());
+    }
+
+    #[test]
+    fn client_identity() {
+        let mut builder = SessionBuilder::new();
+        assert_eq!(builder.config.client_identity, ClientIdentity::default());
+
+        let client_id = Uuid::new_v4();
+        builder = builder
+            .application_name("app")
+            .application_version("1.2.3")
+            .client_id(client_id)
+            .startup_option("DEPLOYMENT", "canary")
+            .startup_option("COMPRESSION", "lz4")
+            .startup_option("Driver_Name", "other")
+            .startup_option("SCYLLA_LWT_ADD_METADATA_MARK", "");
+
+        let identity = &builder.config.client_identity;
+        assert_eq!(identity.application_name.as_deref(), Some("app"));
+        assert_eq!(identity.application_version.as_deref(), Some("1.2.3"));
+        assert_eq!(identity.client_id, Some(client_id));
+        assert_eq!(
+            identity.extra_options.get("DEPLOYMENT").map(String::as_str),
+            Some("canary")
+        );
+        // Options reserved for the driver are ignored
+        assert_eq!(identity.extra_options.len(), 1);
+    }
+
+    #[test]
+    fn hostname_resolution_interval() {
+        let mut builder = SessionBuilder::new();
+        assert_eq!(builder.config.hostname_resolution_interval, None);
+
+        builder = builder.hostname_resolution_interval(Some(Duration::from_secs(30)));
+        assert_eq!(
+            builder.config.hostname_resolution_interval,
+            Some(Duration::from_secs(30))
+        );
+    }
+
+    #[test]
+    fn replication_strategy() {
+        #[derive(Debug)]
+        struct NoReplicas;
+
+        impl ReplicationStrategy for NoReplicas {
+            fn replicas<'a>(
+                &self,
+                _token: Token,
+                _locator: &'a ReplicaLocator,
+                _options: &HashMap<String, String>,
+            ) -> Vec<NodeRef<'a>> {
+                Vec::new()
+            }
+        }
+
+        let mut builder = SessionBuilder::new();
+        assert!(builder.config.replication_strategies.is_empty());
+
+        builder = builder.replication_strategy("com.example.NoReplicas", Arc::new(NoReplicas));
+        assert_eq!(builder.config.replication_strategies.len(), 1);
+        assert!(builder
+            .config
+            .replication_strategies
+            .contains_key("com.example.NoReplicas"));
+    }
+
     #[test]
     fn fetch_schema_metadata() {
         let mut builder = SessionBuilder::new();
@@ -11,6 +11,7 @@ use crate::tracing::{GetTracingConfig, TracingInfo};
 use crate::transport::cluster::Datacenter;
 use crate::transport::errors::{BadKeyspaceName, BadQuery, DbError, QueryError};
 use crate::transport::partitioner::{Murmur3Partitioner, Partitioner, PartitionerName};
+use crate::transport::session::SchemaAgreementScope;
 use crate::transport::topology::Strategy::NetworkTopologyStrategy;
 use crate::transport::topology::{
     CollectionType, ColumnKind, CqlType, NativeType, UserDefinedType,
@@ -224,13 +225,13 @@ async fn test_prepared_statement() {
             .unwrap()
             .rows
             .unwrap();
-        let token = Token {
-            value: rs.first().unwrap().columns[0]
+        let token = Token::new(
+            rs.first().unwrap().columns[0]
                 .as_ref()
                 .unwrap()
                 .as_bigint()
                 .unwrap(),
-        };
+        );
         let prepared_token = Murmur3Partitioner::hash(
             &prepared_statement
                 .compute_partition_key(&serialized_values)
@@ -250,13 +251,13 @@ async fn test_prepared_statement() {
             .unwrap()
             .rows
             .unwrap();
-        let token = Token {
-            value: rs.first().unwrap().columns[0]
+        let token = Token::new(
+            rs.first().unwrap().columns[0]
                 .as_ref()
                 .unwrap()
                 .as_bigint()
                 .unwrap(),
-        };
+        );
         let prepared_token = Murmur3Partitioner::hash(
             &prepared_complex_pk_statement
                 .compute_partition_key(&serialized_values)
@@ -522,13 +523,13 @@ async fn test_token_calculation() {
             .unwrap()
             .rows
             .unwrap();
-        let token = Token {
-            value: rs.first().unwrap().columns[0]
+        let token = Token::new(
+            rs.first().unwrap().columns[0]
                 .as_ref()
                 .unwrap()
                 .as_bigint()
                 .unwrap(),
-        };
+        );
         let prepared_token = Murmur3Partitioner::hash(
             &prepared_statement
                 .compute_partition_key(&serialized_values)
@@ -1151,6 +1152,46 @@ async fn test_await_timed_schema_agreement() {
         .unwrap();
 }
 
+#[tokio::test]
+async fn test_fetch_schema_versions() {
+    let session = create_new_session_builder().build().await.unwrap();
+    let versions = session.fetch_schema_versions().await;
+
+    let cluster_data = session.get_cluster_data();
+    assert_eq!(versions.len(), cluster_data.get_nodes_info().len());
+    assert!(versions.iter().all(|v| v.is_reachable()));
+    assert!(versions.iter().map(|v| v.version).all_equal());
+}
+
+#[tokio::test]
+async fn test_schema_agreement_in_scope() {
+    let session = create_new_session_builder().build().await.unwrap();
+    let dc = session.get_cluster_data().get_nodes_info()[0]
+        .datacenter
+        .clone()
+        .unwrap();
+
+    assert!(session
+        .check_schema_agreement_in_scope(&SchemaAgreementScope::Datacenter(dc.clone()))
+        .await
+        .unwrap());
+    assert!(session
+        .await_timed_schema_agreement_in_scope(
+            &SchemaAgreementScope::Datacenter(dc),
+            Duration::from_secs(5)
+        )
+        .await
+        .unwrap());
+
+    // No node can be reached in a nonexistent datacenter
+    session
+        .check_schema_agreement_in_scope(&SchemaAgreementScope::Datacenter(
+            "no_such_dc".to_string(),
+        ))
+        .await
+        .unwrap_err();
+}
+
 #[tokio::test]
 async fn test_timestamp() {
     let session = create_new_session_builder().build().await.unwrap();
@@ -2798,3 +2839,364 @@ async fn simple_strategy_test() {
 
     assert_eq!(rows, vec![(1, 2, 3), (4, 5, 6), (7, 8, 9)]);
 }
+
+#[tokio::test]
+async fn test_pool_snapshot() {
+    let session = create_new_session_builder().build().await.unwrap();
+    session
+        .query("SELECT * FROM system.local", &[])
+        .await
+        .unwrap();
+
+    let cluster_data = session.get_cluster_data();
+    for node in cluster_data.get_nodes_info() {
+        let snapshot = node.pool_snapshot().unwrap();
+        assert_eq!(snapshot.state, crate::transport::PoolState::Ready);
+        assert!(snapshot.open_connections() > 0);
+        assert!(snapshot.last_refill.is_some());
+
+        let expected_shards = node.sharder().map_or(1, |s| s.nr_shards.get() as usize);
+        assert_eq!(snapshot.shards.len(), expected_shards);
+        for (idx, shard) in snapshot.shards.iter().enumerate() {
+            assert_eq!(shard.shard as usize, idx);
+        }
+    }
+}
+
+#[tokio::test]
+async fn test_node_version_and_capabilities() {
+    let session = create_new_session_builder().build().await.unwrap();
+
+    let cluster_data = session.get_cluster_data();
+    for node in cluster_data.get_nodes_info() {
+        assert!(!node.tokens().is_empty());
+        assert!(node.release_version().is_some());
+        assert!(node.schema_version().is_some());
+
+        let capabilities = node.capabilities().unwrap();
+        assert_eq!(capabilities.sharder, node.sharder());
+        assert_eq!(capabilities.is_scylla(), node.sharder().is_some());
+    }
+}
+
+#[tokio::test]
+async fn test_node_target() {
+    use crate::statement::NodeTarget;
+
+    let session = create_new_session_builder().build().await.unwrap();
+
+    let cluster_data = session.get_cluster_data();
+    for node in cluster_data.get_nodes_info() {
+        let mut query = Query::new("SELECT host_id FROM system.local");
+        query.set_node_target(Some(NodeTarget::node(node.clone())));
+        let (host_id,) = session
+            .query(query.clone(), &[])
+            .await
+            .unwrap()
+            .single_row_typed::<(Uuid,)>()
+            .unwrap();
+        assert_eq!(host_id, node.host_id);
+
+        // Paging also sticks to the target node
+        query.set_page_size(1);
+        let host_ids: Vec<Uuid> = session
+            .query_iter(query.clone(), &[])
+            .await
+            .unwrap()
+            .into_typed::<(Uuid,)>()
+            .map(|row| row.unwrap().0)
+            .collect()
+            .await;
+        assert_eq!(host_ids, vec![node.host_id]);
+
+        let nr_shards = node.sharder().map_or(1, |s| s.nr_shards.get() as u32);
+        for shard in 0..nr_shards {
+            query.set_node_target(Some(NodeTarget::shard(node.clone(), shard)));
+            session.query(query.clone(), &[]).await.unwrap();
+        }
+
+        query.set_node_target(Some(NodeTarget::shard(node.clone(), nr_shards)));
+        assert_matches!(
+            session.query(query.clone(), &[]).await,
+            Err(QueryError::BadQuery(BadQuery::Other(_)))
+        );
+    }
+}
+
+#[tokio::test]
+async fn test_system_tables() {
+    let session = create_new_session_builder().build().await.unwrap();
+    let ks = unique_keyspace_name();
+    session.query(format!("CREATE KEYSPACE IF NOT EXISTS {} WITH REPLICATION = {{'class' : 'SimpleStrategy', 'replication_factor' : 1}}", ks), &[]).await.unwrap();
+    session
+        .query(
+            format!("CREATE TABLE IF NOT EXISTS {}.t (a int primary key)", ks),
+            &[],
+        )
+        .await
+        .unwrap();
+
+    let system_tables = session.system_tables();
+    let cluster_data = session.get_cluster_data();
+    let nodes = cluster_data.get_nodes_info();
+    for node in nodes {
+        let local = system_tables.local(node).await.unwrap();
+        assert_eq!(local.host_id, Some(node.host_id));
+        assert_eq!(local.data_center, node.datacenter);
+        assert!(!local.tokens.unwrap_or_default().is_empty());
+
+        let peers: Vec<_> = system_tables
+            .peers(node)
+            .await
+            .unwrap()
+            .try_collect()
+            .await
+            .unwrap();
+        assert_eq!(peers.len(), nodes.len() - 1);
+        assert!(peers.iter().all(|peer| peer.host_id != Some(node.host_id)));
+
+        let _: Vec<_> = system_tables
+            .size_estimates(node, &ks, "t")
+            .await
+            .unwrap()
+            .try_collect()
+            .await
+            .unwrap();
+
+        if node.sharder().is_some() {
+            let clients: Vec<_> = system_tables
+                .clients(node)
+                .await
+                .unwrap()
+                .try_collect()
+                .await
+                .unwrap();
+            assert!(!clients.is_empty());
+
+            let _: Vec<_> = system_tables
+                .large_partitions(node)
+                .await
+                .unwrap()
+                .try_collect()
+                .await
+                .unwrap();
+        }
+    }
+}
+
+#[tokio::test]
+async fn test_explain_plan() {
+    let session = create_new_session_builder().build().await.unwrap();
+    let ks = unique_keyspace_name();
+
+    session.query(format!("CREATE KEYSPACE IF NOT EXISTS {} WITH REPLICATION = {{'class' : 'NetworkTopologyStrategy', 'replication_factor' : 1}}", ks), &[]).await.unwrap();
+    session
+        .query(
+            format!(
+                "CREATE TABLE IF NOT EXISTS {}.t (a int primary key, b int)",
+                ks
+            ),
+            &[],
+        )
+        .await
+        .unwrap();
+    session.await_schema_agreement().await.unwrap();
+    session.refresh_metadata().await.unwrap();
+
+    let prepared = session
+        .prepare(format!("SELECT b FROM {}.t WHERE a = ?", ks))
+        .await
+        .unwrap();
+    let values = (7,).serialized().unwrap().into_owned();
+    let explanation = session.explain_plan(&prepared, &values).unwrap();
+
+    assert_eq!(explanation.policy_name, "DefaultPolicy");
+    assert!(!explanation.bypasses_load_balancing);
+    assert_eq!(explanation.keyspace.as_deref(), Some(ks.as_str()));
+    assert_eq!(
+        explanation.token,
+        session.calculate_token(&prepared, &values).unwrap()
+    );
+    assert_eq!(explanation.replicas.len(), 1);
+
+    let first = &explanation.plan[0];
+    assert!(first.is_replica);
+    assert!(Arc::ptr_eq(&first.node, &explanation.replicas[0]));
+    assert_eq!(
+        first.shard,
+        first
+            .node
+            .sharder()
+            .map(|sharder| sharder.shard_of(explanation.token.unwrap()))
+    );
+    assert_eq!(
+        explanation.plan.len(),
+        session.get_cluster_data().get_nodes_info().len()
+    );
+
+    let explanation = session
+        .explain_query_plan(&Query::new("SELECT now() FROM system.local"))
+        .unwrap();
+    assert_eq!(explanation.token, None);
+    assert!(explanation.replicas.is_empty());
+    assert!(explanation.plan.iter().all(|planned| !planned.is_replica));
+}
+
+#[tokio::test]
+async fn test_scan_table() {
+    use crate::transport::token_range_scan::{TokenRangeScanCheckpoint, TokenRangeScanOptions};
+
+    let session = create_new_session_builder().build().await.unwrap();
+    let ks = unique_keyspace_name();
+
+    session.query(format!("CREATE KEYSPACE IF NOT EXISTS {} WITH REPLICATION = {{'class' : 'NetworkTopologyStrategy', 'replication_factor' : 1}}", ks), &[]).await.unwrap();
+    session
+        .query(
+            format!(
+                "CREATE TABLE IF NOT EXISTS {}.t (a int, b int, c text, primary key ((a, b), c))",
+                ks
+            ),
+            &[],
+        )
+        .await
+        .unwrap();
+    session.await_schema_agreement().await.unwrap();
+    session.refresh_metadata().await.unwrap();
+
+    let insert = session
+        .prepare(format!("INSERT INTO {}.t (a, b, c) VALUES (?, ?, ?)", ks))
+        .await
+        .unwrap();
+    for a in 0..100 {
+        session
+            .execute(&insert, (a, a * 2, a.to_string()))
+            .await
+            .unwrap();
+    }
+
+    let mut rows = session
+        .scan_table::<(i32, String)>(
+            &ks,
+            "t",
+            TokenRangeScanOptions::new()
+                .columns(vec!["a".to_owned(), "c".to_owned()])
+                .concurrency(4)
+                .page_size(7),
+        )
+        .await
+        .unwrap();
+    assert!(!rows.checkpoint().is_finished());
+
+    let mut scanned = BTreeSet::new();
+    while let Some(row) = rows.next().await {
+        let (a, c) = row.unwrap();
+        assert_eq!(c, a.to_string());
+        assert!(scanned.insert(a));
+    }
+    assert_eq!(scanned, (0..100).collect());
+    assert!(rows.checkpoint().is_finished());
+
+    // Resuming from a checkpoint scans only the ranges left in it.
+    let all_ranges = session
+        .scan_table::<(i32, i32, String)>(&ks, "t", TokenRangeScanOptions::new())
+        .await
+        .unwrap()
+        .checkpoint();
+    let (first_half, second_half) = all_ranges.ranges().split_at(all_ranges.ranges().len() / 2);
+    let mut resumed = BTreeSet::new();
+    for half in [first_half, second_half] {
+        let rows: Vec<(i32, i32, String)> = session
+            .scan_table(
+                &ks,
+                "t",
+                TokenRangeScanOptions::new()
+                    .resume_from(TokenRangeScanCheckpoint::new(half.to_vec())),
+            )
+            .await
+            .unwrap()
+            .try_collect()
+            .await
+            .unwrap();
+        for (a, b, _) in rows {
+            assert_eq!(b, a * 2);
+            assert!(resumed.insert(a));
+        }
+    }
+    assert_eq!(resumed, scanned);
+
+    assert_matches!(
+        session
+            .scan_table::<(i32,)>(&ks, "no_such_table", TokenRangeScanOptions::new())
+            .await
+            .err(),
+        Some(QueryError::BadQuery(BadQuery::Other(_)))
+    );
+}
+
+#[tokio::test]
+async fn test_query_routing_hint() {
+    use crate::query::RoutingHint;
+
+    let session = create_new_session_builder().build().await.unwrap();
+    let ks = unique_keyspace_name();
+
+    session.query(format!("CREATE KEYSPACE IF NOT EXISTS {} WITH REPLICATION = {{'class' : 'NetworkTopologyStrategy', 'replication_factor' : 1}}", ks), &[]).await.unwrap();
+    session
+        .query(
+            format!(
+                "CREATE TABLE IF NOT EXISTS {}.t (a int, b text, c int, primary key ((a, b), c))",
+                ks
+            ),
+            &[],
+        )
+        .await
+        .unwrap();
+    session.await_schema_agreement().await.unwrap();
+    session.refresh_metadata().await.unwrap();
+
+    let prepared = session
+        .prepare(format!("INSERT INTO {}.t (a, b, c) VALUES (?, ?, ?)", ks))
+        .await
+        .unwrap();
+    let values = (7, "seven", 1).serialized().unwrap().into_owned();
+    let expected_token = session.calculate_token(&prepared, &values).unwrap();
+    let routing_key = prepared.compute_partition_key(&values).unwrap();
+
+    let hints = [
+        RoutingHint::token(ks.clone(), expected_token.clone().unwrap()),
+        RoutingHint::routing_key(ks.clone(), "t", routing_key),
+        RoutingHint::partition_key(ks.clone(), "t", (7, "seven")).unwrap(),
+    ];
+    for hint in hints {
+        let mut query = Query::new(format!("INSERT INTO {}.t (a, b, c) VALUES (?, ?, ?)", ks));
+        query.set_routing_hint(Some(hint));
+        assert_eq!(
+            session.calculate_query_token(&query).unwrap(),
+            expected_token
+        );
+
+        let explanation = session.explain_query_plan(&query).unwrap();
+        assert_eq!(explanation.token, expected_token);
+        assert_eq!(explanation.keyspace.as_deref(), Some(ks.as_str()));
+        assert!(explanation.plan[0].is_replica);
+
+        session.query(query.clone(), (7, "seven", 1)).await.unwrap();
+        let mut rows = session.query_iter(query, (7, "seven", 1)).await.unwrap();
+        assert!(rows.next().await.is_none());
+    }
+
+    let mut query = Query::new(format!("SELECT c FROM {}.t WHERE a = ? AND b = ?", ks));
+    assert_eq!(session.calculate_query_token(&query).unwrap(), None);
+    query.set_routing_hint(Some(
+        RoutingHint::partition_key(ks.clone(), "t", (7, "seven")).unwrap(),
+    ));
+    let rows = session
+        .query(query, (7, "seven"))
+        .await
+        .unwrap()
+        .rows_typed::<(i32,)>()
+        .unwrap()
+        .map(|row| row.unwrap().0)
+        .collect::<Vec<_>>();
+    assert_eq!(rows, vec![1]);
+}
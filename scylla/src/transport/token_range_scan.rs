@@ -0,0 +1,550 @@
+//! Full-table scans split into token ranges, see [`Session::scan_table`](crate::Session::scan_table).
+
+use std::collections::VecDeque;
+use std::marker::PhantomData;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::{Context, Poll};
+
+use arc_swap::ArcSwap;
+use futures::future;
+use futures::stream::{self, BoxStream, SelectAll};
+use futures::{Stream, StreamExt};
+
+use super::errors::{BadQuery, QueryError};
+use super::execution_profile::{ExecutionProfileHandle, ExecutionProfileInner};
+use super::iterator::{NextRowError, PreparedIteratorConfig, RowIterator};
+use crate::cql_to_rust::FromRow;
+use crate::frame::response::result::Row;
+use crate::frame::value::ValueList;
+use crate::routing::{Sharder, Token};
+use crate::statement::prepared_statement::PreparedStatement;
+use crate::statement::Consistency;
+use crate::transport::cluster::ClusterData;
+use crate::transport::locator::ReplicaLocator;
+use crate::transport::metrics::Metrics;
+
+// Scans are supported for partitioners with 64-bit tokens.
+const MIN_TOKEN: Token = Token::new(i64::MIN);
+const MAX_TOKEN: Token = Token::new(i64::MAX);
+
+const DEFAULT_CONCURRENCY: usize = 16;
+
+/// A range of tokens, from `start` (exclusive) to `end` (inclusive).
+///
+/// The range is scanned with `token(pk) > start AND token(pk) <= end`.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub struct TokenRange {
+    start: Token,
+    end: Token,
+}
+
+impl TokenRange {
+    /// Creates a range of tokens greater than `start` and lower than or equal to `end`.
+    pub fn new(start: Token, end: Token) -> Self {
+        Self { start, end }
+    }
+
+    /// The exclusive start of the range.
+    pub fn start(&self) -> Token {
+        self.start.clone()
+    }
+
+    /// The inclusive end of the range.
+    pub fn end(&self) -> Token {
+        self.end.clone()
+    }
+
+    /// Returns `true` if the token belongs to the range.
+    pub fn contains(&self, token: &Token) -> bool {
+        &self.start < token && token <= &self.end
+    }
+}
+
+/// Ranges of a scan which have not been read entirely yet.
+///
+/// A checkpoint taken with [`TokenRangeScanIterator::checkpoint`] can be passed to
+/// [`TokenRangeScanOptions::resume_from`] to continue an interrupted scan. The ranges
+/// which were being read when the checkpoint was taken are read again from the beginning,
+/// so rows may be returned more than once across the resumed scans.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct TokenRangeScanCheckpoint {
+    ranges: Vec<TokenRange>,
+}
+
+impl TokenRangeScanCheckpoint {
+    /// Creates a checkpoint from the ranges left to scan, e.g. restored from a persisted checkpoint.
+    pub fn new(ranges: Vec<TokenRange>) -> Self {
+        Self { ranges }
+    }
+
+    /// The ranges left to scan, ordered by their start token.
+    pub fn ranges(&self) -> &[TokenRange] {
+        &self.ranges
+    }
+
+    /// Returns `true` if there is nothing left to scan.
+    pub fn is_finished(&self) -> bool {
+        self.ranges.is_empty()
+    }
+}
+
+/// Configures a scan started with [`Session::scan_table`](crate::Session::scan_table).
+///
+/// # Example
+/// ```rust
+/// use scylla::transport::token_range_scan::TokenRangeScanOptions;
+/// use scylla::statement::Consistency;
+///
+/// let options = TokenRangeScanOptions::new()
+///     .columns(vec!["a".to_string(), "b".to_string()])
+///     .concurrency(32)
+///     .consistency(Consistency::LocalOne);
+/// ```
+#[derive(Debug, Clone)]
+pub struct TokenRangeScanOptions {
+    pub(crate) columns: Option<Vec<String>>,
+    pub(crate) concurrency: usize,
+    pub(crate) split_by_shards: bool,
+    pub(crate) page_size: Option<i32>,
+    pub(crate) consistency: Option<Consistency>,
+    pub(crate) execution_profile_handle: Option<ExecutionProfileHandle>,
+    pub(crate) resume_from: Option<TokenRangeScanCheckpoint>,
+}
+
+impl TokenRangeScanOptions {
+    /// Creates the default options: all columns are read, 16 ranges at a time,
+    /// with ranges split at shard boundaries.
+    pub fn new() -> Self {
+        Self {
+            columns: None,
+            concurrency: DEFAULT_CONCURRENCY,
+            split_by_shards: true,
+            page_size: None,
+            consistency: None,
+            execution_profile_handle: None,
+            resume_from: None,
+        }
+    }
+
+    /// Sets the columns to read, in the order in which they are deserialized into rows.
+    /// By default, all columns are read (`SELECT *`).
+    pub fn columns(self, columns: Vec<String>) -> Self {
+        Self {
+            columns: Some(columns),
+            ..self
+        }
+    }
+
+    /// Sets how many token ranges are read at the same time. The default is **16**.
+    pub fn concurrency(self, concurrency: usize) -> Self {
+        Self {
+            concurrency: concurrency.max(1),
+            ..self
+        }
+    }
+
+    /// Sets whether vnode ranges are further split at shard boundaries of the owning
+    /// node, so that each query is served by a single shard. This creates many more,
+    /// smaller ranges. Enabled by default; has no effect on nodes which are not sharded.
+    pub fn split_by_shards(self, split_by_shards: bool) -> Self {
+        Self {
+            split_by_shards,
+            ..self
+        }
+    }
+
+    /// Sets the page size of the queries reading the ranges.
+    pub fn page_size(self, page_size: i32) -> Self {
+        Self {
+            page_size: Some(page_size),
+            ..self
+        }
+    }
+
+    /// Sets the consistency of the queries reading the ranges, overriding the one
+    /// from the execution profile.
+    pub fn consistency(self, consistency: Consistency) -> Self {
+        Self {
+            consistency: Some(consistency),
+            ..self
+        }
+    }
+
+    /// Sets the execution profile of the queries reading the ranges.
+    /// By default, the session's default execution profile is used.
+    pub fn execution_profile_handle(self, handle: ExecutionProfileHandle) -> Self {
+        Self {
+            execution_profile_handle: Some(handle),
+            ..self
+        }
+    }
+
+    /// Scans only the ranges left in the checkpoint of an interrupted scan.
+    pub fn resume_from(self, checkpoint: TokenRangeScanCheckpoint) -> Self {
+        Self {
+            resume_from: Some(checkpoint),
+            ..self
+        }
+    }
+}
+
+impl Default for TokenRangeScanOptions {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Builds the query reading a token range of the table.
+pub(crate) fn scan_query(
+    keyspace: &str,
+    table: &str,
+    partition_key: &[String],
+    columns: Option<&[String]>,
+) -> String {
+    let selected = match columns {
+        Some(columns) => columns
+            .iter()
+            .map(|column| quote_identifier(column))
+            .collect::<Vec<_>>()
+            .join(", "),
+        None => "*".to_owned(),
+    };
+    let token = format!(
+        "token({})",
+        partition_key
+            .iter()
+            .map(|column| quote_identifier(column))
+            .collect::<Vec<_>>()
+            .join(", ")
+    );
+
+    format!(
+        "SELECT {} FROM {}.{} WHERE {} > ? AND {} <= ?",
+        selected,
+        quote_identifier(keyspace),
+        quote_identifier(table),
+        token,
+        token
+    )
+}
+
+fn quote_identifier(identifier: &str) -> String {
+    format!("\"{}\"", identifier.replace('"', "\"\""))
+}
+
+/// Splits the whole ring into ranges between consecutive tokens of the ring (vnodes),
+/// optionally split further at the shard boundaries of the nodes owning them.
+pub(crate) fn token_ranges(locator: &ReplicaLocator, split_by_shards: bool) -> Vec<TokenRange> {
+    let ring = locator.ring();
+    let vnodes = vnode_ranges(ring.iter().map(|(token, _)| token.clone()));
+    if !split_by_shards {
+        return vnodes;
+    }
+
+    vnodes
+        .into_iter()
+        .flat_map(|range| {
+            // The primary replica of the end token owns the range.
+            match ring
+                .get_elem_for_token(range.end.clone())
+                .and_then(|node| node.sharder())
+            {
+                Some(sharder) => split_by_shard(range, &sharder),
+                None => vec![range],
+            }
+        })
+        .collect()
+}
+
+fn vnode_ranges(sorted_tokens: impl Iterator<Item = Token>) -> Vec<TokenRange> {
+    let mut tokens: Vec<Token> = sorted_tokens.collect();
+    tokens.dedup();
+
+    let (first, last) = match (tokens.first(), tokens.last()) {
+        (Some(first), Some(last)) => (first.clone(), last.clone()),
+        _ => return vec![TokenRange::new(MIN_TOKEN, MAX_TOKEN)],
+    };
+
+    let mut ranges = Vec::with_capacity(tokens.len() + 1);
+    // The range wrapping around the ring is split in two, as the queries can't wrap.
+    if first > MIN_TOKEN {
+        ranges.push(TokenRange::new(MIN_TOKEN, first));
+    }
+    ranges.extend(
+        tokens
+            .windows(2)
+            .map(|window| TokenRange::new(window[0].clone(), window[1].clone())),
+    );
+    if last < MAX_TOKEN {
+        ranges.push(TokenRange::new(last, MAX_TOKEN));
+    }
+    ranges
+}
+
+fn split_by_shard(range: TokenRange, sharder: &Sharder) -> Vec<TokenRange> {
+    let mut ranges = Vec::new();
+    let (mut start, end) = match (range.start.as_i64(), range.end.as_i64()) {
+        (Some(start), Some(end)) => (start, end),
+        _ => return vec![range],
+    };
+    loop {
+        match sharder.next_shard_boundary(&Token::new(start + 1)) {
+            Some(boundary) if boundary <= range.end => {
+                let last_in_shard = boundary.as_i64().unwrap() - 1;
+                ranges.push(TokenRange::new(
+                    Token::new(start),
+                    Token::new(last_in_shard),
+                ));
+                start = last_in_shard;
+            }
+            _ => {
+                ranges.push(TokenRange::new(Token::new(start), Token::new(end)));
+                return ranges;
+            }
+        }
+    }
+}
+
+pub(crate) struct ScanConfig {
+    pub(crate) prepared: PreparedStatement,
+    pub(crate) execution_profile: Arc<ExecutionProfileInner>,
+    // Read anew for every range, so that topology changes during the scan are taken into account.
+    pub(crate) cluster_data: Arc<ArcSwap<ClusterData>>,
+    pub(crate) metrics: Arc<Metrics>,
+}
+
+/// The outcome of reading a range: a row, the end of the range or a failure.
+type RangeItem = (TokenRange, Result<Option<Row>, QueryError>);
+
+/// Rows of a table read by [`Session::scan_table`](crate::Session::scan_table).
+///
+/// Rows come from several token ranges read concurrently, so they are not ordered by token.
+/// If reading a range fails, the error is returned and the scan continues with the
+/// other ranges; the failed range stays in the [checkpoint](Self::checkpoint).
+pub struct TokenRangeScanIterator<RowT> {
+    config: Arc<ScanConfig>,
+    concurrency: usize,
+    pending: VecDeque<TokenRange>,
+    in_flight: Vec<TokenRange>,
+    failed: Vec<TokenRange>,
+    streams: SelectAll<BoxStream<'static, RangeItem>>,
+    phantom_data: PhantomData<RowT>,
+}
+
+impl<RowT> TokenRangeScanIterator<RowT> {
+    pub(crate) fn new(config: ScanConfig, ranges: Vec<TokenRange>, concurrency: usize) -> Self {
+        Self {
+            config: Arc::new(config),
+            concurrency: concurrency.max(1),
+            pending: ranges.into(),
+            in_flight: Vec::new(),
+            failed: Vec::new(),
+            streams: SelectAll::new(),
+            phantom_data: PhantomData,
+        }
+    }
+
+    /// Returns the ranges which have not been read entirely yet: the ranges not started,
+    /// being read, and the ones whose reading failed.
+    pub fn checkpoint(&self) -> TokenRangeScanCheckpoint {
+        let mut ranges: Vec<TokenRange> = self
+            .pending
+            .iter()
+            .chain(self.in_flight.iter())
+            .chain(self.failed.iter())
+            .cloned()
+            .collect();
+        ranges.sort();
+        TokenRangeScanCheckpoint { ranges }
+    }
+
+    fn read_range(config: Arc<ScanConfig>, range: TokenRange) -> BoxStream<'static, RangeItem> {
+        let bounds = (range.start.as_i64(), range.end.as_i64());
+        let end = range.end();
+        let rows = async move {
+            let values = match bounds {
+                (Some(start), Some(end)) => (start, end).serialized()?.into_owned(),
+                _ => {
+                    return Err(QueryError::BadQuery(BadQuery::Other(
+                        "Token range scans require 64-bit tokens".to_owned(),
+                    )))
+                }
+            };
+            RowIterator::new_for_prepared_statement(PreparedIteratorConfig {
+                prepared: config.prepared.clone(),
+                values,
+                partition_key: None,
+                // Routes the query to the replicas owning the range, and to the shard owning it.
+                token: Some(end),
+                execution_profile: config.execution_profile.clone(),
+                cluster_data: config.cluster_data.load_full(),
+                metrics: config.metrics.clone(),
+            })
+            .await
+        };
+
+        stream::once(rows)
+            .flat_map(|rows| match rows {
+                Ok(rows) => rows
+                    .map(|row| row.map(Some))
+                    .chain(stream::once(future::ready(Ok(None))))
+                    .left_stream(),
+                Err(err) => stream::once(future::ready(Err(err))).right_stream(),
+            })
+            // A failed range must not be reported as read entirely.
+            .scan(false, |failed, item| {
+                if *failed {
+                    return future::ready(None);
+                }
+                *failed = item.is_err();
+                future::ready(Some(item))
+            })
+            .map(move |item| (range.clone(), item))
+            .boxed()
+    }
+
+    fn finish_range(&mut self, range: &TokenRange) {
+        if let Some(idx) = self.in_flight.iter().position(|r| r == range) {
+            self.in_flight.swap_remove(idx);
+        }
+    }
+}
+
+impl<RowT: FromRow> Stream for TokenRangeScanIterator<RowT> {
+    type Item = Result<RowT, NextRowError>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let s = self.as_mut().get_mut();
+        loop {
+            while s.in_flight.len() < s.concurrency {
+                let range = match s.pending.pop_front() {
+                    Some(range) => range,
+                    None => break,
+                };
+                s.in_flight.push(range.clone());
+                s.streams.push(Self::read_range(s.config.clone(), range));
+            }
+
+            match Pin::new(&mut s.streams).poll_next(cx) {
+                Poll::Ready(Some((_, Ok(Some(row))))) => {
+                    return Poll::Ready(Some(RowT::from_row(row).map_err(Into::into)))
+                }
+                Poll::Ready(Some((range, Ok(None)))) => s.finish_range(&range),
+                Poll::Ready(Some((range, Err(err)))) => {
+                    s.finish_range(&range);
+                    s.failed.push(range);
+                    return Poll::Ready(Some(Err(err.into())));
+                }
+                Poll::Ready(None) => return Poll::Ready(None),
+                Poll::Pending => return Poll::Pending,
+            }
+        }
+    }
+}
+
+impl<RowT> Unpin for TokenRangeScanIterator<RowT> {}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        scan_query, split_by_shard, token_ranges, vnode_ranges, TokenRange, MAX_TOKEN, MIN_TOKEN,
+    };
+    use crate::routing::{ShardCount, Sharder, Token};
+    use crate::transport::locator::test::mock_metadata_for_token_aware_tests;
+    use crate::transport::ClusterData;
+    use std::collections::HashMap;
+
+    fn token(value: i64) -> Token {
+        Token::new(value)
+    }
+
+    #[test]
+    fn test_scan_query() {
+        assert_eq!(
+            scan_query("ks", "t", &["a".to_owned()], None),
+            r#"SELECT * FROM "ks"."t" WHERE token("a") > ? AND token("a") <= ?"#
+        );
+        assert_eq!(
+            scan_query(
+                "Ks",
+                "t",
+                &["a".to_owned(), "we\"ird".to_owned()],
+                Some(&["c".to_owned(), "a".to_owned()])
+            ),
+            r#"SELECT "c", "a" FROM "Ks"."t" WHERE token("a", "we""ird") > ? AND token("a", "we""ird") <= ?"#
+        );
+    }
+
+    #[test]
+    fn test_vnode_ranges() {
+        assert_eq!(
+            vnode_ranges(std::iter::empty()),
+            vec![TokenRange::new(MIN_TOKEN, MAX_TOKEN)]
+        );
+        assert_eq!(
+            vnode_ranges([token(-10), token(5), token(5), token(20)].into_iter()),
+            vec![
+                TokenRange::new(MIN_TOKEN, token(-10)),
+                TokenRange::new(token(-10), token(5)),
+                TokenRange::new(token(5), token(20)),
+                TokenRange::new(token(20), MAX_TOKEN),
+            ]
+        );
+        assert_eq!(
+            vnode_ranges([MIN_TOKEN, token(0)].into_iter()),
+            vec![
+                TokenRange::new(MIN_TOKEN, token(0)),
+                TokenRange::new(token(0), MAX_TOKEN),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_split_by_shard() {
+        let sharder = Sharder::new(ShardCount::new(4).unwrap(), 12);
+        let range = TokenRange::new(token(-1_000_000_000_000_000), token(1_000_000_000_000_000));
+        let ranges = split_by_shard(range.clone(), &sharder);
+
+        // The subranges cover the range without gaps and each of them belongs to a single shard.
+        assert!(ranges.len() > 1);
+        assert_eq!(ranges.first().unwrap().start(), range.start());
+        assert_eq!(ranges.last().unwrap().end(), range.end());
+        for window in ranges.windows(2) {
+            assert_eq!(window[0].end(), window[1].start());
+            assert_ne!(
+                sharder.shard_of(window[0].end()),
+                sharder.shard_of(window[1].end())
+            );
+        }
+        for subrange in &ranges {
+            let first = token(subrange.start().as_i64().unwrap() + 1);
+            assert!(subrange.contains(&first));
+            assert_eq!(sharder.shard_of(first), sharder.shard_of(subrange.end()));
+        }
+
+        let whole_ring = split_by_shard(TokenRange::new(MIN_TOKEN, MAX_TOKEN), &sharder);
+        assert_eq!(whole_ring.len(), 4 << 12);
+    }
+
+    #[tokio::test]
+    async fn test_token_ranges_of_cluster() {
+        let cluster = ClusterData::new(
+            mock_metadata_for_token_aware_tests(),
+            &Default::default(),
+            &HashMap::new(),
+            &None,
+            None,
+            &HashMap::new(),
+        )
+        .await;
+
+        // Mock nodes are not sharded, so only the vnode ranges are created.
+        let ranges = token_ranges(cluster.replica_locator(), true);
+        assert_eq!(ranges, token_ranges(cluster.replica_locator(), false));
+        assert_eq!(ranges.first(), Some(&TokenRange::new(MIN_TOKEN, token(50))));
+        assert_eq!(ranges.last(), Some(&TokenRange::new(token(900), MAX_TOKEN)));
+        // 17 ring tokens give 16 ranges between them, and the range wrapping around the ring.
+        assert_eq!(ranges.len(), 18);
+    }
+}
@@ -5,6 +5,9 @@ use crate::transport::connection::{Connection, ConnectionConfig};
 use crate::transport::connection_pool::{NodeConnectionPool, PoolConfig, PoolSize};
 use crate::transport::errors::{DbError, QueryError};
 use crate::transport::host_filter::HostFilter;
+use crate::transport::partitioner::PartitionerName;
+use crate::transport::reconnection_policy::ReconnectionPolicy;
+use crate::transport::session::resolve_hostname_all;
 use crate::utils::parse::{ParseErrorCause, ParseResult, ParserState};
 
 use futures::future::{self, FutureExt};
@@ -17,7 +20,7 @@ use scylla_cql::frame::value::ValueList;
 use scylla_macros::FromRow;
 use std::borrow::BorrowMut;
 use std::cell::Cell;
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::fmt;
 use std::fmt::Formatter;
 use std::net::{IpAddr, SocketAddr};
@@ -37,12 +40,19 @@ use super::NodeAddr;
 pub(crate) struct MetadataReader {
     connection_config: ConnectionConfig,
     keepalive_interval: Option<Duration>,
+    reconnection_policy: Arc<dyn ReconnectionPolicy>,
 
     control_connection_endpoint: UntranslatedEndpoint,
     control_connection: NodeConnectionPool,
 
     // when control connection fails, MetadataReader tries to connect to one of known_peers
     known_peers: Vec<UntranslatedEndpoint>,
+    // if none of known_peers is reachable, MetadataReader falls back to the contact points
+    // given by the user, resolving the hostnames among them again
+    contact_points: Vec<ContactPoint>,
+    // addresses of the hostnames at their last resolution; contact points with other
+    // addresses are kept until metadata is fetched successfully
+    resolved_contact_points: HashMap<(String, Option<String>), Vec<SocketAddr>>,
     keyspaces_to_fetch: Vec<String>,
     fetch_schema: bool,
     host_filter: Option<Arc<dyn HostFilter>>,
@@ -52,6 +62,8 @@ pub(crate) struct MetadataReader {
 pub struct Metadata {
     pub peers: Vec<Peer>,
     pub keyspaces: HashMap<String, Keyspace>,
+    /// The partitioner used by the cluster, as reported in `system.local`
+    pub partitioner: Option<String>,
 }
 
 #[non_exhaustive] // <- so that we can add more fields in a backwards-compatible way
@@ -61,6 +73,8 @@ pub struct Peer {
     pub tokens: Vec<Token>,
     pub datacenter: Option<String>,
     pub rack: Option<String>,
+    pub(crate) release_version: Option<String>,
+    pub(crate) schema_version: Option<Uuid>,
 }
 
 /// An endpoint for a node that the driver is to issue connections to,
@@ -105,6 +119,16 @@ pub struct PeerEndpoint {
 }
 
 impl Peer {
+    /// Cassandra/Scylla release version reported by the node, e.g. "3.0.8".
+    pub fn release_version(&self) -> Option<&str> {
+        self.release_version.as_deref()
+    }
+
+    /// Schema version the node reported when the metadata was fetched.
+    pub fn schema_version(&self) -> Option<Uuid> {
+        self.schema_version
+    }
+
     pub(crate) fn to_peer_endpoint(&self) -> PeerEndpoint {
         PeerEndpoint {
             host_id: self.host_id,
@@ -306,6 +330,7 @@ pub enum ColumnKind {
 
 #[derive(Clone, Debug, PartialEq, Eq)]
 #[allow(clippy::enum_variant_names)]
+#[non_exhaustive]
 pub enum Strategy {
     SimpleStrategy {
         replication_factor: usize,
@@ -314,7 +339,8 @@ pub enum Strategy {
         // Replication factors of datacenters with given names
         datacenter_repfactors: HashMap<String, usize>,
     },
-    LocalStrategy, // replication_factor == 1
+    EverywhereStrategy, // every node is a replica
+    LocalStrategy,      // data is local to each node, so every node is a replica
     Other {
         name: String,
         data: HashMap<String, String>,
@@ -360,12 +386,12 @@ impl Metadata {
 
                 Peer {
                     address: endpoint.address(),
-                    tokens: vec![Token {
-                        value: token as i64,
-                    }],
+                    tokens: vec![Token::new(token as i64)],
                     datacenter: None,
                     rack: None,
                     host_id: Uuid::new_v4(),
+                    release_version: None,
+                    schema_version: None,
                 }
             })
             .collect();
@@ -373,6 +399,7 @@ impl Metadata {
         Metadata {
             peers,
             keyspaces: HashMap::new(),
+            partitioner: None,
         }
     }
 }
@@ -384,6 +411,7 @@ impl MetadataReader {
         initially_known_peers: Vec<ContactPoint>,
         mut connection_config: ConnectionConfig,
         keepalive_interval: Option<Duration>,
+        reconnection_policy: Arc<dyn ReconnectionPolicy>,
         server_event_sender: mpsc::Sender<Event>,
         keyspaces_to_fetch: Vec<String>,
         fetch_schema: bool,
@@ -405,17 +433,22 @@ impl MetadataReader {
             control_connection_endpoint.clone(),
             connection_config.clone(),
             keepalive_interval,
+            reconnection_policy.clone(),
         );
 
         MetadataReader {
             control_connection_endpoint,
             control_connection,
             keepalive_interval,
+            reconnection_policy,
             connection_config,
             known_peers: initially_known_peers
-                .into_iter()
+                .iter()
+                .cloned()
                 .map(UntranslatedEndpoint::ContactPoint)
                 .collect(),
+            contact_points: initially_known_peers,
+            resolved_contact_points: HashMap::new(),
             keyspaces_to_fetch,
             fetch_schema,
             host_filter: host_filter.clone(),
@@ -427,6 +460,7 @@ impl MetadataReader {
         let mut result = self.fetch_metadata(initial).await;
         if let Ok(metadata) = result {
             self.update_known_peers(&metadata);
+            self.prune_stale_contact_points();
             if initial {
                 self.handle_unaccepted_host_in_control_connection(&metadata);
             }
@@ -444,15 +478,66 @@ impl MetadataReader {
                 .join(", ")
         );
 
-        let address_of_failed_control_connection = self.control_connection_endpoint.address();
-        let filtered_known_peers = self
-            .known_peers
-            .iter()
-            .filter(|&peer| peer.address() != address_of_failed_control_connection);
+        let mut tried_addresses = HashSet::new();
+        tried_addresses.insert(self.control_connection_endpoint.address());
 
         // if fetching metadata on current control connection failed,
         // try to fetch metadata from other known peer
-        for peer in filtered_known_peers {
+        let known_peers = self.known_peers.clone();
+        result = self
+            .fetch_metadata_from_other_endpoints(known_peers, result, &mut tried_addresses, initial)
+            .await;
+
+        if result.is_err() {
+            // All known peers might have changed their addresses, e.g. after being
+            // rescheduled by Kubernetes. Fall back to the contact points, whose
+            // hostnames may now resolve to the new addresses.
+            self.resolve_contact_points().await;
+            let contact_points = self
+                .contact_points
+                .iter()
+                .cloned()
+                .map(UntranslatedEndpoint::ContactPoint)
+                .collect();
+            result = self
+                .fetch_metadata_from_other_endpoints(
+                    contact_points,
+                    result,
+                    &mut tried_addresses,
+                    initial,
+                )
+                .await;
+        }
+
+        match &result {
+            Ok(metadata) => {
+                self.update_known_peers(metadata);
+                self.prune_stale_contact_points();
+                self.handle_unaccepted_host_in_control_connection(metadata);
+                debug!("Fetched new metadata");
+            }
+            Err(error) => error!(
+                error = %error,
+                "Could not fetch metadata"
+            ),
+        }
+
+        result
+    }
+
+    // Moves the control connection to consecutive endpoints which weren't tried yet,
+    // until fetching metadata succeeds.
+    async fn fetch_metadata_from_other_endpoints(
+        &mut self,
+        endpoints: Vec<UntranslatedEndpoint>,
+        mut result: Result<Metadata, QueryError>,
+        tried_addresses: &mut HashSet<NodeAddr>,
+        initial: bool,
+    ) -> Result<Metadata, QueryError> {
+        for peer in endpoints {
+            if !tried_addresses.insert(peer.address()) {
+                continue;
+            }
             let err = match result {
                 Ok(_) => break,
                 Err(err) => err,
@@ -468,11 +553,12 @@ impl MetadataReader {
                 "Failed to fetch metadata using current control connection"
             );
 
-            self.control_connection_endpoint = peer.clone();
+            self.control_connection_endpoint = peer;
             self.control_connection = Self::make_control_connection_pool(
                 self.control_connection_endpoint.clone(),
                 self.connection_config.clone(),
                 self.keepalive_interval,
+                self.reconnection_policy.clone(),
             );
 
             debug!(
@@ -482,19 +568,75 @@ impl MetadataReader {
             result = self.fetch_metadata(initial).await;
         }
 
-        match &result {
-            Ok(metadata) => {
-                self.update_known_peers(metadata);
-                self.handle_unaccepted_host_in_control_connection(metadata);
-                debug!("Fetched new metadata");
+        result
+    }
+
+    /// Resolves the contact points given as hostnames again
+    /// and merges the new addresses into the contact point list.
+    /// Addresses which the hostnames no longer resolve to are kept
+    /// until metadata is fetched successfully, see [`Self::prune_stale_contact_points`].
+    pub(crate) async fn resolve_contact_points(&mut self) {
+        let mut hostnames: Vec<(String, Option<String>)> = self
+            .contact_points
+            .iter()
+            .filter_map(|cp| Some((cp.hostname.clone()?, cp.datacenter.clone())))
+            .collect();
+        hostnames.sort();
+        hostnames.dedup();
+
+        if hostnames.is_empty() {
+            return;
+        }
+
+        let resolved = future::join_all(hostnames.into_iter().map(
+            |(hostname, datacenter)| async move {
+                let result = resolve_hostname_all(&hostname).await;
+                ((hostname, datacenter), result)
+            },
+        ))
+        .await;
+
+        let mut resolved_addresses = HashMap::new();
+        for (key, result) in resolved {
+            match result {
+                Ok(addresses) => {
+                    resolved_addresses.insert(key, addresses);
+                }
+                // Keep the previous addresses, they might still work
+                Err(err) => warn!(
+                    hostname = key.0.as_str(),
+                    error = %err,
+                    "Failed to resolve the contact point hostname again"
+                ),
             }
-            Err(error) => error!(
-                error = %error,
-                "Could not fetch metadata"
-            ),
         }
 
-        result
+        self.contact_points =
+            merge_resolved_contact_points(&self.contact_points, &resolved_addresses);
+        self.resolved_contact_points.extend(resolved_addresses);
+        debug!(
+            "Contact points: {}",
+            self.contact_points
+                .iter()
+                .map(|cp| format!("{:?}", cp))
+                .collect::<Vec<String>>()
+                .join(", ")
+        );
+    }
+
+    // Drops the contact points whose hostnames no longer resolve to their addresses,
+    // after metadata was fetched without them.
+    fn prune_stale_contact_points(&mut self) {
+        if self.resolved_contact_points.is_empty() {
+            return;
+        }
+        let resolved = std::mem::take(&mut self.resolved_contact_points);
+        let control_connection_address = self.control_connection_endpoint.address().into_inner();
+        prune_stale_contact_points(
+            &mut self.contact_points,
+            &resolved,
+            control_connection_address,
+        );
     }
 
     async fn fetch_metadata(&self, initial: bool) -> Result<Metadata, QueryError> {
@@ -585,6 +727,7 @@ impl MetadataReader {
                         self.control_connection_endpoint.clone(),
                         self.connection_config.clone(),
                         self.keepalive_interval,
+                        self.reconnection_policy.clone(),
                     );
                 }
             }
@@ -595,10 +738,12 @@ impl MetadataReader {
         endpoint: UntranslatedEndpoint,
         connection_config: ConnectionConfig,
         keepalive_interval: Option<Duration>,
+        reconnection_policy: Arc<dyn ReconnectionPolicy>,
     ) -> NodeConnectionPool {
         let pool_config = PoolConfig {
             connection_config,
             keepalive_interval,
+            reconnection_policy,
 
             // We want to have only one connection to receive events from
             pool_size: PoolSize::PerHost(NonZeroUsize::new(1).unwrap()),
@@ -606,22 +751,110 @@ impl MetadataReader {
             // The shard-aware port won't be used with PerHost pool size anyway,
             // so explicitly disable it here
             can_use_shard_aware_port: false,
+
+            // The control connection is reopened with the current TLS context
+            // whenever it breaks, so it doesn't need to be recycled
+            tls_reload_check_interval: None,
         };
 
         NodeConnectionPool::new(endpoint, pool_config, None)
     }
 }
 
+// Merges the addresses of the contact points which were resolved again into the
+// contact point list. The new addresses of a hostname are put in place of its first
+// contact point, followed by its previous addresses which it no longer resolves to.
+fn merge_resolved_contact_points(
+    contact_points: &[ContactPoint],
+    resolved_addresses: &HashMap<(String, Option<String>), Vec<SocketAddr>>,
+) -> Vec<ContactPoint> {
+    let mut merged = Vec::with_capacity(contact_points.len());
+    let mut merged_hostnames = HashSet::new();
+    for contact_point in contact_points {
+        let hostname = match &contact_point.hostname {
+            Some(hostname) => hostname,
+            None => {
+                merged.push(contact_point.clone());
+                continue;
+            }
+        };
+        let key = (hostname.clone(), contact_point.datacenter.clone());
+        let addresses = match resolved_addresses.get(&key) {
+            Some(addresses) => addresses,
+            None => {
+                // Not resolved again, keep the old address
+                merged.push(contact_point.clone());
+                continue;
+            }
+        };
+        if !merged_hostnames.insert(key) {
+            continue;
+        }
+
+        let previous_addresses = contact_points
+            .iter()
+            .filter(|cp| {
+                cp.hostname.as_ref() == Some(hostname) && cp.datacenter == contact_point.datacenter
+            })
+            .map(|cp| cp.address)
+            .filter(|address| !addresses.contains(address));
+        merged.extend(
+            addresses
+                .iter()
+                .copied()
+                .chain(previous_addresses)
+                .map(|address| ContactPoint {
+                    address,
+                    datacenter: contact_point.datacenter.clone(),
+                    hostname: Some(hostname.clone()),
+                }),
+        );
+    }
+    merged
+}
+
+// Drops the contact points whose hostnames didn't resolve to their addresses
+// at the last resolution. The address of the control connection is kept,
+// as it evidently still works.
+fn prune_stale_contact_points(
+    contact_points: &mut Vec<ContactPoint>,
+    resolved_addresses: &HashMap<(String, Option<String>), Vec<SocketAddr>>,
+    control_connection_address: SocketAddr,
+) {
+    contact_points.retain(|contact_point| {
+        let hostname = match &contact_point.hostname {
+            Some(hostname) => hostname,
+            None => return true,
+        };
+        match resolved_addresses.get(&(hostname.clone(), contact_point.datacenter.clone())) {
+            Some(addresses) => {
+                addresses.contains(&contact_point.address)
+                    || contact_point.address == control_connection_address
+            }
+            None => true,
+        }
+    });
+}
+
 async fn query_metadata(
     conn: &Arc<Connection>,
     connect_port: u16,
     keyspace_to_fetch: &[String],
     fetch_schema: bool,
 ) -> Result<Metadata, QueryError> {
-    let peers_query = query_peers(conn, connect_port);
+    // Tokens can be parsed only once the partitioner is known
+    let peers_query = async {
+        let partitioner = query_partitioner(conn).await?;
+        let partitioner_name = partitioner
+            .as_deref()
+            .and_then(PartitionerName::from_str)
+            .unwrap_or_default();
+        let peers = query_peers(conn, connect_port, &partitioner_name).await?;
+        Ok::<_, QueryError>((peers, partitioner))
+    };
     let keyspaces_query = query_keyspaces(conn, keyspace_to_fetch, fetch_schema);
 
-    let (peers, keyspaces) = tokio::try_join!(peers_query, keyspaces_query)?;
+    let ((peers, partitioner), keyspaces) = tokio::try_join!(peers_query, keyspaces_query)?;
 
     // There must be at least one peer
     if peers.is_empty() {
@@ -637,7 +870,29 @@ async fn query_metadata(
         ));
     }
 
-    Ok(Metadata { peers, keyspaces })
+    Ok(Metadata {
+        peers,
+        keyspaces,
+        partitioner,
+    })
+}
+
+async fn query_partitioner(conn: &Arc<Connection>) -> Result<Option<String>, QueryError> {
+    let rows = conn
+        .query_single_page("select partitioner from system.local", &[])
+        .await?
+        .rows
+        .unwrap_or_default();
+
+    let partitioner = match rows.into_iter().next() {
+        Some(row) => {
+            row.into_typed::<(Option<String>,)>()
+                .map_err(|_| QueryError::ProtocolError("system.local has invalid column type"))?
+                .0
+        }
+        None => None,
+    };
+    Ok(partitioner)
 }
 
 #[derive(FromRow)]
@@ -648,6 +903,8 @@ struct NodeInfoRow {
     datacenter: Option<String>,
     rack: Option<String>,
     tokens: Option<Vec<String>>,
+    release_version: Option<String>,
+    schema_version: Option<Uuid>,
 }
 
 #[derive(Clone, Copy)]
@@ -665,19 +922,28 @@ impl NodeInfoSource {
     }
 }
 
-async fn query_peers(conn: &Arc<Connection>, connect_port: u16) -> Result<Vec<Peer>, QueryError> {
+async fn query_peers(
+    conn: &Arc<Connection>,
+    connect_port: u16,
+    partitioner: &PartitionerName,
+) -> Result<Vec<Peer>, QueryError> {
     let mut peers_query =
-        Query::new("select host_id, rpc_address, data_center, rack, tokens from system.peers");
+        Query::new("select host_id, rpc_address, data_center, rack, tokens, release_version, schema_version from system.peers");
     peers_query.set_page_size(1024);
-    let peers_query_stream = conn
-        .clone()
-        .query_iter(peers_query, &[])
-        .into_stream()
-        .try_flatten()
-        .and_then(|row_result| future::ok((NodeInfoSource::Peer, row_result)));
+    // A node reached through a Unix domain socket is the only node the driver can connect to
+    let peers_query_stream = if conn.uses_unix_socket() {
+        stream::empty().left_stream()
+    } else {
+        conn.clone()
+            .query_iter(peers_query, &[])
+            .into_stream()
+            .try_flatten()
+            .and_then(|row_result| future::ok((NodeInfoSource::Peer, row_result)))
+            .right_stream()
+    };
 
     let mut local_query =
-        Query::new("select host_id, rpc_address, data_center, rack, tokens from system.local");
+        Query::new("select host_id, rpc_address, data_center, rack, tokens, release_version, schema_version from system.local");
     local_query.set_page_size(1024);
     let local_query_stream = conn
         .clone()
@@ -696,7 +962,7 @@ async fn query_peers(conn: &Arc<Connection>, connect_port: u16) -> Result<Vec<Pe
         let row = raw_row.into_typed().map_err(|_| {
             QueryError::ProtocolError("system.peers or system.local has invalid column type")
         })?;
-        create_peer_from_row(source, row, local_address).await
+        create_peer_from_row(source, row, local_address, partitioner).await
     });
 
     let peers = translated_peers_futures
@@ -710,6 +976,7 @@ async fn create_peer_from_row(
     source: NodeInfoSource,
     row: NodeInfoRow,
     local_address: SocketAddr,
+    partitioner: &PartitionerName,
 ) -> Result<Option<Peer>, QueryError> {
     let NodeInfoRow {
         host_id,
@@ -717,6 +984,8 @@ async fn create_peer_from_row(
         datacenter,
         rack,
         tokens,
+        release_version,
+        schema_version,
     } = row;
 
     let host_id = match host_id {
@@ -746,22 +1015,18 @@ async fn create_peer_from_row(
 
     let tokens_str: Vec<String> = tokens.unwrap_or_default();
 
-    // Parse string representation of tokens as integer values
+    // Parse string representation of tokens according to the partitioner
     let tokens: Vec<Token> = match tokens_str
         .iter()
-        .map(|s| Token::from_str(s))
+        .map(|s| partitioner.parse_token(s).ok_or(s))
         .collect::<Result<Vec<Token>, _>>()
     {
         Ok(parsed) => parsed,
-        Err(e) => {
+        Err(s) => {
             // FIXME: we could allow the users to provide custom partitioning information
-            // in order for it to work with non-standard token sizes.
-            // Also, we could implement support for Cassandra's other standard partitioners
-            // like RandomPartitioner or ByteOrderedPartitioner.
-            trace!("Couldn't parse tokens as 64-bit integers: {}, proceeding with a dummy token. If you're using a partitioner with different token size, consider migrating to murmur3", e);
-            vec![Token {
-                value: rand::thread_rng().gen::<i64>(),
-            }]
+            // in order for it to work with non-standard partitioners.
+            trace!("Couldn't parse token {} of {:?}, proceeding with a dummy token. If you're using a custom partitioner, consider migrating to murmur3", s, partitioner);
+            vec![Token::new(rand::thread_rng().gen::<i64>())]
         }
     };
 
@@ -771,6 +1036,8 @@ async fn create_peer_from_row(
         tokens,
         datacenter,
         rack,
+        release_version,
+        schema_version,
     }))
 }
 
@@ -1588,6 +1855,9 @@ fn strategy_from_string_map(
             }
         }
         "org.apache.cassandra.locator.LocalStrategy" | "LocalStrategy" => Strategy::LocalStrategy,
+        "org.apache.cassandra.locator.EverywhereStrategy" | "EverywhereStrategy" => {
+            Strategy::EverywhereStrategy
+        }
         _ => Strategy::Other {
             name: strategy_name,
             data: strategy_map,
@@ -1764,4 +2034,67 @@ mod tests {
             assert_eq!(parsed, expected);
         }
     }
+
+    #[test]
+    fn test_merge_resolved_contact_points() {
+        let contact_point = |address: &str, hostname: Option<&str>| ContactPoint {
+            address: address.parse().unwrap(),
+            datacenter: None,
+            hostname: hostname.map(String::from),
+        };
+        let contact_points = vec![
+            contact_point("10.0.0.1:9042", None),
+            contact_point("10.0.1.1:9042", Some("a.example.com")),
+            contact_point("10.0.1.2:9042", Some("a.example.com")),
+            contact_point("10.0.2.1:9042", Some("b.example.com")),
+        ];
+
+        // "b.example.com" failed to resolve, so its old address is kept
+        let mut resolved = HashMap::new();
+        resolved.insert(
+            ("a.example.com".to_string(), None),
+            vec![
+                "10.0.1.2:9042".parse().unwrap(),
+                "10.0.1.3:9042".parse().unwrap(),
+                "[fd00::1]:9042".parse().unwrap(),
+            ],
+        );
+
+        let addresses = |contact_points: &[ContactPoint]| -> Vec<(SocketAddr, Option<String>)> {
+            contact_points
+                .iter()
+                .map(|cp| (cp.address, cp.hostname.clone()))
+                .collect()
+        };
+        let mut merged = merge_resolved_contact_points(&contact_points, &resolved);
+        let a = || Some("a.example.com".to_string());
+        let expected: Vec<(SocketAddr, Option<String>)> = vec![
+            ("10.0.0.1:9042".parse().unwrap(), None),
+            ("10.0.1.2:9042".parse().unwrap(), a()),
+            ("10.0.1.3:9042".parse().unwrap(), a()),
+            ("[fd00::1]:9042".parse().unwrap(), a()),
+            // Not resolved anymore, but kept until metadata is fetched without it
+            ("10.0.1.1:9042".parse().unwrap(), a()),
+            (
+                "10.0.2.1:9042".parse().unwrap(),
+                Some("b.example.com".into()),
+            ),
+        ];
+        assert_eq!(addresses(&merged), expected);
+
+        // Resolving again doesn't duplicate addresses
+        assert_eq!(
+            addresses(&merge_resolved_contact_points(&merged, &resolved)),
+            expected
+        );
+
+        // The control connection still uses the stale address, so it's not dropped
+        prune_stale_contact_points(&mut merged, &resolved, "10.0.1.1:9042".parse().unwrap());
+        assert_eq!(addresses(&merged), expected);
+
+        prune_stale_contact_points(&mut merged, &resolved, "10.0.1.2:9042".parse().unwrap());
+        let mut pruned = expected.clone();
+        pruned.remove(4);
+        assert_eq!(addresses(&merged), pruned);
+    }
 }